@@ -4,11 +4,11 @@ mod db;
 mod dto;
 mod entities;
 mod migration;
+mod security;
 mod services;
 
 use dotenv::dotenv;
 use rocket::{launch, routes, Build, Rocket, Request, Data, fairing::{Fairing, Info, Kind}, http::Status};
-use rocket_cors::{AllowedOrigins, CorsOptions};
 use rocket::serde::json::Json;
 use rocket::State;
 use sea_orm::DatabaseConnection;
@@ -58,22 +58,11 @@ async fn rocket() -> Rocket<Build> {
         std::process::exit(1);
     }
 
-    // Configure CORS
-    let cors = match CorsOptions::default()
-        .allowed_origins(AllowedOrigins::all())
-        .allowed_methods(
-            vec!["GET", "POST", "PUT", "DELETE", "OPTIONS"]
-                .into_iter()
-                .map(|s| s.parse().unwrap())
-                .collect(),
-        )
-        .allowed_headers(rocket_cors::AllowedHeaders::all())
-        .allow_credentials(true)
-        .to_cors()
-    {
+    // Configure CORS from the `cors` key of the Rocket config
+    let cors = match security::cors(&rocket::Config::figment()) {
         Ok(cors) => cors,
         Err(e) => {
-            error!(error = ?e, "Failed to create CORS configuration");
+            error!(error = %e, "Failed to create CORS configuration");
             std::process::exit(1);
         }
     };
@@ -86,6 +75,8 @@ async fn rocket() -> Rocket<Build> {
     rocket::build()
         .manage(db)
         .attach(cors)
+        .attach(security::shield())
+        .attach(security::ContentSecurityPolicy)
         .attach(RequestLogger)
         .mount("/", api::get_routes())
         .mount("/", routes![health_check, status])
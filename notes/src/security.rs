@@ -0,0 +1,113 @@
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    figment::Figment,
+    http::Header,
+    shield::{Frame, Hsts, NoSniff, Referrer, Shield},
+    time::Duration,
+    Request, Response,
+};
+use rocket_cors::{AllowedHeaders, AllowedOrigins, Cors, CorsOptions};
+use serde::Deserialize;
+
+/// Origins the bundled frontend is served from in development
+/// (`frontend/serve.py` and the nginx container).
+const DEV_ORIGINS: &[&str] = &[
+    "http://localhost:3000",
+    "http://127.0.0.1:3000",
+    "http://localhost:8080",
+    "http://127.0.0.1:8080",
+];
+
+/// The API only returns JSON, so nothing it serves should load resources or
+/// be framed.
+const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; frame-ancestors 'none'";
+
+/// CORS policy read from the `cors` key of the Rocket config, e.g.
+/// `ROCKET_CORS={allowed_origins=["https://notes.example.com"]}`.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    /// Defaults to the local frontend origins in the debug profile and to
+    /// none in release, where they must be listed explicitly.
+    pub allowed_origins: Option<Vec<String>>,
+    pub allowed_methods: Vec<String>,
+    pub allowed_headers: Vec<String>,
+    pub allow_credentials: bool,
+    pub max_age_secs: usize,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: None,
+            allowed_methods: ["GET", "POST", "PUT", "DELETE", "OPTIONS"].map(String::from).to_vec(),
+            allowed_headers: ["Authorization", "Content-Type"].map(String::from).to_vec(),
+            allow_credentials: false,
+            max_age_secs: 600,
+        }
+    }
+}
+
+/// Builds the CORS fairing for the active profile.
+pub fn cors(figment: &Figment) -> Result<Cors, String> {
+    let config: CorsConfig = match figment.find_value("cors") {
+        Ok(_) => figment.extract_inner("cors").map_err(|e| e.to_string())?,
+        Err(_) => CorsConfig::default(),
+    };
+
+    let origins = config.allowed_origins.unwrap_or_else(|| {
+        if figment.profile() == rocket::Config::DEBUG_PROFILE {
+            DEV_ORIGINS.iter().map(|o| o.to_string()).collect()
+        } else {
+            Vec::new()
+        }
+    });
+    if origins.iter().any(|o| o == "*") {
+        return Err("cors.allowed_origins must list origins; \"*\" is not allowed".to_string());
+    }
+
+    let methods = config
+        .allowed_methods
+        .iter()
+        .map(|m| m.parse().map_err(|_| format!("Unknown HTTP method '{}'", m)))
+        .collect::<Result<_, _>>()?;
+    let headers: Vec<&str> = config.allowed_headers.iter().map(String::as_str).collect();
+
+    CorsOptions::default()
+        .allowed_origins(AllowedOrigins::some_exact(&origins))
+        .allowed_methods(methods)
+        .allowed_headers(AllowedHeaders::some(&headers))
+        .allow_credentials(config.allow_credentials)
+        .max_age(Some(config.max_age_secs))
+        .to_cors()
+        .map_err(|e| e.to_string())
+}
+
+/// Rocket's shield with the policies this API wants: no sniffing, no
+/// framing, no referrer and a year of HSTS.
+pub fn shield() -> Shield {
+    Shield::default()
+        .enable(NoSniff::Enable)
+        .enable(Frame::Deny)
+        .enable(Referrer::NoReferrer)
+        .enable(Hsts::IncludeSubDomains(Duration::days(365)))
+}
+
+/// Adds the `Content-Security-Policy` header, which the shield does not cover.
+pub struct ContentSecurityPolicy;
+
+#[rocket::async_trait]
+impl Fairing for ContentSecurityPolicy {
+    fn info(&self) -> Info {
+        Info {
+            name: "Content-Security-Policy",
+            kind: Kind::Response,
+        }
+    }
+
+    async fn on_response<'r>(&self, _request: &'r Request<'_>, response: &mut Response<'r>) {
+        if !response.headers().contains("Content-Security-Policy") {
+            response.set_header(Header::new("Content-Security-Policy", CONTENT_SECURITY_POLICY));
+        }
+    }
+}
@@ -0,0 +1,16 @@
+-- Backfill rows written before the columns were constrained, then forbid NULLs.
+UPDATE users SET created_at = NOW() WHERE created_at IS NULL;
+UPDATE users SET updated_at = created_at WHERE updated_at IS NULL;
+
+UPDATE todos SET completed = FALSE WHERE completed IS NULL;
+UPDATE todos SET created_at = NOW() WHERE created_at IS NULL;
+UPDATE todos SET updated_at = created_at WHERE updated_at IS NULL;
+
+ALTER TABLE users
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL;
+
+ALTER TABLE todos
+    ALTER COLUMN completed SET NOT NULL,
+    ALTER COLUMN created_at SET NOT NULL,
+    ALTER COLUMN updated_at SET NOT NULL;
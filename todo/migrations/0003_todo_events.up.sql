@@ -0,0 +1,14 @@
+-- Change log behind GET /api/todos/events. Ids only increase, so clients
+-- resume with the last id they saw (Last-Event-ID).
+CREATE TABLE todo_events (
+    id BIGSERIAL PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    todo_id UUID NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('created', 'updated', 'deleted')),
+    -- The todo after the change; NULL for deletions.
+    todo JSONB,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_todo_events_user_id_id ON todo_events(user_id, id);
+CREATE INDEX idx_todo_events_created_at ON todo_events(created_at);
@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS todo_tombstones;
+DROP INDEX IF EXISTS idx_todos_user_id_seq;
+ALTER TABLE todos DROP COLUMN IF EXISTS field_versions;
+ALTER TABLE todos DROP COLUMN IF EXISTS seq;
+ALTER TABLE users DROP COLUMN IF EXISTS change_seq;
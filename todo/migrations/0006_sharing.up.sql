@@ -0,0 +1,20 @@
+-- Sharing and assignment. A share grants another user a role on one todo,
+-- or on every todo of the owner's list when todo_id is NULL.
+ALTER TABLE todos ADD COLUMN assignee_id UUID REFERENCES users(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_todos_assignee_id ON todos(assignee_id) WHERE assignee_id IS NOT NULL;
+
+CREATE TABLE todo_shares (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    owner_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    grantee_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    todo_id UUID REFERENCES todos(id) ON DELETE CASCADE,
+    role VARCHAR(16) NOT NULL CHECK (role IN ('viewer', 'editor')),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CHECK (owner_id <> grantee_id)
+);
+
+-- One share per grantee for the whole list and per todo
+CREATE UNIQUE INDEX idx_todo_shares_list ON todo_shares(owner_id, grantee_id) WHERE todo_id IS NULL;
+CREATE UNIQUE INDEX idx_todo_shares_todo ON todo_shares(todo_id, grantee_id) WHERE todo_id IS NOT NULL;
+CREATE INDEX idx_todo_shares_grantee_id ON todo_shares(grantee_id);
@@ -0,0 +1,34 @@
+-- Discussion threads on todos. Every version of a comment's body is kept in
+-- todo_comment_revisions, the current one included.
+CREATE TABLE todo_comments (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    todo_id UUID NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    author_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Markdown source; rendered to HTML when served.
+    body TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- NULL until the body is first edited.
+    edited_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX idx_todo_comments_todo_id ON todo_comments(todo_id, created_at);
+
+CREATE TABLE todo_comment_revisions (
+    id BIGSERIAL PRIMARY KEY,
+    comment_id UUID NOT NULL REFERENCES todo_comments(id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_todo_comment_revisions_comment_id ON todo_comment_revisions(comment_id, id);
+
+-- Users @mentioned in a comment; the mentions inbox.
+CREATE TABLE comment_mentions (
+    comment_id UUID NOT NULL REFERENCES todo_comments(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    read_at TIMESTAMP WITH TIME ZONE,
+    PRIMARY KEY (comment_id, user_id)
+);
+
+CREATE INDEX idx_comment_mentions_user_id ON comment_mentions(user_id, created_at);
@@ -0,0 +1,18 @@
+-- Files attached to todos. The bytes live in the blob store under blob_key.
+-- Deleting a todo only detaches its attachments (todo_id becomes NULL), so
+-- the cleanup can delete each file before its row.
+CREATE TABLE todo_attachments (
+    id UUID PRIMARY KEY,
+    todo_id UUID REFERENCES todos(id) ON DELETE SET NULL,
+    -- The uploader, whose quota the file counts against.
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    filename TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    blob_key TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_todo_attachments_todo_id ON todo_attachments(todo_id, created_at);
+CREATE INDEX idx_todo_attachments_user_id ON todo_attachments(user_id) WHERE todo_id IS NOT NULL;
+CREATE INDEX idx_todo_attachments_orphaned ON todo_attachments(created_at) WHERE todo_id IS NULL;
@@ -0,0 +1,5 @@
+-- Priority (1 low, 2 medium, 3 high), tags and an iCalendar RRULE for
+-- todos that repeat.
+ALTER TABLE todos ADD COLUMN priority SMALLINT CHECK (priority BETWEEN 1 AND 3);
+ALTER TABLE todos ADD COLUMN tags JSONB NOT NULL DEFAULT '[]';
+ALTER TABLE todos ADD COLUMN recurrence TEXT;
@@ -0,0 +1,4 @@
+-- Where each user is (an IANA time zone) and how they read dates (a BCP 47
+-- language tag). "Today" and dates without a time are read in the zone.
+ALTER TABLE users ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
+ALTER TABLE users ADD COLUMN locale TEXT NOT NULL DEFAULT 'en-US';
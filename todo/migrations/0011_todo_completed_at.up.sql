@@ -0,0 +1,5 @@
+-- When each todo was completed, for statistics. Todos completed before this
+-- column existed take their last update as the best guess.
+ALTER TABLE todos ADD COLUMN completed_at TIMESTAMP WITH TIME ZONE;
+UPDATE todos SET completed_at = updated_at WHERE completed;
+CREATE INDEX idx_todos_user_id_completed_at ON todos(user_id, completed_at);
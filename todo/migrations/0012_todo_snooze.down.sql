@@ -0,0 +1,3 @@
+ALTER TABLE todos DROP COLUMN IF EXISTS hidden_until;
+ALTER TABLE todos DROP COLUMN IF EXISTS original_scheduled_for;
+ALTER TABLE todos DROP COLUMN IF EXISTS snooze_count;
@@ -0,0 +1,22 @@
+-- Time spent on todos, tracked with a timer or entered by hand. An entry
+-- without ended_at is a running timer; each user has at most one.
+CREATE TABLE time_entries (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    todo_id UUID NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    -- Who spent the time.
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    ended_at TIMESTAMP WITH TIME ZONE,
+    -- Whole seconds from started_at to ended_at, once stopped.
+    duration_seconds BIGINT,
+    note TEXT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_time_entries_todo_id ON time_entries(todo_id, started_at);
+CREATE INDEX idx_time_entries_user_id ON time_entries(user_id, started_at);
+CREATE UNIQUE INDEX idx_time_entries_running ON time_entries(user_id) WHERE ended_at IS NULL;
+
+-- The total of each todo's stopped entries, kept up to date with them.
+ALTER TABLE todos ADD COLUMN tracked_seconds BIGINT NOT NULL DEFAULT 0;
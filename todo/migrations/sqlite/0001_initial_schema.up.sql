@@ -0,0 +1,25 @@
+-- Ids are 16-byte UUID blobs and timestamps RFC 3339 text, both written by
+-- the service rather than by column defaults.
+CREATE TABLE users (
+    id BLOB PRIMARY KEY NOT NULL,
+    username TEXT UNIQUE NOT NULL,
+    email TEXT UNIQUE NOT NULL,
+    password_hash TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE todos (
+    id BLOB PRIMARY KEY NOT NULL,
+    user_id BLOB NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    description TEXT,
+    completed BOOLEAN NOT NULL DEFAULT FALSE,
+    scheduled_for TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_todos_user_id ON todos(user_id);
+CREATE INDEX idx_todos_completed ON todos(completed);
+CREATE INDEX idx_todos_scheduled_for ON todos(scheduled_for);
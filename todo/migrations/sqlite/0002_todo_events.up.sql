@@ -0,0 +1,14 @@
+-- Change log behind GET /api/todos/events. AUTOINCREMENT keeps ids from
+-- being reused after old events are pruned, so Last-Event-ID stays valid.
+CREATE TABLE todo_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id BLOB NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    todo_id BLOB NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('created', 'updated', 'deleted')),
+    -- JSON of the todo after the change; NULL for deletions.
+    todo TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_todo_events_user_id_id ON todo_events(user_id, id);
+CREATE INDEX idx_todo_events_created_at ON todo_events(created_at);
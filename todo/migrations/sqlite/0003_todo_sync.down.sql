@@ -0,0 +1,5 @@
+DROP TABLE IF EXISTS todo_tombstones;
+DROP INDEX IF EXISTS idx_todos_user_id_seq;
+ALTER TABLE todos DROP COLUMN field_versions;
+ALTER TABLE todos DROP COLUMN seq;
+ALTER TABLE users DROP COLUMN change_seq;
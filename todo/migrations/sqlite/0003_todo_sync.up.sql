@@ -0,0 +1,41 @@
+-- Change sequence behind GET /api/sync. Each user's todo mutations take
+-- the next value of users.change_seq; a todo records the value of its
+-- latest change and a deletion leaves a tombstone with its value.
+ALTER TABLE users ADD COLUMN change_seq INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE todos ADD COLUMN seq INTEGER NOT NULL DEFAULT 0;
+-- When each field last changed, for last-writer-wins merging of client
+-- mutations: JSON {"title": "<RFC 3339 time>", ...}.
+ALTER TABLE todos ADD COLUMN field_versions TEXT NOT NULL DEFAULT '{}';
+
+-- Existing todos get distinct numbers in creation order and every field
+-- the version of the todo's last update.
+UPDATE todos
+SET seq = (
+        SELECT numbered.seq
+        FROM (
+            SELECT id, ROW_NUMBER() OVER (PARTITION BY user_id ORDER BY created_at, id) AS seq
+            FROM todos
+        ) AS numbered
+        WHERE numbered.id = todos.id
+    ),
+    field_versions = json_object(
+        'title', updated_at,
+        'description', updated_at,
+        'completed', updated_at,
+        'scheduled_for', updated_at
+    );
+
+UPDATE users
+SET change_seq = (SELECT COALESCE(MAX(seq), 0) FROM todos WHERE todos.user_id = users.id);
+
+CREATE UNIQUE INDEX idx_todos_user_id_seq ON todos(user_id, seq);
+
+CREATE TABLE todo_tombstones (
+    todo_id BLOB PRIMARY KEY NOT NULL,
+    user_id BLOB NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    seq INTEGER NOT NULL,
+    deleted_at TEXT NOT NULL
+);
+
+CREATE UNIQUE INDEX idx_todo_tombstones_user_id_seq ON todo_tombstones(user_id, seq);
@@ -0,0 +1,3 @@
+DROP TABLE IF EXISTS todo_shares;
+DROP INDEX IF EXISTS idx_todos_assignee_id;
+ALTER TABLE todos DROP COLUMN assignee_id;
@@ -0,0 +1,34 @@
+-- Discussion threads on todos. Every version of a comment's body is kept in
+-- todo_comment_revisions, the current one included.
+CREATE TABLE todo_comments (
+    id BLOB PRIMARY KEY,
+    todo_id BLOB NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    author_id BLOB NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    -- Markdown source; rendered to HTML when served.
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    -- NULL until the body is first edited.
+    edited_at TEXT
+);
+
+CREATE INDEX idx_todo_comments_todo_id ON todo_comments(todo_id, created_at);
+
+CREATE TABLE todo_comment_revisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    comment_id BLOB NOT NULL REFERENCES todo_comments(id) ON DELETE CASCADE,
+    body TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_todo_comment_revisions_comment_id ON todo_comment_revisions(comment_id, id);
+
+-- Users @mentioned in a comment; the mentions inbox.
+CREATE TABLE comment_mentions (
+    comment_id BLOB NOT NULL REFERENCES todo_comments(id) ON DELETE CASCADE,
+    user_id BLOB NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TEXT NOT NULL,
+    read_at TEXT,
+    PRIMARY KEY (comment_id, user_id)
+);
+
+CREATE INDEX idx_comment_mentions_user_id ON comment_mentions(user_id, created_at);
@@ -0,0 +1,3 @@
+ALTER TABLE todos DROP COLUMN recurrence;
+ALTER TABLE todos DROP COLUMN tags;
+ALTER TABLE todos DROP COLUMN priority;
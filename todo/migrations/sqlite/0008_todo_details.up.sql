@@ -0,0 +1,5 @@
+-- Priority (1 low, 2 medium, 3 high), tags as a JSON array and an
+-- iCalendar RRULE for todos that repeat.
+ALTER TABLE todos ADD COLUMN priority INTEGER CHECK (priority BETWEEN 1 AND 3);
+ALTER TABLE todos ADD COLUMN tags TEXT NOT NULL DEFAULT '[]';
+ALTER TABLE todos ADD COLUMN recurrence TEXT;
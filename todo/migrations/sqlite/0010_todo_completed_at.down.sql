@@ -0,0 +1,2 @@
+DROP INDEX IF EXISTS idx_todos_user_id_completed_at;
+ALTER TABLE todos DROP COLUMN completed_at;
@@ -0,0 +1,3 @@
+ALTER TABLE todos DROP COLUMN hidden_until;
+ALTER TABLE todos DROP COLUMN original_scheduled_for;
+ALTER TABLE todos DROP COLUMN snooze_count;
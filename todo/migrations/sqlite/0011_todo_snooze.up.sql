@@ -0,0 +1,5 @@
+-- Snoozing: how often each todo was snoozed, when it was scheduled before
+-- its first snooze, and until when it stays out of listings.
+ALTER TABLE todos ADD COLUMN snooze_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE todos ADD COLUMN original_scheduled_for TEXT;
+ALTER TABLE todos ADD COLUMN hidden_until TEXT;
@@ -0,0 +1,22 @@
+-- Time spent on todos, tracked with a timer or entered by hand. An entry
+-- without ended_at is a running timer; each user has at most one.
+CREATE TABLE time_entries (
+    id BLOB PRIMARY KEY,
+    todo_id BLOB NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+    -- Who spent the time.
+    user_id BLOB NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    started_at TEXT NOT NULL,
+    ended_at TEXT,
+    -- Whole seconds from started_at to ended_at, once stopped.
+    duration_seconds INTEGER,
+    note TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_time_entries_todo_id ON time_entries(todo_id, started_at);
+CREATE INDEX idx_time_entries_user_id ON time_entries(user_id, started_at);
+CREATE UNIQUE INDEX idx_time_entries_running ON time_entries(user_id) WHERE ended_at IS NULL;
+
+-- The total of each todo's stopped entries, kept up to date with them.
+ALTER TABLE todos ADD COLUMN tracked_seconds INTEGER NOT NULL DEFAULT 0;
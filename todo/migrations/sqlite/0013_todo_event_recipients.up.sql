@@ -0,0 +1,3 @@
+-- Who each event's todo was shared with when it changed, so grantees get
+-- it on their streams too.
+ALTER TABLE todo_events ADD COLUMN recipients TEXT NOT NULL DEFAULT '[]';
@@ -0,0 +1,119 @@
+//! Who may do what with a todo. Every route that reads or changes a todo by
+//! id, a share, a comment, an attachment or a time entry asks here first;
+//! the store methods behind them take no user and do not check.
+
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{Attachment, Comment, Role, Share, TimeEntry, Todo},
+    store::DynStore,
+};
+
+/// Something done to an existing todo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    View,
+    Comment,
+    /// Track time spent on it.
+    Track,
+    Edit,
+    Assign,
+    Delete,
+    Share,
+}
+
+impl Action {
+    /// The least role allowed to take the action.
+    pub fn required_role(self) -> Role {
+        match self {
+            Action::View | Action::Comment | Action::Track => Role::Viewer,
+            Action::Edit | Action::Assign => Role::Editor,
+            Action::Delete | Action::Share => Role::Owner,
+        }
+    }
+}
+
+/// Loads a todo the user may take `action` on, with their role.
+///
+/// Todos the user cannot see at all are reported as not found, so ids of
+/// other users' todos cannot be probed; those they can see but not act on
+/// are forbidden.
+pub async fn authorize(store: &DynStore, user_id: Uuid, todo_id: Uuid, action: Action) -> Result<(Todo, Role), AppError> {
+    let (todo, role) = store
+        .get_todo_access(todo_id, user_id)
+        .await?
+        .ok_or(AppError::NotFound("Todo not found".to_string()))?;
+    if role < action.required_role() {
+        return Err(AppError::Forbidden);
+    }
+
+    Ok((todo, role))
+}
+
+/// Whether the user can see the todo at all; used to check assignees.
+pub async fn can_view(store: &DynStore, user_id: Uuid, todo_id: Uuid) -> Result<bool, AppError> {
+    Ok(store.get_todo_access(todo_id, user_id).await?.is_some())
+}
+
+/// Only the owner changes a share; either side can remove it.
+pub fn authorize_share(share: &Share, user_id: Uuid, action: Action) -> Result<(), AppError> {
+    let allowed = match action {
+        Action::View | Action::Delete => share.owner_id == user_id || share.grantee_id == user_id,
+        Action::Comment | Action::Track | Action::Edit | Action::Assign | Action::Share => share.owner_id == user_id,
+    };
+    if allowed {
+        return Ok(());
+    }
+    if share.grantee_id == user_id {
+        return Err(AppError::Forbidden);
+    }
+    Err(AppError::NotFound("Share not found".to_string()))
+}
+
+/// Anyone who can see the todo reads its comments; only the author edits
+/// one, and the todo's owner can also delete it. `role` is the user's role
+/// on the comment's todo.
+pub fn authorize_comment(comment: &Comment, user_id: Uuid, role: Role, action: Action) -> Result<(), AppError> {
+    let allowed = match action {
+        Action::View | Action::Comment => true,
+        Action::Track | Action::Edit | Action::Assign | Action::Share => comment.author_id == user_id,
+        Action::Delete => comment.author_id == user_id || role == Role::Owner,
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden)
+    }
+}
+
+/// Anyone who can see the todo downloads its attachments; its uploader or
+/// the todo's owner can delete one. `role` is the user's role on the todo.
+pub fn authorize_attachment(attachment: &Attachment, user_id: Uuid, role: Role, action: Action) -> Result<(), AppError> {
+    let allowed = match action {
+        Action::View => true,
+        Action::Delete => attachment.user_id == user_id || role == Role::Owner,
+        Action::Comment | Action::Track | Action::Edit | Action::Assign | Action::Share => attachment.user_id == user_id,
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden)
+    }
+}
+
+/// Anyone who can see the todo sees the time tracked on it; only whoever
+/// tracked an entry edits it, and the todo's owner can also delete it.
+/// `role` is the user's role on the todo.
+pub fn authorize_time_entry(entry: &TimeEntry, user_id: Uuid, role: Role, action: Action) -> Result<(), AppError> {
+    let allowed = match action {
+        Action::View => true,
+        Action::Delete => entry.user_id == user_id || role == Role::Owner,
+        Action::Comment | Action::Track | Action::Edit | Action::Assign | Action::Share => entry.user_id == user_id,
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden)
+    }
+}
@@ -0,0 +1,249 @@
+//! Upload limits, download helpers and the cleanup of files left behind by
+//! deleted todos.
+
+use std::{ops::Range, sync::Arc, time::Duration};
+use tokio::time::sleep;
+use tracing::{debug, error, warn};
+use uuid::Uuid;
+
+use crate::{
+    blobs::{DynBlobStore, MemoryBlobStore},
+    shutdown::Shutdown,
+    store::DynStore,
+};
+
+/// Detached attachments deleted per round; more are taken straight away if full.
+const CLEANUP_BATCH_SIZE: i64 = 100;
+
+/// Longest filename kept, in characters.
+const MAX_FILENAME_LEN: usize = 255;
+
+/// What uploads are accepted and how often leftovers are cleaned up.
+#[derive(Debug, Clone)]
+pub struct AttachmentOptions {
+    /// Largest file accepted, in bytes.
+    pub max_size: u64,
+    /// MIME types accepted; `type/*` accepts a whole type.
+    pub allowed_types: Vec<String>,
+    /// Total bytes each user may have attached.
+    pub quota: u64,
+    pub cleanup_interval: Duration,
+}
+
+impl Default for AttachmentOptions {
+    fn default() -> Self {
+        Self {
+            max_size: 10 * 1024 * 1024,
+            allowed_types: ["image/png", "image/jpeg", "image/gif", "image/webp", "application/pdf", "text/plain"]
+                .map(String::from)
+                .to_vec(),
+            quota: 100 * 1024 * 1024,
+            cleanup_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl AttachmentOptions {
+    /// Whether `media_type`, without parameters and lowercase, may be uploaded.
+    pub fn allows(&self, media_type: &str) -> bool {
+        self.allowed_types.iter().any(|allowed| match allowed.strip_suffix("/*") {
+            Some(kind) => media_type.split_once('/').is_some_and(|(k, _)| k.eq_ignore_ascii_case(kind)),
+            None => allowed.eq_ignore_ascii_case(media_type),
+        })
+    }
+}
+
+/// Handle shared by the handlers and the cleanup task.
+#[derive(Clone)]
+pub struct Attachments {
+    blobs: DynBlobStore,
+    options: Arc<AttachmentOptions>,
+}
+
+impl Default for Attachments {
+    fn default() -> Self {
+        Self::new(Arc::new(MemoryBlobStore::default()), AttachmentOptions::default())
+    }
+}
+
+impl Attachments {
+    pub fn new(blobs: DynBlobStore, options: AttachmentOptions) -> Self {
+        Self {
+            blobs,
+            options: Arc::new(options),
+        }
+    }
+
+    pub fn blobs(&self) -> &DynBlobStore {
+        &self.blobs
+    }
+
+    pub fn options(&self) -> &AttachmentOptions {
+        &self.options
+    }
+}
+
+/// Where an attachment's bytes are stored.
+pub fn blob_key(attachment_id: Uuid) -> String {
+    format!("attachments/{}", attachment_id.simple())
+}
+
+/// The media type of a `Content-Type` value: lowercase, without parameters.
+pub fn media_type(content_type: &str) -> String {
+    content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
+
+/// Whether `data` starts the way files of `media_type` do. Types without a
+/// known signature always match.
+pub fn matches_signature(media_type: &str, data: &[u8]) -> bool {
+    match media_type {
+        "image/png" => data.starts_with(b"\x89PNG\r\n\x1a\n"),
+        "image/jpeg" => data.starts_with(b"\xff\xd8\xff"),
+        "image/gif" => data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a"),
+        "image/webp" => data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP",
+        "application/pdf" => data.starts_with(b"%PDF-"),
+        _ => true,
+    }
+}
+
+/// The name an uploaded file is kept under: its last path component,
+/// without control characters, quotes or backslashes, and at most 255
+/// characters.
+pub fn sanitize_filename(name: &str) -> String {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .filter(|c| !c.is_control() && *c != '"')
+        .take(MAX_FILENAME_LEN)
+        .collect();
+    let name = name.trim();
+
+    if name.is_empty() || name == "." || name == ".." {
+        "attachment".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// A `Content-Disposition` value that makes browsers download the file
+/// under `filename`, with an ASCII fallback for old clients.
+pub fn content_disposition(filename: &str) -> String {
+    let fallback: String = filename
+        .chars()
+        .map(|c| if c.is_ascii() && !c.is_ascii_control() { c } else { '_' })
+        .collect();
+    let encoded: String = filename
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'!' | b'#' | b'$' | b'&' | b'+' | b'-' | b'.' | b'^' | b'_'
+            | b'`' | b'|' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect();
+
+    format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", fallback, encoded)
+}
+
+/// What a `Range` header asks of a blob.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RangeRequest {
+    /// No usable range: send the whole blob.
+    Full,
+    /// Send these bytes.
+    Partial(Range<u64>),
+    /// The range lies outside the blob.
+    Unsatisfiable,
+}
+
+/// Reads a `Range` header against a blob of `size` bytes. Only single
+/// `bytes` ranges are served; anything else gets the whole blob, as the
+/// header may be ignored.
+pub fn parse_range(header: Option<&str>, size: u64) -> RangeRequest {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return RangeRequest::Full;
+    };
+    if spec.contains(',') {
+        return RangeRequest::Full;
+    }
+    let Some((start, end)) = spec.split_once('-') else {
+        return RangeRequest::Full;
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        // The last `end` bytes
+        match end.parse::<u64>() {
+            Ok(0) => return RangeRequest::Unsatisfiable,
+            Ok(suffix) => size.saturating_sub(suffix)..size,
+            Err(_) => return RangeRequest::Full,
+        }
+    } else {
+        let Ok(start) = start.parse::<u64>() else {
+            return RangeRequest::Full;
+        };
+        let end = if end.is_empty() {
+            size
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => end.saturating_add(1).min(size),
+                _ => return RangeRequest::Full,
+            }
+        };
+        start..end
+    };
+
+    if range.start >= size {
+        RangeRequest::Unsatisfiable
+    } else {
+        RangeRequest::Partial(range)
+    }
+}
+
+/// Deletes attachments detached from their todo, files first, every
+/// `cleanup_interval`. Instances sharing a database may both try one;
+/// deleting a blob twice is harmless.
+pub fn spawn_cleanup(store: DynStore, attachments: Attachments, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        loop {
+            cleanup(&store, attachments.blobs()).await;
+
+            tokio::select! {
+                _ = sleep(attachments.options().cleanup_interval) => {}
+                _ = shutdown.requested() => return,
+            }
+        }
+    });
+}
+
+async fn cleanup(store: &DynStore, blobs: &DynBlobStore) {
+    loop {
+        let orphaned = match store.get_orphaned_attachments(CLEANUP_BATCH_SIZE).await {
+            Ok(orphaned) => orphaned,
+            Err(e) => {
+                error!("Failed to list detached attachments: {}", e);
+                return;
+            }
+        };
+
+        let mut purged = 0;
+        for attachment in &orphaned {
+            // The record goes last, so a file is never left without one
+            if let Err(e) = blobs.delete(&attachment.blob_key).await {
+                warn!("Failed to delete blob {}: {}", attachment.blob_key, e);
+                continue;
+            }
+            match store.purge_attachment(attachment.id).await {
+                Ok(_) => purged += 1,
+                Err(e) => warn!("Failed to purge attachment {}: {}", attachment.id, e),
+            }
+        }
+        if purged > 0 {
+            debug!("Deleted {} detached attachments", purged);
+        }
+
+        // Stop on failures rather than retrying the same ones straight away
+        if orphaned.len() < CLEANUP_BATCH_SIZE as usize || purged < orphaned.len() {
+            return;
+        }
+    }
+}
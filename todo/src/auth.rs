@@ -1,32 +1,33 @@
 use axum::{
     async_trait,
-    extract::{FromRequestParts, State},
-    http::{header::AUTHORIZATION, request::Parts, StatusCode},
-    RequestPartsExt,
+    extract::{FromRef, FromRequestParts},
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap},
 };
+use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
-use std::env;
 use uuid::Uuid;
 
 use crate::{
-    database::Database,
     error::AppError,
     models::{Claims, User},
+    store::DynStore,
+    telemetry,
 };
 
+/// Issues and verifies tokens signed with the configured `auth.jwt_secret`.
+#[derive(Clone)]
 pub struct JwtService {
     encoding_key: EncodingKey,
     decoding_key: DecodingKey,
+    token_ttl: Duration,
 }
 
 impl JwtService {
-    pub fn new() -> Self {
-        let secret = env::var("JWT_SECRET")
-            .unwrap_or_else(|_| "your-super-secret-jwt-key-change-in-production".to_string());
-        
+    pub fn new(secret: &str, token_ttl: Duration) -> Self {
         Self {
             encoding_key: EncodingKey::from_secret(secret.as_ref()),
             decoding_key: DecodingKey::from_secret(secret.as_ref()),
+            token_ttl,
         }
     }
 
@@ -34,7 +35,7 @@ impl JwtService {
         let claims = Claims {
             sub: user.id.to_string(),
             username: user.username.clone(),
-            exp: (chrono::Utc::now() + chrono::Duration::hours(24)).timestamp() as usize,
+            exp: (Utc::now() + self.token_ttl).timestamp() as usize,
         };
 
         encode(&Header::default(), &claims, &self.encoding_key)
@@ -55,37 +56,30 @@ pub struct AuthenticatedUser {
 #[async_trait]
 impl<S> FromRequestParts<S> for AuthenticatedUser
 where
-    Database: FromRef<S>,
+    DynStore: FromRef<S>,
+    JwtService: FromRef<S>,
     S: Send + Sync,
 {
     type Rejection = AppError;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        // Extract the authorization header
-        let auth_header = parts
-            .headers
-            .get(AUTHORIZATION)
-            .and_then(|header| header.to_str().ok())
-            .ok_or(AppError::Unauthorized)?;
-
-        // Check if it starts with "Bearer "
-        let token = auth_header
-            .strip_prefix("Bearer ")
-            .ok_or(AppError::Unauthorized)?;
-
-        // Verify the token
-        let jwt_service = JwtService::new();
-        let claims = jwt_service.verify_token(token)?;
-
-        // Parse user ID from claims
+        let token = bearer_token(&parts.headers).ok_or(AppError::Unauthorized)?;
+        let claims = JwtService::from_ref(state).verify_token(token)?;
+        let user = AuthenticatedUser::from_claims(&claims, &DynStore::from_ref(state)).await?;
+
+        telemetry::record_user(user.user.id);
+
+        Ok(user)
+    }
+}
+
+impl AuthenticatedUser {
+    /// Looks up the user a verified token was issued to.
+    pub async fn from_claims(claims: &Claims, store: &DynStore) -> Result<Self, AppError> {
         let user_id = Uuid::parse_str(&claims.sub)
             .map_err(|_| AppError::Auth("Invalid user ID in token".to_string()))?;
 
-        // Get database from state
-        let db = Database::from_ref(state);
-
-        // Fetch user from database
-        let user = db
+        let user = store
             .get_user_by_id(user_id)
             .await?
             .ok_or(AppError::Auth("User not found".to_string()))?;
@@ -94,15 +88,12 @@ where
     }
 }
 
-// Helper trait to extract Database from state
-pub trait FromRef<T> {
-    fn from_ref(input: &T) -> Self;
-}
-
-impl FromRef<Database> for Database {
-    fn from_ref(input: &Database) -> Self {
-        input.clone()
-    }
+/// The token of an `Authorization: Bearer <token>` header.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
 }
 
 pub fn hash_password(password: &str) -> Result<String, AppError> {
@@ -0,0 +1,97 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::{
+    io::{ErrorKind, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use crate::{
+    blobs::{self, BlobStore, BlobStream},
+    error::AppError,
+};
+
+/// Blobs as files under a directory, one per key; `/` in keys makes
+/// subdirectories.
+pub struct LocalBlobStore {
+    root: PathBuf,
+}
+
+impl LocalBlobStore {
+    /// Uses `root`, creating it if missing.
+    pub async fn new(root: &Path) -> Result<Self, AppError> {
+        fs::create_dir_all(root)
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to create {}: {}", root.display(), e)))?;
+        Ok(Self { root: root.to_path_buf() })
+    }
+
+    /// The file for `key`. Keys are made by the service, but are checked
+    /// anyway so none can point outside the directory.
+    fn path(&self, key: &str) -> Result<PathBuf, AppError> {
+        let valid = !key.is_empty()
+            && key.split('/').all(|segment| {
+                !segment.is_empty()
+                    && !segment.starts_with('.')
+                    && segment.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            });
+        if !valid {
+            return Err(AppError::Internal(format!("Invalid blob key '{}'", key)));
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+fn io_error(action: &str, path: &Path, err: std::io::Error) -> AppError {
+    AppError::Internal(format!("Failed to {} {}: {}", action, path.display(), err))
+}
+
+#[async_trait]
+impl BlobStore for LocalBlobStore {
+    async fn put(&self, key: &str, data: Bytes, _content_type: &str) -> Result<(), AppError> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await.map_err(|e| io_error("create", parent, e))?;
+        }
+
+        // Written aside and renamed into place, so readers never see part of a file
+        let partial = path.with_extension(format!("{}.partial", Uuid::new_v4().simple()));
+        if let Err(e) = fs::write(&partial, &data).await {
+            fs::remove_file(&partial).await.ok();
+            return Err(io_error("write", &partial, e));
+        }
+        fs::rename(&partial, &path).await.map_err(|e| io_error("rename", &partial, e))
+    }
+
+    async fn get(&self, key: &str, range: Option<Range<u64>>) -> Result<BlobStream, AppError> {
+        let path = self.path(key)?;
+        let mut file = match fs::File::open(&path).await {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Err(blobs::not_found()),
+            Err(e) => return Err(io_error("open", &path, e)),
+        };
+
+        Ok(match range {
+            Some(range) => {
+                file.seek(SeekFrom::Start(range.start)).await.map_err(|e| io_error("read", &path, e))?;
+                Box::pin(ReaderStream::new(file.take(range.end - range.start)))
+            }
+            None => Box::pin(ReaderStream::new(file)),
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        let path = self.path(key)?;
+        match fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(io_error("delete", &path, e)),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::{
+    collections::HashMap,
+    ops::Range,
+    sync::RwLock,
+};
+
+use crate::{
+    blobs::{self, BlobStore, BlobStream},
+    error::AppError,
+};
+
+/// Process-local blobs for tests and throwaway local runs. Nothing survives
+/// a restart.
+#[derive(Default)]
+pub struct MemoryBlobStore {
+    blobs: RwLock<HashMap<String, Bytes>>,
+}
+
+impl MemoryBlobStore {
+    pub fn contains(&self, key: &str) -> bool {
+        self.blobs.read().is_ok_and(|blobs| blobs.contains_key(key))
+    }
+}
+
+fn poisoned() -> AppError {
+    AppError::Internal("Memory blob store lock poisoned".to_string())
+}
+
+#[async_trait]
+impl BlobStore for MemoryBlobStore {
+    async fn put(&self, key: &str, data: Bytes, _content_type: &str) -> Result<(), AppError> {
+        self.blobs.write().map_err(|_| poisoned())?.insert(key.to_string(), data);
+        Ok(())
+    }
+
+    async fn get(&self, key: &str, range: Option<Range<u64>>) -> Result<BlobStream, AppError> {
+        let data = self.blobs.read().map_err(|_| poisoned())?.get(key).cloned().ok_or_else(blobs::not_found)?;
+        let data = match range {
+            Some(range) => data.slice(range.start as usize..range.end as usize),
+            None => data,
+        };
+
+        Ok(Box::pin(tokio_stream::once(Ok(data))))
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        self.blobs.write().map_err(|_| poisoned())?.remove(key);
+        Ok(())
+    }
+}
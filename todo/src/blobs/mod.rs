@@ -0,0 +1,51 @@
+//! Where attachment files are kept. The records describing them are in the
+//! [`crate::store::AttachmentStore`]; the bytes are here, under keys the
+//! service picks.
+
+pub mod local;
+pub mod memory;
+pub mod s3;
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use std::{io, ops::Range, pin::Pin, sync::Arc};
+use tokio_stream::Stream;
+
+use crate::{
+    config::{AttachmentsConfig, BlobBackend},
+    error::AppError,
+};
+
+pub use local::LocalBlobStore;
+pub use memory::MemoryBlobStore;
+pub use s3::S3BlobStore;
+
+/// Shared handle to whichever backend the service was started with.
+pub type DynBlobStore = Arc<dyn BlobStore>;
+
+/// A blob's bytes, read as they are sent on.
+pub type BlobStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+#[async_trait]
+pub trait BlobStore: Send + Sync {
+    /// Stores `data` under `key`, replacing any blob there.
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<(), AppError>;
+    /// Streams the blob, or the bytes of it in `range`, which must lie
+    /// within it.
+    async fn get(&self, key: &str, range: Option<Range<u64>>) -> Result<BlobStream, AppError>;
+    /// Deletes the blob; deleting one that is not there succeeds.
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+}
+
+/// Opens the backend selected by `config.backend`.
+pub async fn connect(config: &AttachmentsConfig) -> Result<DynBlobStore, AppError> {
+    Ok(match config.backend {
+        BlobBackend::Local => Arc::new(LocalBlobStore::new(&config.dir).await?),
+        BlobBackend::S3 => Arc::new(S3BlobStore::new(&config.s3)?),
+        BlobBackend::Memory => Arc::new(MemoryBlobStore::default()),
+    })
+}
+
+pub(crate) fn not_found() -> AppError {
+    AppError::NotFound("Blob not found".to_string())
+}
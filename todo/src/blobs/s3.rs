@@ -0,0 +1,232 @@
+//! A client for the few S3 operations blobs need, signing requests with AWS
+//! Signature Version 4 itself rather than pulling in an SDK.
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{header, Client, Method, Response, StatusCode};
+use sha2::{Digest, Sha256};
+use std::{io, ops::Range, time::Duration};
+use tokio_stream::StreamExt;
+use url::Url;
+
+use crate::{
+    blobs::{self, BlobStore, BlobStream},
+    config::S3Config,
+    error::AppError,
+};
+
+/// Hash of an empty payload, sent with requests that have no body.
+const EMPTY_PAYLOAD_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// Who requests are signed as, and for which region.
+#[derive(Debug, Clone)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub region: String,
+}
+
+/// Blobs as objects in an S3-compatible bucket.
+pub struct S3BlobStore {
+    client: Client,
+    endpoint: Url,
+    bucket: String,
+    path_style: bool,
+    credentials: Credentials,
+}
+
+impl S3BlobStore {
+    pub fn new(config: &S3Config) -> Result<Self, AppError> {
+        let endpoint = Url::parse(&config.endpoint)
+            .map_err(|e| AppError::Internal(format!("Invalid S3 endpoint '{}': {}", config.endpoint, e)))?;
+        let client = Client::builder()
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build S3 client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            endpoint,
+            bucket: config.bucket.clone(),
+            path_style: config.path_style,
+            credentials: Credentials {
+                access_key_id: config.access_key_id.clone(),
+                secret_access_key: config.secret_access_key.clone(),
+                region: config.region.clone(),
+            },
+        })
+    }
+
+    fn object_url(&self, key: &str) -> Result<Url, AppError> {
+        let key = key.split('/').map(uri_encode).collect::<Vec<_>>().join("/");
+        let base = self.endpoint.as_str().trim_end_matches('/');
+        let url = if self.path_style {
+            format!("{}/{}/{}", base, uri_encode(&self.bucket), key)
+        } else {
+            let (scheme, host) = base.split_once("://").unwrap_or(("https", base));
+            format!("{}://{}.{}/{}", scheme, self.bucket, host, key)
+        };
+        Url::parse(&url).map_err(|e| AppError::Internal(format!("Invalid S3 object URL '{}': {}", url, e)))
+    }
+
+    /// Sends a signed request for the object under `key`.
+    async fn send(
+        &self,
+        method: Method,
+        key: &str,
+        body: Option<Bytes>,
+        headers: &[(&str, String)],
+    ) -> Result<Response, AppError> {
+        let url = self.object_url(key)?;
+        let host = match url.port() {
+            Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+            None => url.host_str().unwrap_or_default().to_string(),
+        };
+        let payload_hash = match &body {
+            Some(body) => hex::encode(Sha256::digest(body)),
+            None => EMPTY_PAYLOAD_HASH.to_string(),
+        };
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut signed: Vec<(&str, &str)> = vec![
+            ("host", &host),
+            ("x-amz-content-sha256", &payload_hash),
+            ("x-amz-date", &amz_date),
+        ];
+        signed.extend(headers.iter().map(|(name, value)| (*name, value.as_str())));
+        let authorization = authorization(&self.credentials, method.as_str(), url.path(), &signed, &payload_hash, now);
+
+        let mut request = self
+            .client
+            .request(method.clone(), url)
+            .header("x-amz-content-sha256", &payload_hash)
+            .header("x-amz-date", &amz_date)
+            .header(header::AUTHORIZATION, authorization);
+        for (name, value) in headers {
+            request = request.header(*name, value);
+        }
+        if let Some(body) = body {
+            request = request.body(body);
+        }
+
+        request
+            .send()
+            .await
+            .map_err(|e| AppError::Internal(format!("S3 {} of '{}' failed: {}", method, key, e)))
+    }
+}
+
+/// An error for a response S3 answered with an unexpected status.
+async fn unexpected(method: &str, key: &str, response: Response) -> AppError {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    AppError::Internal(format!(
+        "S3 {} of '{}' returned {}: {}",
+        method,
+        key,
+        status,
+        body.chars().take(500).collect::<String>()
+    ))
+}
+
+#[async_trait]
+impl BlobStore for S3BlobStore {
+    async fn put(&self, key: &str, data: Bytes, content_type: &str) -> Result<(), AppError> {
+        let headers = [("content-type", content_type.to_string())];
+        let response = self.send(Method::PUT, key, Some(data), &headers).await?;
+        if !response.status().is_success() {
+            return Err(unexpected("PUT", key, response).await);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str, range: Option<Range<u64>>) -> Result<BlobStream, AppError> {
+        let headers: Vec<(&str, String)> = range
+            .map(|range| ("range", format!("bytes={}-{}", range.start, range.end - 1)))
+            .into_iter()
+            .collect();
+        let response = self.send(Method::GET, key, None, &headers).await?;
+        match response.status() {
+            status if status.is_success() => {
+                Ok(Box::pin(response.bytes_stream().map(|chunk| chunk.map_err(io::Error::other))))
+            }
+            StatusCode::NOT_FOUND => Err(blobs::not_found()),
+            _ => Err(unexpected("GET", key, response).await),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        let response = self.send(Method::DELETE, key, None, &[]).await?;
+        match response.status() {
+            status if status.is_success() || status == StatusCode::NOT_FOUND => Ok(()),
+            _ => Err(unexpected("DELETE", key, response).await),
+        }
+    }
+}
+
+/// The `Authorization` header signing an S3 request without a query string.
+///
+/// `path` is the URI-encoded object path, `headers` the headers to sign with
+/// lowercase names (at least `host`, `x-amz-date` and
+/// `x-amz-content-sha256`), and `payload_hash` the hex SHA-256 of the body.
+pub fn authorization(
+    credentials: &Credentials,
+    method: &str,
+    path: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+    at: DateTime<Utc>,
+) -> String {
+    let mut headers: Vec<(&str, &str)> = headers.to_vec();
+    headers.sort_by_key(|(name, _)| *name);
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_request = format!(
+        "{}\n{}\n\n{}\n{}\n{}",
+        method, path, canonical_headers, signed_headers, payload_hash
+    );
+
+    let date = at.format("%Y%m%d").to_string();
+    let scope = format!("{}/{}/s3/aws4_request", date, credentials.region);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        at.format("%Y%m%dT%H%M%SZ"),
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let key = hmac(format!("AWS4{}", credentials.secret_access_key).as_bytes(), date.as_bytes());
+    let key = hmac(&key, credentials.region.as_bytes());
+    let key = hmac(&key, b"s3");
+    let key = hmac(&key, b"aws4_request");
+    let signature = hex::encode(hmac(&key, string_to_sign.as_bytes()));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{},SignedHeaders={},Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes everything but unreserved characters, as S3 expects of
+/// each path segment.
+fn uri_encode(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
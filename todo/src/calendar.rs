@@ -0,0 +1,105 @@
+//! Days as users see them: the time zone and locale they set, where their
+//! days start and end, and when snoozed todos come back.
+
+use chrono::{DateTime, Datelike, Days, Duration, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::models::SnoozePreset;
+
+/// The time zone of users who have not set one.
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+/// The locale of users who have not set one.
+pub const DEFAULT_LOCALE: &str = "en-US";
+/// How far [`SnoozePreset::LaterToday`] pushes a todo out.
+const LATER_TODAY_HOURS: i64 = 3;
+
+/// The time zone with the IANA name `name`, such as `Europe/Paris`.
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    name.parse().ok()
+}
+
+/// `tag` with its parts cased the usual way, if it is a BCP 47 language
+/// tag of a language, an optional script and an optional region, like
+/// `en`, `pt-BR`, `zh-Hant-TW` or `es-419`.
+pub fn normalize_locale(tag: &str) -> Option<String> {
+    let mut parts = tag.split(['-', '_']);
+    let language = parts.next().filter(|part| (2..=3).contains(&part.len()) && is_alphabetic(part))?;
+    let mut normalized = language.to_ascii_lowercase();
+
+    let mut rest = parts.peekable();
+    if let Some(script) = rest.next_if(|part| part.len() == 4 && is_alphabetic(part)) {
+        normalized.push('-');
+        normalized.push_str(&script[..1].to_ascii_uppercase());
+        normalized.push_str(&script[1..].to_ascii_lowercase());
+    }
+    if let Some(region) = rest.next() {
+        let letters = region.len() == 2 && is_alphabetic(region);
+        let digits = region.len() == 3 && region.bytes().all(|b| b.is_ascii_digit());
+        if !letters && !digits {
+            return None;
+        }
+        normalized.push('-');
+        normalized.push_str(&region.to_ascii_uppercase());
+    }
+    if rest.next().is_some() {
+        return None;
+    }
+
+    Some(normalized)
+}
+
+fn is_alphabetic(part: &str) -> bool {
+    part.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+/// The day it is at `now` in `tz`.
+pub fn today(tz: Tz, now: DateTime<Utc>) -> NaiveDate {
+    now.with_timezone(&tz).date_naive()
+}
+
+/// The instant `local` names in `tz`. Of a time that happens twice as
+/// clocks go back, the first is taken; a time skipped as they go forward
+/// is moved on by the hour that was skipped.
+pub fn local_to_utc(tz: Tz, local: NaiveDateTime) -> DateTime<Utc> {
+    let mut local = local;
+    // A day at most is ever skipped
+    for _ in 0..24 {
+        match tz.from_local_datetime(&local) {
+            LocalResult::Single(at) | LocalResult::Ambiguous(at, _) => return at.with_timezone(&Utc),
+            LocalResult::None => local += chrono::Duration::hours(1),
+        }
+    }
+    Utc.from_utc_datetime(&local)
+}
+
+/// The first instant of `date` in `tz` and that of the day after. Days
+/// clocks change on are 23 or 25 hours long.
+pub fn day_bounds(tz: Tz, date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let start = local_to_utc(tz, date.and_time(NaiveTime::MIN));
+    let end = local_to_utc(tz, (date + Days::new(1)).and_time(NaiveTime::MIN));
+    (start, end)
+}
+
+/// When a todo with a date but no time is due.
+pub fn end_of_day() -> NaiveTime {
+    NaiveTime::from_hms_opt(23, 59, 0).unwrap_or_default()
+}
+
+/// When morning snoozes resurface a todo.
+pub fn morning() -> NaiveTime {
+    NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default()
+}
+
+/// When a todo snoozed with `preset` at `now` by someone in `tz`
+/// resurfaces.
+pub fn snooze_until(preset: SnoozePreset, tz: Tz, now: DateTime<Utc>) -> DateTime<Utc> {
+    let today = today(tz, now);
+    match preset {
+        SnoozePreset::LaterToday => now + Duration::hours(LATER_TODAY_HOURS),
+        SnoozePreset::TomorrowMorning => local_to_utc(tz, (today + Days::new(1)).and_time(morning())),
+        SnoozePreset::NextWeek => {
+            let monday = today + Days::new((7 - today.weekday().num_days_from_monday()).into());
+            local_to_utc(tz, monday.and_time(morning()))
+        }
+    }
+}
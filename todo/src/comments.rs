@@ -0,0 +1,64 @@
+//! Markdown rendering and `@username` mentions for todo comments.
+
+use pulldown_cmark::{html, Event, Options, Parser};
+
+/// Mentions honoured per comment; further names are ignored.
+pub const MAX_MENTIONS: usize = 20;
+
+fn options() -> Options {
+    Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TABLES | Options::ENABLE_TASKLISTS
+}
+
+/// Renders a comment body to HTML safe to insert into a page: raw HTML is
+/// stripped and links get `rel="noopener noreferrer nofollow"`.
+pub fn render_markdown(body: &str) -> String {
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, Parser::new_ext(body, options()));
+
+    ammonia::Builder::default()
+        .link_rel(Some("noopener noreferrer nofollow"))
+        .clean(&unsafe_html)
+        .to_string()
+}
+
+/// Usernames mentioned as `@username` in a comment body, in order of first
+/// mention and at most [`MAX_MENTIONS`]. Mentions inside code spans and
+/// blocks, and the `@` of email addresses, do not count.
+pub fn mentions(body: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut text = String::new();
+    // Text can arrive in several events; scan each run of it whole
+    for event in Parser::new_ext(body, options()) {
+        match event {
+            Event::Text(chunk) => text.push_str(&chunk),
+            _ => {
+                scan(&text, &mut names);
+                text.clear();
+            }
+        }
+    }
+    scan(&text, &mut names);
+
+    names.truncate(MAX_MENTIONS);
+    names
+}
+
+fn scan(text: &str, names: &mut Vec<String>) {
+    let mut previous = None;
+    for (i, c) in text.char_indices() {
+        if c == '@' && !previous.is_some_and(is_name_char) {
+            let rest = &text[i + 1..];
+            let end = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+            // A mention can end a sentence
+            let name = rest[..end].trim_end_matches(['.', '-']);
+            if (3..=50).contains(&name.chars().count()) && !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+        previous = Some(c);
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.')
+}
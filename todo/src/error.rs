@@ -1,70 +1,294 @@
 use axum::{
-    http::StatusCode,
+    extract::{
+        multipart::{MultipartError, MultipartRejection},
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+    },
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
-use serde_json::json;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use thiserror::Error;
+use utoipa::ToSchema;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::request_id;
 
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
 
+    #[error(transparent)]
+    Migrate(#[from] sqlx::migrate::MigrateError),
+
     #[error("Database error: {0}")]
     Database(String),
-    
+
     #[error("Authentication error: {0}")]
     Auth(String),
-    
+
     #[error("Validation error: {0}")]
-    Validation(String),
-    
+    Validation(#[from] ValidationErrors),
+
     #[error("Not found: {0}")]
     NotFound(String),
-    
+
     #[error("Internal server error: {0}")]
     Internal(String),
-    
+
     #[error("Bad request: {0}")]
     BadRequest(String),
-    
+
     #[error("Unauthorized")]
     Unauthorized,
-    
+
     #[error("Forbidden")]
     Forbidden,
+
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
+impl AppError {
+    /// A validation failure on a single field that the `validator` derive
+    /// cannot express, e.g. a rule that depends on the current time.
+    pub fn invalid_field(field: &'static str, code: &'static str, message: &'static str) -> Self {
+        let mut errors = ValidationErrors::new();
+        errors.add(field, ValidationError::new(code).with_message(message.into()));
+        AppError::Validation(errors)
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Sqlx(_) | AppError::Migrate(_) | AppError::Database(_) | AppError::Internal(_) => {
+                ErrorCode::InternalError
+            }
+            AppError::Auth(_) | AppError::Unauthorized => ErrorCode::Unauthorized,
+            AppError::Validation(_) => ErrorCode::ValidationFailed,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::BadRequest(_) => ErrorCode::BadRequest,
+            AppError::Forbidden => ErrorCode::Forbidden,
+            AppError::PayloadTooLarge(_) => ErrorCode::PayloadTooLarge,
+            AppError::UnsupportedMediaType(_) => ErrorCode::UnsupportedMediaType,
+            AppError::QuotaExceeded(_) => ErrorCode::QuotaExceeded,
+        }
+    }
+}
+
+/// Stable, machine-readable error identifier carried in every problem body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    ValidationFailed,
+    BadRequest,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    PayloadTooLarge,
+    UnsupportedMediaType,
+    /// The upload would take the user over their storage quota.
+    QuotaExceeded,
+    InternalError,
+}
+
+impl ErrorCode {
+    pub fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::ValidationFailed | ErrorCode::BadRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::PayloadTooLarge | ErrorCode::QuotaExceeded => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ErrorCode::InternalError => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// RFC 7807 problem details, sent as `application/problem+json`.
+///
+/// `type` is always `about:blank`, so `title` is the HTTP status phrase;
+/// clients should branch on `code` and show `detail` to humans.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    #[schema(example = "about:blank")]
+    pub problem_type: &'static str,
+    #[schema(example = "Not Found")]
+    pub title: String,
+    #[schema(example = 404)]
+    pub status: u16,
+    #[schema(example = "Todo not found")]
+    pub detail: String,
+    pub code: ErrorCode,
+    /// Matches the `X-Request-Id` response header.
+    pub request_id: Option<String>,
+    /// Present for `validation_failed`: one entry per violated constraint.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FieldError {
+    #[schema(example = "title")]
+    pub field: String,
+    /// The violated constraint, e.g. `length`, `email` or `not_in_past`.
+    #[schema(example = "length")]
+    pub code: String,
+    #[schema(example = "must be between 1 and 255 characters")]
+    pub message: String,
+    /// Constraint parameters such as `min` and `max`.
+    #[schema(value_type = Object)]
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, Value>,
+}
+
+impl AppError {
+    /// The problem details body for this error. Server-side failures are
+    /// logged here and described only generically.
+    pub fn problem(&self) -> ProblemDetails {
+        let code = self.code();
+        let detail = match *self {
             AppError::Sqlx(ref err) => {
                 tracing::error!("SQLx error: {:?}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+                "Internal server error".to_string()
+            }
+            AppError::Migrate(ref err) => {
+                tracing::error!("Migration error: {:?}", err);
+                "Internal server error".to_string()
             }
             AppError::Database(ref message) => {
                 tracing::error!("Database error: {}", message);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+                "Internal server error".to_string()
             }
-            AppError::Auth(ref message) => (StatusCode::UNAUTHORIZED, message.as_str()),
-            AppError::Validation(ref message) => (StatusCode::BAD_REQUEST, message.as_str()),
-            AppError::NotFound(ref message) => (StatusCode::NOT_FOUND, message.as_str()),
             AppError::Internal(ref message) => {
                 tracing::error!("Internal error: {}", message);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error")
+                "Internal server error".to_string()
             }
-            AppError::BadRequest(ref message) => (StatusCode::BAD_REQUEST, message.as_str()),
-            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized"),
-            AppError::Forbidden => (StatusCode::FORBIDDEN, "Forbidden"),
+            AppError::Validation(_) => "Request validation failed".to_string(),
+            AppError::Auth(ref message)
+            | AppError::NotFound(ref message)
+            | AppError::BadRequest(ref message)
+            | AppError::PayloadTooLarge(ref message)
+            | AppError::UnsupportedMediaType(ref message)
+            | AppError::QuotaExceeded(ref message) => message.clone(),
+            AppError::Unauthorized => "Unauthorized".to_string(),
+            AppError::Forbidden => "Forbidden".to_string(),
+        };
+
+        let errors = match *self {
+            AppError::Validation(ref errors) => field_errors(errors),
+            _ => Vec::new(),
         };
 
-        let body = Json(json!({
-            "error": error_message,
-        }));
+        let status = code.status();
+        ProblemDetails {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail,
+            code,
+            request_id: request_id::current(),
+            errors,
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let body = self.problem();
+        let mut response = (self.code().status(), Json(body)).into_response();
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        response
+    }
+}
+
+fn field_errors(errors: &ValidationErrors) -> Vec<FieldError> {
+    let mut fields: Vec<_> = errors.field_errors().into_iter().collect();
+    fields.sort_by_key(|(field, _)| *field);
+
+    fields
+        .into_iter()
+        .flat_map(|(field, errors)| {
+            errors.iter().map(move |error| FieldError {
+                field: field.to_string(),
+                code: error.code.to_string(),
+                message: error
+                    .message
+                    .as_ref()
+                    .map(|m| m.to_string())
+                    .unwrap_or_else(|| describe(error)),
+                // The rejected input is deliberately not echoed back;
+                // it may be a password.
+                params: error
+                    .params
+                    .iter()
+                    .filter(|(name, _)| *name != "value")
+                    .map(|(name, value)| (name.to_string(), value.clone()))
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+fn describe(error: &ValidationError) -> String {
+    let param = |name: &str| error.params.get(name).map(|v| v.to_string());
+    match error.code.as_ref() {
+        "length" => match (param("min"), param("max")) {
+            (Some(min), Some(max)) => format!("must be between {} and {} characters", min, max),
+            (Some(min), None) => format!("must be at least {} characters", min),
+            (None, Some(max)) => format!("must be at most {} characters", max),
+            (None, None) => "has an invalid length".to_string(),
+        },
+        "email" => "must be a valid email address".to_string(),
+        code => format!("failed the `{}` check", code),
+    }
+}
+
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        AppError::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for AppError {
+    fn from(rejection: PathRejection) -> Self {
+        AppError::BadRequest(rejection.body_text())
+    }
+}
+
+impl From<MultipartRejection> for AppError {
+    fn from(rejection: MultipartRejection) -> Self {
+        AppError::BadRequest(rejection.body_text())
+    }
+}
 
-        (status, body).into_response()
+impl From<MultipartError> for AppError {
+    fn from(err: MultipartError) -> Self {
+        if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            AppError::PayloadTooLarge(err.body_text())
+        } else {
+            AppError::BadRequest(err.body_text())
+        }
     }
 }
 
-pub type Result<T> = std::result::Result<T, AppError>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, AppError>;
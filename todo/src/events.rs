@@ -0,0 +1,99 @@
+use chrono::Utc;
+use std::time::Duration;
+use tokio::{sync::broadcast, time::interval};
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::{error::AppError, models::TodoEvent, shutdown::Shutdown, store::DynStore};
+
+/// Events a stream replays on resume before it asks the client to refetch
+/// everything instead.
+pub const BACKLOG_LIMIT: i64 = 1000;
+
+/// Live events buffered per subscriber before it lags and must resync.
+const BUS_CAPACITY: usize = 1024;
+
+/// What a subscriber receives from an [`EventBus`].
+#[derive(Debug, Clone)]
+pub enum Notification {
+    Event(Box<TodoEvent>),
+    /// Events may have been lost (e.g. the Postgres listener reconnected);
+    /// subscribers should tell their clients to refetch.
+    Missed,
+}
+
+/// In-process fan-out of committed todo events to every open stream.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Notification>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(BUS_CAPACITY).0,
+        }
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Notification> {
+        self.sender.subscribe()
+    }
+
+    /// Sends `event` to current subscribers; a no-op when there are none.
+    pub fn publish(&self, event: TodoEvent) {
+        let _ = self.sender.send(Notification::Event(Box::new(event)));
+    }
+
+    pub fn missed(&self) {
+        let _ = self.sender.send(Notification::Missed);
+    }
+}
+
+/// The user's events after `after_id`, oldest first, or `None` when some may
+/// have been pruned or there are more than [`BACKLOG_LIMIT`], in which case
+/// the client has to refetch its todos.
+pub async fn backlog(store: &DynStore, user_id: Uuid, after_id: i64) -> Result<Option<Vec<TodoEvent>>, AppError> {
+    // Pruning always keeps the newest event, so an empty log means the
+    // database was reset.
+    let complete = match store.oldest_todo_event_id().await? {
+        Some(oldest) => oldest <= after_id + 1,
+        None => after_id == 0,
+    };
+    if !complete {
+        return Ok(None);
+    }
+
+    let events = store.todo_events_since(user_id, after_id, BACKLOG_LIMIT).await?;
+    if events.len() as i64 >= BACKLOG_LIMIT {
+        return Ok(None);
+    }
+    Ok(Some(events))
+}
+
+/// Deletes events older than `retention` every hour (or every `retention`,
+/// if shorter) until shutdown.
+pub fn spawn_pruner(store: DynStore, retention: Duration, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        let mut ticker = interval(retention.min(Duration::from_secs(3600)));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.requested() => return,
+            }
+
+            let Ok(retention) = chrono::Duration::from_std(retention) else {
+                return;
+            };
+            match store.prune_todo_events(Utc::now() - retention).await {
+                Ok(pruned) => debug!("Pruned {} todo events", pruned),
+                Err(e) => error!("Failed to prune todo events: {}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,43 @@
+//! Drop-in replacements for axum's `Json`, `Path`, `Query` and `Multipart`
+//! whose rejections are rendered as [`AppError`] problem details instead of
+//! plain text.
+
+use async_trait::async_trait;
+use axum::{
+    extract::{FromRequest, FromRequestParts, Request},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+use crate::error::AppError;
+
+#[derive(Debug, FromRequest)]
+#[from_request(via(axum::Json), rejection(AppError))]
+pub struct Json<T>(pub T);
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Path), rejection(AppError))]
+pub struct Path<T>(pub T);
+
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(AppError))]
+pub struct Query<T>(pub T);
+
+/// Wraps axum's `Multipart`, which takes no type parameter and so cannot go
+/// through `via`.
+pub struct Multipart(pub axum::extract::Multipart);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequest<S> for Multipart {
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Multipart(axum::extract::Multipart::from_request(req, state).await?))
+    }
+}
@@ -0,0 +1,288 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use bytes::BytesMut;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{
+    access::{self, Action},
+    attachments::{self, Attachments, RangeRequest},
+    auth::AuthenticatedUser,
+    error::{AppError, Result},
+    extract::{Json, Multipart, Path},
+    models::{Attachment, AttachmentResponse, AttachmentUsage, NewAttachment},
+    store::DynStore,
+};
+
+/// List a todo's attachments, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}/attachments",
+    tag = "attachments",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The todo's attachments", body = [AttachmentResponse]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_attachments(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+) -> Result<Json<Vec<AttachmentResponse>>> {
+    access::authorize(&store, user.user.id, todo_id, Action::View).await?;
+    let attachments = store.get_attachments(todo_id).await?;
+
+    Ok(Json(attachments.into_iter().map(AttachmentResponse::from).collect()))
+}
+
+/// Attach a file to a todo; its owner and editors can.
+///
+/// The file goes in a `file` part with its name and content type. Its type
+/// must be one of the allowed ones and its contents must match it; it
+/// counts against the uploader's quota until it is deleted.
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/attachments",
+    tag = "attachments",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    request_body(content = AttachmentUpload, content_type = "multipart/form-data"),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "File attached", body = AttachmentResponse),
+        (status = 400, description = "No file, or an empty one", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The user may only view the todo", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 413, description = "The file is too large or would exceed the user's quota", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 415, description = "The file's type is not allowed or does not match its contents", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn upload_attachment(
+    State(store): State<DynStore>,
+    State(attachments): State<Attachments>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+    Multipart(mut multipart): Multipart,
+) -> Result<(StatusCode, Json<AttachmentResponse>)> {
+    access::authorize(&store, user.user.id, todo_id, Action::Edit).await?;
+    let options = attachments.options();
+
+    let mut field = loop {
+        match multipart.next_field().await? {
+            Some(field) if field.name() == Some("file") => break field,
+            Some(_) => continue,
+            None => return Err(AppError::BadRequest("Expected the file in a `file` part".to_string())),
+        }
+    };
+    let filename = attachments::sanitize_filename(field.file_name().unwrap_or_default());
+    let content_type = attachments::media_type(field.content_type().unwrap_or("application/octet-stream"));
+    if !options.allows(&content_type) {
+        return Err(AppError::UnsupportedMediaType(format!(
+            "Files of type {} cannot be attached",
+            content_type
+        )));
+    }
+
+    // Read part by part so oversized files are refused without buffering them
+    let mut data = BytesMut::new();
+    while let Some(chunk) = field.chunk().await? {
+        if (data.len() + chunk.len()) as u64 > options.max_size {
+            return Err(AppError::PayloadTooLarge(format!(
+                "Attachments are limited to {} bytes",
+                options.max_size
+            )));
+        }
+        data.extend_from_slice(&chunk);
+    }
+    if data.is_empty() {
+        return Err(AppError::BadRequest("The file is empty".to_string()));
+    }
+    if !attachments::matches_signature(&content_type, &data) {
+        return Err(AppError::UnsupportedMediaType(format!(
+            "The file's contents are not {}",
+            content_type
+        )));
+    }
+
+    // Checked again when recording; this only saves storing a file for nothing
+    let size_bytes = i64::try_from(data.len()).unwrap_or(i64::MAX);
+    let quota = i64::try_from(options.quota).unwrap_or(i64::MAX);
+    if store.get_attachment_usage(user.user.id).await? + size_bytes > quota {
+        return Err(quota_exceeded(options.quota));
+    }
+
+    let id = Uuid::new_v4();
+    let blob_key = attachments::blob_key(id);
+    attachments.blobs().put(&blob_key, data.freeze(), &content_type).await?;
+    let new = NewAttachment {
+        id,
+        todo_id,
+        user_id: user.user.id,
+        filename,
+        content_type,
+        size_bytes,
+        blob_key: blob_key.clone(),
+    };
+    let attachment = match store.create_attachment(new, quota).await {
+        Ok(Some(attachment)) => attachment,
+        outcome => {
+            // Nothing refers to the file
+            if let Err(e) = attachments.blobs().delete(&blob_key).await {
+                warn!("Failed to delete unrecorded blob {}: {}", blob_key, e);
+            }
+            return Err(outcome.err().unwrap_or_else(|| quota_exceeded(options.quota)));
+        }
+    };
+
+    Ok((StatusCode::CREATED, Json(AttachmentResponse::from(attachment))))
+}
+
+/// Download an attachment.
+///
+/// A single `Range` of bytes is served as `206 Partial Content`; other
+/// ranges get the whole file.
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}/attachments/{attachment_id}",
+    tag = "attachments",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("attachment_id" = Uuid, Path, description = "Attachment id"),
+        ("Range" = Option<String>, Header, description = "Bytes to send, e.g. `bytes=0-1023`"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The file", content_type = "application/octet-stream"),
+        (status = 206, description = "The requested bytes of the file", content_type = "application/octet-stream"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo or attachment", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 416, description = "The range lies outside the file"),
+    )
+)]
+pub async fn download_attachment(
+    State(store): State<DynStore>,
+    State(attachments): State<Attachments>,
+    user: AuthenticatedUser,
+    Path((todo_id, attachment_id)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let attachment = find(&store, todo_id, attachment_id, user.user.id, Action::View).await?;
+    let size = u64::try_from(attachment.size_bytes).unwrap_or_default();
+
+    let range = headers.get(header::RANGE).and_then(|value| value.to_str().ok());
+    let range = match attachments::parse_range(range, size) {
+        RangeRequest::Full => None,
+        RangeRequest::Partial(range) => Some(range),
+        RangeRequest::Unsatisfiable => {
+            let content_range = format!("bytes */{}", size);
+            return Ok((StatusCode::RANGE_NOT_SATISFIABLE, [(header::CONTENT_RANGE, content_range)]).into_response());
+        }
+    };
+    let stream = attachments.blobs().get(&attachment.blob_key, range.clone()).await?;
+
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, &attachment.content_type)
+        .header(header::CONTENT_DISPOSITION, attachments::content_disposition(&attachment.filename))
+        .header(header::ACCEPT_RANGES, "bytes");
+    response = match range {
+        Some(range) => response
+            .status(StatusCode::PARTIAL_CONTENT)
+            .header(header::CONTENT_LENGTH, range.end - range.start)
+            .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.end - 1, size)),
+        None => response.header(header::CONTENT_LENGTH, size),
+    };
+
+    response
+        .body(Body::from_stream(stream))
+        .map_err(|e| AppError::Internal(format!("Failed to build download response: {}", e)))
+}
+
+/// Delete an attachment; its uploader or the todo's owner can. The file
+/// is removed by the next cleanup, but stops counting against the quota
+/// straight away.
+#[utoipa::path(
+    delete,
+    path = "/api/todos/{id}/attachments/{attachment_id}",
+    tag = "attachments",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("attachment_id" = Uuid, Path, description = "Attachment id"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Attachment deleted"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The user is neither the uploader nor the todo's owner", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo or attachment", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn delete_attachment(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path((todo_id, attachment_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    find(&store, todo_id, attachment_id, user.user.id, Action::Delete).await?;
+    if !store.detach_attachment(attachment_id).await? {
+        return Err(AppError::NotFound("Attachment not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The authenticated user's attachment storage, and the upload limits.
+#[utoipa::path(
+    get,
+    path = "/api/attachments/usage",
+    tag = "attachments",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Storage used and the limits", body = AttachmentUsage),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_attachment_usage(
+    State(store): State<DynStore>,
+    State(attachments): State<Attachments>,
+    user: AuthenticatedUser,
+) -> Result<Json<AttachmentUsage>> {
+    let options = attachments.options();
+    let used_bytes = store.get_attachment_usage(user.user.id).await?;
+
+    Ok(Json(AttachmentUsage {
+        used_bytes,
+        quota_bytes: options.quota,
+        max_size_bytes: options.max_size,
+        allowed_types: options.allowed_types.clone(),
+    }))
+}
+
+/// Loads an attachment of a todo the user can see and checks they may take
+/// `action` on it.
+async fn find(
+    store: &DynStore,
+    todo_id: Uuid,
+    attachment_id: Uuid,
+    user_id: Uuid,
+    action: Action,
+) -> Result<Attachment> {
+    let (_, role) = access::authorize(store, user_id, todo_id, Action::View).await?;
+    let attachment = store
+        .get_attachment(attachment_id)
+        .await?
+        .filter(|attachment| attachment.todo_id == Some(todo_id))
+        .ok_or(AppError::NotFound("Attachment not found".to_string()))?;
+    access::authorize_attachment(&attachment, user_id, role, action)?;
+
+    Ok(attachment)
+}
+
+fn quota_exceeded(quota: u64) -> AppError {
+    AppError::QuotaExceeded(format!("Attachments are limited to {} bytes per user", quota))
+}
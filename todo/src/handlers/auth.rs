@@ -1,23 +1,35 @@
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, http::StatusCode};
 use validator::Validate;
 
 use crate::{
     auth::{hash_password, verify_password, JwtService},
-    database::Database,
     error::{AppError, Result},
+    extract::Json,
     models::{AuthResponse, CreateUser, LoginRequest, UserResponse},
+    store::DynStore,
 };
 
+/// Create an account and return a token for it.
+#[utoipa::path(
+    post,
+    path = "/api/auth/register",
+    tag = "auth",
+    request_body = CreateUser,
+    responses(
+        (status = 201, description = "User registered", body = AuthResponse),
+        (status = 400, description = "Invalid input or username/email taken", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub async fn register(
-    State(db): State<Database>,
+    State(store): State<DynStore>,
+    State(jwt): State<JwtService>,
     Json(payload): Json<CreateUser>,
 ) -> Result<(StatusCode, Json<AuthResponse>)> {
     // Validate input
-    payload.validate()
-        .map_err(|e| AppError::Validation(format!("Validation error: {}", e)))?;
+    payload.validate()?;
 
     // Check if user already exists
-    if db.get_user_by_username(&payload.username).await?.is_some() {
+    if store.get_user_by_username(&payload.username).await?.is_some() {
         return Err(AppError::BadRequest("Username already exists".to_string()));
     }
 
@@ -25,13 +37,12 @@ pub async fn register(
     let password_hash = hash_password(&payload.password)?;
 
     // Create user
-    let user = db
+    let user = store
         .create_user(&payload.username, &payload.email, &password_hash)
         .await?;
 
     // Generate JWT token
-    let jwt_service = JwtService::new();
-    let token = jwt_service.generate_token(&user)?;
+    let token = jwt.generate_token(&user)?;
 
     let response = AuthResponse {
         token,
@@ -41,16 +52,28 @@ pub async fn register(
     Ok((StatusCode::CREATED, Json(response)))
 }
 
+/// Exchange username and password for a token.
+#[utoipa::path(
+    post,
+    path = "/api/auth/login",
+    tag = "auth",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Logged in", body = AuthResponse),
+        (status = 400, description = "Invalid input", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Invalid credentials", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub async fn login(
-    State(db): State<Database>,
+    State(store): State<DynStore>,
+    State(jwt): State<JwtService>,
     Json(payload): Json<LoginRequest>,
 ) -> Result<Json<AuthResponse>> {
     // Validate input
-    payload.validate()
-        .map_err(|e| AppError::Validation(format!("Validation error: {}", e)))?;
+    payload.validate()?;
 
     // Get user by username
-    let user = db
+    let user = store
         .get_user_by_username(&payload.username)
         .await?
         .ok_or(AppError::Auth("Invalid credentials".to_string()))?;
@@ -61,8 +84,7 @@ pub async fn login(
     }
 
     // Generate JWT token
-    let jwt_service = JwtService::new();
-    let token = jwt_service.generate_token(&user)?;
+    let token = jwt.generate_token(&user)?;
 
     let response = AuthResponse {
         token,
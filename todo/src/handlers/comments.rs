@@ -0,0 +1,311 @@
+use axum::{extract::State, http::StatusCode};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    access::{self, Action},
+    auth::AuthenticatedUser,
+    comments,
+    error::{AppError, Result},
+    extract::{Json, Path, Query},
+    models::{Comment, CommentBody, CommentResponse, CommentRevision, MentionResponse},
+    store::DynStore,
+};
+
+/// Mentions listed per inbox request, newest first.
+const INBOX_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct MentionParams {
+    /// Only list mentions not yet marked read.
+    #[serde(default)]
+    unread: bool,
+}
+
+/// List a todo's comments, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}/comments",
+    tag = "comments",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The todo's comments", body = [CommentResponse]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_comments(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+) -> Result<Json<Vec<CommentResponse>>> {
+    access::authorize(&store, user.user.id, todo_id, Action::View).await?;
+    let comments = store.get_comments(todo_id).await?;
+
+    Ok(Json(comments.into_iter().map(CommentResponse::from).collect()))
+}
+
+/// Comment on a todo; anyone who can see the todo can.
+///
+/// Each `@username` of a user who can see the todo, other than the author,
+/// lands in that user's mentions inbox.
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/comments",
+    tag = "comments",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    request_body = CommentBody,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Comment created", body = CommentResponse),
+        (status = 400, description = "Invalid input", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn create_comment(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+    Json(payload): Json<CommentBody>,
+) -> Result<(StatusCode, Json<CommentResponse>)> {
+    payload.validate()?;
+    access::authorize(&store, user.user.id, todo_id, Action::Comment).await?;
+
+    let mentioned = resolve_mentions(&store, todo_id, user.user.id, &payload.body).await?;
+    let comment = store
+        .create_comment(todo_id, user.user.id, &payload.body, &mentioned)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(CommentResponse::from(comment))))
+}
+
+/// Fetch a single comment.
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}/comments/{comment_id}",
+    tag = "comments",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("comment_id" = Uuid, Path, description = "Comment id"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The comment", body = CommentResponse),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo or comment", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_comment(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path((todo_id, comment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<CommentResponse>> {
+    let comment = find(&store, todo_id, comment_id, user.user.id, Action::View).await?;
+
+    Ok(Json(CommentResponse::from(comment)))
+}
+
+/// Edit a comment; only its author can. The previous body stays in the
+/// comment's history, and newly mentioned users are notified.
+#[utoipa::path(
+    put,
+    path = "/api/todos/{id}/comments/{comment_id}",
+    tag = "comments",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("comment_id" = Uuid, Path, description = "Comment id"),
+    ),
+    request_body = CommentBody,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated comment", body = CommentResponse),
+        (status = 400, description = "Invalid input", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The user is not the comment's author", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo or comment", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn update_comment(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path((todo_id, comment_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<CommentBody>,
+) -> Result<Json<CommentResponse>> {
+    payload.validate()?;
+    find(&store, todo_id, comment_id, user.user.id, Action::Edit).await?;
+
+    let mentioned = resolve_mentions(&store, todo_id, user.user.id, &payload.body).await?;
+    let comment = store
+        .update_comment(comment_id, &payload.body, &mentioned)
+        .await?
+        .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+
+    Ok(Json(CommentResponse::from(comment)))
+}
+
+/// Delete a comment with its history; its author or the todo's owner can.
+#[utoipa::path(
+    delete,
+    path = "/api/todos/{id}/comments/{comment_id}",
+    tag = "comments",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("comment_id" = Uuid, Path, description = "Comment id"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Comment deleted"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The user is neither the author nor the todo's owner", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo or comment", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn delete_comment(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path((todo_id, comment_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    find(&store, todo_id, comment_id, user.user.id, Action::Delete).await?;
+    if !store.delete_comment(comment_id).await? {
+        return Err(AppError::NotFound("Comment not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Every version of a comment's body, oldest first; the last is the
+/// current one.
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}/comments/{comment_id}/history",
+    tag = "comments",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("comment_id" = Uuid, Path, description = "Comment id"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The comment's revisions", body = [CommentRevision]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo or comment", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_comment_history(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path((todo_id, comment_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<CommentRevision>>> {
+    let comment = find(&store, todo_id, comment_id, user.user.id, Action::View).await?;
+    let revisions = store.get_comment_revisions(comment.id).await?;
+
+    Ok(Json(revisions))
+}
+
+/// List the comments mentioning the authenticated user, newest first and at
+/// most 100. Mentions on todos the user can no longer see are left out.
+#[utoipa::path(
+    get,
+    path = "/api/mentions",
+    tag = "comments",
+    params(MentionParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The user's mentions", body = [MentionResponse]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_mentions(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Query(params): Query<MentionParams>,
+) -> Result<Json<Vec<MentionResponse>>> {
+    let mentions = store.get_mentions(user.user.id, params.unread, INBOX_LIMIT).await?;
+
+    Ok(Json(mentions.into_iter().map(MentionResponse::from).collect()))
+}
+
+/// Mark every mention of the authenticated user read.
+#[utoipa::path(
+    post,
+    path = "/api/mentions/read",
+    tag = "comments",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Mentions marked read"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn mark_all_mentions_read(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+) -> Result<StatusCode> {
+    store.mark_mentions_read(user.user.id, None).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Mark the authenticated user's mention in one comment read.
+#[utoipa::path(
+    post,
+    path = "/api/mentions/{comment_id}/read",
+    tag = "comments",
+    params(("comment_id" = Uuid, Path, description = "Comment id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Mention marked read"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No unread mention of this user in the comment", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn mark_mention_read(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(comment_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if store.mark_mentions_read(user.user.id, Some(comment_id)).await? == 0 {
+        return Err(AppError::NotFound("Mention not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Loads a comment of a todo the user can see and checks they may take
+/// `action` on it.
+async fn find(
+    store: &DynStore,
+    todo_id: Uuid,
+    comment_id: Uuid,
+    user_id: Uuid,
+    action: Action,
+) -> Result<Comment> {
+    let (_, role) = access::authorize(store, user_id, todo_id, Action::View).await?;
+    let comment = store
+        .get_comment(comment_id)
+        .await?
+        .filter(|comment| comment.todo_id == todo_id)
+        .ok_or(AppError::NotFound("Comment not found".to_string()))?;
+    access::authorize_comment(&comment, user_id, role, action)?;
+
+    Ok(comment)
+}
+
+/// The users `@mentioned` in `body` who can see the todo, except the author.
+/// Unknown names are left as plain text.
+async fn resolve_mentions(store: &DynStore, todo_id: Uuid, author_id: Uuid, body: &str) -> Result<Vec<Uuid>> {
+    let mut mentioned = Vec::new();
+    for username in comments::mentions(body) {
+        let Some(user) = store.get_user_by_username(&username).await? else {
+            continue;
+        };
+        if user.id != author_id && access::can_view(store, user.id, todo_id).await? {
+            mentioned.push(user.id);
+        }
+    }
+
+    Ok(mentioned)
+}
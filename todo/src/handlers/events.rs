@@ -0,0 +1,112 @@
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use std::{collections::HashSet, time::Duration};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tokio_stream::{wrappers::ReceiverStream, Stream};
+
+use crate::{
+    auth::AuthenticatedUser,
+    error::Result,
+    events::{self, Notification},
+    models::TodoEvent,
+    shutdown::Shutdown,
+    store::DynStore,
+};
+
+/// Comment sent on idle streams so proxies do not time them out.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Stream changes to the authenticated user's todos, and those shared with
+/// them, as Server-Sent Events.
+///
+/// Each event is named `created`, `updated` or `deleted`, carries the
+/// change as JSON and has an id. Reconnecting with `Last-Event-ID` replays
+/// what was missed; when that is no longer possible, or events were dropped,
+/// a `resync` event asks the client to refetch its todos. The stream ends
+/// when the server shuts down.
+#[utoipa::path(
+    get,
+    path = "/api/todos/events",
+    tag = "todos",
+    params(("Last-Event-ID" = Option<i64>, Header, description = "Id of the last event received, to resume after it")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Event stream; each event's data is a TodoEvent", body = TodoEvent, content_type = "text/event-stream"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn todo_events(
+    State(store): State<DynStore>,
+    State(shutdown): State<Shutdown>,
+    user: AuthenticatedUser,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, axum::Error>>>> {
+    let user_id = user.user.id;
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<i64>().ok());
+
+    // Subscribe before reading the backlog so nothing committed in between
+    // is lost; duplicates are skipped below.
+    let mut live = store.subscribe_todo_events().await?;
+    let backlog = match last_event_id {
+        Some(after_id) => events::backlog(&store, user_id, after_id).await?,
+        None => Some(Vec::new()),
+    };
+
+    let (sender, receiver) = mpsc::channel(32);
+    tokio::spawn(async move {
+        let mut replayed = HashSet::new();
+        match backlog {
+            Some(backlog) => {
+                for event in backlog {
+                    replayed.insert(event.id);
+                    if sender.send(sse_event(&event)).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            None => {
+                if sender.send(Ok(resync())).await.is_err() {
+                    return;
+                }
+            }
+        }
+
+        loop {
+            let next = tokio::select! {
+                next = live.recv() => next,
+                _ = shutdown.requested() => return,
+                _ = sender.closed() => return,
+            };
+            let message = match next {
+                Ok(Notification::Event(event)) if event.is_for(user_id) && !replayed.contains(&event.id) => {
+                    sse_event(&event)
+                }
+                Ok(Notification::Event(_)) => continue,
+                Ok(Notification::Missed) | Err(RecvError::Lagged(_)) => Ok(resync()),
+                Err(RecvError::Closed) => return,
+            };
+            if sender.send(message).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    Ok(Sse::new(ReceiverStream::new(receiver)).keep_alive(KeepAlive::new().interval(KEEP_ALIVE_INTERVAL)))
+}
+
+fn sse_event(event: &TodoEvent) -> std::result::Result<Event, axum::Error> {
+    Event::default()
+        .id(event.id.to_string())
+        .event(event.kind.as_str())
+        .json_data(event)
+}
+
+fn resync() -> Event {
+    Event::default().event("resync").data("{}")
+}
@@ -0,0 +1,128 @@
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::DateTime;
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+use tokio::time::timeout;
+use tracing::warn;
+
+use crate::{
+    models::{HealthStatus, ReadinessCheck, ReadinessChecks, ReadinessResponse, VersionResponse},
+    shutdown::Shutdown,
+    store::{DynStore, MigrationState},
+};
+
+/// Upper bound for each database check, so a stuck pool fails readiness
+/// instead of hanging the probe.
+const READINESS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Liveness: the process is up and serving requests.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, description = "The process is alive", body = String, example = json!("ok")))
+)]
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// Readiness: the service can take traffic.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Every check passed", body = ReadinessResponse),
+        (status = 503, description = "At least one check failed", body = ReadinessResponse),
+    )
+)]
+pub async fn readyz(
+    State(store): State<DynStore>,
+    State(shutdown): State<Shutdown>,
+) -> (StatusCode, Json<ReadinessResponse>) {
+    // The probe is unauthenticated, so errors are logged and only summed up
+    // in the body
+    let database = check(async {
+        store.ping().await.map_err(|e| {
+            warn!("Readiness database check failed: {}", e);
+            "Database unreachable".to_string()
+        })
+    })
+    .await;
+    let migrations = check(async {
+        let pending = store
+            .migration_status()
+            .await
+            .map_err(|e| {
+                warn!("Readiness migration check failed: {}", e);
+                "Migration status unavailable".to_string()
+            })?
+            .iter()
+            .filter(|m| !matches!(m.state, MigrationState::Applied { .. }))
+            .count();
+        match pending {
+            0 => Ok(()),
+            n => Err(format!("{} migration(s) pending", n)),
+        }
+    })
+    .await;
+    let shutdown = ReadinessCheck {
+        status: if shutdown.is_requested() { HealthStatus::Fail } else { HealthStatus::Ok },
+        detail: shutdown.is_requested().then(|| "Shutting down".to_string()),
+        duration_ms: 0,
+    };
+
+    let checks = ReadinessChecks { database, migrations, shutdown };
+    let ready = [&checks.database, &checks.migrations, &checks.shutdown]
+        .iter()
+        .all(|c| c.status == HealthStatus::Ok);
+
+    let (code, status) = if ready {
+        (StatusCode::OK, HealthStatus::Ok)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, HealthStatus::Fail)
+    };
+
+    (code, Json(ReadinessResponse { status, checks }))
+}
+
+/// Build metadata for this binary.
+#[utoipa::path(
+    get,
+    path = "/version",
+    tag = "health",
+    responses((status = 200, description = "Build metadata", body = VersionResponse))
+)]
+pub async fn version() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_sha: env!("BUILD_GIT_SHA").to_string(),
+        build_time: env!("BUILD_EPOCH")
+            .parse()
+            .ok()
+            .and_then(|epoch| DateTime::from_timestamp(epoch, 0)),
+        rustc: env!("BUILD_RUSTC_VERSION").to_string(),
+        profile: env!("BUILD_PROFILE").to_string(),
+    })
+}
+
+async fn check(probe: impl Future<Output = Result<(), String>>) -> ReadinessCheck {
+    let started = Instant::now();
+    let result = timeout(READINESS_TIMEOUT, probe).await;
+    let duration_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+    let detail = match result {
+        Ok(Ok(())) => None,
+        Ok(Err(detail)) => Some(detail),
+        Err(_) => Some(format!("Timed out after {:?}", READINESS_TIMEOUT)),
+    };
+
+    ReadinessCheck {
+        status: if detail.is_none() { HealthStatus::Ok } else { HealthStatus::Fail },
+        detail,
+        duration_ms,
+    }
+}
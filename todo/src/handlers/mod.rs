@@ -1,5 +1,13 @@
+pub mod attachments;
 pub mod auth;
+pub mod comments;
+pub mod events;
+pub mod health;
+pub mod preferences;
+pub mod shares;
+pub mod stats;
+pub mod sync;
+pub mod time_entries;
 pub mod todo;
-
-pub use auth::*;
-pub use todo::*;
\ No newline at end of file
+pub mod ws;
+pub mod webhooks;
@@ -0,0 +1,75 @@
+use axum::extract::State;
+
+use crate::{
+    auth::AuthenticatedUser,
+    calendar,
+    error::{AppError, Result},
+    extract::Json,
+    models::{Preferences, UpdatePreferences},
+    store::DynStore,
+};
+
+/// Fetch the authenticated user's time zone and locale.
+#[utoipa::path(
+    get,
+    path = "/api/preferences",
+    tag = "preferences",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The user's preferences", body = Preferences),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_preferences(user: AuthenticatedUser) -> Result<Json<Preferences>> {
+    Ok(Json(Preferences::from(user.user)))
+}
+
+/// Change the authenticated user's time zone or locale.
+///
+/// The time zone decides which day is "today" for the agenda views and when
+/// todos scheduled for a date alone are due; todos already scheduled keep
+/// their instant.
+#[utoipa::path(
+    put,
+    path = "/api/preferences",
+    tag = "preferences",
+    request_body = UpdatePreferences,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Preferences updated", body = Preferences),
+        (status = 400, description = "Unknown time zone or invalid locale", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn update_preferences(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Json(payload): Json<UpdatePreferences>,
+) -> Result<Json<Preferences>> {
+    let timezone = payload
+        .timezone
+        .as_deref()
+        .map(|name| {
+            calendar::parse_timezone(name)
+                .ok_or(AppError::invalid_field("timezone", "unknown_timezone", "Unknown IANA time zone"))
+        })
+        .transpose()?;
+    let locale = payload
+        .locale
+        .as_deref()
+        .map(|tag| {
+            calendar::normalize_locale(tag).ok_or(AppError::invalid_field(
+                "locale",
+                "invalid_locale",
+                "Not a language tag such as en-US",
+            ))
+        })
+        .transpose()?;
+
+    let user = store
+        .update_user_preferences(user.user.id, timezone.map(|tz| tz.name()), locale.as_deref())
+        .await?
+        .ok_or(AppError::NotFound("User not found".to_string()))?;
+
+    Ok(Json(Preferences::from(user)))
+}
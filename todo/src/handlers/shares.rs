@@ -0,0 +1,165 @@
+use axum::{extract::State, http::StatusCode};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    access::{self, Action},
+    auth::AuthenticatedUser,
+    error::{AppError, Result},
+    extract::{Json, Path},
+    models::{CreateShare, Share, ShareResponse, UpdateShare},
+    store::DynStore,
+};
+
+/// Share one of the authenticated user's todos, or their whole list, with
+/// another user. Sharing again with the same user and scope changes the role.
+#[utoipa::path(
+    post,
+    path = "/api/shares",
+    tag = "shares",
+    request_body = CreateShare,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Share created or updated", body = ShareResponse),
+        (status = 400, description = "Invalid input, unknown user or sharing with oneself", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The user is not the todo's owner", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn create_share(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Json(payload): Json<CreateShare>,
+) -> Result<(StatusCode, Json<ShareResponse>)> {
+    payload.validate()?;
+
+    let grantee = store
+        .get_user_by_username(&payload.username)
+        .await?
+        .ok_or_else(|| AppError::invalid_field("username", "unknown_user", "No user with this username"))?;
+    if grantee.id == user.user.id {
+        return Err(AppError::invalid_field("username", "self", "Todos cannot be shared with their owner"));
+    }
+    if let Some(todo_id) = payload.todo_id {
+        access::authorize(&store, user.user.id, todo_id, Action::Share).await?;
+    }
+
+    let share = store
+        .upsert_share(user.user.id, grantee.id, payload.todo_id, payload.role)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(ShareResponse::from(share))))
+}
+
+/// List the shares the authenticated user granted or received, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/shares",
+    tag = "shares",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The user's shares", body = [ShareResponse]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_shares(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<ShareResponse>>> {
+    let shares = store.get_shares_for_user(user.user.id).await?;
+
+    Ok(Json(shares.into_iter().map(ShareResponse::from).collect()))
+}
+
+/// Fetch a share the user granted or received.
+#[utoipa::path(
+    get,
+    path = "/api/shares/{id}",
+    tag = "shares",
+    params(("id" = Uuid, Path, description = "Share id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The share", body = ShareResponse),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such share for this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_share(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(share_id): Path<Uuid>,
+) -> Result<Json<ShareResponse>> {
+    let share = find(&store, share_id, user.user.id, Action::View).await?;
+
+    Ok(Json(ShareResponse::from(share)))
+}
+
+/// Change the role a share grants; only its owner can.
+#[utoipa::path(
+    put,
+    path = "/api/shares/{id}",
+    tag = "shares",
+    params(("id" = Uuid, Path, description = "Share id")),
+    request_body = UpdateShare,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated share", body = ShareResponse),
+        (status = 400, description = "Invalid input", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The user only received the share", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such share for this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn update_share(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(share_id): Path<Uuid>,
+    Json(payload): Json<UpdateShare>,
+) -> Result<Json<ShareResponse>> {
+    find(&store, share_id, user.user.id, Action::Edit).await?;
+
+    let share = store
+        .update_share(share_id, payload.role)
+        .await?
+        .ok_or(AppError::NotFound("Share not found".to_string()))?;
+
+    Ok(Json(ShareResponse::from(share)))
+}
+
+/// Revoke a share, or give it up as its grantee. Assignments are kept but
+/// stop showing once the assignee can no longer see the todo.
+#[utoipa::path(
+    delete,
+    path = "/api/shares/{id}",
+    tag = "shares",
+    params(("id" = Uuid, Path, description = "Share id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Share deleted"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such share for this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn delete_share(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(share_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    find(&store, share_id, user.user.id, Action::Delete).await?;
+    if !store.delete_share(share_id).await? {
+        return Err(AppError::NotFound("Share not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn find(store: &DynStore, share_id: Uuid, user_id: Uuid, action: Action) -> Result<Share> {
+    let share = store
+        .get_share_by_id(share_id)
+        .await?
+        .ok_or(AppError::NotFound("Share not found".to_string()))?;
+    access::authorize_share(&share, user_id, action)?;
+
+    Ok(share)
+}
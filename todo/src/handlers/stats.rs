@@ -0,0 +1,80 @@
+use axum::extract::State;
+use chrono::{Days, NaiveDate, Utc};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use crate::{
+    auth::AuthenticatedUser,
+    calendar,
+    error::{AppError, Result},
+    extract::{Json, Query},
+    models::{StatsResponse, User},
+    stats::{self, StatsRange},
+    store::DynStore,
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatsParams {
+    /// First day to cover, as `YYYY-MM-DD`; 29 days before `to` by default.
+    from: Option<NaiveDate>,
+    /// Last day to cover, included; today by default.
+    to: Option<NaiveDate>,
+    /// IANA time zone the days are taken in; the user's by default.
+    timezone: Option<String>,
+}
+
+/// Productivity figures for the todos the authenticated user can see:
+/// completions per day and week, time from creation to completion,
+/// overdue todos, streaks of days with completions, and breakdowns by list
+/// and tag.
+///
+/// Covers up to 366 days, the last 30 by default.
+#[utoipa::path(
+    get,
+    path = "/api/stats",
+    tag = "stats",
+    params(StatsParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Figures for the range", body = StatsResponse),
+        (status = 400, description = "Unknown time zone, or range reversed or too long", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_stats(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Query(params): Query<StatsParams>,
+) -> Result<Json<StatsResponse>> {
+    let range = resolve_range(&user.user, params.from, params.to, params.timezone.as_deref())?;
+    let counts = store.todo_stats(user.user.id, &range).await?;
+
+    Ok(Json(stats::summarize(&range, counts)))
+}
+
+/// The range asked for, defaulting to the last 30 days in the user's time
+/// zone; shared with the time report.
+pub(crate) fn resolve_range(
+    user: &User,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    timezone: Option<&str>,
+) -> Result<StatsRange> {
+    let tz = match timezone {
+        Some(name) => calendar::parse_timezone(name)
+            .ok_or(AppError::invalid_field("timezone", "unknown_timezone", "Unknown IANA time zone"))?,
+        None => user.tz(),
+    };
+    let now = Utc::now();
+    let to = to.unwrap_or_else(|| calendar::today(tz, now));
+    let from = from.unwrap_or(to - Days::new(stats::DEFAULT_DAYS - 1));
+    if from > to {
+        return Err(AppError::invalid_field("from", "after_to", "The range starts after it ends"));
+    }
+    if (to - from).num_days() >= stats::MAX_DAYS {
+        return Err(AppError::invalid_field("from", "out_of_range", "A range covers at most 366 days"));
+    }
+
+    Ok(StatsRange::new(tz, from, to, now))
+}
@@ -0,0 +1,155 @@
+use axum::extract::State;
+use serde::Deserialize;
+use utoipa::IntoParams;
+use validator::Validate;
+
+use crate::{
+    auth::AuthenticatedUser,
+    error::{AppError, Result},
+    extract::{Json, Query},
+    handlers::todo::{normalize_recurrence, normalize_tags},
+    metrics::Metrics,
+    models::{DeletedTodo, SyncChanges, SyncMutation, SyncPush, SyncPushResponse, SyncResult, SyncStatus},
+    store::DynStore,
+    sync::{TodoChange, Write, PAGE_LIMIT},
+};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PullParams {
+    /// Token from the previous pull; omit it for a full sync.
+    since: Option<String>,
+}
+
+/// Fetch the changes to the user's todos since a sync token.
+///
+/// Returns at most 500 changes, oldest first. Each todo appears once, in its
+/// current state, under `todos` or, if deleted, `deleted`. Store the returned
+/// token and pass it as `since` next time; while `has_more` is set, pull again
+/// straight away.
+#[utoipa::path(
+    get,
+    path = "/api/sync",
+    tag = "sync",
+    params(PullParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Changes since the token", body = SyncChanges),
+        (status = 400, description = "Invalid sync token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn pull(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Query(params): Query<PullParams>,
+) -> Result<Json<SyncChanges>> {
+    let since = match params.since.as_deref() {
+        None | Some("") => 0,
+        Some(token) => token
+            .parse::<i64>()
+            .ok()
+            .filter(|since| *since >= 0)
+            .ok_or(AppError::BadRequest("Invalid sync token".to_string()))?,
+    };
+
+    // One extra change tells whether another page follows
+    let mut changes = store.todo_changes_since(user.user.id, since, PAGE_LIMIT + 1).await?;
+    let has_more = changes.len() > PAGE_LIMIT;
+    changes.truncate(PAGE_LIMIT);
+    let token = changes.last().map_or(since, TodoChange::seq);
+
+    let mut todos = Vec::new();
+    let mut deleted = Vec::new();
+    for change in changes {
+        match change {
+            TodoChange::Upserted { todo, .. } => todos.push((*todo).into()),
+            TodoChange::Deleted { id, deleted_at, .. } => deleted.push(DeletedTodo { id, deleted_at }),
+        }
+    }
+
+    Ok(Json(SyncChanges {
+        todos,
+        deleted,
+        token: token.to_string(),
+        has_more,
+    }))
+}
+
+/// Apply a batch of changes made on a client.
+///
+/// Mutations are applied in order, each on its own, and get one result each.
+/// Conflicts are resolved per field: the later of the client's `changed_at`
+/// and the server's last change to that field wins. Fields the server kept
+/// are listed as conflicts along with their current values. New todos use
+/// ids generated by the client, so retrying a batch is safe.
+#[utoipa::path(
+    post,
+    path = "/api/sync",
+    tag = "sync",
+    request_body = SyncPush,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "One result per mutation", body = SyncPushResponse),
+        (status = 400, description = "Malformed batch or more than 500 mutations", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn push(
+    State(store): State<DynStore>,
+    State(metrics): State<Metrics>,
+    user: AuthenticatedUser,
+    Json(mut payload): Json<SyncPush>,
+) -> Result<Json<SyncPushResponse>> {
+    if payload.mutations.len() > PAGE_LIMIT {
+        return Err(AppError::invalid_field(
+            "mutations",
+            "length",
+            "At most 500 mutations can be pushed at once",
+        ));
+    }
+
+    let mut results = Vec::with_capacity(payload.mutations.len());
+    for mutation in &mut payload.mutations {
+        let merged = match prepare(mutation) {
+            Ok(()) => store.apply_sync_mutation(user.user.id, mutation).await,
+            Err(e) => Err(e),
+        };
+        let merged = match merged {
+            Ok(merged) => merged,
+            Err(e) => {
+                results.push(SyncResult {
+                    id: mutation.id,
+                    status: SyncStatus::Rejected,
+                    todo: None,
+                    conflicts: Vec::new(),
+                    error: Some(e.problem()),
+                });
+                continue;
+            }
+        };
+
+        if matches!(merged.write, Write::Insert(..)) {
+            metrics.todos_created.inc();
+        }
+        let was_completed = merged.before.as_ref().is_some_and(|todo| todo.completed);
+        if merged.after.as_ref().is_some_and(|todo| todo.completed) && !was_completed {
+            metrics.todos_completed.inc();
+        }
+        results.push(merged.into_result(mutation.id));
+    }
+
+    Ok(Json(SyncPushResponse { results }))
+}
+
+/// Validates a mutation and normalizes its tags and repeat rule as the REST
+/// API does.
+fn prepare(mutation: &mut SyncMutation) -> Result<()> {
+    mutation.validate()?;
+    mutation.tags = mutation.tags.take().map(normalize_tags).transpose()?;
+    if let Some(Some(rule)) = &mutation.recurrence {
+        mutation.recurrence = Some(Some(normalize_recurrence(rule)?));
+    }
+
+    Ok(())
+}
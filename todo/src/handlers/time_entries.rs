@@ -0,0 +1,324 @@
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    access::{self, Action},
+    auth::AuthenticatedUser,
+    error::{AppError, Result},
+    extract::{Json, Path, Query},
+    handlers::stats::resolve_range,
+    models::{CreateTimeEntry, TimeEntry, TimerStarted, UpdateTimeEntry},
+    store::DynStore,
+    time_tracking,
+};
+
+/// How the time report is sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    /// One line per day and list, as a download.
+    Csv,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReportParams {
+    /// First day to cover, as `YYYY-MM-DD`; 29 days before `to` by default.
+    from: Option<NaiveDate>,
+    /// Last day to cover, included; today by default.
+    to: Option<NaiveDate>,
+    /// IANA time zone the days are taken in; the user's by default.
+    timezone: Option<String>,
+    #[serde(default)]
+    #[param(inline)]
+    format: ReportFormat,
+}
+
+/// Start the authenticated user's timer on a todo they can see. A timer
+/// they had running, on this todo or another, is stopped first.
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/timer/start",
+    tag = "time",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Timer started", body = TimerStarted),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn start_timer(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<TimerStarted>)> {
+    access::authorize(&store, user.user.id, todo_id, Action::Track).await?;
+    let (running, stopped) = store.start_timer(todo_id, user.user.id, Utc::now()).await?;
+
+    Ok((StatusCode::CREATED, Json(TimerStarted { running, stopped })))
+}
+
+/// Stop the authenticated user's timer on a todo.
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/timer/stop",
+    tag = "time",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The stopped entry", body = TimeEntry),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo, or no timer of this user running on it", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn stop_timer(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+) -> Result<Json<TimeEntry>> {
+    access::authorize(&store, user.user.id, todo_id, Action::View).await?;
+    let stopped = store
+        .stop_timer(todo_id, user.user.id, Utc::now())
+        .await?
+        .ok_or(AppError::NotFound("No timer running on this todo".to_string()))?;
+
+    Ok(Json(stopped))
+}
+
+/// The authenticated user's running timer, or `null`.
+#[utoipa::path(
+    get,
+    path = "/api/timer",
+    tag = "time",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The running timer, if any", body = Option<TimeEntry>),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_timer(State(store): State<DynStore>, user: AuthenticatedUser) -> Result<Json<Option<TimeEntry>>> {
+    let running = store.get_running_time_entry(user.user.id).await?;
+
+    Ok(Json(running))
+}
+
+/// List the time tracked on a todo by everyone, oldest first, running
+/// timers included.
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}/time-entries",
+    tag = "time",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The todo's time entries", body = [TimeEntry]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_time_entries(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+) -> Result<Json<Vec<TimeEntry>>> {
+    access::authorize(&store, user.user.id, todo_id, Action::View).await?;
+    let entries = store.get_time_entries(todo_id).await?;
+
+    Ok(Json(entries))
+}
+
+/// Record time the authenticated user already spent on a todo.
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/time-entries",
+    tag = "time",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    request_body = CreateTimeEntry,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Entry created", body = TimeEntry),
+        (status = 400, description = "Invalid input, or times reversed or in the future", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn create_time_entry(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+    Json(payload): Json<CreateTimeEntry>,
+) -> Result<(StatusCode, Json<TimeEntry>)> {
+    payload.validate()?;
+    time_tracking::check_times(payload.started_at, Some(payload.ended_at), Utc::now())?;
+    access::authorize(&store, user.user.id, todo_id, Action::Track).await?;
+
+    let entry = store
+        .create_time_entry(todo_id, user.user.id, payload.started_at, payload.ended_at, payload.note.as_deref())
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(entry)))
+}
+
+/// Fetch a single time entry.
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}/time-entries/{entry_id}",
+    tag = "time",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("entry_id" = Uuid, Path, description = "Time entry id"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The time entry", body = TimeEntry),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo or time entry", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_time_entry(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path((todo_id, entry_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<TimeEntry>> {
+    let entry = find(&store, todo_id, entry_id, user.user.id, Action::View).await?;
+
+    Ok(Json(entry))
+}
+
+/// Edit a time entry; only whoever tracked it can. Setting `ended_at` on a
+/// running timer stops it.
+#[utoipa::path(
+    put,
+    path = "/api/todos/{id}/time-entries/{entry_id}",
+    tag = "time",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("entry_id" = Uuid, Path, description = "Time entry id"),
+    ),
+    request_body = UpdateTimeEntry,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated entry", body = TimeEntry),
+        (status = 400, description = "Invalid input, or times reversed or in the future", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The entry was tracked by someone else", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo or time entry", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn update_time_entry(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path((todo_id, entry_id)): Path<(Uuid, Uuid)>,
+    Json(payload): Json<UpdateTimeEntry>,
+) -> Result<Json<TimeEntry>> {
+    payload.validate()?;
+    let entry = find(&store, todo_id, entry_id, user.user.id, Action::Edit).await?;
+
+    let started_at = payload.started_at.unwrap_or(entry.started_at);
+    let ended_at = payload.ended_at.or(entry.ended_at);
+    time_tracking::check_times(started_at, ended_at, Utc::now())?;
+    let note = payload.note.unwrap_or(entry.note);
+    let entry = store
+        .update_time_entry(entry_id, started_at, ended_at, note.as_deref())
+        .await?
+        .ok_or(AppError::NotFound("Time entry not found".to_string()))?;
+
+    Ok(Json(entry))
+}
+
+/// Delete a time entry; whoever tracked it or the todo's owner can.
+#[utoipa::path(
+    delete,
+    path = "/api/todos/{id}/time-entries/{entry_id}",
+    tag = "time",
+    params(
+        ("id" = Uuid, Path, description = "Todo id"),
+        ("entry_id" = Uuid, Path, description = "Time entry id"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Entry deleted"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The user neither tracked the entry nor owns the todo", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo or time entry", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn delete_time_entry(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path((todo_id, entry_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode> {
+    find(&store, todo_id, entry_id, user.user.id, Action::Delete).await?;
+    if !store.delete_time_entry(entry_id).await? {
+        return Err(AppError::NotFound("Time entry not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The time the authenticated user tracked, by day and by list, counting
+/// stopped entries by the day they started on. With `format=csv`, the
+/// day and list lines come as a CSV download.
+///
+/// Covers up to 366 days, the last 30 by default.
+#[utoipa::path(
+    get,
+    path = "/api/time-entries/report",
+    tag = "time",
+    params(ReportParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Time tracked in the range", body = TimeReport, content_type = ["application/json", "text/csv"]),
+        (status = 400, description = "Unknown time zone, or range reversed or too long", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_time_report(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Query(params): Query<ReportParams>,
+) -> Result<Response> {
+    let range = resolve_range(&user.user, params.from, params.to, params.timezone.as_deref())?;
+    let rows = store.time_report(user.user.id, &range).await?;
+    let report = time_tracking::summarize(&range, rows);
+
+    Ok(match params.format {
+        ReportFormat::Json => Json(report).into_response(),
+        ReportFormat::Csv => (
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"time-{}-{}.csv\"", report.from, report.to),
+                ),
+            ],
+            time_tracking::to_csv(&report),
+        )
+            .into_response(),
+    })
+}
+
+/// Loads a time entry on a todo the user can see and checks they may take
+/// `action` on it.
+async fn find(store: &DynStore, todo_id: Uuid, entry_id: Uuid, user_id: Uuid, action: Action) -> Result<TimeEntry> {
+    let (_, role) = access::authorize(store, user_id, todo_id, Action::View).await?;
+    let entry = store
+        .get_time_entry(entry_id)
+        .await?
+        .filter(|entry| entry.todo_id == todo_id)
+        .ok_or(AppError::NotFound("Time entry not found".to_string()))?;
+    access::authorize_time_entry(&entry, user_id, role, action)?;
+
+    Ok(entry)
+}
@@ -1,106 +1,627 @@
-use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    Json,
-};
-use chrono::Utc;
+use axum::{extract::State, http::StatusCode};
+use chrono::{DateTime, Days, Duration, Utc};
+use chrono_tz::Tz;
+use serde::Deserialize;
+use utoipa::IntoParams;
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::{
+    access::{self, Action},
     auth::AuthenticatedUser,
-    database::Database,
+    calendar,
     error::{AppError, Result},
-    models::{CreateTodo, TodoResponse, UpdateTodo},
+    extract::{Json, Path, Query},
+    metrics::Metrics,
+    quick_add,
+    recurrence::Recurrence,
+    store::DynStore,
+    models::{
+        self, AgendaDay, AssignTodo, CreateTodo, QuickAddResponse, QuickAddTodo, Schedule, SharedTodoResponse, SnoozeTodo, Todo,
+        TodoResponse, UpdateTodo, User,
+    },
 };
 
+/// Days after today `GET /api/todos/upcoming` covers at most.
+const MAX_UPCOMING_DAYS: u32 = 90;
+/// Longest custom snooze, a year.
+const MAX_SNOOZE_MINUTES: i64 = 525_600;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TodoListParams {
+    /// Also list snoozed todos that have not resurfaced yet.
+    include_hidden: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UpcomingParams {
+    /// How many days after today to cover, 1 to 90; 7 by default.
+    days: Option<u32>,
+}
+
+/// Create a todo for the authenticated user.
+#[utoipa::path(
+    post,
+    path = "/api/todos",
+    tag = "todos",
+    request_body = CreateTodo,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Todo created", body = TodoResponse),
+        (status = 400, description = "Invalid input or scheduled date in the past", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub async fn create_todo(
-    State(db): State<Database>,
+    State(store): State<DynStore>,
+    State(metrics): State<Metrics>,
     user: AuthenticatedUser,
     Json(payload): Json<CreateTodo>,
 ) -> Result<(StatusCode, Json<TodoResponse>)> {
-    // Validate input
-    payload.validate()
-        .map_err(|e| AppError::Validation(format!("Validation error: {}", e)))?;
-
-    // Validate scheduled_for is not in the past (optional validation)
-    if let Some(scheduled_for) = payload.scheduled_for {
-        let now = Utc::now();
-        if scheduled_for < now {
-            return Err(AppError::Validation(
-                "Scheduled date cannot be in the past".to_string()
-            ));
-        }
+    let todo = create(&store, &metrics, &user.user, payload).await?;
+
+    Ok((StatusCode::CREATED, Json(TodoResponse::from(todo))))
+}
+
+/// Read a todo typed as one line, such as `Pay rent every 1st at 9am
+/// #finance !high`, and create it if `commit` is set.
+///
+/// Dates and times are read in `timezone`, or the user's time zone if it
+/// is omitted. Without `commit` nothing is
+/// stored and the response only holds what was read, for a preview.
+#[utoipa::path(
+    post,
+    path = "/api/todos/quick",
+    tag = "todos",
+    request_body = QuickAddTodo,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "What the text was read as", body = QuickAddResponse),
+        (status = 201, description = "Todo created", body = QuickAddResponse),
+        (status = 400, description = "Invalid input or time zone, no title left, or scheduled date in the past", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn quick_add_todo(
+    State(store): State<DynStore>,
+    State(metrics): State<Metrics>,
+    user: AuthenticatedUser,
+    Json(payload): Json<QuickAddTodo>,
+) -> Result<(StatusCode, Json<QuickAddResponse>)> {
+    payload.validate()?;
+    let timezone = match payload.timezone.as_deref() {
+        Some(name) => calendar::parse_timezone(name)
+            .ok_or(AppError::invalid_field("timezone", "unknown_timezone", "Unknown IANA time zone"))?,
+        None => user.user.tz(),
+    };
+    let parsed = quick_add::parse(&payload.text, Utc::now(), timezone);
+    if !payload.commit {
+        return Ok((StatusCode::OK, Json(QuickAddResponse { parsed, todo: None })));
     }
 
-    // Create todo
-    let todo = db.create_todo(user.user.id, payload).await?;
+    if parsed.title.is_empty() {
+        return Err(AppError::invalid_field(
+            "text",
+            "no_title",
+            "Nothing is left for the title once dates, tags and priority are taken out",
+        ));
+    }
+    let new = CreateTodo {
+        title: parsed.title.clone(),
+        scheduled_for: parsed.scheduled_for,
+        priority: parsed.priority,
+        tags: Some(parsed.tags.clone()),
+        recurrence: parsed.recurrence.clone(),
+        ..Default::default()
+    };
+    let todo = create(&store, &metrics, &user.user, new).await?;
 
-    Ok((StatusCode::CREATED, Json(TodoResponse::from(todo))))
+    Ok((
+        StatusCode::CREATED,
+        Json(QuickAddResponse {
+            parsed,
+            todo: Some(todo.into()),
+        }),
+    ))
 }
 
+/// List the authenticated user's todos, scheduled ones first. Snoozed todos
+/// are left out until they resurface unless `include_hidden` is set.
+#[utoipa::path(
+    get,
+    path = "/api/todos",
+    tag = "todos",
+    params(TodoListParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The user's todos", body = [TodoResponse]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub async fn get_todos(
-    State(db): State<Database>,
+    State(store): State<DynStore>,
     user: AuthenticatedUser,
+    Query(params): Query<TodoListParams>,
 ) -> Result<Json<Vec<TodoResponse>>> {
-    let todos = db.get_todos_by_user(user.user.id).await?;
-    let todo_responses: Vec<TodoResponse> = todos.into_iter().map(TodoResponse::from).collect();
+    let todos = store.get_todos_by_user(user.user.id).await?;
+    let now = Utc::now();
+    let todo_responses: Vec<TodoResponse> = todos
+        .into_iter()
+        .filter(|todo| params.include_hidden == Some(true) || !todo.is_hidden(now))
+        .map(TodoResponse::from)
+        .collect();
     
     Ok(Json(todo_responses))
 }
 
+/// List other users' todos shared with the authenticated user, with the
+/// role they hold on each.
+#[utoipa::path(
+    get,
+    path = "/api/todos/shared",
+    tag = "todos",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Todos shared with the user", body = [SharedTodoResponse]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_shared_todos(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<SharedTodoResponse>>> {
+    let todos = store.get_shared_todos(user.user.id).await?;
+    let now = Utc::now();
+
+    Ok(Json(
+        todos
+            .into_iter()
+            .filter(|(todo, _)| !todo.is_hidden(now))
+            .map(SharedTodoResponse::from)
+            .collect(),
+    ))
+}
+
+/// List the todos assigned to the authenticated user, whoever owns them.
+#[utoipa::path(
+    get,
+    path = "/api/todos/assigned",
+    tag = "todos",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Todos assigned to the user", body = [TodoResponse]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_assigned_todos(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<TodoResponse>>> {
+    let todos = store.get_assigned_todos(user.user.id).await?;
+
+    Ok(Json(visible(todos, Utc::now())))
+}
+
+/// List the authenticated user's open todos due today in their time zone,
+/// including those whose time has passed.
+#[utoipa::path(
+    get,
+    path = "/api/todos/today",
+    tag = "todos",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Today and its todos", body = AgendaDay),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_today_todos(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+) -> Result<Json<AgendaDay>> {
+    let tz = user.user.tz();
+    let now = Utc::now();
+    let today = calendar::today(tz, now);
+    let (start, end) = calendar::day_bounds(tz, today);
+    let todos = store.get_scheduled_todos(user.user.id, Some(start), end).await?;
+
+    Ok(Json(AgendaDay {
+        date: today,
+        todos: visible(todos, now),
+    }))
+}
+
+/// List the authenticated user's open todos due before today began in
+/// their time zone, oldest first.
+#[utoipa::path(
+    get,
+    path = "/api/todos/overdue",
+    tag = "todos",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Overdue todos", body = [TodoResponse]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_overdue_todos(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<TodoResponse>>> {
+    let tz = user.user.tz();
+    let now = Utc::now();
+    let (start_of_today, _) = calendar::day_bounds(tz, calendar::today(tz, now));
+    let todos = store.get_scheduled_todos(user.user.id, None, start_of_today).await?;
+
+    Ok(Json(visible(todos, now)))
+}
+
+/// List the authenticated user's open todos due on the days after today in
+/// their time zone, one entry per day whether or not anything is due.
+#[utoipa::path(
+    get,
+    path = "/api/todos/upcoming",
+    tag = "todos",
+    params(UpcomingParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The coming days and their todos", body = [AgendaDay]),
+        (status = 400, description = "Number of days out of range", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_upcoming_todos(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Query(params): Query<UpcomingParams>,
+) -> Result<Json<Vec<AgendaDay>>> {
+    let days = params.days.unwrap_or(7);
+    if !(1..=MAX_UPCOMING_DAYS).contains(&days) {
+        return Err(AppError::invalid_field("days", "out_of_range", "Upcoming covers 1 to 90 days"));
+    }
+
+    let tz = user.user.tz();
+    let first = calendar::today(tz, Utc::now()) + Days::new(1);
+    let last = first + Days::new(u64::from(days - 1));
+    let (from, _) = calendar::day_bounds(tz, first);
+    let (_, until) = calendar::day_bounds(tz, last);
+    let todos = store.get_scheduled_todos(user.user.id, Some(from), until).await?;
+
+    let mut agenda: Vec<AgendaDay> = first
+        .iter_days()
+        .take(days as usize)
+        .map(|date| AgendaDay { date, todos: Vec::new() })
+        .collect();
+    for todo in todos {
+        let Some(date) = todo.scheduled_for.map(|at| at.with_timezone(&tz).date_naive()) else {
+            continue;
+        };
+        if let Some(day) = agenda.iter_mut().find(|day| day.date == date) {
+            day.todos.push(todo.into());
+        }
+    }
+
+    Ok(Json(agenda))
+}
+
+/// Fetch a single todo the user owns or has been shared.
+#[utoipa::path(
+    get,
+    path = "/api/todos/{id}",
+    tag = "todos",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The todo", body = TodoResponse),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub async fn get_todo(
-    State(db): State<Database>,
+    State(store): State<DynStore>,
     user: AuthenticatedUser,
     Path(todo_id): Path<Uuid>,
 ) -> Result<Json<TodoResponse>> {
-    let todo = db
-        .get_todo_by_id(todo_id, user.user.id)
-        .await?
-        .ok_or(AppError::NotFound("Todo not found".to_string()))?;
+    let (todo, _) = access::authorize(&store, user.user.id, todo_id, Action::View).await?;
 
     Ok(Json(TodoResponse::from(todo)))
 }
 
+/// Update some or all fields of a todo; needs the owner or an editor.
+#[utoipa::path(
+    put,
+    path = "/api/todos/{id}",
+    tag = "todos",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    request_body = UpdateTodo,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated todo", body = TodoResponse),
+        (status = 400, description = "Invalid input or scheduled date in the past", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The todo is only shared for viewing", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub async fn update_todo(
-    State(db): State<Database>,
+    State(store): State<DynStore>,
+    State(metrics): State<Metrics>,
     user: AuthenticatedUser,
     Path(todo_id): Path<Uuid>,
     Json(payload): Json<UpdateTodo>,
 ) -> Result<Json<TodoResponse>> {
-    // Validate input
-    payload.validate()
-        .map_err(|e| AppError::Validation(format!("Validation error: {}", e)))?;
-
-    // Validate scheduled_for is not in the past (optional validation)
-    if let Some(scheduled_for) = payload.scheduled_for {
-        let now = Utc::now();
-        if scheduled_for < now {
-            return Err(AppError::Validation(
-                "Scheduled date cannot be in the past".to_string()
+    let todo = update(&store, &metrics, &user.user, todo_id, payload).await?;
+
+    Ok(Json(TodoResponse::from(todo)))
+}
+
+/// Assign a todo to a user who can see it, or unassign it with `null`;
+/// needs the owner or an editor.
+#[utoipa::path(
+    put,
+    path = "/api/todos/{id}/assignee",
+    tag = "todos",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    request_body = AssignTodo,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated todo", body = TodoResponse),
+        (status = 400, description = "The assignee cannot see the todo", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The todo is only shared for viewing", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn assign_todo(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+    Json(payload): Json<AssignTodo>,
+) -> Result<Json<TodoResponse>> {
+    access::authorize(&store, user.user.id, todo_id, Action::Assign).await?;
+    if let Some(assignee_id) = payload.assignee_id {
+        if !access::can_view(&store, assignee_id, todo_id).await? {
+            return Err(AppError::invalid_field(
+                "assignee_id",
+                "no_access",
+                "Todos can only be assigned to their owner or users they are shared with",
             ));
         }
     }
 
-    // Update todo
-    let todo = db
-        .update_todo(todo_id, user.user.id, payload)
+    let todo = store
+        .assign_todo(todo_id, payload.assignee_id)
+        .await?
+        .ok_or(AppError::NotFound("Todo not found".to_string()))?;
+
+    Ok(Json(TodoResponse::from(todo)))
+}
+
+/// Snooze a todo: move its schedule to a preset time or some minutes from
+/// now, and leave it out of listings until then; needs the owner or an
+/// editor.
+///
+/// Presets are taken in the user's time zone: `later_today` is three hours
+/// from now, `tomorrow_morning` 9:00 tomorrow and `next_week` 9:00 next
+/// Monday. The first snooze keeps the schedule it replaced in
+/// `original_scheduled_for`.
+#[utoipa::path(
+    post,
+    path = "/api/todos/{id}/snooze",
+    tag = "todos",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    request_body = SnoozeTodo,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Snoozed todo", body = TodoResponse),
+        (status = 400, description = "Neither or both of a preset and minutes, or minutes out of range", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The todo is only shared for viewing", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn snooze_todo(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+    Json(payload): Json<SnoozeTodo>,
+) -> Result<Json<TodoResponse>> {
+    let now = Utc::now();
+    let until = match (payload.preset, payload.minutes) {
+        (Some(preset), None) => calendar::snooze_until(preset, user.user.tz(), now),
+        (None, Some(minutes)) if (1..=MAX_SNOOZE_MINUTES).contains(&minutes) => now + Duration::minutes(minutes),
+        (None, Some(_)) => {
+            return Err(AppError::invalid_field("minutes", "out_of_range", "Snoozes last 1 minute to a year"));
+        }
+        (None, None) => {
+            return Err(AppError::invalid_field("preset", "required", "Give a preset or a number of minutes"));
+        }
+        (Some(_), Some(_)) => {
+            return Err(AppError::invalid_field("minutes", "exclusive", "Give a preset or a number of minutes, not both"));
+        }
+    };
+    access::authorize(&store, user.user.id, todo_id, Action::Edit).await?;
+
+    let todo = store
+        .snooze_todo(todo_id, until)
+        .await?
+        .ok_or(AppError::NotFound("Todo not found".to_string()))?;
+
+    Ok(Json(TodoResponse::from(todo)))
+}
+
+/// Bring a snoozed todo back into listings now, keeping its schedule; needs
+/// the owner or an editor.
+#[utoipa::path(
+    delete,
+    path = "/api/todos/{id}/snooze",
+    tag = "todos",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The todo, no longer hidden", body = TodoResponse),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The todo is only shared for viewing", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn unsnooze_todo(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(todo_id): Path<Uuid>,
+) -> Result<Json<TodoResponse>> {
+    access::authorize(&store, user.user.id, todo_id, Action::Edit).await?;
+
+    let todo = store
+        .unsnooze_todo(todo_id)
         .await?
         .ok_or(AppError::NotFound("Todo not found".to_string()))?;
 
     Ok(Json(TodoResponse::from(todo)))
 }
 
+/// Delete a todo; only its owner can.
+#[utoipa::path(
+    delete,
+    path = "/api/todos/{id}",
+    tag = "todos",
+    params(("id" = Uuid, Path, description = "Todo id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Todo deleted"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 403, description = "The user is not the todo's owner", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such todo visible to this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
 pub async fn delete_todo(
-    State(db): State<Database>,
+    State(store): State<DynStore>,
     user: AuthenticatedUser,
     Path(todo_id): Path<Uuid>,
 ) -> Result<StatusCode> {
-    let deleted = db.delete_todo(todo_id, user.user.id).await?;
+    access::authorize(&store, user.user.id, todo_id, Action::Delete).await?;
+    let deleted = store.delete_todo(todo_id).await?;
     
     if deleted {
         Ok(StatusCode::NO_CONTENT)
     } else {
         Err(AppError::NotFound("Todo not found".to_string()))
     }
-}
\ No newline at end of file
+}
+
+/// Todos that are not snoozed at `now`.
+fn visible(todos: Vec<Todo>, now: DateTime<Utc>) -> Vec<TodoResponse> {
+    todos.into_iter().filter(|todo| !todo.is_hidden(now)).map(TodoResponse::from).collect()
+}
+
+/// Validates and stores a new todo; shared by the REST and WebSocket APIs.
+pub(crate) async fn create(store: &DynStore, metrics: &Metrics, user: &User, mut payload: CreateTodo) -> Result<Todo> {
+    // Validate input
+    payload.validate()?;
+    match payload.schedule.take() {
+        Some(schedule) => payload.scheduled_for = Some(resolve_schedule(schedule, user.tz())?),
+        None => check_not_in_past(payload.scheduled_for)?,
+    }
+    payload.tags = payload.tags.map(normalize_tags).transpose()?;
+    payload.recurrence = payload.recurrence.as_deref().map(normalize_recurrence).transpose()?;
+
+    // Create todo
+    let todo = store.create_todo(user.id, payload).await?;
+    metrics.todos_created.inc();
+    if todo.completed {
+        metrics.todos_completed.inc();
+    }
+
+    Ok(todo)
+}
+
+/// Validates and applies a partial update; shared by the REST and WebSocket
+/// APIs.
+pub(crate) async fn update(
+    store: &DynStore,
+    metrics: &Metrics,
+    user: &User,
+    todo_id: Uuid,
+    mut payload: UpdateTodo,
+) -> Result<Todo> {
+    // Validate input
+    payload.validate()?;
+    match payload.schedule.take() {
+        Some(schedule) => payload.scheduled_for = Some(resolve_schedule(schedule, user.tz())?),
+        None => check_not_in_past(payload.scheduled_for)?,
+    }
+    payload.tags = payload.tags.map(normalize_tags).transpose()?;
+    if let Some(Some(rule)) = &payload.recurrence {
+        payload.recurrence = Some(Some(normalize_recurrence(rule)?));
+    }
+
+    let (current, _) = access::authorize(store, user.id, todo_id, Action::Edit).await?;
+
+    // Only count completions that change the todo's state
+    let completing = payload.completed == Some(true) && !current.completed;
+
+    // Update todo
+    let todo = store
+        .update_todo(todo_id, payload)
+        .await?
+        .ok_or(AppError::NotFound("Todo not found".to_string()))?;
+
+    if completing && todo.completed {
+        metrics.todos_completed.inc();
+    }
+
+    Ok(todo)
+}
+
+/// Scheduled dates may be omitted but not in the past.
+fn check_not_in_past(scheduled_for: Option<DateTime<Utc>>) -> Result<()> {
+    match scheduled_for {
+        Some(scheduled_for) if scheduled_for < Utc::now() => Err(in_past()),
+        _ => Ok(()),
+    }
+}
+
+/// When a todo scheduled as sent is due. A date alone is due at the end of
+/// that day in `tz`, and may be any day from the user's today on.
+fn resolve_schedule(schedule: Schedule, tz: Tz) -> Result<DateTime<Utc>> {
+    let now = Utc::now();
+    match schedule {
+        Schedule::At(at) if at < now => Err(in_past()),
+        Schedule::At(at) => Ok(at),
+        Schedule::On(date) if date < calendar::today(tz, now) => Err(in_past()),
+        Schedule::On(date) => Ok(calendar::local_to_utc(tz, date.and_time(calendar::end_of_day()))),
+    }
+}
+
+fn in_past() -> AppError {
+    AppError::invalid_field("scheduled_for", "not_in_past", "Scheduled date cannot be in the past")
+}
+
+/// Tags are stored lowercase, each once.
+pub(crate) fn normalize_tags(tags: Vec<String>) -> Result<Vec<String>> {
+    let mut normalized: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = models::normalize_tag(&tag).ok_or(AppError::invalid_field(
+            "tags",
+            "invalid_tag",
+            "Tags are up to 50 letters, digits, '-' and '_', starting with a letter",
+        ))?;
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.len() > models::MAX_TAGS {
+        return Err(AppError::invalid_field("tags", "too_many", "A todo can have at most 20 tags"));
+    }
+
+    Ok(normalized)
+}
+
+/// Repeat rules are stored the way they are written back out.
+pub(crate) fn normalize_recurrence(rule: &str) -> Result<String> {
+    let rule = rule.parse::<Recurrence>().map_err(|_| {
+        AppError::invalid_field("recurrence", "invalid_recurrence", "Not a supported iCalendar RRULE")
+    })?;
+
+    Ok(rule.to_string())
+}
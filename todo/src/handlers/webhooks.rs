@@ -0,0 +1,252 @@
+use axum::{extract::State, http::StatusCode};
+use serde_json::json;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    auth::AuthenticatedUser,
+    error::{AppError, Result},
+    extract::{Json, Path},
+    models::{
+        CreateWebhook, UpdateWebhook, Webhook, WebhookDelivery, WebhookDeliveryLog, WebhookResponse,
+    },
+    store::DynStore,
+    webhooks::{self, Webhooks},
+};
+
+/// Deliveries listed per webhook, newest first.
+const DELIVERY_LOG_LIMIT: i64 = 100;
+
+/// Subscribe a URL to the authenticated user's todo events.
+///
+/// Each delivery is a `POST` of `{"id", "type", "data"}` signed with the
+/// webhook's secret: `X-Webhook-Signature` is `sha256=` and the hex
+/// HMAC-SHA256 of `"{X-Webhook-Timestamp}.{body}"`. The secret is only
+/// returned here.
+#[utoipa::path(
+    post,
+    path = "/api/webhooks",
+    tag = "webhooks",
+    request_body = CreateWebhook,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 201, description = "Webhook created, with its secret", body = WebhookResponse),
+        (status = 400, description = "Invalid input or private target", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn create_webhook(
+    State(store): State<DynStore>,
+    State(webhooks): State<Webhooks>,
+    user: AuthenticatedUser,
+    Json(payload): Json<CreateWebhook>,
+) -> Result<(StatusCode, Json<WebhookResponse>)> {
+    payload.validate()?;
+    webhooks.check_target(&payload.url)?;
+
+    let secret = payload.secret.unwrap_or_else(webhooks::generate_secret);
+    let webhook = store
+        .create_webhook(user.user.id, &payload.url, &payload.events, &secret, payload.active.unwrap_or(true))
+        .await?;
+
+    let mut response = WebhookResponse::from(webhook);
+    response.secret = Some(secret);
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// List the authenticated user's webhooks.
+#[utoipa::path(
+    get,
+    path = "/api/webhooks",
+    tag = "webhooks",
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The user's webhooks", body = [WebhookResponse]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_webhooks(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+) -> Result<Json<Vec<WebhookResponse>>> {
+    let webhooks = store.get_webhooks_by_user(user.user.id).await?;
+
+    Ok(Json(webhooks.into_iter().map(WebhookResponse::from).collect()))
+}
+
+/// Fetch a single webhook.
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The webhook", body = WebhookResponse),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such webhook for this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_webhook(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<Json<WebhookResponse>> {
+    let webhook = find(&store, webhook_id, user.user.id).await?;
+
+    Ok(Json(WebhookResponse::from(webhook)))
+}
+
+/// Update some or all fields of a webhook. Deliveries already queued keep
+/// going to the webhook's current URL.
+#[utoipa::path(
+    put,
+    path = "/api/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    request_body = UpdateWebhook,
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Updated webhook", body = WebhookResponse),
+        (status = 400, description = "Invalid input or private target", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such webhook for this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn update_webhook(
+    State(store): State<DynStore>,
+    State(webhooks): State<Webhooks>,
+    user: AuthenticatedUser,
+    Path(webhook_id): Path<Uuid>,
+    Json(payload): Json<UpdateWebhook>,
+) -> Result<Json<WebhookResponse>> {
+    payload.validate()?;
+    if let Some(url) = &payload.url {
+        webhooks.check_target(url)?;
+    }
+
+    let webhook = store
+        .update_webhook(webhook_id, user.user.id, payload)
+        .await?
+        .ok_or(AppError::NotFound("Webhook not found".to_string()))?;
+
+    Ok(Json(WebhookResponse::from(webhook)))
+}
+
+/// Delete a webhook along with its queued deliveries and logs.
+#[utoipa::path(
+    delete,
+    path = "/api/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 204, description = "Webhook deleted"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such webhook for this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn delete_webhook(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<StatusCode> {
+    if !store.delete_webhook(webhook_id, user.user.id).await? {
+        return Err(AppError::NotFound("Webhook not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Queue a `ping` delivery to check the receiver, even if the webhook is
+/// inactive. Follow it in the delivery log.
+#[utoipa::path(
+    post,
+    path = "/api/webhooks/{id}/test",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 202, description = "Test delivery queued", body = WebhookDelivery),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such webhook for this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn test_webhook(
+    State(store): State<DynStore>,
+    State(webhooks): State<Webhooks>,
+    user: AuthenticatedUser,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<WebhookDelivery>)> {
+    let webhook = find(&store, webhook_id, user.user.id).await?;
+    let payload = json!({
+        "webhook_id": webhook.id,
+        "message": "Test event from the todo service",
+    });
+    let delivery = store.enqueue_webhook_delivery(webhook.id, "ping", &payload).await?;
+    webhooks.wake();
+
+    Ok((StatusCode::ACCEPTED, Json(delivery)))
+}
+
+/// List a webhook's most recent 100 deliveries, newest first.
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/{id}/deliveries",
+    tag = "webhooks",
+    params(("id" = Uuid, Path, description = "Webhook id")),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "Recent deliveries", body = [WebhookDelivery]),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such webhook for this user", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_deliveries(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path(webhook_id): Path<Uuid>,
+) -> Result<Json<Vec<WebhookDelivery>>> {
+    let webhook = find(&store, webhook_id, user.user.id).await?;
+    let deliveries = store.get_webhook_deliveries(webhook.id, DELIVERY_LOG_LIMIT).await?;
+
+    Ok(Json(deliveries))
+}
+
+/// Fetch a delivery with every attempt made at it.
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/{id}/deliveries/{delivery_id}",
+    tag = "webhooks",
+    params(
+        ("id" = Uuid, Path, description = "Webhook id"),
+        ("delivery_id" = Uuid, Path, description = "Delivery id"),
+    ),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 200, description = "The delivery and its attempts", body = WebhookDeliveryLog),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "No such webhook or delivery", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+pub async fn get_delivery(
+    State(store): State<DynStore>,
+    user: AuthenticatedUser,
+    Path((webhook_id, delivery_id)): Path<(Uuid, Uuid)>,
+) -> Result<Json<WebhookDeliveryLog>> {
+    let webhook = find(&store, webhook_id, user.user.id).await?;
+    let delivery = store
+        .get_webhook_delivery(delivery_id, webhook.id)
+        .await?
+        .ok_or(AppError::NotFound("Delivery not found".to_string()))?;
+    let attempts = store.get_webhook_attempts(delivery.id).await?;
+
+    Ok(Json(WebhookDeliveryLog { delivery, attempts }))
+}
+
+async fn find(store: &DynStore, webhook_id: Uuid, user_id: Uuid) -> Result<Webhook> {
+    store
+        .get_webhook_by_id(webhook_id, user_id)
+        .await?
+        .ok_or(AppError::NotFound("Webhook not found".to_string()))
+}
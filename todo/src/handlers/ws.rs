@@ -0,0 +1,315 @@
+use axum::{
+    extract::{
+        ws::{close_code, rejection::WebSocketUpgradeRejection, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        State,
+    },
+    http::HeaderMap,
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::{
+    sync::broadcast::error::RecvError,
+    time::{interval_at, sleep, timeout, Instant},
+};
+use tracing::{debug, error, info_span, warn, Instrument};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::{
+    auth::{bearer_token, AuthenticatedUser, JwtService},
+    error::{AppError, Result},
+    events::Notification,
+    extract::Query,
+    handlers::todo,
+    metrics::Metrics,
+    models::{ClientMessage, ServerMessage, Todo, UpdateTodo, User, UserResponse, Viewer},
+    presence::{Presence, PresenceGuard},
+    shutdown::Shutdown,
+    store::DynStore,
+    telemetry,
+};
+
+/// How often the server pings; a connection that sends nothing, not even a
+/// pong, for two intervals is dropped.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long a single outgoing message may wait for the client to read.
+/// Clients that fall further behind are disconnected.
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Largest message accepted from a client.
+const MAX_MESSAGE_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SocketParams {
+    /// JWT for clients that cannot set the `Authorization` header, such as
+    /// browsers.
+    access_token: Option<String>,
+}
+
+/// Open a WebSocket for live collaboration on the user's todos and those
+/// shared with them.
+///
+/// Clients send `ClientMessage` commands and receive `ServerMessage`s, all
+/// as JSON text frames: acknowledgements, changes made by any client,
+/// presence and pongs. The token is read from the `Authorization` header or,
+/// failing that, the `access_token` query parameter; the connection is
+/// closed when it expires.
+#[utoipa::path(
+    get,
+    path = "/api/ws",
+    tag = "todos",
+    params(SocketParams),
+    security(("bearer_auth" = [])),
+    responses(
+        (status = 101, description = "Switching to the WebSocket protocol; see ClientMessage and ServerMessage"),
+        (status = 400, description = "Not a WebSocket upgrade request", body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "Missing or invalid token", body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn todo_socket(
+    State(store): State<DynStore>,
+    State(jwt): State<JwtService>,
+    State(metrics): State<Metrics>,
+    State(presence): State<Presence>,
+    State(shutdown): State<Shutdown>,
+    Query(params): Query<SocketParams>,
+    headers: HeaderMap,
+    upgrade: std::result::Result<WebSocketUpgrade, WebSocketUpgradeRejection>,
+) -> Result<Response> {
+    // Authenticate before looking at the upgrade so that plain requests
+    // without a token get the same 401 as every other route.
+    let token = bearer_token(&headers)
+        .or(params.access_token.as_deref())
+        .ok_or(AppError::Unauthorized)?;
+    let claims = jwt.verify_token(token)?;
+    let user = AuthenticatedUser::from_claims(&claims, &store).await?.user;
+    telemetry::record_user(user.id);
+
+    let upgrade = upgrade.map_err(|rejection| AppError::BadRequest(rejection.body_text()))?;
+    let expires_at = DateTime::from_timestamp(claims.exp as i64, 0).unwrap_or_else(Utc::now);
+    let session = Session {
+        id: Uuid::new_v4(),
+        user,
+        expires_at,
+        store,
+        metrics,
+    };
+    let span = info_span!("websocket", user_id = %session.user.id, session_id = %session.id);
+
+    Ok(upgrade
+        .max_message_size(MAX_MESSAGE_BYTES)
+        .on_upgrade(move |socket| session.run(socket, presence, shutdown).instrument(span)))
+}
+
+/// One authenticated connection.
+struct Session {
+    id: Uuid,
+    user: User,
+    expires_at: DateTime<Utc>,
+    store: DynStore,
+    metrics: Metrics,
+}
+
+impl Session {
+    async fn run(self, mut socket: WebSocket, presence: Presence, shutdown: Shutdown) {
+        self.metrics.websocket_connections.inc();
+        debug!("WebSocket connected");
+
+        if let Err(e) = self.serve(&mut socket, presence, shutdown).await {
+            debug!("WebSocket closed: {}", e);
+        }
+
+        self.metrics.websocket_connections.dec();
+    }
+
+    async fn serve(&self, socket: &mut WebSocket, presence: Presence, shutdown: Shutdown) -> anyhow::Result<()> {
+        let mut live = self.store.subscribe_todo_events().await?;
+        // Lists shared later show up on the next connection; revoked ones are
+        // left as soon as that is noticed
+        let shared = self.shared_lists().await?;
+        let mut presence = presence.join(
+            std::iter::once(self.user.id).chain(shared),
+            Viewer {
+                session_id: self.id,
+                user_id: self.user.id,
+                username: self.user.username.clone(),
+                connected_at: Utc::now(),
+            },
+        );
+        let expiry = sleep((self.expires_at - Utc::now()).to_std().unwrap_or_default());
+        tokio::pin!(expiry);
+        let mut heartbeat = interval_at(Instant::now() + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+        let mut last_heard = Instant::now();
+
+        send(socket, &ServerMessage::Welcome {
+            session_id: self.id,
+            user: UserResponse::from(self.user.clone()),
+            heartbeat_secs: HEARTBEAT_INTERVAL.as_secs(),
+        })
+        .await?;
+        for (owner_id, viewers) in presence.viewers() {
+            send(socket, &ServerMessage::Presence { owner_id, viewers }).await?;
+        }
+
+        loop {
+            // Each iteration handles one thing and sends at most one reply,
+            // so a client that stops reading also stops being read from.
+            // Events it falls behind on pile up in the bus until it lags,
+            // and is told to resync, or the send times out.
+            let message = tokio::select! {
+                incoming = socket.recv() => {
+                    let Some(incoming) = incoming else {
+                        return Ok(());
+                    };
+                    last_heard = Instant::now();
+                    match incoming? {
+                        Message::Text(text) => self.handle(&text).await,
+                        Message::Binary(_) => ServerMessage::Error {
+                            reference: None,
+                            error: AppError::BadRequest("Messages must be JSON text frames".to_string()).problem(),
+                        },
+                        // Pings are answered by the library; any frame counts
+                        // as a sign of life.
+                        Message::Ping(_) | Message::Pong(_) => continue,
+                        Message::Close(_) => return Ok(()),
+                    }
+                }
+                next = live.recv() => match next {
+                    Ok(Notification::Event(event)) if event.is_for(self.user.id) => ServerMessage::Event { event: *event },
+                    Ok(Notification::Event(_)) => continue,
+                    Ok(Notification::Missed) | Err(RecvError::Lagged(_)) => ServerMessage::Resync,
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                (owner_id, viewers) = presence.changed() => {
+                    // The share may have been revoked since joining
+                    if owner_id != self.user.id && !self.shared_lists().await?.contains(&owner_id) {
+                        presence.leave(owner_id);
+                        continue;
+                    }
+                    ServerMessage::Presence { owner_id, viewers }
+                }
+                _ = heartbeat.tick() => {
+                    if last_heard.elapsed() >= 2 * HEARTBEAT_INTERVAL {
+                        warn!("Dropping WebSocket that stopped answering pings");
+                        return Ok(());
+                    }
+                    self.leave_revoked(&mut presence).await?;
+                    send_frame(socket, Message::Ping(Vec::new())).await?;
+                    continue;
+                }
+                _ = &mut expiry => return close(socket, close_code::POLICY, "Token expired").await,
+                _ = shutdown.requested() => return close(socket, close_code::AWAY, "Server shutting down").await,
+            };
+
+            send(socket, &message).await?;
+        }
+    }
+
+    /// Owners who share their whole list with the user. A share of single
+    /// todos does not show the grantee who else has the list open.
+    async fn shared_lists(&self) -> Result<Vec<Uuid>> {
+        let shares = self.store.get_shares_for_user(self.user.id).await?;
+        Ok(shares
+            .into_iter()
+            .filter(|share| share.grantee_id == self.user.id && share.todo_id.is_none())
+            .map(|share| share.owner_id)
+            .collect())
+    }
+
+    /// Leaves the lists that are no longer shared with the user, so the owner
+    /// stops seeing them there even while nothing changes.
+    async fn leave_revoked(&self, presence: &mut PresenceGuard) -> Result<()> {
+        let lists = presence.lists();
+        if lists.len() == 1 {
+            return Ok(());
+        }
+        let shared = self.shared_lists().await?;
+        for list in lists {
+            if list != self.user.id && !shared.contains(&list) {
+                presence.leave(list);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs one command and returns the reply.
+    async fn handle(&self, text: &str) -> ServerMessage {
+        // Parse in two steps so even a malformed command gets its `ref` back
+        let value: Value = match serde_json::from_str(text) {
+            Ok(value) => value,
+            Err(e) => return reply(None, Err(AppError::BadRequest(format!("Invalid JSON: {}", e)))),
+        };
+        let reference = value.get("ref").and_then(Value::as_str).map(str::to_string);
+        let command = match serde_json::from_value(value) {
+            Ok(command) => command,
+            Err(e) => return reply(reference, Err(AppError::BadRequest(format!("Invalid message: {}", e)))),
+        };
+
+        let user = &self.user;
+        match command {
+            ClientMessage::Create { reference, todo } => {
+                reply(reference, todo::create(&self.store, &self.metrics, user, todo).await)
+            }
+            ClientMessage::Update { reference, todo_id, changes } => {
+                reply(reference, todo::update(&self.store, &self.metrics, user, todo_id, changes).await)
+            }
+            ClientMessage::Complete { reference, todo_id, completed } => {
+                let changes = UpdateTodo {
+                    completed: Some(completed),
+                    ..Default::default()
+                };
+                reply(reference, todo::update(&self.store, &self.metrics, user, todo_id, changes).await)
+            }
+            ClientMessage::Ping { reference } => ServerMessage::Pong { reference },
+        }
+    }
+}
+
+fn reply(reference: Option<String>, result: Result<Todo>) -> ServerMessage {
+    match result {
+        Ok(todo) => ServerMessage::Ack {
+            reference,
+            todo: todo.into(),
+        },
+        Err(e) => ServerMessage::Error {
+            reference,
+            error: e.problem(),
+        },
+    }
+}
+
+async fn send(socket: &mut WebSocket, message: &ServerMessage) -> anyhow::Result<()> {
+    let text = match serde_json::to_string(message) {
+        Ok(text) => text,
+        Err(e) => {
+            error!("Failed to serialize WebSocket message: {}", e);
+            return Ok(());
+        }
+    };
+    send_frame(socket, Message::Text(text)).await
+}
+
+/// Sends a frame, giving up on clients that stop reading.
+async fn send_frame(socket: &mut WebSocket, frame: Message) -> anyhow::Result<()> {
+    match timeout(SEND_TIMEOUT, socket.send(frame)).await {
+        Ok(result) => Ok(result?),
+        Err(_) => {
+            warn!("Dropping WebSocket that stopped reading");
+            anyhow::bail!("send timed out")
+        }
+    }
+}
+
+async fn close(socket: &mut WebSocket, code: u16, reason: &'static str) -> anyhow::Result<()> {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    send_frame(socket, Message::Close(Some(frame))).await
+}
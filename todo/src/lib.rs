@@ -0,0 +1,30 @@
+pub mod access;
+pub mod attachments;
+pub mod auth;
+pub mod blobs;
+pub mod calendar;
+pub mod comments;
+pub mod config;
+pub mod error;
+pub mod events;
+pub mod extract;
+pub mod handlers;
+pub mod metrics;
+pub mod models;
+pub mod openapi;
+pub mod presence;
+pub mod quick_add;
+pub mod recurrence;
+pub mod request_id;
+pub mod routes;
+pub mod security;
+pub mod server;
+pub mod shutdown;
+pub mod state;
+pub mod stats;
+pub mod store;
+pub mod sync;
+pub mod telemetry;
+pub mod time_tracking;
+pub mod tls;
+pub mod webhooks;
@@ -1,57 +1,184 @@
-mod auth;
-mod config;
-mod database;
-mod error;
-mod handlers;
-mod models;
-mod routes;
-
-use axum::{
-    http::{header, Method},
-    Router,
-};
-use std::net::SocketAddr;
-use tower_http::{cors::CorsLayer, services::ServeDir};
-use tracing_subscriber;
+mod cli;
+
+use anyhow::bail;
+use axum::Router;
+use clap::Parser;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tower_http::services::ServeDir;
+use tracing::info;
 
-use crate::{
+use todo_service::{
+    attachments::{self, Attachments},
+    auth::JwtService,
+    blobs,
     config::Config,
-    database::Database,
+    events,
+    metrics,
     routes::create_routes,
+    security,
+    server,
+    state::AppState,
+    store::{self, DynStore, MigrationState},
+    telemetry,
+    tls,
+    webhooks::{self, Webhooks},
 };
 
+use crate::cli::{Cli, Command, ConfigAction, MigrateAction};
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt::init();
+    let cli = Cli::parse();
 
     // Load configuration
-    let config = Config::from_env()?;
-    
-    // Initialize database
-    let db = Database::new(&config.database_url).await?;
-    
+    let config = Config::load(cli.config.as_deref())?;
+    let command = cli.command.unwrap_or(Command::Serve);
+
+    if let Command::Config { action: ConfigAction::Check } = command {
+        return check_config(&config);
+    }
+    if let Err(problems) = config.validate() {
+        bail!("Invalid configuration:\n  {}", problems.join("\n  "));
+    }
+
+    // Initialize logging and trace export
+    let _telemetry = telemetry::init(&config.logging)?;
+
+    // Initialize storage backend
+    let store = store::connect(&config.database).await?;
+
+    match command {
+        Command::Serve => serve(config, store).await,
+        Command::Migrate { action } => migrate(store, action).await,
+        Command::Config { .. } => unreachable!("handled before connecting"),
+    }
+}
+
+async fn serve(config: Config, store: DynStore) -> anyhow::Result<()> {
     // Run migrations
-    db.migrate().await?;
+    store.migrate().await?;
 
-    // Create CORS layer
-    let cors = CorsLayer::new()
-        .allow_origin(tower_http::cors::Any)
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION]);
+    let jwt = JwtService::new(
+        &config.auth.jwt_secret,
+        chrono::Duration::hours(config.auth.token_ttl_hours),
+    );
+    let blobs = blobs::connect(&config.attachments).await?;
+    let state = AppState::new(store.clone(), jwt)
+        .with_webhooks(Webhooks::new(config.webhook_options()))
+        .with_attachments(Attachments::new(blobs, config.attachment_options()));
+    let shutdown = state.shutdown.clone();
+    let metrics = state.metrics.clone();
+    shutdown.listen_for_signals();
+    events::spawn_pruner(
+        store.clone(),
+        Duration::from_secs(config.events.retention_hours.saturating_mul(3600)),
+        shutdown.clone(),
+    );
+    webhooks::spawn_dispatcher(store.clone(), state.webhooks.clone(), metrics.clone(), shutdown.clone());
+    attachments::spawn_cleanup(store.clone(), state.attachments.clone(), shutdown.clone());
 
     // Build our application with routes
     let app = Router::new()
-        .merge(create_routes(db.clone()))
-        .nest_service("/", ServeDir::new("static"))
-        .layer(cors);
+        .merge(create_routes(state))
+        .nest_service("/", ServeDir::new("static"));
+    let app = security::apply(app, &config.cors, &config.security)?;
 
     // Run the server
-    let addr = SocketAddr::from(([0, 0, 0, 0], config.server_port));
-    println!("🚀 Server running on http://{}", addr);
-    
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    let host = config.server.host.as_str();
+    let options = config.shutdown_options();
+    let listener = TcpListener::bind((host, config.server.port)).await?;
+    let rustls = match config.tls.paths() {
+        Some((cert, key)) => {
+            let rustls = tls::load(cert, key).await?;
+            if config.tls.reload_interval_secs > 0 {
+                let every = Duration::from_secs(config.tls.reload_interval_secs);
+                tls::watch(rustls.clone(), cert.to_path_buf(), key.to_path_buf(), every, shutdown.clone());
+            }
+            info!("🚀 Server running on https://{}", listener.local_addr()?);
+            Some(rustls)
+        }
+        None => {
+            info!("🚀 Server running on http://{}", listener.local_addr()?);
+            None
+        }
+    };
+    let api = async {
+        match rustls {
+            Some(rustls) => server::serve_tls(listener, app, rustls, shutdown.clone(), options).await,
+            None => server::serve(listener, app, shutdown.clone(), options).await,
+        }
+    };
+
+    // Plain-HTTP listener sending browsers to the HTTPS port
+    let redirect_listener = match config.tls.redirect_port {
+        Some(port) => {
+            let listener = TcpListener::bind((host, port)).await?;
+            info!("↪️  Redirecting http://{} to HTTPS", listener.local_addr()?);
+            Some(listener)
+        }
+        None => None,
+    };
+    let redirect = async {
+        match redirect_listener {
+            Some(listener) => {
+                let app = tls::redirect_router(config.server.port);
+                server::serve(listener, app, shutdown.clone(), options).await
+            }
+            None => Ok(()),
+        }
+    };
+
+    let metrics_listener = TcpListener::bind((host, config.server.metrics_port)).await?;
+    info!("📈 Metrics on http://{}/metrics", metrics_listener.local_addr()?);
+
+    tokio::try_join!(
+        api,
+        redirect,
+        server::serve(metrics_listener, metrics::router(metrics), shutdown.clone(), options),
+    )?;
+
+    store.close().await;
+
+    Ok(())
+}
+
+/// Prints the effective configuration with secrets redacted, then any
+/// validation problems. Fails when the configuration would not start.
+fn check_config(config: &Config) -> anyhow::Result<()> {
+    print!("{}", toml::to_string_pretty(&config.redacted())?);
+
+    match config.validate() {
+        Ok(()) => {
+            eprintln!("Configuration is valid");
+            Ok(())
+        }
+        Err(problems) => {
+            for problem in &problems {
+                eprintln!("error: {}", problem);
+            }
+            bail!("{} configuration problem(s)", problems.len())
+        }
+    }
+}
+
+async fn migrate(store: DynStore, action: MigrateAction) -> anyhow::Result<()> {
+    match action {
+        MigrateAction::Up => store.migrate().await?,
+        MigrateAction::Down { version } => store.migrate_down(version).await?,
+        MigrateAction::Status => {
+            for status in store.migration_status().await? {
+                let state = match status.state {
+                    MigrationState::Applied { installed_on } => format!("applied {}", installed_on.to_rfc3339()),
+                    MigrationState::Pending => "pending".to_string(),
+                    MigrationState::Modified => "applied, checksum mismatch".to_string(),
+                    MigrationState::Failed => "failed".to_string(),
+                    MigrationState::Missing => "applied, missing from binary".to_string(),
+                };
+                println!("{:>6}  {:<40} {}", status.version, status.description, state);
+            }
+        }
+    }
 
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,190 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::header,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use prometheus::{
+    core::{Collector, Desc},
+    proto::{self, MetricFamily, MetricType},
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+use std::{collections::HashMap, time::Instant};
+use tracing::error;
+
+use crate::store::DynStore;
+
+/// Route label for requests that matched no route, so unknown URLs cannot
+/// grow the label set without bound.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Prometheus registry for one running service.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    latency: HistogramVec,
+    pub todos_created: IntCounter,
+    pub todos_completed: IntCounter,
+    pub websocket_connections: IntGauge,
+    /// Webhook delivery attempts by outcome: `delivered`, `retry` or `dead`.
+    pub webhook_deliveries: IntCounterVec,
+}
+
+impl Metrics {
+    pub fn new(store: DynStore) -> Self {
+        let registry = Registry::new();
+
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route, method and status class"),
+            &["method", "route", "status"],
+        )
+        .expect("valid http_requests_total metric");
+        let latency = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route and method"),
+            &["method", "route"],
+        )
+        .expect("valid http_request_duration_seconds metric");
+        let todos_created = IntCounter::new("todos_created_total", "Todos created")
+            .expect("valid todos_created_total metric");
+        let todos_completed = IntCounter::new("todos_completed_total", "Todos marked as completed")
+            .expect("valid todos_completed_total metric");
+        let websocket_connections = IntGauge::new("websocket_connections", "Open /api/ws connections")
+            .expect("valid websocket_connections metric");
+        let webhook_deliveries = IntCounterVec::new(
+            Opts::new("webhook_deliveries_total", "Webhook delivery attempts by outcome"),
+            &["outcome"],
+        )
+        .expect("valid webhook_deliveries_total metric");
+
+        registry.register(Box::new(requests.clone())).expect("register http_requests_total");
+        registry.register(Box::new(latency.clone())).expect("register http_request_duration_seconds");
+        registry.register(Box::new(todos_created.clone())).expect("register todos_created_total");
+        registry.register(Box::new(todos_completed.clone())).expect("register todos_completed_total");
+        registry.register(Box::new(websocket_connections.clone())).expect("register websocket_connections");
+        registry.register(Box::new(webhook_deliveries.clone())).expect("register webhook_deliveries_total");
+        registry.register(Box::new(PoolCollector::new(store))).expect("register pool metrics");
+
+        Self {
+            registry,
+            requests,
+            latency,
+            todos_created,
+            todos_completed,
+            websocket_connections,
+            webhook_deliveries,
+        }
+    }
+
+    /// Current values in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Records the count, latency and status class of every request, labelled
+/// with the route template (`/api/todos/:id`) rather than the raw URI.
+pub async fn track_requests(State(metrics): State<Metrics>, request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+    let method = request.method().to_string();
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let status = format!("{}xx", response.status().as_u16() / 100);
+    metrics.requests.with_label_values(&[&method, &route, &status]).inc();
+    metrics.latency.with_label_values(&[&method, &route]).observe(elapsed);
+
+    response
+}
+
+/// The scrape endpoint, served on its own port so it is not exposed
+/// alongside the public API.
+pub fn router(metrics: Metrics) -> Router {
+    Router::new()
+        .route("/metrics", get(render))
+        .with_state(metrics)
+}
+
+async fn render(State(metrics): State<Metrics>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], metrics.render())
+}
+
+/// Reads the store's pool statistics at scrape time. Exports nothing for
+/// backends without a connection pool.
+struct PoolCollector {
+    store: DynStore,
+    descs: Vec<Desc>,
+}
+
+const POOL_SIZE: (&str, &str) = ("db_pool_connections", "Open database connections, idle or in use");
+const POOL_IDLE: (&str, &str) = ("db_pool_idle_connections", "Idle database connections");
+const POOL_MAX: (&str, &str) = ("db_pool_max_connections", "Configured maximum database connections");
+const POOL_WAIT: (&str, &str) = ("db_pool_acquire_wait_seconds", "Time spent waiting to check out a database connection");
+
+impl PoolCollector {
+    fn new(store: DynStore) -> Self {
+        let descs = [POOL_SIZE, POOL_IDLE, POOL_MAX, POOL_WAIT]
+            .into_iter()
+            .map(|(name, help)| {
+                Desc::new(name.to_string(), help.to_string(), Vec::new(), HashMap::new())
+                    .expect("valid pool metric descriptor")
+            })
+            .collect();
+
+        Self { store, descs }
+    }
+}
+
+impl Collector for PoolCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        self.descs.iter().collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let Some(stats) = self.store.pool_stats() else {
+            return Vec::new();
+        };
+
+        let mut wait = proto::Summary::default();
+        wait.set_sample_count(stats.acquires);
+        wait.set_sample_sum(stats.acquire_wait.as_secs_f64());
+        let mut wait_metric = proto::Metric::default();
+        wait_metric.set_summary(wait);
+
+        vec![
+            gauge_family(POOL_SIZE, stats.size.into()),
+            gauge_family(POOL_IDLE, stats.idle.into()),
+            gauge_family(POOL_MAX, stats.max_size.into()),
+            family(POOL_WAIT, MetricType::SUMMARY, wait_metric),
+        ]
+    }
+}
+
+fn gauge_family(meta: (&str, &str), value: f64) -> MetricFamily {
+    let mut gauge = proto::Gauge::default();
+    gauge.set_value(value);
+    let mut metric = proto::Metric::default();
+    metric.set_gauge(gauge);
+    family(meta, MetricType::GAUGE, metric)
+}
+
+fn family((name, help): (&str, &str), kind: MetricType, metric: proto::Metric) -> MetricFamily {
+    let mut family = MetricFamily::default();
+    family.set_name(name.to_string());
+    family.set_help(help.to_string());
+    family.set_field_type(kind);
+    family.set_metric(vec![metric]);
+    family
+}
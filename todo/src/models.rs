@@ -1,48 +1,73 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use utoipa::ToSchema;
 use uuid::Uuid;
 use validator::Validate;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
     pub email: String,
     #[serde(skip_serializing)]
     pub password_hash: String,
+    /// IANA time zone name.
+    pub timezone: String,
+    /// BCP 47 language tag.
+    pub locale: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+impl User {
+    /// The user's time zone; UTC if the stored name is not known.
+    pub fn tz(&self) -> Tz {
+        crate::calendar::parse_timezone(&self.timezone).unwrap_or(Tz::UTC)
+    }
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct CreateUser {
     #[validate(length(min = 3, max = 50))]
+    #[schema(min_length = 3, max_length = 50, example = "alice")]
     pub username: String,
     #[validate(email)]
+    #[schema(format = "email", example = "alice@example.com")]
     pub email: String,
     #[validate(length(min = 6))]
+    #[schema(min_length = 6, format = Password)]
     pub password: String,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct LoginRequest {
     #[validate(length(min = 3, max = 50))]
+    #[schema(min_length = 3, max_length = 50, example = "alice")]
     pub username: String,
     #[validate(length(min = 6))]
+    #[schema(min_length = 6, format = Password)]
     pub password: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct AuthResponse {
+    /// JWT to send as `Authorization: Bearer <token>`; valid for
+    /// `auth.token_ttl_hours` (24 by default).
     pub token: String,
     pub user: UserResponse,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UserResponse {
     pub id: Uuid,
     pub username: String,
     pub email: String,
+    #[schema(example = "Europe/Paris")]
+    pub timezone: String,
+    #[schema(example = "fr-FR")]
+    pub locale: String,
     pub created_at: DateTime<Utc>,
 }
 
@@ -52,12 +77,43 @@ impl From<User> for UserResponse {
             id: user.id,
             username: user.username,
             email: user.email,
+            timezone: user.timezone,
+            locale: user.locale,
             created_at: user.created_at,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Where a user is and how they read dates.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Preferences {
+    /// IANA time zone "today" and dates without a time are read in.
+    #[schema(example = "Europe/Paris")]
+    pub timezone: String,
+    /// BCP 47 language tag for clients to format dates with.
+    #[schema(example = "fr-FR")]
+    pub locale: String,
+}
+
+impl From<User> for Preferences {
+    fn from(user: User) -> Self {
+        Preferences {
+            timezone: user.timezone,
+            locale: user.locale,
+        }
+    }
+}
+
+/// `PUT /api/preferences` body; omitted fields are left unchanged.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdatePreferences {
+    #[schema(example = "America/New_York")]
+    pub timezone: Option<String>,
+    #[schema(example = "en-US")]
+    pub locale: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Todo {
     pub id: Uuid,
     pub user_id: Uuid,
@@ -65,37 +121,164 @@ pub struct Todo {
     pub description: Option<String>,
     pub completed: bool,
     pub scheduled_for: Option<DateTime<Utc>>,
+    pub assignee_id: Option<Uuid>,
+    pub priority: Option<Priority>,
+    #[sqlx(json)]
+    pub tags: Vec<String>,
+    /// An iCalendar `RRULE`, see [`crate::recurrence`].
+    pub recurrence: Option<String>,
+    /// When the todo was last marked completed; cleared when it is reopened.
+    pub completed_at: Option<DateTime<Utc>>,
+    /// How often the todo was snoozed.
+    pub snooze_count: i32,
+    /// When the todo was scheduled before it was first snoozed.
+    pub original_scheduled_for: Option<DateTime<Utc>>,
+    /// Listings leave the todo out until then.
+    pub hidden_until: Option<DateTime<Utc>>,
+    /// Total of the todo's stopped time entries.
+    pub tracked_seconds: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+impl Todo {
+    /// Whether the todo is snoozed and kept out of listings at `now`.
+    pub fn is_hidden(&self, now: DateTime<Utc>) -> bool {
+        self.hidden_until.is_some_and(|until| until > now)
+    }
+}
+
+/// How important a todo is; stored as 1 to 3 so it sorts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[repr(i16)]
+pub enum Priority {
+    Low = 1,
+    Medium = 2,
+    High = 3,
+}
+
+/// Most tags a todo can have.
+pub const MAX_TAGS: usize = 20;
+
+/// A tag as stored, lowercase, if `tag` is a valid one: up to 50 letters,
+/// digits, `-` and `_`, starting with a letter.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let valid = tag.chars().next().is_some_and(char::is_alphabetic)
+        && tag.chars().count() <= 50
+        && tag.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    valid.then(|| tag.to_lowercase())
+}
+
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
 pub struct CreateTodo {
     #[validate(length(min = 1, max = 255))]
+    #[schema(min_length = 1, max_length = 255, example = "Learn Rust")]
     pub title: String,
     #[validate(length(max = 1000))]
+    #[schema(max_length = 1000)]
     pub description: Option<String>,
     pub completed: Option<bool>,
+    /// A date and time, or a date alone, due at 23:59 in the user's time
+    /// zone. Must not be in the past.
+    #[serde(rename = "scheduled_for")]
+    #[schema(value_type = Option<String>, example = "2026-11-03")]
+    pub schedule: Option<Schedule>,
+    /// When the todo is due, resolved from `schedule` before storing.
+    #[serde(skip)]
     pub scheduled_for: Option<DateTime<Utc>>,
+    pub priority: Option<Priority>,
+    /// Up to 20 tags of letters, digits, `-` and `_`, starting with a
+    /// letter; stored lowercase.
+    #[schema(example = json!(["finance"]))]
+    pub tags: Option<Vec<String>>,
+    /// An iCalendar `RRULE` such as `FREQ=WEEKLY;BYDAY=MO,WE`. `FREQ`,
+    /// `INTERVAL`, `BYDAY` (weekly rules) and `BYMONTHDAY` (monthly rules)
+    /// are supported.
+    #[schema(example = "FREQ=MONTHLY;BYMONTHDAY=1")]
+    pub recurrence: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+/// When a todo is due, as sent: an RFC 3339 date and time, or a
+/// `YYYY-MM-DD` date read in the user's time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    At(DateTime<Utc>),
+    On(NaiveDate),
+}
+
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        if let Ok(at) = DateTime::parse_from_rfc3339(&text) {
+            return Ok(Schedule::At(at.with_timezone(&Utc)));
+        }
+        NaiveDate::parse_from_str(&text, "%Y-%m-%d").map(Schedule::On).map_err(|_| {
+            serde::de::Error::custom(format!("expected an RFC 3339 date and time or a YYYY-MM-DD date, got '{}'", text))
+        })
+    }
+}
+
+/// Partial update; omitted fields are left unchanged.
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
 pub struct UpdateTodo {
     #[validate(length(min = 1, max = 255))]
+    #[schema(min_length = 1, max_length = 255)]
     pub title: Option<String>,
     #[validate(length(max = 1000))]
+    #[schema(max_length = 1000)]
     pub description: Option<String>,
     pub completed: Option<bool>,
+    /// A date and time, or a date alone, due at 23:59 in the user's time
+    /// zone. Must not be in the past.
+    #[serde(rename = "scheduled_for")]
+    #[schema(value_type = Option<String>, example = "2026-11-03T09:00:00Z")]
+    pub schedule: Option<Schedule>,
+    /// When the todo is due, resolved from `schedule` before storing.
+    #[serde(skip)]
     pub scheduled_for: Option<DateTime<Utc>>,
+    /// `null` clears the priority.
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<Priority>)]
+    pub priority: Option<Option<Priority>>,
+    /// Replaces the tags; `[]` removes them all.
+    pub tags: Option<Vec<String>>,
+    /// `null` stops the todo repeating.
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub recurrence: Option<Option<String>>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct TodoResponse {
     pub id: Uuid,
     pub title: String,
     pub description: Option<String>,
     pub completed: bool,
     pub scheduled_for: Option<DateTime<Utc>>,
+    /// User the todo is assigned to, if any.
+    pub assignee_id: Option<Uuid>,
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// How the todo repeats, as an iCalendar `RRULE`.
+    pub recurrence: Option<String>,
+    /// When the todo was completed, if it is.
+    pub completed_at: Option<DateTime<Utc>>,
+    /// How often the todo was snoozed.
+    #[serde(default)]
+    pub snooze_count: i32,
+    /// When the todo was scheduled before it was first snoozed; `null` if it
+    /// was not scheduled then or was never snoozed.
+    pub original_scheduled_for: Option<DateTime<Utc>>,
+    /// While in the future, the todo is snoozed and left out of listings.
+    pub hidden_until: Option<DateTime<Utc>>,
+    /// Time tracked on the todo by everyone, running timers aside.
+    #[serde(default)]
+    pub tracked_seconds: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -108,12 +291,1043 @@ impl From<Todo> for TodoResponse {
             description: todo.description,
             completed: todo.completed,
             scheduled_for: todo.scheduled_for,
+            assignee_id: todo.assignee_id,
+            priority: todo.priority,
+            tags: todo.tags,
+            recurrence: todo.recurrence,
+            completed_at: todo.completed_at,
+            snooze_count: todo.snooze_count,
+            original_scheduled_for: todo.original_scheduled_for,
+            hidden_until: todo.hidden_until,
+            tracked_seconds: todo.tracked_seconds,
             created_at: todo.created_at,
             updated_at: todo.updated_at,
         }
     }
 }
 
+/// A ready-made snooze, taken in the user's time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SnoozePreset {
+    /// Three hours from now.
+    LaterToday,
+    /// 9:00 tomorrow.
+    TomorrowMorning,
+    /// 9:00 next Monday.
+    NextWeek,
+}
+
+/// `POST /api/todos/{id}/snooze` body: a preset or a number of minutes.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SnoozeTodo {
+    pub preset: Option<SnoozePreset>,
+    /// Minutes from now, 1 to 525600 (a year).
+    #[schema(minimum = 1, maximum = 525600, example = 90)]
+    pub minutes: Option<i64>,
+}
+
+/// `PUT /api/todos/{id}/assignee` body.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AssignTodo {
+    /// The owner or a user the todo is shared with; `null` unassigns it.
+    pub assignee_id: Option<Uuid>,
+}
+
+/// `POST /api/todos/quick` body.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct QuickAddTodo {
+    #[validate(length(min = 1, max = 500))]
+    #[schema(min_length = 1, max_length = 500, example = "Pay rent every 1st at 9am #finance !high")]
+    pub text: String,
+    /// IANA time zone dates and times are read in; the user's if omitted.
+    #[schema(example = "Europe/Paris")]
+    pub timezone: Option<String>,
+    /// Create the todo rather than only returning what was parsed.
+    #[serde(default)]
+    pub commit: bool,
+}
+
+/// The fields read from quick-add text.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ParsedTodo {
+    /// The text left once dates, times, repeats, tags and priority are taken out.
+    pub title: String,
+    pub scheduled_for: Option<DateTime<Utc>>,
+    /// An iCalendar `RRULE`.
+    pub recurrence: Option<String>,
+    pub tags: Vec<String>,
+    pub priority: Option<Priority>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct QuickAddResponse {
+    pub parsed: ParsedTodo,
+    /// The created todo, when committing.
+    pub todo: Option<TodoResponse>,
+}
+
+/// The open todos due on one of the user's days.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AgendaDay {
+    /// The day in the user's time zone.
+    pub date: NaiveDate,
+    /// Soonest first.
+    pub todos: Vec<TodoResponse>,
+}
+
+/// Completions on one of the user's days.
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow, ToSchema)]
+pub struct DayCount {
+    pub date: NaiveDate,
+    pub count: i64,
+}
+
+/// Completions in one week, Monday to Sunday.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct WeekCount {
+    /// The Monday the week starts on; the first week of a range may start
+    /// before `from`.
+    pub week_start: NaiveDate,
+    pub count: i64,
+}
+
+/// Runs of consecutive days with at least one completion.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Streaks {
+    /// Days in the run ending on `to`, or on the day before when `to` is
+    /// today and nothing has been completed yet.
+    pub current: i64,
+    /// Days in the longest run within the range.
+    pub longest: i64,
+}
+
+/// Figures for one user's list.
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow, ToSchema)]
+pub struct ListStats {
+    pub owner_id: Uuid,
+    pub username: String,
+    pub created: i64,
+    pub completed: i64,
+}
+
+/// Figures for the todos carrying one tag.
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow, ToSchema)]
+pub struct TagStats {
+    pub tag: String,
+    pub created: i64,
+    pub completed: i64,
+}
+
+/// Productivity figures over a range of the user's days, counting every
+/// todo they can see.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StatsResponse {
+    /// IANA time zone the days are taken in.
+    pub timezone: String,
+    /// First day of the range.
+    pub from: NaiveDate,
+    /// Last day of the range, included.
+    pub to: NaiveDate,
+    /// Todos created in the range.
+    pub created: i64,
+    /// Todos completed in the range.
+    pub completed: i64,
+    /// Of those, the ones completed after they were due.
+    pub completed_late: i64,
+    /// Open todos that fell due in the range and are now past due.
+    pub overdue: i64,
+    /// Mean time from creation to completion of the todos completed in the
+    /// range; `null` when there are none.
+    pub average_completion_seconds: Option<f64>,
+    /// Every day of the range, oldest first.
+    pub completed_per_day: Vec<DayCount>,
+    /// Every week the range touches, oldest first.
+    pub completed_per_week: Vec<WeekCount>,
+    pub streaks: Streaks,
+    /// Lists with todos created or completed in the range, busiest first.
+    pub by_list: Vec<ListStats>,
+    /// Tags on todos created or completed in the range, busiest first.
+    pub by_tag: Vec<TagStats>,
+}
+
+/// Time a user spent on a todo. Without `ended_at`, it is their running
+/// timer.
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct TimeEntry {
+    pub id: Uuid,
+    pub todo_id: Uuid,
+    /// Who spent the time.
+    pub user_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    /// `null` while the timer runs.
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Whole seconds from `started_at` to `ended_at`; `null` while the
+    /// timer runs.
+    pub duration_seconds: Option<i64>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// `POST /api/todos/{id}/timer/start` response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TimerStarted {
+    pub running: TimeEntry,
+    /// The timer the user had running before, now stopped.
+    pub stopped: Option<TimeEntry>,
+}
+
+/// `POST /api/todos/{id}/time-entries` body: time already spent.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateTimeEntry {
+    pub started_at: DateTime<Utc>,
+    /// After `started_at` and not in the future.
+    pub ended_at: DateTime<Utc>,
+    #[validate(length(max = 1000))]
+    #[schema(max_length = 1000, example = "Call with the client")]
+    pub note: Option<String>,
+}
+
+/// `PUT /api/todos/{id}/time-entries/{entry_id}` body; omitted fields are
+/// left unchanged.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct UpdateTimeEntry {
+    pub started_at: Option<DateTime<Utc>>,
+    /// Setting it on a running timer stops it.
+    pub ended_at: Option<DateTime<Utc>>,
+    /// `null` clears the note.
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(length(max = 1000))]
+    #[schema(value_type = Option<String>, max_length = 1000)]
+    pub note: Option<Option<String>>,
+}
+
+/// Time tracked on one of the user's days in one list.
+#[derive(Debug, Clone, PartialEq, Serialize, FromRow, ToSchema)]
+pub struct TimeReportRow {
+    pub date: NaiveDate,
+    /// The list's owner.
+    pub owner_id: Uuid,
+    pub username: String,
+    pub seconds: i64,
+}
+
+/// Time tracked on one of the user's days.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TimeReportDay {
+    pub date: NaiveDate,
+    pub seconds: i64,
+}
+
+/// Time tracked in one user's list.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TimeReportList {
+    pub owner_id: Uuid,
+    pub username: String,
+    pub seconds: i64,
+}
+
+/// The user's stopped time entries over a range of their days, by the day
+/// each started on.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TimeReport {
+    /// IANA time zone the days are taken in.
+    pub timezone: String,
+    /// First day of the range.
+    pub from: NaiveDate,
+    /// Last day of the range, included.
+    pub to: NaiveDate,
+    pub total_seconds: i64,
+    /// Every day of the range, oldest first.
+    pub by_day: Vec<TimeReportDay>,
+    /// Lists with time tracked in the range, most time first.
+    pub by_list: Vec<TimeReportList>,
+    /// Each day and list with time tracked, by day then list owner's name.
+    pub rows: Vec<TimeReportRow>,
+}
+
+/// What a user may do with a todo. Viewers can read it, editors can also
+/// change and assign it, and only the owner can delete or share it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Viewer,
+    Editor,
+    Owner,
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(Role::Viewer),
+            "editor" => Ok(Role::Editor),
+            "owner" => Ok(Role::Owner),
+            other => Err(format!("Unknown role '{}'", other)),
+        }
+    }
+}
+
+/// Role granted by a share.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ShareRole {
+    Viewer,
+    Editor,
+}
+
+impl ShareRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ShareRole::Viewer => "viewer",
+            ShareRole::Editor => "editor",
+        }
+    }
+}
+
+impl std::str::FromStr for ShareRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "viewer" => Ok(ShareRole::Viewer),
+            "editor" => Ok(ShareRole::Editor),
+            other => Err(format!("Unknown share role '{}'", other)),
+        }
+    }
+}
+
+impl From<ShareRole> for Role {
+    fn from(role: ShareRole) -> Self {
+        match role {
+            ShareRole::Viewer => Role::Viewer,
+            ShareRole::Editor => Role::Editor,
+        }
+    }
+}
+
+/// Access to one todo, or to a whole list, granted by its owner.
+#[derive(Debug, Clone)]
+pub struct Share {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub owner_username: String,
+    pub grantee_id: Uuid,
+    pub grantee_username: String,
+    /// `None` for a share of every todo of the owner's list.
+    pub todo_id: Option<Uuid>,
+    pub role: ShareRole,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateShare {
+    /// User to share with.
+    #[validate(length(min = 3, max = 50))]
+    #[schema(min_length = 3, max_length = 50, example = "bob")]
+    pub username: String,
+    pub role: ShareRole,
+    /// Todo to share; omit it to share the whole list, including todos
+    /// created later.
+    pub todo_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateShare {
+    pub role: ShareRole,
+}
+
+/// Another user, as shown on shares, comments and attachments.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserRef {
+    pub id: Uuid,
+    pub username: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ShareResponse {
+    pub id: Uuid,
+    pub owner: UserRef,
+    pub grantee: UserRef,
+    /// `null` when the whole list is shared.
+    pub todo_id: Option<Uuid>,
+    pub role: ShareRole,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Share> for ShareResponse {
+    fn from(share: Share) -> Self {
+        ShareResponse {
+            id: share.id,
+            owner: UserRef {
+                id: share.owner_id,
+                username: share.owner_username,
+            },
+            grantee: UserRef {
+                id: share.grantee_id,
+                username: share.grantee_username,
+            },
+            todo_id: share.todo_id,
+            role: share.role,
+            created_at: share.created_at,
+        }
+    }
+}
+
+/// A todo of another user, with the caller's role on it.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SharedTodoResponse {
+    #[serde(flatten)]
+    pub todo: TodoResponse,
+    pub owner_id: Uuid,
+    pub role: Role,
+}
+
+impl From<(Todo, Role)> for SharedTodoResponse {
+    fn from((todo, role): (Todo, Role)) -> Self {
+        SharedTodoResponse {
+            owner_id: todo.user_id,
+            todo: todo.into(),
+            role,
+        }
+    }
+}
+
+/// A comment on a todo, with its author's name.
+#[derive(Debug, Clone, FromRow)]
+pub struct Comment {
+    pub id: Uuid,
+    pub todo_id: Uuid,
+    pub author_id: Uuid,
+    pub author_username: String,
+    /// Markdown source.
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub edited_at: Option<DateTime<Utc>>,
+}
+
+/// One version of a comment's body.
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct CommentRevision {
+    pub body: String,
+    /// When this version was written.
+    pub created_at: DateTime<Utc>,
+}
+
+/// A comment that mentions a user, as listed in their inbox.
+#[derive(Debug, Clone, FromRow)]
+pub struct Mention {
+    #[sqlx(flatten)]
+    pub comment: Comment,
+    pub mentioned_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+/// `POST /api/todos/{id}/comments` and `PUT /api/todos/{id}/comments/{comment_id}` body.
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CommentBody {
+    /// Markdown; `@username` mentions users who can see the todo.
+    #[validate(length(min = 1, max = 10000))]
+    #[schema(min_length = 1, max_length = 10000, example = "Blocked on @bob's review")]
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CommentResponse {
+    pub id: Uuid,
+    pub todo_id: Uuid,
+    pub author: UserRef,
+    /// Markdown source.
+    pub body: String,
+    /// `body` rendered to sanitized HTML.
+    pub body_html: String,
+    pub created_at: DateTime<Utc>,
+    /// `null` until the comment is edited.
+    pub edited_at: Option<DateTime<Utc>>,
+}
+
+impl From<Comment> for CommentResponse {
+    fn from(comment: Comment) -> Self {
+        CommentResponse {
+            id: comment.id,
+            todo_id: comment.todo_id,
+            author: UserRef {
+                id: comment.author_id,
+                username: comment.author_username,
+            },
+            body_html: crate::comments::render_markdown(&comment.body),
+            body: comment.body,
+            created_at: comment.created_at,
+            edited_at: comment.edited_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MentionResponse {
+    pub comment: CommentResponse,
+    pub mentioned_at: DateTime<Utc>,
+    /// `null` while unread.
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+impl From<Mention> for MentionResponse {
+    fn from(mention: Mention) -> Self {
+        MentionResponse {
+            comment: mention.comment.into(),
+            mentioned_at: mention.mentioned_at,
+            read_at: mention.read_at,
+        }
+    }
+}
+
+/// A file attached to a todo, with its uploader's name. The bytes are in
+/// the blob store under `blob_key`.
+#[derive(Debug, Clone, FromRow)]
+pub struct Attachment {
+    pub id: Uuid,
+    /// `None` once the todo is deleted, until the cleanup removes the
+    /// attachment and its file.
+    pub todo_id: Option<Uuid>,
+    pub user_id: Uuid,
+    pub username: String,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub blob_key: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// An uploaded file to record, already stored under `blob_key`.
+#[derive(Debug, Clone)]
+pub struct NewAttachment {
+    pub id: Uuid,
+    pub todo_id: Uuid,
+    pub user_id: Uuid,
+    pub filename: String,
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub blob_key: String,
+}
+
+/// `POST /api/todos/{id}/attachments` form; documents the request body,
+/// which the handler reads part by part.
+#[derive(ToSchema)]
+pub struct AttachmentUpload {
+    /// The file, with its name and content type.
+    #[schema(value_type = String, format = Binary)]
+    pub file: Vec<u8>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AttachmentResponse {
+    pub id: Uuid,
+    pub uploaded_by: UserRef,
+    #[schema(example = "receipt.pdf")]
+    pub filename: String,
+    #[schema(example = "application/pdf")]
+    pub content_type: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Attachment> for AttachmentResponse {
+    fn from(attachment: Attachment) -> Self {
+        AttachmentResponse {
+            id: attachment.id,
+            uploaded_by: UserRef {
+                id: attachment.user_id,
+                username: attachment.username,
+            },
+            filename: attachment.filename,
+            content_type: attachment.content_type,
+            size_bytes: attachment.size_bytes,
+            created_at: attachment.created_at,
+        }
+    }
+}
+
+/// The user's attachment storage and the upload limits.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AttachmentUsage {
+    /// Total size of the user's uploads still attached to a todo.
+    pub used_bytes: i64,
+    pub quota_bytes: u64,
+    /// Largest file accepted.
+    pub max_size_bytes: u64,
+    /// MIME types accepted; `type/*` accepts a whole type.
+    pub allowed_types: Vec<String>,
+}
+
+/// What happened to a todo; also the SSE event name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoEventKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl TodoEventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TodoEventKind::Created => "created",
+            TodoEventKind::Updated => "updated",
+            TodoEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+impl std::str::FromStr for TodoEventKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "created" => Ok(TodoEventKind::Created),
+            "updated" => Ok(TodoEventKind::Updated),
+            "deleted" => Ok(TodoEventKind::Deleted),
+            other => Err(format!("Unknown todo event kind '{}'", other)),
+        }
+    }
+}
+
+/// A change to a todo the user owns or has been shared, streamed by
+/// `/api/todos/events`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TodoEvent {
+    /// Increasing id, also sent as the SSE `id`. Send the last one seen as
+    /// `Last-Event-ID` to receive what was missed.
+    pub id: i64,
+    #[serde(rename = "type")]
+    pub kind: TodoEventKind,
+    /// The todo's owner.
+    pub user_id: Uuid,
+    pub todo_id: Uuid,
+    /// The todo after the change; `null` for deletions.
+    pub todo: Option<TodoResponse>,
+    pub created_at: DateTime<Utc>,
+    /// Who the todo was shared with at the time. Assignees must be able to
+    /// see the todo, so they are among them or the owner. Kept out of the
+    /// JSON clients get.
+    #[serde(skip)]
+    pub recipients: Vec<Uuid>,
+}
+
+impl TodoEvent {
+    /// Whether `user_id` owned or could see the todo when it changed.
+    pub fn is_for(&self, user_id: Uuid) -> bool {
+        self.user_id == user_id || self.recipients.contains(&user_id)
+    }
+}
+
+/// Whether a sync mutation creates or changes a todo, or deletes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOp {
+    Upsert,
+    Delete,
+}
+
+/// One change made on a client, possibly while offline.
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct SyncMutation {
+    /// The todo's id, generated by the client for new todos.
+    pub id: Uuid,
+    pub op: SyncOp,
+    /// When the change was made on the client. Later than the server's
+    /// version of a field, it wins; times ahead of the server's clock count
+    /// as now.
+    pub changed_at: DateTime<Utc>,
+    /// Required to create a todo.
+    #[validate(length(min = 1, max = 255))]
+    #[schema(min_length = 1, max_length = 255)]
+    pub title: Option<String>,
+    /// `null` clears the description; omit it to leave it unchanged.
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(length(max = 1000))]
+    #[schema(value_type = Option<String>, max_length = 1000)]
+    pub description: Option<Option<String>>,
+    pub completed: Option<bool>,
+    /// `null` unschedules the todo. Unlike the REST API, past dates are
+    /// accepted, since the change may have been made offline.
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<DateTime<Utc>>)]
+    pub scheduled_for: Option<Option<DateTime<Utc>>>,
+    /// `null` clears the priority.
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<Priority>)]
+    pub priority: Option<Option<Priority>>,
+    /// Replaces the tags; `[]` removes them all.
+    pub tags: Option<Vec<String>>,
+    /// An iCalendar `RRULE`; `null` stops the todo repeating.
+    #[serde(default, deserialize_with = "double_option")]
+    #[schema(value_type = Option<String>)]
+    pub recurrence: Option<Option<String>>,
+}
+
+/// Distinguishes an explicit `null` (`Some(None)`) from an omitted field,
+/// which `#[serde(default)]` leaves as `None`.
+fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SyncPush {
+    /// Applied in order, each on its own.
+    #[schema(max_items = 500)]
+    pub mutations: Vec<SyncMutation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncStatus {
+    /// Every field of the mutation was applied, or already had its value.
+    Applied,
+    /// Some or all fields were changed later on the server and kept.
+    Conflict,
+    /// Invalid, or the id belongs to another user; nothing was applied.
+    Rejected,
+}
+
+/// A field whose server value won over the client's.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SyncConflict {
+    /// The field, or `deleted` when the todo was deleted on the server.
+    pub field: String,
+    #[schema(value_type = Object)]
+    pub server_value: serde_json::Value,
+    pub server_changed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncResult {
+    pub id: Uuid,
+    pub status: SyncStatus,
+    /// The todo after the mutation; `null` if it is deleted or was never
+    /// created.
+    pub todo: Option<TodoResponse>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<SyncConflict>,
+    /// Why the mutation was rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<crate::error::ProblemDetails>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncPushResponse {
+    /// One result per mutation, in order.
+    pub results: Vec<SyncResult>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeletedTodo {
+    pub id: Uuid,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// The user's changes since a sync token.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncChanges {
+    /// Todos created or changed since the token, in their current state.
+    pub todos: Vec<TodoResponse>,
+    /// Todos deleted since the token.
+    pub deleted: Vec<DeletedTodo>,
+    /// Pass as `since` next time.
+    #[schema(example = "42")]
+    pub token: String,
+    /// More changes are waiting; request again with `token` straight away.
+    pub has_more: bool,
+}
+
+/// A command sent over `/api/ws`. `ref` is an optional client-chosen string
+/// echoed back in the matching `ack`, `error` or `pong`.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Create a todo, as `POST /api/todos`.
+    Create {
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        todo: CreateTodo,
+    },
+    /// Update some fields of a todo, as `PUT /api/todos/{id}`.
+    Update {
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        todo_id: Uuid,
+        changes: UpdateTodo,
+    },
+    /// Mark a todo completed, or pending with `"completed": false`.
+    Complete {
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        todo_id: Uuid,
+        #[serde(default = "default_completed")]
+        completed: bool,
+    },
+    /// Application-level heartbeat for clients that cannot send ping frames.
+    Ping {
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+    },
+}
+
+fn default_completed() -> bool {
+    true
+}
+
+/// A message pushed over `/api/ws`.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// First message on every connection.
+    Welcome {
+        session_id: Uuid,
+        user: UserResponse,
+        /// How often the server pings; connections silent for twice this
+        /// long are closed.
+        heartbeat_secs: u64,
+    },
+    /// A command succeeded; `todo` is the todo after the change.
+    Ack {
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        todo: TodoResponse,
+    },
+    /// A command, or an unreadable message, failed.
+    Error {
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+        error: crate::error::ProblemDetails,
+    },
+    /// A change to one of the user's todos, or one shared with them, from
+    /// any client.
+    Event { event: TodoEvent },
+    /// Changes were dropped for this connection; refetch `GET /api/todos`.
+    Resync,
+    /// Who is viewing the list of `owner_id`'s todos, sent for each list the
+    /// user can see on connect and whenever it changes.
+    Presence { owner_id: Uuid, viewers: Vec<Viewer> },
+    Pong {
+        #[serde(rename = "ref")]
+        reference: Option<String>,
+    },
+}
+
+/// One open WebSocket connection viewing a list.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Viewer {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub username: String,
+    pub connected_at: DateTime<Utc>,
+}
+
+/// A user's subscription to their todo events.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    pub events: Vec<TodoEventKind>,
+    /// Key for the HMAC-SHA256 signature on every delivery.
+    pub secret: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateWebhook {
+    /// `http` or `https` URL that receives a `POST` per event.
+    #[validate(url, length(max = 2048))]
+    #[schema(format = "uri", max_length = 2048, example = "https://example.com/hooks/todos")]
+    pub url: String,
+    /// Event types to deliver.
+    #[validate(length(min = 1))]
+    #[schema(min_items = 1)]
+    pub events: Vec<TodoEventKind>,
+    /// Signing secret; one is generated when omitted.
+    #[validate(length(min = 16, max = 255))]
+    #[schema(min_length = 16, max_length = 255, format = Password)]
+    pub secret: Option<String>,
+    /// Defaults to `true`.
+    pub active: Option<bool>,
+}
+
+/// Partial update; omitted fields are left unchanged.
+#[derive(Debug, Default, Deserialize, Validate, ToSchema)]
+pub struct UpdateWebhook {
+    #[validate(url, length(max = 2048))]
+    #[schema(format = "uri", max_length = 2048)]
+    pub url: Option<String>,
+    #[validate(length(min = 1))]
+    #[schema(min_items = 1)]
+    pub events: Option<Vec<TodoEventKind>>,
+    /// Replaces the signing secret.
+    #[validate(length(min = 16, max = 255))]
+    #[schema(min_length = 16, max_length = 255, format = Password)]
+    pub secret: Option<String>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookResponse {
+    pub id: Uuid,
+    pub url: String,
+    pub events: Vec<TodoEventKind>,
+    pub active: bool,
+    /// Only returned when the webhook is created; store it to verify
+    /// signatures.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<Webhook> for WebhookResponse {
+    fn from(webhook: Webhook) -> Self {
+        WebhookResponse {
+            id: webhook.id,
+            url: webhook.url,
+            events: webhook.events,
+            active: webhook.active,
+            secret: None,
+            created_at: webhook.created_at,
+            updated_at: webhook.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    /// Waiting for its first attempt or a retry.
+    Pending,
+    Delivered,
+    /// Every attempt failed; it will not be retried.
+    Dead,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookDeliveryStatus::Pending => "pending",
+            WebhookDeliveryStatus::Delivered => "delivered",
+            WebhookDeliveryStatus::Dead => "dead",
+        }
+    }
+}
+
+impl std::str::FromStr for WebhookDeliveryStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(WebhookDeliveryStatus::Pending),
+            "delivered" => Ok(WebhookDeliveryStatus::Delivered),
+            "dead" => Ok(WebhookDeliveryStatus::Dead),
+            other => Err(format!("Unknown webhook delivery status '{}'", other)),
+        }
+    }
+}
+
+/// One event queued for one webhook, and how sending it went.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WebhookDelivery {
+    /// Also sent as the `X-Webhook-Id` header; receivers can use it to drop
+    /// duplicates.
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    /// `created`, `updated`, `deleted` or `ping` for test events.
+    #[schema(example = "created")]
+    pub event: String,
+    /// The `data` of the body sent: a `TodoEvent`, or details of the test.
+    #[schema(value_type = Object)]
+    pub payload: serde_json::Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    /// When the next attempt is due; `null` unless pending.
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// HTTP status of the last attempt, if the receiver answered.
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// One try at sending a delivery.
+#[derive(Debug, Clone, Serialize, FromRow, ToSchema)]
+pub struct WebhookAttempt {
+    pub attempted_at: DateTime<Utc>,
+    /// The receiver's HTTP status; `null` if it could not be reached.
+    pub status_code: Option<i32>,
+    /// Why the attempt failed; `null` on success.
+    pub error: Option<String>,
+    pub duration_ms: i64,
+}
+
+/// A delivery with every attempt at it, oldest first.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WebhookDeliveryLog {
+    #[serde(flatten)]
+    pub delivery: WebhookDelivery,
+    pub attempts: Vec<WebhookAttempt>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Ok,
+    Fail,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessCheck {
+    pub status: HealthStatus,
+    /// Why the check failed; omitted when it passed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessChecks {
+    /// A pooled connection answered within the readiness timeout.
+    pub database: ReadinessCheck,
+    /// Every embedded migration has been applied.
+    pub migrations: ReadinessCheck,
+    /// The service is not draining for shutdown.
+    pub shutdown: ReadinessCheck,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReadinessResponse {
+    /// `ok` only when every check passed.
+    pub status: HealthStatus,
+    pub checks: ReadinessChecks,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VersionResponse {
+    #[schema(example = "todo-service")]
+    pub name: String,
+    #[schema(example = "0.1.0")]
+    pub version: String,
+    /// Short commit hash, or `unknown` when built outside a git checkout.
+    pub git_sha: String,
+    pub build_time: Option<DateTime<Utc>>,
+    pub rustc: String,
+    /// Cargo profile, `debug` or `release`.
+    pub profile: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // user_id
@@ -0,0 +1,240 @@
+use axum::{
+    http::header,
+    response::{Html, IntoResponse},
+    Json,
+};
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+use crate::{
+    error::{ErrorCode, FieldError, ProblemDetails},
+    handlers,
+    models::{
+        AgendaDay, AssignTodo, AttachmentResponse, AttachmentUpload, AttachmentUsage, AuthResponse, ClientMessage, CommentBody, CommentResponse, CommentRevision, CreateShare, CreateTimeEntry, CreateTodo,
+        CreateUser, CreateWebhook, DayCount, DeletedTodo, HealthStatus, ListStats, LoginRequest, MentionResponse, ReadinessCheck,
+        ParsedTodo, Preferences, Priority, QuickAddResponse, QuickAddTodo, ReadinessChecks, ReadinessResponse, Role, ServerMessage, ShareResponse, ShareRole, SharedTodoResponse, SnoozePreset, SnoozeTodo, StatsResponse, Streaks,
+        SyncChanges, SyncConflict, SyncMutation, SyncOp, SyncPush, SyncPushResponse, SyncResult, SyncStatus, TagStats, TimeEntry, TimeReport,
+        TimeReportDay, TimeReportList, TimeReportRow, TimerStarted, TodoEvent,
+        TodoEventKind, TodoResponse, UpdatePreferences, UpdateShare, UpdateTimeEntry, UpdateTodo, UpdateWebhook, UserRef, UserResponse, VersionResponse, WeekCount,
+        Viewer, WebhookAttempt, WebhookDelivery, WebhookDeliveryLog, WebhookDeliveryStatus, WebhookResponse,
+    },
+};
+
+/// OpenAPI description of the public API. Every route registered in
+/// `routes::create_routes` must be listed under `paths`; `tests/openapi.rs`
+/// fails when the two drift apart.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Todo Service API"),
+    paths(
+        handlers::health::healthz,
+        handlers::health::readyz,
+        handlers::health::version,
+        handlers::auth::register,
+        handlers::auth::login,
+        handlers::todo::create_todo,
+        handlers::todo::quick_add_todo,
+        handlers::todo::get_todos,
+        handlers::todo::get_shared_todos,
+        handlers::todo::get_assigned_todos,
+        handlers::todo::get_today_todos,
+        handlers::todo::get_overdue_todos,
+        handlers::todo::get_upcoming_todos,
+        handlers::todo::get_todo,
+        handlers::todo::update_todo,
+        handlers::todo::assign_todo,
+        handlers::todo::snooze_todo,
+        handlers::todo::unsnooze_todo,
+        handlers::todo::delete_todo,
+        handlers::preferences::get_preferences,
+        handlers::preferences::update_preferences,
+        handlers::stats::get_stats,
+        handlers::comments::get_comments,
+        handlers::comments::create_comment,
+        handlers::comments::get_comment,
+        handlers::comments::update_comment,
+        handlers::comments::delete_comment,
+        handlers::comments::get_comment_history,
+        handlers::comments::get_mentions,
+        handlers::comments::mark_all_mentions_read,
+        handlers::comments::mark_mention_read,
+        handlers::attachments::get_attachments,
+        handlers::attachments::upload_attachment,
+        handlers::attachments::download_attachment,
+        handlers::attachments::delete_attachment,
+        handlers::attachments::get_attachment_usage,
+        handlers::time_entries::start_timer,
+        handlers::time_entries::stop_timer,
+        handlers::time_entries::get_timer,
+        handlers::time_entries::get_time_entries,
+        handlers::time_entries::create_time_entry,
+        handlers::time_entries::get_time_entry,
+        handlers::time_entries::update_time_entry,
+        handlers::time_entries::delete_time_entry,
+        handlers::time_entries::get_time_report,
+        handlers::events::todo_events,
+        handlers::ws::todo_socket,
+        handlers::shares::create_share,
+        handlers::shares::get_shares,
+        handlers::shares::get_share,
+        handlers::shares::update_share,
+        handlers::shares::delete_share,
+        handlers::sync::pull,
+        handlers::sync::push,
+        handlers::webhooks::create_webhook,
+        handlers::webhooks::get_webhooks,
+        handlers::webhooks::get_webhook,
+        handlers::webhooks::update_webhook,
+        handlers::webhooks::delete_webhook,
+        handlers::webhooks::test_webhook,
+        handlers::webhooks::get_deliveries,
+        handlers::webhooks::get_delivery,
+    ),
+    components(schemas(
+        CreateUser,
+        LoginRequest,
+        AuthResponse,
+        UserResponse,
+        Preferences,
+        UpdatePreferences,
+        CreateTodo,
+        UpdateTodo,
+        TodoResponse,
+        Priority,
+        QuickAddTodo,
+        ParsedTodo,
+        QuickAddResponse,
+        AgendaDay,
+        StatsResponse,
+        DayCount,
+        WeekCount,
+        Streaks,
+        ListStats,
+        TagStats,
+        AssignTodo,
+        SnoozePreset,
+        SnoozeTodo,
+        Role,
+        SharedTodoResponse,
+        ShareRole,
+        CreateShare,
+        UpdateShare,
+        UserRef,
+        ShareResponse,
+        CommentBody,
+        CommentResponse,
+        CommentRevision,
+        MentionResponse,
+        AttachmentUpload,
+        AttachmentResponse,
+        AttachmentUsage,
+        TimeEntry,
+        TimerStarted,
+        CreateTimeEntry,
+        UpdateTimeEntry,
+        TimeReport,
+        TimeReportDay,
+        TimeReportList,
+        TimeReportRow,
+        TodoEvent,
+        TodoEventKind,
+        ClientMessage,
+        ServerMessage,
+        Viewer,
+        SyncOp,
+        SyncMutation,
+        SyncPush,
+        SyncStatus,
+        SyncConflict,
+        SyncResult,
+        SyncPushResponse,
+        SyncChanges,
+        DeletedTodo,
+        CreateWebhook,
+        UpdateWebhook,
+        WebhookResponse,
+        WebhookDeliveryStatus,
+        WebhookDelivery,
+        WebhookAttempt,
+        WebhookDeliveryLog,
+        ProblemDetails,
+        FieldError,
+        ErrorCode,
+        HealthStatus,
+        ReadinessCheck,
+        ReadinessChecks,
+        ReadinessResponse,
+        VersionResponse,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "health", description = "Probes and build metadata"),
+        (name = "auth", description = "Registration and login"),
+        (name = "todos", description = "Todo management for the authenticated user"),
+        (name = "preferences", description = "Time zone and locale of the authenticated user"),
+        (name = "stats", description = "Productivity figures over a range of days"),
+        (name = "shares", description = "Sharing todos and lists with other users"),
+        (name = "comments", description = "Comment threads on todos and the mentions inbox"),
+        (name = "attachments", description = "Files attached to todos"),
+        (name = "time", description = "Time tracked on todos, with timers or by hand, and reports of it"),
+        (name = "sync", description = "Delta sync for offline clients"),
+        (name = "webhooks", description = "Signed HTTP callbacks for todo events"),
+    )
+)]
+pub struct ApiDoc;
+
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Swagger UI for `/api/openapi.json`, with assets loaded from a CDN. The
+/// page carries its own content security policy admitting that CDN.
+pub async fn swagger_ui() -> impl IntoResponse {
+    ([(header::CONTENT_SECURITY_POLICY, SWAGGER_UI_CSP)], Html(SWAGGER_UI_HTML))
+}
+
+/// Boots Swagger UI; served as a file so the page needs no inline script.
+pub async fn swagger_initializer() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/javascript")], SWAGGER_INITIALIZER_JS)
+}
+
+const SWAGGER_UI_CSP: &str = "default-src 'self'; script-src 'self' https://unpkg.com; \
+style-src 'self' https://unpkg.com; img-src 'self' data:; connect-src 'self'; object-src 'none'; \
+base-uri 'self'; frame-ancestors 'none'";
+
+const SWAGGER_INITIALIZER_JS: &str = r##"window.ui = SwaggerUIBundle({ url: "/api/openapi.json", dom_id: "#swagger-ui" });
+"##;
+
+const SWAGGER_UI_HTML: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Todo Service API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script src="/api/docs/swagger-initializer.js"></script>
+</body>
+</html>
+"##;
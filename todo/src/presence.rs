@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    future::{poll_fn, Future},
+    sync::{Arc, Mutex},
+    task::Poll,
+};
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use crate::models::Viewer;
+
+/// Who has each list open over `/api/ws`, on this instance.
+///
+/// A list is a user's todos, keyed by the owner's id. Its viewers are the
+/// owner's tabs and devices and those of the users the whole list is shared
+/// with.
+#[derive(Clone, Default)]
+pub struct Presence {
+    lists: Arc<Mutex<HashMap<Uuid, watch::Sender<Vec<Viewer>>>>>,
+}
+
+impl Presence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `viewer` to each of `lists` until the returned guard is dropped.
+    pub fn join(&self, lists: impl IntoIterator<Item = Uuid>, viewer: Viewer) -> PresenceGuard {
+        let session_id = viewer.session_id;
+        let mut registry = self.lists.lock().unwrap();
+        let mut receivers: Vec<(Uuid, watch::Receiver<Vec<Viewer>>)> = Vec::new();
+        for list in lists {
+            if receivers.iter().any(|(joined, _)| *joined == list) {
+                continue;
+            }
+            let sender = registry.entry(list).or_insert_with(|| watch::channel(Vec::new()).0);
+            sender.send_modify(|viewers| viewers.push(viewer.clone()));
+            receivers.push((list, sender.subscribe()));
+        }
+
+        PresenceGuard {
+            presence: self.clone(),
+            session_id,
+            receivers,
+        }
+    }
+
+    fn leave(&self, list: Uuid, session_id: Uuid) {
+        let mut lists = self.lists.lock().unwrap();
+        let Some(sender) = lists.get(&list) else {
+            return;
+        };
+        sender.send_modify(|viewers| viewers.retain(|v| v.session_id != session_id));
+        if sender.borrow().is_empty() {
+            lists.remove(&list);
+        }
+    }
+}
+
+/// A connection's membership of its lists; leaves them when dropped.
+pub struct PresenceGuard {
+    presence: Presence,
+    session_id: Uuid,
+    receivers: Vec<(Uuid, watch::Receiver<Vec<Viewer>>)>,
+}
+
+impl PresenceGuard {
+    /// Waits until the viewers of one of the lists change and returns that
+    /// list with them.
+    pub async fn changed(&mut self) -> (Uuid, Vec<Viewer>) {
+        let index = {
+            // The senders live in the registry for as long as this guard
+            // does; the waits not yet done are dropped, which is safe.
+            let mut changes: Vec<_> = self.receivers.iter_mut().map(|(_, receiver)| Box::pin(receiver.changed())).collect();
+            poll_fn(|cx| {
+                for (index, change) in changes.iter_mut().enumerate() {
+                    if change.as_mut().poll(cx).is_ready() {
+                        return Poll::Ready(index);
+                    }
+                }
+                Poll::Pending
+            })
+            .await
+        };
+        let (list, receiver) = &mut self.receivers[index];
+        (*list, receiver.borrow_and_update().clone())
+    }
+
+    /// The lists this connection is on.
+    pub fn lists(&self) -> Vec<Uuid> {
+        self.receivers.iter().map(|(list, _)| *list).collect()
+    }
+
+    /// Leaves one list, as when it stops being shared with the viewer.
+    pub fn leave(&mut self, list: Uuid) {
+        if let Some(index) = self.receivers.iter().position(|(joined, _)| *joined == list) {
+            self.receivers.remove(index);
+            self.presence.leave(list, self.session_id);
+        }
+    }
+
+    /// Each list with its viewers as of the last change seen.
+    pub fn viewers(&mut self) -> Vec<(Uuid, Vec<Viewer>)> {
+        self.receivers
+            .iter_mut()
+            .map(|(list, receiver)| (*list, receiver.borrow_and_update().clone()))
+            .collect()
+    }
+}
+
+impl Drop for PresenceGuard {
+    fn drop(&mut self) {
+        for (list, _) in &self.receivers {
+            self.presence.leave(*list, self.session_id);
+        }
+    }
+}
@@ -0,0 +1,405 @@
+//! Reads a todo typed as one line, like `Pay rent every 1st at 9am #finance
+//! !high`: dates, times, repeats, `#tags` and a `!priority` are taken out
+//! and the words left are the title.
+
+use chrono::{DateTime, Datelike, Days, Duration, Months, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::{
+    calendar::{self, end_of_day, local_to_utc},
+    models::{self, ParsedTodo, Priority},
+    recurrence::{Frequency, Recurrence},
+};
+
+/// Words looked at for one phrase, as in `every mon, wed and fri`.
+const LOOKAHEAD: usize = 8;
+
+/// Reads `text` as typed at `now` by someone in `tz`.
+///
+/// A date without a time means the end of that day, a time without a date
+/// its next occurrence, and a repeat without a date its first day from
+/// today. Only the first date, time, repeat and priority count, and the
+/// first 20 tags; later ones stay in the title.
+pub fn parse(text: &str, now: DateTime<Utc>, tz: Tz) -> ParsedTodo {
+    let today = calendar::today(tz, now);
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut found = Found::default();
+    let mut title = Vec::new();
+
+    let mut i = 0;
+    while i < words.len() {
+        let phrase: Vec<String> = words[i..].iter().take(LOOKAHEAD).map(|word| normalize(word)).collect();
+        match found.take(&phrase, today) {
+            Some(used) => i += used,
+            None => {
+                title.push(words[i]);
+                i += 1;
+            }
+        }
+    }
+
+    ParsedTodo {
+        title: title.join(" "),
+        scheduled_for: found.scheduled_for(now, today, tz),
+        recurrence: found.recurrence.as_ref().map(Recurrence::to_string),
+        tags: found.tags,
+        priority: found.priority,
+    }
+}
+
+#[derive(Default)]
+struct Found {
+    date: Option<NaiveDate>,
+    time: Option<NaiveTime>,
+    /// How long from now, for `in 2 hours`.
+    at: Option<Duration>,
+    recurrence: Option<Recurrence>,
+    tags: Vec<String>,
+    priority: Option<Priority>,
+}
+
+impl Found {
+    /// Takes what the phrase starts with, returning how many words it used,
+    /// or `None` if the first word belongs in the title.
+    fn take(&mut self, phrase: &[String], today: NaiveDate) -> Option<usize> {
+        let words: Vec<&str> = phrase.iter().map(String::as_str).collect();
+        let first = words[0];
+
+        if let Some(tag) = first.strip_prefix('#').and_then(models::normalize_tag) {
+            if self.tags.contains(&tag) {
+                return Some(1);
+            }
+            if self.tags.len() < models::MAX_TAGS {
+                self.tags.push(tag);
+                return Some(1);
+            }
+            return None;
+        }
+
+        if self.priority.is_none() {
+            if let Some(priority) = priority(first) {
+                self.priority = Some(priority);
+                return Some(1);
+            }
+        }
+
+        if self.recurrence.is_none() {
+            let repeat = match first {
+                "every" | "each" => repeat(&words[1..]).map(|(rule, used)| (rule, used + 1)),
+                "daily" => Some((Recurrence::new(Frequency::Daily), 1)),
+                "weekly" => Some((Recurrence::new(Frequency::Weekly), 1)),
+                "monthly" => Some((Recurrence::new(Frequency::Monthly), 1)),
+                "yearly" | "annually" => Some((Recurrence::new(Frequency::Yearly), 1)),
+                _ => None,
+            };
+            if let Some((rule, used)) = repeat {
+                self.recurrence = Some(rule);
+                return Some(used);
+            }
+        }
+
+        let dated = self.date.is_some() || self.at.is_some();
+        let timed = self.time.is_some() || self.at.is_some();
+        match first {
+            "on" | "by" | "due" if !dated => {
+                if let Some((date, used)) = date(&words[1..], today, true) {
+                    self.date = Some(date);
+                    return Some(used + 1);
+                }
+            }
+            "at" if !timed => {
+                if let Some((time, used)) = time(&words[1..], true) {
+                    self.time = Some(time);
+                    return Some(used + 1);
+                }
+            }
+            "in" if !dated && !timed => {
+                if let Some((offset, used)) = offset(&words[1..], today) {
+                    match offset {
+                        Offset::Exact(duration) => self.at = Some(duration),
+                        Offset::Date(date) => self.date = Some(date),
+                    }
+                    return Some(used + 1);
+                }
+            }
+            _ => {}
+        }
+        if !dated {
+            if let Some((date, used)) = date(&words, today, false) {
+                self.date = Some(date);
+                return Some(used);
+            }
+        }
+        if !timed {
+            if let Some((time, used)) = time(&words, false) {
+                self.time = Some(time);
+                return Some(used);
+            }
+        }
+
+        None
+    }
+
+    fn scheduled_for(&self, now: DateTime<Utc>, today: NaiveDate, tz: Tz) -> Option<DateTime<Utc>> {
+        if let Some(duration) = self.at {
+            return Some(now + duration);
+        }
+        let at = |date: NaiveDate, time: NaiveTime| local_to_utc(tz, date.and_time(time));
+
+        let date = match (self.date, &self.recurrence, self.time) {
+            (Some(date), _, _) => date,
+            // Today's occurrence if its time is still to come
+            (None, Some(rule), Some(time)) => {
+                let first = rule.first_on_or_after(today);
+                if at(first, time) > now {
+                    first
+                } else {
+                    rule.first_on_or_after(first + Days::new(1))
+                }
+            }
+            (None, Some(rule), None) => rule.first_on_or_after(today),
+            (None, None, Some(time)) if at(today, time) > now => today,
+            (None, None, Some(_)) => today + Days::new(1),
+            (None, None, None) => return None,
+        };
+        Some(at(date, self.time.unwrap_or_else(end_of_day)))
+    }
+}
+
+/// Lowercase, without punctuation trailing it in a sentence.
+fn normalize(word: &str) -> String {
+    word.trim_end_matches([',', ';', '.']).to_lowercase()
+}
+
+fn priority(word: &str) -> Option<Priority> {
+    match word.strip_prefix('!')? {
+        "high" => Some(Priority::High),
+        "medium" | "med" => Some(Priority::Medium),
+        "low" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// The repeat after `every`, as in `every 2 weeks`, `every mon and thu`
+/// or `every 1st`.
+fn repeat(words: &[&str]) -> Option<(Recurrence, usize)> {
+    let first = *words.first()?;
+
+    let weekly_on = |days: &[Weekday]| Recurrence {
+        weekdays: days.to_vec(),
+        ..Recurrence::new(Frequency::Weekly)
+    };
+    match first {
+        "weekday" | "weekdays" => {
+            return Some((weekly_on(&[Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]), 1))
+        }
+        "weekend" | "weekends" => return Some((weekly_on(&[Weekday::Sat, Weekday::Sun]), 1)),
+        _ => {}
+    }
+    if let Some(frequency) = frequency(first) {
+        return Some((Recurrence::new(frequency), 1));
+    }
+
+    // `every other week`, `every 3 days`
+    let interval = match first {
+        "other" => Some(2),
+        number => number.parse::<u32>().ok().filter(|n| (1..=366).contains(n)),
+    };
+    if let (Some(interval), Some(frequency)) = (interval, words.get(1).and_then(|unit| frequency(unit))) {
+        return Some((Recurrence { interval, ..Recurrence::new(frequency) }, 2));
+    }
+
+    // `every mon, wed and fri`; the commas are gone by now
+    let mut days = Vec::new();
+    let mut used = 0;
+    for (i, word) in words.iter().enumerate() {
+        match weekday(word, true) {
+            Some(day) => {
+                if !days.contains(&day) {
+                    days.push(day);
+                }
+                used = i + 1;
+            }
+            None if *word == "and" && !days.is_empty() => {}
+            None => break,
+        }
+    }
+    if !days.is_empty() {
+        return Some((weekly_on(&days), used));
+    }
+
+    // `every 1st`: only with the suffix, so `every 2 things` is left alone
+    if !first.ends_with(|c: char| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    let month_day = day_of_month(first)?;
+    Some((
+        Recurrence {
+            month_day: Some(month_day),
+            ..Recurrence::new(Frequency::Monthly)
+        },
+        1,
+    ))
+}
+
+fn frequency(unit: &str) -> Option<Frequency> {
+    match unit {
+        "day" | "days" => Some(Frequency::Daily),
+        "week" | "weeks" => Some(Frequency::Weekly),
+        "month" | "months" => Some(Frequency::Monthly),
+        "year" | "years" => Some(Frequency::Yearly),
+        _ => None,
+    }
+}
+
+/// A day, as in `tomorrow`, `friday`, `next week`, `2026-11-03`,
+/// `nov 3rd` or `3 november 2027`. Abbreviated weekdays, which are also
+/// words like `sun` and `sat`, are only taken after `on`, `by` or `due`.
+fn date(words: &[&str], today: NaiveDate, after_keyword: bool) -> Option<(NaiveDate, usize)> {
+    let first = *words.first()?;
+    let second = words.get(1).copied();
+
+    match first {
+        "today" => return Some((today, 1)),
+        "tomorrow" => return Some((today + Days::new(1), 1)),
+        "next" => {
+            return match second? {
+                "week" => Some((today + Days::new(7), 2)),
+                "month" => Some((today.checked_add_months(Months::new(1))?, 2)),
+                "year" => Some((today.checked_add_months(Months::new(12))?, 2)),
+                day => Some((next_weekday(today, weekday(day, true)?), 2)),
+            };
+        }
+        _ => {}
+    }
+    if let Some(day) = weekday(first, after_keyword) {
+        return Some((next_weekday(today, day), 1));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(first, "%Y-%m-%d") {
+        return Some((date, 1));
+    }
+
+    // `nov 3rd [2027]` or `3 nov [2027]`
+    let (month, day) = match (month(first), second.and_then(day_of_month)) {
+        (Some(month), Some(day)) => (month, day),
+        _ => (month(second?)?, day_of_month(first)?),
+    };
+    let year = words.get(2).and_then(|word| word.parse::<i32>().ok()).filter(|year| (1000..=9999).contains(year));
+    match year {
+        Some(year) => Some((NaiveDate::from_ymd_opt(year, month, day)?, 3)),
+        None => {
+            // The next time it comes round
+            let this_year = NaiveDate::from_ymd_opt(today.year(), month, day);
+            match this_year.filter(|date| *date >= today) {
+                Some(date) => Some((date, 2)),
+                None => Some((NaiveDate::from_ymd_opt(today.year() + 1, month, day)?, 2)),
+            }
+        }
+    }
+}
+
+/// The first `day` after today.
+fn next_weekday(today: NaiveDate, day: Weekday) -> NaiveDate {
+    let ahead = (day.num_days_from_monday() + 7 - today.weekday().num_days_from_monday() - 1) % 7 + 1;
+    today + Days::new(u64::from(ahead))
+}
+
+fn weekday(word: &str, abbreviated: bool) -> Option<Weekday> {
+    let day = match word {
+        "monday" => Weekday::Mon,
+        "tuesday" => Weekday::Tue,
+        "wednesday" => Weekday::Wed,
+        "thursday" => Weekday::Thu,
+        "friday" => Weekday::Fri,
+        "saturday" => Weekday::Sat,
+        "sunday" => Weekday::Sun,
+        _ if !abbreviated => return None,
+        "mon" => Weekday::Mon,
+        "tue" | "tues" => Weekday::Tue,
+        "wed" => Weekday::Wed,
+        "thu" | "thur" | "thurs" => Weekday::Thu,
+        "fri" => Weekday::Fri,
+        "sat" => Weekday::Sat,
+        "sun" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(day)
+}
+
+fn month(word: &str) -> Option<u32> {
+    const MONTHS: [&str; 12] = [
+        "january", "february", "march", "april", "may", "june", "july", "august", "september", "october", "november",
+        "december",
+    ];
+    MONTHS
+        .iter()
+        .position(|name| *name == word || (word.len() >= 3 && name.starts_with(word)))
+        .map(|i| i as u32 + 1)
+}
+
+/// `3`, `3rd` or `23rd`.
+fn day_of_month(word: &str) -> Option<u32> {
+    let digits = word.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let suffix = &word[digits.len()..];
+    if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+        return None;
+    }
+    digits.parse::<u32>().ok().filter(|day| (1..=31).contains(day))
+}
+
+/// A time, as in `9am`, `9:30 pm`, `17:00` or `noon`. A bare hour like
+/// `9` only counts after `at`.
+fn time(words: &[&str], after_at: bool) -> Option<(NaiveTime, usize)> {
+    let first = *words.first()?;
+    if first == "noon" {
+        return Some((NaiveTime::from_hms_opt(12, 0, 0)?, 1));
+    }
+
+    let (clock, meridiem, used) = match ["am", "pm"].into_iter().find(|m| first.ends_with(m)) {
+        Some(meridiem) => (&first[..first.len() - 2], Some(meridiem), 1),
+        None => match words.get(1).copied() {
+            Some(meridiem @ ("am" | "pm")) => (first, Some(meridiem), 2),
+            _ => (first, None, 1),
+        },
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour, minute.parse::<u32>().ok()?),
+        Some(_) => return None,
+        None if meridiem.is_some() || after_at => (clock, 0),
+        None => return None,
+    };
+    if hour.is_empty() || hour.len() > 2 {
+        return None;
+    }
+    let hour = hour.parse::<u32>().ok()?;
+    let hour = match meridiem {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some("am") => hour % 12,
+        Some(_) => hour % 12 + 12,
+        None => hour,
+    };
+
+    Some((NaiveTime::from_hms_opt(hour, minute, 0)?, used))
+}
+
+enum Offset {
+    Exact(Duration),
+    Date(NaiveDate),
+}
+
+/// What follows `in`, as in `in 2 hours` or `in a week`.
+fn offset(words: &[&str], today: NaiveDate) -> Option<(Offset, usize)> {
+    let count = match *words.first()? {
+        "a" | "an" => 1,
+        number => number.parse::<u32>().ok().filter(|n| (1..=1000).contains(n))?,
+    };
+    let offset = match *words.get(1)? {
+        "minute" | "minutes" | "min" | "mins" => Offset::Exact(Duration::minutes(count.into())),
+        "hour" | "hours" | "hr" | "hrs" => Offset::Exact(Duration::hours(count.into())),
+        "day" | "days" => Offset::Date(today + Days::new(count.into())),
+        "week" | "weeks" => Offset::Date(today + Days::new(u64::from(count) * 7)),
+        "month" | "months" => Offset::Date(today.checked_add_months(Months::new(count))?),
+        _ => return None,
+    };
+    Some((offset, 2))
+}
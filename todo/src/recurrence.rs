@@ -0,0 +1,166 @@
+//! Repeat rules of todos, stored as the subset of iCalendar `RRULE`s
+//! (RFC 5545) the quick-add parser produces: `FREQ`, `INTERVAL`, `BYDAY`
+//! with plain weekdays and a single `BYMONTHDAY`.
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use std::{fmt, str::FromStr};
+
+/// How often a rule repeats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    fn as_str(self) -> &'static str {
+        match self {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    /// Every how many days, weeks, months or years; at least 1.
+    pub interval: u32,
+    /// Weekly rules only: the days of the week it falls on.
+    pub weekdays: Vec<Weekday>,
+    /// Monthly rules only: the day of the month it falls on, 1 to 31.
+    pub month_day: Option<u32>,
+}
+
+impl Recurrence {
+    pub fn new(frequency: Frequency) -> Self {
+        Self {
+            frequency,
+            interval: 1,
+            weekdays: Vec::new(),
+            month_day: None,
+        }
+    }
+
+    /// The first day on or after `date` the rule falls on. Rules without
+    /// days fall on any day; months without the rule's day are skipped.
+    pub fn first_on_or_after(&self, date: NaiveDate) -> NaiveDate {
+        let matches = |day: NaiveDate| {
+            (self.weekdays.is_empty() || self.weekdays.contains(&day.weekday()))
+                && self.month_day.is_none_or(|month_day| day.day() == month_day)
+        };
+        // Every rule falls on some day within a year
+        let mut day = date;
+        for _ in 0..366 {
+            if matches(day) {
+                return day;
+            }
+            day = day + Days::new(1);
+        }
+        date
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FREQ={}", self.frequency.as_str())?;
+        if self.interval > 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<&str> = self.weekdays.iter().map(|day| weekday_code(*day)).collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(month_day) = self.month_day {
+            write!(f, ";BYMONTHDAY={}", month_day)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut frequency = None;
+        let mut rule = Recurrence::new(Frequency::Daily);
+
+        for part in s.trim().trim_start_matches("RRULE:").split(';') {
+            let (name, value) = part.split_once('=').ok_or_else(|| format!("Invalid rule part '{}'", part))?;
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => return Err(format!("Unsupported frequency '{}'", other)),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|interval| (1..=366).contains(interval))
+                        .ok_or_else(|| format!("Invalid interval '{}'", value))?;
+                }
+                "BYDAY" => {
+                    for code in value.split(',') {
+                        let day = weekday_from_code(code).ok_or_else(|| format!("Invalid weekday '{}'", code))?;
+                        if !rule.weekdays.contains(&day) {
+                            rule.weekdays.push(day);
+                        }
+                    }
+                }
+                "BYMONTHDAY" => {
+                    rule.month_day = Some(
+                        value
+                            .parse()
+                            .ok()
+                            .filter(|day| (1..=31).contains(day))
+                            .ok_or_else(|| format!("Invalid day of the month '{}'", value))?,
+                    );
+                }
+                other => return Err(format!("Unsupported rule part '{}'", other)),
+            }
+        }
+
+        rule.frequency = frequency.ok_or("The rule needs a FREQ")?;
+        if !rule.weekdays.is_empty() && rule.frequency != Frequency::Weekly {
+            return Err("BYDAY is only supported on weekly rules".to_string());
+        }
+        if rule.month_day.is_some() && rule.frequency != Frequency::Monthly {
+            return Err("BYMONTHDAY is only supported on monthly rules".to_string());
+        }
+        Ok(rule)
+    }
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn weekday_from_code(code: &str) -> Option<Weekday> {
+    Some(match code.trim().to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
@@ -1,18 +1,43 @@
 use axum::{
+    extract::DefaultBodyLimit,
+    middleware,
     routing::{delete, get, post, put},
     Router,
 };
 
 use crate::{
-    database::Database,
     handlers::{
+        attachments,
         auth::{login, register},
-        todo::{create_todo, delete_todo, get_todo, get_todos, update_todo},
+        comments,
+        events::todo_events,
+        health::{healthz, readyz, version},
+        preferences,
+        shares,
+        stats,
+        sync,
+        time_entries,
+        todo::{
+            assign_todo, create_todo, delete_todo, get_assigned_todos, get_overdue_todos, get_shared_todos,
+            get_today_todos, get_todo, get_todos, get_upcoming_todos, quick_add_todo, snooze_todo, unsnooze_todo, update_todo,
+        },
+        webhooks,
+        ws::todo_socket,
     },
+    metrics,
+    openapi::{openapi_json, swagger_initializer, swagger_ui},
+    request_id,
+    state::AppState,
+    telemetry,
 };
 
-pub fn create_routes(db: Database) -> Router {
+pub fn create_routes(state: AppState) -> Router {
     Router::new()
+        // Probes and build metadata
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/version", get(version))
+
         // Authentication routes
         .route("/api/auth/register", post(register))
         .route("/api/auth/login", post(login))
@@ -20,9 +45,89 @@ pub fn create_routes(db: Database) -> Router {
         // Todo routes
         .route("/api/todos", post(create_todo))
         .route("/api/todos", get(get_todos))
+        .route("/api/todos/quick", post(quick_add_todo))
+        .route("/api/todos/events", get(todo_events))
+        .route("/api/todos/shared", get(get_shared_todos))
+        .route("/api/todos/assigned", get(get_assigned_todos))
+        .route("/api/todos/today", get(get_today_todos))
+        .route("/api/todos/overdue", get(get_overdue_todos))
+        .route("/api/todos/upcoming", get(get_upcoming_todos))
         .route("/api/todos/:id", get(get_todo))
         .route("/api/todos/:id", put(update_todo))
         .route("/api/todos/:id", delete(delete_todo))
-        
-        .with_state(db)
+        .route("/api/todos/:id/assignee", put(assign_todo))
+        .route("/api/todos/:id/snooze", post(snooze_todo))
+        .route("/api/todos/:id/snooze", delete(unsnooze_todo))
+        .route("/api/todos/:id/comments", get(comments::get_comments))
+        .route("/api/todos/:id/comments", post(comments::create_comment))
+        .route("/api/todos/:id/comments/:comment_id", get(comments::get_comment))
+        .route("/api/todos/:id/comments/:comment_id", put(comments::update_comment))
+        .route("/api/todos/:id/comments/:comment_id", delete(comments::delete_comment))
+        .route("/api/todos/:id/comments/:comment_id/history", get(comments::get_comment_history))
+        .route("/api/todos/:id/attachments", get(attachments::get_attachments))
+        // The handler enforces the configured size limit while reading
+        .route(
+            "/api/todos/:id/attachments",
+            post(attachments::upload_attachment).layer(DefaultBodyLimit::disable()),
+        )
+        .route("/api/todos/:id/attachments/:attachment_id", get(attachments::download_attachment))
+        .route("/api/todos/:id/attachments/:attachment_id", delete(attachments::delete_attachment))
+        .route("/api/todos/:id/timer/start", post(time_entries::start_timer))
+        .route("/api/todos/:id/timer/stop", post(time_entries::stop_timer))
+        .route("/api/todos/:id/time-entries", get(time_entries::get_time_entries))
+        .route("/api/todos/:id/time-entries", post(time_entries::create_time_entry))
+        .route("/api/todos/:id/time-entries/:entry_id", get(time_entries::get_time_entry))
+        .route("/api/todos/:id/time-entries/:entry_id", put(time_entries::update_time_entry))
+        .route("/api/todos/:id/time-entries/:entry_id", delete(time_entries::delete_time_entry))
+        .route("/api/ws", get(todo_socket))
+
+        // Sharing
+        .route("/api/shares", post(shares::create_share))
+        .route("/api/shares", get(shares::get_shares))
+        .route("/api/shares/:id", get(shares::get_share))
+        .route("/api/shares/:id", put(shares::update_share))
+        .route("/api/shares/:id", delete(shares::delete_share))
+
+        // Preferences
+        .route("/api/preferences", get(preferences::get_preferences))
+        .route("/api/preferences", put(preferences::update_preferences))
+
+        // Statistics
+        .route("/api/stats", get(stats::get_stats))
+
+        // Time tracking
+        .route("/api/timer", get(time_entries::get_timer))
+        .route("/api/time-entries/report", get(time_entries::get_time_report))
+
+        // Mentions inbox
+        .route("/api/mentions", get(comments::get_mentions))
+        .route("/api/mentions/read", post(comments::mark_all_mentions_read))
+        .route("/api/mentions/:comment_id/read", post(comments::mark_mention_read))
+
+        // Attachment storage
+        .route("/api/attachments/usage", get(attachments::get_attachment_usage))
+
+        // Offline sync
+        .route("/api/sync", get(sync::pull))
+        .route("/api/sync", post(sync::push))
+
+        // Outbound webhooks
+        .route("/api/webhooks", post(webhooks::create_webhook))
+        .route("/api/webhooks", get(webhooks::get_webhooks))
+        .route("/api/webhooks/:id", get(webhooks::get_webhook))
+        .route("/api/webhooks/:id", put(webhooks::update_webhook))
+        .route("/api/webhooks/:id", delete(webhooks::delete_webhook))
+        .route("/api/webhooks/:id/test", post(webhooks::test_webhook))
+        .route("/api/webhooks/:id/deliveries", get(webhooks::get_deliveries))
+        .route("/api/webhooks/:id/deliveries/:delivery_id", get(webhooks::get_delivery))
+
+        // API documentation
+        .route("/api/openapi.json", get(openapi_json))
+        .route("/api/docs", get(swagger_ui))
+        .route("/api/docs/swagger-initializer.js", get(swagger_initializer))
+
+        .layer(middleware::from_fn_with_state(state.metrics.clone(), metrics::track_requests))
+        .layer(telemetry::trace_layer())
+        .with_state(state)
+        .layer(middleware::from_fn(request_id::middleware))
 }
\ No newline at end of file
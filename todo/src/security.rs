@@ -0,0 +1,119 @@
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+use std::{sync::Arc, time::Duration};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::{CorsConfig, SecurityConfig};
+
+/// Adds the security headers and, when any origins are allowed, the CORS
+/// policy to `router`. Apply it outermost so static files are covered too.
+pub fn apply(router: Router, cors: &CorsConfig, security: &SecurityConfig) -> Result<Router> {
+    let headers = Arc::new(SecurityHeaders::new(security)?);
+    let router = router.layer(middleware::from_fn_with_state(headers, set_headers));
+
+    Ok(match cors_layer(cors)? {
+        Some(cors) => router.layer(cors),
+        None => router,
+    })
+}
+
+/// Builds the CORS layer, or `None` when no cross-origin caller is allowed
+/// and browsers should apply the same-origin policy unchanged.
+pub fn cors_layer(config: &CorsConfig) -> Result<Option<CorsLayer>> {
+    if config.allowed_origins.is_empty() {
+        return Ok(None);
+    }
+
+    let origins = if config.allowed_origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .map(|o| HeaderValue::from_str(o))
+                .collect::<Result<Vec<_>, _>>()?,
+        )
+    };
+
+    let methods = config
+        .allowed_methods
+        .iter()
+        .map(|m| Method::from_bytes(m.to_ascii_uppercase().as_bytes()))
+        .collect::<Result<Vec<_>, _>>()?;
+    let allowed_headers = header_names(&config.allowed_headers)?;
+    let exposed_headers = header_names(&config.exposed_headers)?;
+
+    Ok(Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods(methods)
+            .allow_headers(allowed_headers)
+            .expose_headers(exposed_headers)
+            .allow_credentials(config.allow_credentials)
+            .max_age(Duration::from_secs(config.max_age_secs)),
+    ))
+}
+
+fn header_names(names: &[String]) -> Result<Vec<HeaderName>> {
+    Ok(names
+        .iter()
+        .map(|name| HeaderName::from_bytes(name.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Header values prepared once at startup.
+struct SecurityHeaders {
+    content_security_policy: HeaderValue,
+    strict_transport_security: Option<HeaderValue>,
+    referrer_policy: HeaderValue,
+}
+
+impl SecurityHeaders {
+    fn new(config: &SecurityConfig) -> Result<Self> {
+        Ok(Self {
+            content_security_policy: HeaderValue::from_str(&config.content_security_policy)?,
+            strict_transport_security: match config.hsts_max_age_secs {
+                0 => None,
+                max_age => Some(HeaderValue::from_str(&format!(
+                    "max-age={}; includeSubDomains",
+                    max_age
+                ))?),
+            },
+            referrer_policy: HeaderValue::from_str(&config.referrer_policy)?,
+        })
+    }
+}
+
+/// Sets each security header unless the handler already chose its own, so
+/// pages such as the API docs can widen the content security policy.
+async fn set_headers(State(config): State<Arc<SecurityHeaders>>, request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+
+    headers
+        .entry(header::CONTENT_SECURITY_POLICY)
+        .or_insert_with(|| config.content_security_policy.clone());
+    headers
+        .entry(header::X_CONTENT_TYPE_OPTIONS)
+        .or_insert(HeaderValue::from_static("nosniff"));
+    headers
+        .entry(header::X_FRAME_OPTIONS)
+        .or_insert(HeaderValue::from_static("DENY"));
+    headers
+        .entry(header::REFERRER_POLICY)
+        .or_insert_with(|| config.referrer_policy.clone());
+    if let Some(hsts) = &config.strict_transport_security {
+        headers
+            .entry(header::STRICT_TRANSPORT_SECURITY)
+            .or_insert_with(|| hsts.clone());
+    }
+
+    response
+}
@@ -0,0 +1,88 @@
+use axum::Router;
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use std::io;
+use tokio::{net::TcpListener, time::sleep};
+use tracing::{info, warn};
+
+use crate::shutdown::{Shutdown, ShutdownOptions};
+
+/// Serves `app` until `shutdown` is requested, then drains.
+///
+/// After the request, the listener keeps accepting for `drain_delay` while
+/// readiness reports failure, then stops accepting and waits up to `timeout`
+/// for in-flight requests. Connections still open after that are dropped.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    shutdown: Shutdown,
+    options: ShutdownOptions,
+) -> io::Result<()> {
+    let server = axum::serve(listener, app).with_graceful_shutdown(stop_accepting(shutdown.clone(), options));
+
+    tokio::select! {
+        result = server => {
+            result?;
+            info!("All connections drained");
+        }
+        _ = deadline(&shutdown, options) => {
+            warn!("Shutdown timeout of {:?} exceeded; dropping open connections", options.timeout);
+        }
+    }
+
+    Ok(())
+}
+
+/// Like [`serve`], but terminates TLS with `rustls`, negotiating HTTP/2 or
+/// HTTP/1.1 over ALPN. Reloading `rustls` changes the certificate for new
+/// connections.
+pub async fn serve_tls(
+    listener: TcpListener,
+    app: Router,
+    rustls: RustlsConfig,
+    shutdown: Shutdown,
+    options: ShutdownOptions,
+) -> io::Result<()> {
+    let handle = Handle::new();
+    let server = axum_server::from_tcp_rustls(listener.into_std()?, rustls)
+        .handle(handle.clone())
+        .serve(app.into_make_service());
+
+    let server = async {
+        tokio::pin!(server);
+        tokio::select! {
+            result = &mut server => return result,
+            _ = stop_accepting(shutdown.clone(), options) => handle.graceful_shutdown(None),
+        }
+        server.await
+    };
+
+    tokio::select! {
+        result = server => {
+            result?;
+            info!("All connections drained");
+        }
+        _ = deadline(&shutdown, options) => {
+            warn!("Shutdown timeout of {:?} exceeded; dropping open connections", options.timeout);
+            handle.shutdown();
+        }
+    }
+
+    Ok(())
+}
+
+/// Resolves once the listener should close: `drain_delay` after shutdown
+/// was requested.
+async fn stop_accepting(shutdown: Shutdown, options: ShutdownOptions) {
+    shutdown.requested().await;
+    if !options.drain_delay.is_zero() {
+        info!("Draining: waiting {:?} before closing the listener", options.drain_delay);
+        sleep(options.drain_delay).await;
+    }
+    info!("No longer accepting connections");
+}
+
+/// Resolves once in-flight requests have had `timeout` to finish.
+async fn deadline(shutdown: &Shutdown, options: ShutdownOptions) {
+    shutdown.requested().await;
+    sleep(options.drain_delay + options.timeout).await;
+}
@@ -0,0 +1,76 @@
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
+
+/// Process-wide shutdown flag shared by the server, readiness checks and
+/// long-running background work.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+}
+
+/// How the server winds down once shutdown has been requested.
+#[derive(Debug, Clone, Copy)]
+pub struct ShutdownOptions {
+    /// Keep accepting connections (while reporting not-ready) for this long,
+    /// so load balancers stop routing new traffic first.
+    pub drain_delay: Duration,
+    /// Upper bound for in-flight requests to finish once accepting stops.
+    pub timeout: Duration,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts shutting down. Idempotent.
+    pub fn request(&self) {
+        if !self.token.is_cancelled() {
+            info!("Shutdown requested");
+            self.token.cancel();
+        }
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Resolves once [`Shutdown::request`] has been called.
+    pub async fn requested(&self) {
+        self.token.cancelled().await
+    }
+
+    /// Requests shutdown on the first SIGINT or SIGTERM.
+    pub fn listen_for_signals(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            shutdown.request();
+        });
+    }
+}
+
+async fn wait_for_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to install Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received SIGINT"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
@@ -0,0 +1,86 @@
+use axum::extract::FromRef;
+
+use crate::{
+    attachments::Attachments, auth::JwtService, metrics::Metrics, presence::Presence, shutdown::Shutdown,
+    store::DynStore, webhooks::Webhooks,
+};
+
+/// Shared application state handed to every handler.
+#[derive(Clone)]
+pub struct AppState {
+    pub store: DynStore,
+    pub jwt: JwtService,
+    pub shutdown: Shutdown,
+    pub metrics: Metrics,
+    pub presence: Presence,
+    pub webhooks: Webhooks,
+    pub attachments: Attachments,
+}
+
+impl AppState {
+    pub fn new(store: DynStore, jwt: JwtService) -> Self {
+        Self {
+            metrics: Metrics::new(store.clone()),
+            store,
+            jwt,
+            shutdown: Shutdown::new(),
+            presence: Presence::new(),
+            webhooks: Webhooks::default(),
+            attachments: Attachments::default(),
+        }
+    }
+
+    /// Replaces the default webhook options.
+    pub fn with_webhooks(mut self, webhooks: Webhooks) -> Self {
+        self.webhooks = webhooks;
+        self
+    }
+
+    /// Replaces the default in-memory attachment storage and limits.
+    pub fn with_attachments(mut self, attachments: Attachments) -> Self {
+        self.attachments = attachments;
+        self
+    }
+}
+
+impl FromRef<AppState> for DynStore {
+    fn from_ref(state: &AppState) -> Self {
+        state.store.clone()
+    }
+}
+
+impl FromRef<AppState> for JwtService {
+    fn from_ref(state: &AppState) -> Self {
+        state.jwt.clone()
+    }
+}
+
+impl FromRef<AppState> for Shutdown {
+    fn from_ref(state: &AppState) -> Self {
+        state.shutdown.clone()
+    }
+}
+
+impl FromRef<AppState> for Metrics {
+    fn from_ref(state: &AppState) -> Self {
+        state.metrics.clone()
+    }
+}
+
+impl FromRef<AppState> for Presence {
+    fn from_ref(state: &AppState) -> Self {
+        state.presence.clone()
+    }
+}
+
+impl FromRef<AppState> for Webhooks {
+    fn from_ref(state: &AppState) -> Self {
+        state.webhooks.clone()
+    }
+}
+
+impl FromRef<AppState> for Attachments {
+    fn from_ref(state: &AppState) -> Self {
+        state.attachments.clone()
+    }
+}
@@ -0,0 +1,128 @@
+//! Productivity figures over a range of a user's days: the stores count,
+//! this fills in the days, weeks and streaks around their counts.
+
+use chrono::{DateTime, Datelike, Days, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+use crate::{
+    calendar,
+    models::{DayCount, ListStats, StatsResponse, Streaks, TagStats, WeekCount},
+};
+
+/// The longest range asked for at once, in days.
+pub const MAX_DAYS: i64 = 366;
+/// The range covered when none is given, in days ending today.
+pub const DEFAULT_DAYS: u64 = 30;
+
+/// The days `from` to `to`, both included, as seen in `tz`.
+#[derive(Debug, Clone)]
+pub struct StatsRange {
+    pub tz: Tz,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// The first instant of `from`.
+    pub start: DateTime<Utc>,
+    /// The first instant of the day after `to`.
+    pub end: DateTime<Utc>,
+    /// When the figures are taken; todos only become overdue up to then.
+    pub now: DateTime<Utc>,
+}
+
+impl StatsRange {
+    pub fn new(tz: Tz, from: NaiveDate, to: NaiveDate, now: DateTime<Utc>) -> Self {
+        let (start, _) = calendar::day_bounds(tz, from);
+        let (_, end) = calendar::day_bounds(tz, to);
+        Self { tz, from, to, start, end, now }
+    }
+
+    /// Each day of the range with its first instant and that of the day after.
+    pub fn days(&self) -> impl Iterator<Item = (NaiveDate, DateTime<Utc>, DateTime<Utc>)> + '_ {
+        self.from.iter_days().take_while(|date| *date <= self.to).map(|date| {
+            let (start, end) = calendar::day_bounds(self.tz, date);
+            (date, start, end)
+        })
+    }
+}
+
+/// What a store counts over a [`StatsRange`].
+#[derive(Debug, Clone, Default)]
+pub struct StatsCounts {
+    pub created: i64,
+    pub completed: i64,
+    pub completed_late: i64,
+    pub overdue: i64,
+    pub average_completion_seconds: Option<f64>,
+    /// Only the days with completions, in any order.
+    pub completed_per_day: Vec<DayCount>,
+    /// In any order.
+    pub by_list: Vec<ListStats>,
+    /// In any order.
+    pub by_tag: Vec<TagStats>,
+}
+
+/// The response for `range` from the store's `counts`.
+pub fn summarize(range: &StatsRange, counts: StatsCounts) -> StatsResponse {
+    let completed_per_day: Vec<DayCount> = range
+        .from
+        .iter_days()
+        .take_while(|date| *date <= range.to)
+        .map(|date| DayCount {
+            date,
+            count: counts
+                .completed_per_day
+                .iter()
+                .filter(|day| day.date == date)
+                .map(|day| day.count)
+                .sum(),
+        })
+        .collect();
+
+    let mut completed_per_week: Vec<WeekCount> = Vec::new();
+    for day in &completed_per_day {
+        let week_start = day.date - Days::new(day.date.weekday().num_days_from_monday().into());
+        match completed_per_week.last_mut() {
+            Some(week) if week.week_start == week_start => week.count += day.count,
+            _ => completed_per_week.push(WeekCount { week_start, count: day.count }),
+        }
+    }
+
+    let streaks = streaks(&completed_per_day, calendar::today(range.tz, range.now));
+
+    let mut by_list = counts.by_list;
+    by_list.sort_by(|a, b| (b.created + b.completed).cmp(&(a.created + a.completed)).then_with(|| a.username.cmp(&b.username)));
+    let mut by_tag = counts.by_tag;
+    by_tag.sort_by(|a, b| (b.created + b.completed).cmp(&(a.created + a.completed)).then_with(|| a.tag.cmp(&b.tag)));
+
+    StatsResponse {
+        timezone: range.tz.name().to_string(),
+        from: range.from,
+        to: range.to,
+        created: counts.created,
+        completed: counts.completed,
+        completed_late: counts.completed_late,
+        overdue: counts.overdue,
+        average_completion_seconds: counts.average_completion_seconds,
+        completed_per_day,
+        completed_per_week,
+        streaks,
+        by_list,
+        by_tag,
+    }
+}
+
+/// Streaks over `days`, every day of a range in order. A run still open
+/// today is not broken until today is over.
+fn streaks(days: &[DayCount], today: NaiveDate) -> Streaks {
+    let mut longest = 0;
+    let mut run = 0;
+    for day in days {
+        run = if day.count > 0 { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+
+    let mut recent = days.iter().rev().peekable();
+    recent.next_if(|day| day.date == today && day.count == 0);
+    let current = recent.take_while(|day| day.count > 0).count() as i64;
+
+    Streaks { current, longest }
+}
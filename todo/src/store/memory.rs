@@ -0,0 +1,1491 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::{
+    calendar,
+    error::AppError,
+    events::{EventBus, Notification},
+    models::{
+        Attachment, Comment, CommentRevision, CreateTodo, DayCount, ListStats, Mention, NewAttachment, Role, Share, ShareRole, SyncMutation, TagStats, TimeEntry, TimeReportRow, Todo, TodoEvent, TodoEventKind, TodoResponse, UpdateTodo,
+        UpdateWebhook, User, Webhook, WebhookAttempt, WebhookDelivery, WebhookDeliveryStatus,
+    },
+    store::{
+        AdminStore, AttachmentStore, CommentStore, EventStore, MigrationStatus, PoolStats, ShareStore, StatsStore, SyncStore, TimeEntryStore, TodoStore, UserStore, WebhookStore,
+    },
+    stats::{StatsCounts, StatsRange},
+    sync::{self, Existing, FieldVersions, Merged, TodoChange, Write},
+    time_tracking,
+    webhooks::{DeliveryOutcome, PendingDelivery},
+};
+
+/// Process-local store used by tests and throwaway local runs.
+/// Nothing survives a restart.
+#[derive(Default)]
+pub struct MemoryStore {
+    data: RwLock<Data>,
+    events: EventBus,
+}
+
+#[derive(Default)]
+struct Data {
+    users: HashMap<Uuid, User>,
+    todos: HashMap<Uuid, Todo>,
+    /// Each todo's place in its owner's change sequence and field versions.
+    versions: HashMap<Uuid, (i64, FieldVersions)>,
+    tombstones: HashMap<Uuid, Tombstone>,
+    change_seqs: HashMap<Uuid, i64>,
+    events: Vec<TodoEvent>,
+    last_event_id: i64,
+    /// Oldest first.
+    shares: Vec<StoredShare>,
+    /// Oldest first.
+    comments: Vec<StoredComment>,
+    /// Oldest first.
+    mentions: Vec<StoredMention>,
+    /// Oldest first.
+    attachments: Vec<Attachment>,
+    /// Oldest first.
+    time_entries: Vec<TimeEntry>,
+    webhooks: HashMap<Uuid, Webhook>,
+    /// Oldest first.
+    deliveries: Vec<WebhookDelivery>,
+    attempts: HashMap<Uuid, Vec<WebhookAttempt>>,
+}
+
+struct Tombstone {
+    user_id: Uuid,
+    seq: i64,
+    deleted_at: DateTime<Utc>,
+}
+
+struct StoredShare {
+    id: Uuid,
+    owner_id: Uuid,
+    grantee_id: Uuid,
+    todo_id: Option<Uuid>,
+    role: ShareRole,
+    created_at: DateTime<Utc>,
+}
+
+impl StoredShare {
+    fn covers(&self, todo: &Todo) -> bool {
+        self.owner_id == todo.user_id && self.todo_id.is_none_or(|id| id == todo.id)
+    }
+}
+
+struct StoredComment {
+    id: Uuid,
+    todo_id: Uuid,
+    author_id: Uuid,
+    edited_at: Option<DateTime<Utc>>,
+    /// Oldest first; the last is the current body.
+    revisions: Vec<CommentRevision>,
+}
+
+struct StoredMention {
+    comment_id: Uuid,
+    user_id: Uuid,
+    created_at: DateTime<Utc>,
+    read_at: Option<DateTime<Utc>>,
+}
+
+/// Same order as the SQL backends: scheduled first (soonest first), then
+/// unscheduled, newest first within equal schedules.
+fn sort_todos<T>(todos: &mut [T], todo: impl Fn(&T) -> &Todo) {
+    todos.sort_by(|a, b| {
+        let (a, b) = (todo(a), todo(b));
+        a.scheduled_for
+            .is_none()
+            .cmp(&b.scheduled_for.is_none())
+            .then(a.scheduled_for.cmp(&b.scheduled_for))
+            .then(b.created_at.cmp(&a.created_at))
+    });
+}
+
+impl Data {
+    fn next_seq(&mut self, user_id: Uuid) -> i64 {
+        let seq = self.change_seqs.entry(user_id).or_default();
+        *seq += 1;
+        *seq
+    }
+
+    fn delete_with_tombstone(&mut self, todo_id: Uuid, user_id: Uuid) {
+        let seq = self.next_seq(user_id);
+        self.todos.remove(&todo_id);
+        self.versions.remove(&todo_id);
+        self.shares.retain(|share| share.todo_id != Some(todo_id));
+        let comments: Vec<Uuid> = self.comments.iter().filter(|c| c.todo_id == todo_id).map(|c| c.id).collect();
+        for comment_id in comments {
+            self.delete_comment(comment_id);
+        }
+        for attachment in self.attachments.iter_mut().filter(|a| a.todo_id == Some(todo_id)) {
+            attachment.todo_id = None;
+        }
+        self.time_entries.retain(|entry| entry.todo_id != todo_id);
+        self.tombstones.insert(
+            todo_id,
+            Tombstone {
+                user_id,
+                seq,
+                deleted_at: Utc::now(),
+            },
+        );
+    }
+
+    fn existing(&self, user_id: Uuid, todo_id: Uuid) -> Existing {
+        if let Some(todo) = self.todos.get(&todo_id) {
+            if todo.user_id != user_id {
+                return Existing::Foreign;
+            }
+            let versions = self.versions.get(&todo_id).map(|(_, v)| v.clone()).unwrap_or_default();
+            return Existing::Todo { todo: Box::new(todo.clone()), versions };
+        }
+        match self.tombstones.get(&todo_id) {
+            Some(tombstone) if tombstone.user_id == user_id => Existing::Deleted {
+                deleted_at: tombstone.deleted_at,
+            },
+            Some(_) => Existing::Foreign,
+            None => Existing::None,
+        }
+    }
+
+    /// The user's role on the todo, if any.
+    fn role(&self, todo: &Todo, user_id: Uuid) -> Option<Role> {
+        if todo.user_id == user_id {
+            return Some(Role::Owner);
+        }
+        self.shares
+            .iter()
+            .filter(|share| share.grantee_id == user_id && share.covers(todo))
+            .map(|share| Role::from(share.role))
+            .max()
+    }
+
+    fn share(&self, stored: &StoredShare) -> Option<Share> {
+        Some(Share {
+            id: stored.id,
+            owner_id: stored.owner_id,
+            owner_username: self.users.get(&stored.owner_id)?.username.clone(),
+            grantee_id: stored.grantee_id,
+            grantee_username: self.users.get(&stored.grantee_id)?.username.clone(),
+            todo_id: stored.todo_id,
+            role: stored.role,
+            created_at: stored.created_at,
+        })
+    }
+
+    fn comment(&self, stored: &StoredComment) -> Option<Comment> {
+        let first = stored.revisions.first()?;
+        let current = stored.revisions.last()?;
+        Some(Comment {
+            id: stored.id,
+            todo_id: stored.todo_id,
+            author_id: stored.author_id,
+            author_username: self.users.get(&stored.author_id)?.username.clone(),
+            body: current.body.clone(),
+            created_at: first.created_at,
+            edited_at: stored.edited_at,
+        })
+    }
+
+    fn add_mentions(&mut self, comment_id: Uuid, mentioned: &[Uuid], now: DateTime<Utc>) {
+        for &user_id in mentioned {
+            if !self.mentions.iter().any(|m| m.comment_id == comment_id && m.user_id == user_id) {
+                self.mentions.push(StoredMention {
+                    comment_id,
+                    user_id,
+                    created_at: now,
+                    read_at: None,
+                });
+            }
+        }
+    }
+
+    fn delete_comment(&mut self, comment_id: Uuid) -> bool {
+        let before = self.comments.len();
+        self.comments.retain(|c| c.id != comment_id);
+        self.mentions.retain(|m| m.comment_id != comment_id);
+        self.comments.len() < before
+    }
+
+    /// Sets the todo's `tracked_seconds` to the total of its stopped entries.
+    fn update_tracked_seconds(&mut self, todo_id: Uuid) {
+        let total = self
+            .time_entries
+            .iter()
+            .filter(|entry| entry.todo_id == todo_id)
+            .filter_map(|entry| entry.duration_seconds)
+            .sum();
+        if let Some(todo) = self.todos.get_mut(&todo_id) {
+            todo.tracked_seconds = total;
+        }
+    }
+
+    /// Stops the user's running timer at `now`, if there is one and it is
+    /// on `todo_id` when given.
+    fn stop_running(&mut self, user_id: Uuid, todo_id: Option<Uuid>, now: DateTime<Utc>) -> Option<TimeEntry> {
+        let entry = self.time_entries.iter_mut().find(|entry| {
+            entry.user_id == user_id && entry.ended_at.is_none() && todo_id.is_none_or(|id| id == entry.todo_id)
+        })?;
+        let ended_at = now.max(entry.started_at);
+        entry.ended_at = Some(ended_at);
+        entry.duration_seconds = Some(time_tracking::duration_seconds(entry.started_at, ended_at));
+        entry.updated_at = Utc::now();
+        let stopped = entry.clone();
+        self.update_tracked_seconds(stopped.todo_id);
+        Some(stopped)
+    }
+
+    /// Total size of the user's uploads still attached to a todo.
+    fn attachment_usage(&self, user_id: Uuid) -> i64 {
+        self.attachments
+            .iter()
+            .filter(|a| a.user_id == user_id && a.todo_id.is_some())
+            .map(|a| a.size_bytes)
+            .sum()
+    }
+
+    /// Deletions are recorded before the todo goes, while its own shares
+    /// still say who to tell.
+    fn record_event(&mut self, kind: TodoEventKind, user_id: Uuid, todo_id: Uuid, todo: Option<&Todo>) -> TodoEvent {
+        self.last_event_id += 1;
+        let mut recipients: Vec<Uuid> = self
+            .shares
+            .iter()
+            .filter(|share| share.owner_id == user_id && share.todo_id.is_none_or(|id| id == todo_id))
+            .map(|share| share.grantee_id)
+            .collect();
+        recipients.sort();
+        recipients.dedup();
+        let event = TodoEvent {
+            id: self.last_event_id,
+            kind,
+            user_id,
+            todo_id,
+            todo: todo.cloned().map(TodoResponse::from),
+            created_at: Utc::now(),
+            recipients,
+        };
+        self.events.push(event.clone());
+        self.queue_webhooks(&event);
+        event
+    }
+
+    /// Queues a delivery of `event` for each of its owner's active webhooks
+    /// subscribed to it.
+    fn queue_webhooks(&mut self, event: &TodoEvent) {
+        let webhook_ids: Vec<Uuid> = self
+            .webhooks
+            .values()
+            .filter(|w| w.user_id == event.user_id && w.active && w.events.contains(&event.kind))
+            .map(|w| w.id)
+            .collect();
+        if webhook_ids.is_empty() {
+            return;
+        }
+
+        let payload = serde_json::to_value(event).unwrap_or_default();
+        for webhook_id in webhook_ids {
+            self.queue_delivery(webhook_id, event.kind.as_str(), &payload);
+        }
+    }
+
+    fn queue_delivery(&mut self, webhook_id: Uuid, event: &str, payload: &Value) -> WebhookDelivery {
+        let now = Utc::now();
+        let delivery = WebhookDelivery {
+            id: Uuid::new_v4(),
+            webhook_id,
+            event: event.to_string(),
+            payload: payload.clone(),
+            status: WebhookDeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt_at: Some(now),
+            last_status_code: None,
+            last_error: None,
+            created_at: now,
+            delivered_at: None,
+        };
+        self.deliveries.push(delivery.clone());
+        delivery
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, Data>, AppError> {
+        self.data
+            .read()
+            .map_err(|_| AppError::Internal("Memory store lock poisoned".to_string()))
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, Data>, AppError> {
+        self.data
+            .write()
+            .map_err(|_| AppError::Internal("Memory store lock poisoned".to_string()))
+    }
+}
+
+#[async_trait]
+impl AdminStore for MemoryStore {
+    async fn migrate(&self) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn migrate_down(&self, _target: i64) -> Result<(), AppError> {
+        Ok(())
+    }
+
+    async fn migration_status(&self) -> Result<Vec<MigrationStatus>, AppError> {
+        Ok(Vec::new())
+    }
+
+    async fn ping(&self) -> Result<(), AppError> {
+        self.read().map(|_| ())
+    }
+
+    async fn close(&self) {}
+
+    fn pool_stats(&self) -> Option<PoolStats> {
+        None
+    }
+}
+
+#[async_trait]
+impl UserStore for MemoryStore {
+    async fn create_user(&self, username: &str, email: &str, password_hash: &str) -> Result<User, AppError> {
+        let mut data = self.write()?;
+
+        if data.users.values().any(|u| u.username == username) {
+            return Err(AppError::BadRequest("Username already exists".to_string()));
+        }
+        if data.users.values().any(|u| u.email == email) {
+            return Err(AppError::BadRequest("Email already exists".to_string()));
+        }
+
+        let now = Utc::now();
+        let user = User {
+            id: Uuid::new_v4(),
+            username: username.to_string(),
+            email: email.to_string(),
+            password_hash: password_hash.to_string(),
+            timezone: calendar::DEFAULT_TIMEZONE.to_string(),
+            locale: calendar::DEFAULT_LOCALE.to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        data.users.insert(user.id, user.clone());
+
+        Ok(user)
+    }
+
+    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>, AppError> {
+        Ok(self.read()?.users.values().find(|u| u.username == username).cloned())
+    }
+
+    async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>, AppError> {
+        Ok(self.read()?.users.get(&user_id).cloned())
+    }
+
+    async fn update_user_preferences(
+        &self,
+        user_id: Uuid,
+        timezone: Option<&str>,
+        locale: Option<&str>,
+    ) -> Result<Option<User>, AppError> {
+        let mut data = self.write()?;
+        let Some(user) = data.users.get_mut(&user_id) else {
+            return Ok(None);
+        };
+
+        if let Some(timezone) = timezone {
+            user.timezone = timezone.to_string();
+        }
+        if let Some(locale) = locale {
+            user.locale = locale.to_string();
+        }
+        user.updated_at = Utc::now();
+
+        Ok(Some(user.clone()))
+    }
+}
+
+#[async_trait]
+impl TodoStore for MemoryStore {
+    async fn create_todo(&self, user_id: Uuid, todo: CreateTodo) -> Result<Todo, AppError> {
+        let mut data = self.write()?;
+
+        if !data.users.contains_key(&user_id) {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+
+        let now = Utc::now();
+        let todo = Todo {
+            id: Uuid::new_v4(),
+            user_id,
+            title: todo.title,
+            description: todo.description,
+            completed: todo.completed.unwrap_or(false),
+            scheduled_for: todo.scheduled_for,
+            assignee_id: None,
+            priority: todo.priority,
+            tags: todo.tags.unwrap_or_default(),
+            recurrence: todo.recurrence,
+            completed_at: todo.completed.unwrap_or(false).then_some(now),
+            snooze_count: 0,
+            original_scheduled_for: None,
+            hidden_until: None,
+            tracked_seconds: 0,
+            created_at: now,
+            updated_at: now,
+        };
+        let seq = data.next_seq(user_id);
+        data.versions.insert(todo.id, (seq, FieldVersions::all(now)));
+        data.todos.insert(todo.id, todo.clone());
+        let event = data.record_event(TodoEventKind::Created, user_id, todo.id, Some(&todo));
+        drop(data);
+        self.events.publish(event);
+
+        Ok(todo)
+    }
+
+    async fn get_todos_by_user(&self, user_id: Uuid) -> Result<Vec<Todo>, AppError> {
+        let mut todos: Vec<Todo> = self
+            .read()?
+            .todos
+            .values()
+            .filter(|t| t.user_id == user_id)
+            .cloned()
+            .collect();
+        sort_todos(&mut todos, |todo| todo);
+
+        Ok(todos)
+    }
+
+    async fn get_scheduled_todos(
+        &self,
+        user_id: Uuid,
+        from: Option<DateTime<Utc>>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Todo>, AppError> {
+        let mut todos: Vec<Todo> = self
+            .read()?
+            .todos
+            .values()
+            .filter(|t| t.user_id == user_id && !t.completed)
+            .filter(|t| t.scheduled_for.is_some_and(|at| from.is_none_or(|from| at >= from) && at < until))
+            .cloned()
+            .collect();
+        sort_todos(&mut todos, |todo| todo);
+
+        Ok(todos)
+    }
+
+    async fn get_todo_access(&self, todo_id: Uuid, user_id: Uuid) -> Result<Option<(Todo, Role)>, AppError> {
+        let data = self.read()?;
+        let Some(todo) = data.todos.get(&todo_id) else {
+            return Ok(None);
+        };
+
+        Ok(data.role(todo, user_id).map(|role| (todo.clone(), role)))
+    }
+
+    async fn update_todo(&self, todo_id: Uuid, update: UpdateTodo) -> Result<Option<Todo>, AppError> {
+        let mut data = self.write()?;
+
+        let Some(owner_id) = data.todos.get(&todo_id).map(|t| t.user_id) else {
+            return Ok(None);
+        };
+        let now = Utc::now();
+        let seq = data.next_seq(owner_id);
+        let entry = data.versions.entry(todo_id).or_default();
+        entry.0 = seq;
+        entry.1.extend(&FieldVersions::touched(&update, now));
+
+        let Some(todo) = data.todos.get_mut(&todo_id) else {
+            return Ok(None);
+        };
+
+        if let Some(title) = update.title {
+            todo.title = title;
+        }
+        if let Some(description) = update.description {
+            todo.description = Some(description);
+        }
+        if let Some(completed) = update.completed {
+            if completed != todo.completed {
+                todo.completed_at = completed.then(Utc::now);
+            }
+            todo.completed = completed;
+        }
+        if let Some(scheduled_for) = update.scheduled_for {
+            if todo.scheduled_for != Some(scheduled_for) {
+                todo.hidden_until = None;
+            }
+            todo.scheduled_for = Some(scheduled_for);
+        }
+        if let Some(priority) = update.priority {
+            todo.priority = priority;
+        }
+        if let Some(tags) = update.tags {
+            todo.tags = tags;
+        }
+        if let Some(recurrence) = update.recurrence {
+            todo.recurrence = recurrence;
+        }
+        todo.updated_at = now;
+
+        let todo = todo.clone();
+        let event = data.record_event(TodoEventKind::Updated, owner_id, todo_id, Some(&todo));
+        drop(data);
+        self.events.publish(event);
+
+        Ok(Some(todo))
+    }
+
+    async fn assign_todo(&self, todo_id: Uuid, assignee_id: Option<Uuid>) -> Result<Option<Todo>, AppError> {
+        let mut data = self.write()?;
+
+        let Some(owner_id) = data.todos.get(&todo_id).map(|t| t.user_id) else {
+            return Ok(None);
+        };
+        let seq = data.next_seq(owner_id);
+        data.versions.entry(todo_id).or_default().0 = seq;
+
+        let Some(todo) = data.todos.get_mut(&todo_id) else {
+            return Ok(None);
+        };
+        todo.assignee_id = assignee_id;
+        todo.updated_at = Utc::now();
+
+        let todo = todo.clone();
+        let event = data.record_event(TodoEventKind::Updated, owner_id, todo_id, Some(&todo));
+        drop(data);
+        self.events.publish(event);
+
+        Ok(Some(todo))
+    }
+
+    async fn snooze_todo(&self, todo_id: Uuid, until: DateTime<Utc>) -> Result<Option<Todo>, AppError> {
+        let mut data = self.write()?;
+
+        let Some(owner_id) = data.todos.get(&todo_id).map(|t| t.user_id) else {
+            return Ok(None);
+        };
+        let now = Utc::now();
+        let seq = data.next_seq(owner_id);
+        let versions = data.versions.entry(todo_id).or_default();
+        versions.0 = seq;
+        versions.1.scheduled_for = Some(now);
+
+        let Some(todo) = data.todos.get_mut(&todo_id) else {
+            return Ok(None);
+        };
+        if todo.snooze_count == 0 {
+            todo.original_scheduled_for = todo.scheduled_for;
+        }
+        todo.scheduled_for = Some(until);
+        todo.hidden_until = Some(until);
+        todo.snooze_count += 1;
+        todo.updated_at = now;
+
+        let todo = todo.clone();
+        let event = data.record_event(TodoEventKind::Updated, owner_id, todo_id, Some(&todo));
+        drop(data);
+        self.events.publish(event);
+
+        Ok(Some(todo))
+    }
+
+    async fn unsnooze_todo(&self, todo_id: Uuid) -> Result<Option<Todo>, AppError> {
+        let mut data = self.write()?;
+
+        let Some(owner_id) = data.todos.get(&todo_id).map(|t| t.user_id) else {
+            return Ok(None);
+        };
+        let seq = data.next_seq(owner_id);
+        data.versions.entry(todo_id).or_default().0 = seq;
+
+        let Some(todo) = data.todos.get_mut(&todo_id) else {
+            return Ok(None);
+        };
+        todo.hidden_until = None;
+        todo.updated_at = Utc::now();
+
+        let todo = todo.clone();
+        let event = data.record_event(TodoEventKind::Updated, owner_id, todo_id, Some(&todo));
+        drop(data);
+        self.events.publish(event);
+
+        Ok(Some(todo))
+    }
+
+    async fn delete_todo(&self, todo_id: Uuid) -> Result<bool, AppError> {
+        let mut data = self.write()?;
+
+        let Some(owner_id) = data.todos.get(&todo_id).map(|t| t.user_id) else {
+            return Ok(false);
+        };
+        let event = data.record_event(TodoEventKind::Deleted, owner_id, todo_id, None);
+        data.delete_with_tombstone(todo_id, owner_id);
+        drop(data);
+        self.events.publish(event);
+
+        Ok(true)
+    }
+
+    async fn get_shared_todos(&self, user_id: Uuid) -> Result<Vec<(Todo, Role)>, AppError> {
+        let data = self.read()?;
+        let mut todos: Vec<(Todo, Role)> = data
+            .todos
+            .values()
+            .filter(|todo| todo.user_id != user_id)
+            .filter_map(|todo| Some((todo.clone(), data.role(todo, user_id)?)))
+            .collect();
+        sort_todos(&mut todos, |(todo, _)| todo);
+
+        Ok(todos)
+    }
+
+    async fn get_assigned_todos(&self, user_id: Uuid) -> Result<Vec<Todo>, AppError> {
+        let data = self.read()?;
+        let mut todos: Vec<Todo> = data
+            .todos
+            .values()
+            .filter(|todo| todo.assignee_id == Some(user_id) && data.role(todo, user_id).is_some())
+            .cloned()
+            .collect();
+        sort_todos(&mut todos, |todo| todo);
+
+        Ok(todos)
+    }
+}
+
+#[async_trait]
+impl ShareStore for MemoryStore {
+    async fn upsert_share(
+        &self,
+        owner_id: Uuid,
+        grantee_id: Uuid,
+        todo_id: Option<Uuid>,
+        role: ShareRole,
+    ) -> Result<Share, AppError> {
+        let mut data = self.write()?;
+
+        if !data.users.contains_key(&owner_id) || !data.users.contains_key(&grantee_id) {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+        if todo_id.is_some_and(|id| !data.todos.contains_key(&id)) {
+            return Err(AppError::NotFound("Todo not found".to_string()));
+        }
+
+        let existing = data
+            .shares
+            .iter_mut()
+            .find(|s| s.owner_id == owner_id && s.grantee_id == grantee_id && s.todo_id == todo_id);
+        let index = match existing {
+            Some(share) => {
+                share.role = role;
+                let id = share.id;
+                data.shares.iter().position(|s| s.id == id)
+            }
+            None => {
+                data.shares.push(StoredShare {
+                    id: Uuid::new_v4(),
+                    owner_id,
+                    grantee_id,
+                    todo_id,
+                    role,
+                    created_at: Utc::now(),
+                });
+                Some(data.shares.len() - 1)
+            }
+        };
+
+        index
+            .and_then(|i| data.share(&data.shares[i]))
+            .ok_or(AppError::Internal("Share vanished while saving it".to_string()))
+    }
+
+    async fn get_shares_for_user(&self, user_id: Uuid) -> Result<Vec<Share>, AppError> {
+        let data = self.read()?;
+
+        Ok(data
+            .shares
+            .iter()
+            .filter(|s| s.owner_id == user_id || s.grantee_id == user_id)
+            .filter_map(|s| data.share(s))
+            .collect())
+    }
+
+    async fn get_share_by_id(&self, share_id: Uuid) -> Result<Option<Share>, AppError> {
+        let data = self.read()?;
+
+        Ok(data.shares.iter().find(|s| s.id == share_id).and_then(|s| data.share(s)))
+    }
+
+    async fn update_share(&self, share_id: Uuid, role: ShareRole) -> Result<Option<Share>, AppError> {
+        let mut data = self.write()?;
+
+        let Some(share) = data.shares.iter_mut().find(|s| s.id == share_id) else {
+            return Ok(None);
+        };
+        share.role = role;
+        Ok(data.shares.iter().find(|s| s.id == share_id).and_then(|s| data.share(s)))
+    }
+
+    async fn delete_share(&self, share_id: Uuid) -> Result<bool, AppError> {
+        let mut data = self.write()?;
+
+        let before = data.shares.len();
+        data.shares.retain(|s| s.id != share_id);
+        Ok(data.shares.len() < before)
+    }
+}
+
+#[async_trait]
+impl CommentStore for MemoryStore {
+    async fn create_comment(
+        &self,
+        todo_id: Uuid,
+        author_id: Uuid,
+        body: &str,
+        mentioned: &[Uuid],
+    ) -> Result<Comment, AppError> {
+        let mut data = self.write()?;
+
+        if !data.todos.contains_key(&todo_id) {
+            return Err(AppError::NotFound("Todo not found".to_string()));
+        }
+        let now = Utc::now();
+        let stored = StoredComment {
+            id: Uuid::new_v4(),
+            todo_id,
+            author_id,
+            edited_at: None,
+            revisions: vec![CommentRevision {
+                body: body.to_string(),
+                created_at: now,
+            }],
+        };
+        let comment = data
+            .comment(&stored)
+            .ok_or(AppError::NotFound("User not found".to_string()))?;
+        data.comments.push(stored);
+        data.add_mentions(comment.id, mentioned, now);
+
+        Ok(comment)
+    }
+
+    async fn get_comments(&self, todo_id: Uuid) -> Result<Vec<Comment>, AppError> {
+        let data = self.read()?;
+
+        Ok(data
+            .comments
+            .iter()
+            .filter(|c| c.todo_id == todo_id)
+            .filter_map(|c| data.comment(c))
+            .collect())
+    }
+
+    async fn get_comment(&self, comment_id: Uuid) -> Result<Option<Comment>, AppError> {
+        let data = self.read()?;
+
+        Ok(data.comments.iter().find(|c| c.id == comment_id).and_then(|c| data.comment(c)))
+    }
+
+    async fn update_comment(&self, comment_id: Uuid, body: &str, mentioned: &[Uuid]) -> Result<Option<Comment>, AppError> {
+        let mut data = self.write()?;
+
+        let now = Utc::now();
+        let Some(stored) = data.comments.iter_mut().find(|c| c.id == comment_id) else {
+            return Ok(None);
+        };
+        stored.edited_at = Some(now);
+        stored.revisions.push(CommentRevision {
+            body: body.to_string(),
+            created_at: now,
+        });
+        data.add_mentions(comment_id, mentioned, now);
+
+        Ok(data.comments.iter().find(|c| c.id == comment_id).and_then(|c| data.comment(c)))
+    }
+
+    async fn delete_comment(&self, comment_id: Uuid) -> Result<bool, AppError> {
+        Ok(self.write()?.delete_comment(comment_id))
+    }
+
+    async fn get_comment_revisions(&self, comment_id: Uuid) -> Result<Vec<CommentRevision>, AppError> {
+        let data = self.read()?;
+
+        Ok(data
+            .comments
+            .iter()
+            .find(|c| c.id == comment_id)
+            .map(|c| c.revisions.clone())
+            .unwrap_or_default())
+    }
+
+    async fn get_mentions(&self, user_id: Uuid, unread_only: bool, limit: i64) -> Result<Vec<Mention>, AppError> {
+        let data = self.read()?;
+
+        let mentions = data
+            .mentions
+            .iter()
+            .rev()
+            .filter(|m| m.user_id == user_id && !(unread_only && m.read_at.is_some()))
+            .filter_map(|m| {
+                let stored = data.comments.iter().find(|c| c.id == m.comment_id)?;
+                let todo = data.todos.get(&stored.todo_id)?;
+                data.role(todo, user_id)?;
+                Some(Mention {
+                    comment: data.comment(stored)?,
+                    mentioned_at: m.created_at,
+                    read_at: m.read_at,
+                })
+            })
+            .take(usize::try_from(limit).unwrap_or(0))
+            .collect();
+
+        Ok(mentions)
+    }
+
+    async fn mark_mentions_read(&self, user_id: Uuid, comment_id: Option<Uuid>) -> Result<u64, AppError> {
+        let mut data = self.write()?;
+
+        let now = Utc::now();
+        let mut marked = 0;
+        for mention in data.mentions.iter_mut() {
+            if mention.user_id == user_id
+                && mention.read_at.is_none()
+                && comment_id.is_none_or(|id| id == mention.comment_id)
+            {
+                mention.read_at = Some(now);
+                marked += 1;
+            }
+        }
+
+        Ok(marked)
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for MemoryStore {
+    async fn create_attachment(&self, attachment: NewAttachment, quota_bytes: i64) -> Result<Option<Attachment>, AppError> {
+        let mut data = self.write()?;
+
+        if !data.todos.contains_key(&attachment.todo_id) {
+            return Err(AppError::NotFound("Todo not found".to_string()));
+        }
+        let username = data
+            .users
+            .get(&attachment.user_id)
+            .map(|user| user.username.clone())
+            .ok_or(AppError::NotFound("User not found".to_string()))?;
+        if data.attachment_usage(attachment.user_id) + attachment.size_bytes > quota_bytes {
+            return Ok(None);
+        }
+
+        let attachment = Attachment {
+            id: attachment.id,
+            todo_id: Some(attachment.todo_id),
+            user_id: attachment.user_id,
+            username,
+            filename: attachment.filename,
+            content_type: attachment.content_type,
+            size_bytes: attachment.size_bytes,
+            blob_key: attachment.blob_key,
+            created_at: Utc::now(),
+        };
+        data.attachments.push(attachment.clone());
+
+        Ok(Some(attachment))
+    }
+
+    async fn get_attachments(&self, todo_id: Uuid) -> Result<Vec<Attachment>, AppError> {
+        let data = self.read()?;
+
+        Ok(data.attachments.iter().filter(|a| a.todo_id == Some(todo_id)).cloned().collect())
+    }
+
+    async fn get_attachment(&self, attachment_id: Uuid) -> Result<Option<Attachment>, AppError> {
+        let data = self.read()?;
+
+        Ok(data.attachments.iter().find(|a| a.id == attachment_id).cloned())
+    }
+
+    async fn detach_attachment(&self, attachment_id: Uuid) -> Result<bool, AppError> {
+        let mut data = self.write()?;
+
+        let attachment = data
+            .attachments
+            .iter_mut()
+            .find(|a| a.id == attachment_id && a.todo_id.is_some());
+        match attachment {
+            Some(attachment) => {
+                attachment.todo_id = None;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn get_attachment_usage(&self, user_id: Uuid) -> Result<i64, AppError> {
+        Ok(self.read()?.attachment_usage(user_id))
+    }
+
+    async fn get_orphaned_attachments(&self, limit: i64) -> Result<Vec<Attachment>, AppError> {
+        let data = self.read()?;
+
+        Ok(data
+            .attachments
+            .iter()
+            .filter(|a| a.todo_id.is_none())
+            .take(usize::try_from(limit).unwrap_or(0))
+            .cloned()
+            .collect())
+    }
+
+    async fn purge_attachment(&self, attachment_id: Uuid) -> Result<bool, AppError> {
+        let mut data = self.write()?;
+
+        let before = data.attachments.len();
+        data.attachments.retain(|a| !(a.id == attachment_id && a.todo_id.is_none()));
+        Ok(data.attachments.len() < before)
+    }
+}
+
+#[async_trait]
+impl SyncStore for MemoryStore {
+    async fn todo_changes_since(&self, user_id: Uuid, since: i64, limit: usize) -> Result<Vec<TodoChange>, AppError> {
+        let data = self.read()?;
+        let upserted = data.todos.values().filter_map(|todo| {
+            let (seq, _) = data.versions.get(&todo.id)?;
+            (todo.user_id == user_id && *seq > since).then(|| TodoChange::Upserted {
+                seq: *seq,
+                todo: Box::new(todo.clone()),
+            })
+        });
+        let deleted = data
+            .tombstones
+            .iter()
+            .filter(|(_, t)| t.user_id == user_id && t.seq > since)
+            .map(|(id, t)| TodoChange::Deleted {
+                seq: t.seq,
+                id: *id,
+                deleted_at: t.deleted_at,
+            });
+
+        let mut changes: Vec<TodoChange> = upserted.chain(deleted).collect();
+        changes.sort_by_key(TodoChange::seq);
+        changes.truncate(limit);
+        Ok(changes)
+    }
+
+    async fn apply_sync_mutation(&self, user_id: Uuid, mutation: &SyncMutation) -> Result<Merged, AppError> {
+        let mut data = self.write()?;
+
+        if !data.users.contains_key(&user_id) {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+
+        let existing = data.existing(user_id, mutation.id);
+        let merged = sync::merge(user_id, existing, mutation, Utc::now())?;
+
+        let event = match &merged.write {
+            Write::Nothing => return Ok(merged),
+            Write::Insert(todo, versions) | Write::Update(todo, versions) => {
+                let seq = data.next_seq(user_id);
+                data.versions.insert(todo.id, (seq, versions.clone()));
+                data.todos.insert(todo.id, todo.clone());
+                let kind = match merged.write {
+                    Write::Insert(..) => TodoEventKind::Created,
+                    _ => TodoEventKind::Updated,
+                };
+                data.record_event(kind, user_id, todo.id, Some(todo))
+            }
+            Write::Delete(todo_id) => {
+                let event = data.record_event(TodoEventKind::Deleted, user_id, *todo_id, None);
+                data.delete_with_tombstone(*todo_id, user_id);
+                event
+            }
+        };
+        drop(data);
+        self.events.publish(event);
+
+        Ok(merged)
+    }
+}
+
+#[async_trait]
+impl WebhookStore for MemoryStore {
+    async fn create_webhook(
+        &self,
+        user_id: Uuid,
+        url: &str,
+        events: &[TodoEventKind],
+        secret: &str,
+        active: bool,
+    ) -> Result<Webhook, AppError> {
+        let mut data = self.write()?;
+
+        if !data.users.contains_key(&user_id) {
+            return Err(AppError::NotFound("User not found".to_string()));
+        }
+
+        let now = Utc::now();
+        let webhook = Webhook {
+            id: Uuid::new_v4(),
+            user_id,
+            url: url.to_string(),
+            events: events.to_vec(),
+            secret: secret.to_string(),
+            active,
+            created_at: now,
+            updated_at: now,
+        };
+        data.webhooks.insert(webhook.id, webhook.clone());
+
+        Ok(webhook)
+    }
+
+    async fn get_webhooks_by_user(&self, user_id: Uuid) -> Result<Vec<Webhook>, AppError> {
+        let mut webhooks: Vec<Webhook> = self
+            .read()?
+            .webhooks
+            .values()
+            .filter(|w| w.user_id == user_id)
+            .cloned()
+            .collect();
+        webhooks.sort_by_key(|w| w.created_at);
+
+        Ok(webhooks)
+    }
+
+    async fn get_webhook_by_id(&self, webhook_id: Uuid, user_id: Uuid) -> Result<Option<Webhook>, AppError> {
+        Ok(self
+            .read()?
+            .webhooks
+            .get(&webhook_id)
+            .filter(|w| w.user_id == user_id)
+            .cloned())
+    }
+
+    async fn update_webhook(
+        &self,
+        webhook_id: Uuid,
+        user_id: Uuid,
+        update: UpdateWebhook,
+    ) -> Result<Option<Webhook>, AppError> {
+        let mut data = self.write()?;
+
+        let Some(webhook) = data.webhooks.get_mut(&webhook_id).filter(|w| w.user_id == user_id) else {
+            return Ok(None);
+        };
+
+        if let Some(url) = update.url {
+            webhook.url = url;
+        }
+        if let Some(events) = update.events {
+            webhook.events = events;
+        }
+        if let Some(secret) = update.secret {
+            webhook.secret = secret;
+        }
+        if let Some(active) = update.active {
+            webhook.active = active;
+        }
+        webhook.updated_at = Utc::now();
+
+        Ok(Some(webhook.clone()))
+    }
+
+    async fn delete_webhook(&self, webhook_id: Uuid, user_id: Uuid) -> Result<bool, AppError> {
+        let mut data = self.write()?;
+
+        if data.webhooks.get(&webhook_id).is_none_or(|w| w.user_id != user_id) {
+            return Ok(false);
+        }
+        data.webhooks.remove(&webhook_id);
+        let Data { deliveries, attempts, .. } = &mut *data;
+        deliveries.retain(|d| {
+            let keep = d.webhook_id != webhook_id;
+            if !keep {
+                attempts.remove(&d.id);
+            }
+            keep
+        });
+
+        Ok(true)
+    }
+
+    async fn enqueue_webhook_delivery(
+        &self,
+        webhook_id: Uuid,
+        event: &str,
+        payload: &Value,
+    ) -> Result<WebhookDelivery, AppError> {
+        let mut data = self.write()?;
+
+        if !data.webhooks.contains_key(&webhook_id) {
+            return Err(AppError::NotFound("Webhook not found".to_string()));
+        }
+        Ok(data.queue_delivery(webhook_id, event, payload))
+    }
+
+    async fn get_webhook_deliveries(&self, webhook_id: Uuid, limit: i64) -> Result<Vec<WebhookDelivery>, AppError> {
+        Ok(self
+            .read()?
+            .deliveries
+            .iter()
+            .rev()
+            .filter(|d| d.webhook_id == webhook_id)
+            .take(usize::try_from(limit).unwrap_or(0))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_webhook_delivery(
+        &self,
+        delivery_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Option<WebhookDelivery>, AppError> {
+        Ok(self
+            .read()?
+            .deliveries
+            .iter()
+            .find(|d| d.id == delivery_id && d.webhook_id == webhook_id)
+            .cloned())
+    }
+
+    async fn get_webhook_attempts(&self, delivery_id: Uuid) -> Result<Vec<WebhookAttempt>, AppError> {
+        Ok(self.read()?.attempts.get(&delivery_id).cloned().unwrap_or_default())
+    }
+
+    async fn claim_webhook_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<PendingDelivery>, AppError> {
+        let mut data = self.write()?;
+        let Data { deliveries, webhooks, .. } = &mut *data;
+
+        let mut due: Vec<&mut WebhookDelivery> = deliveries
+            .iter_mut()
+            .filter(|d| d.status == WebhookDeliveryStatus::Pending && d.next_attempt_at.is_some_and(|at| at <= now))
+            .collect();
+        due.sort_by_key(|d| d.next_attempt_at);
+
+        let mut claimed = Vec::new();
+        for delivery in due.into_iter().take(usize::try_from(limit).unwrap_or(0)) {
+            let Some(webhook) = webhooks.get(&delivery.webhook_id) else {
+                continue;
+            };
+            delivery.next_attempt_at = Some(lease_until);
+            claimed.push(PendingDelivery {
+                id: delivery.id,
+                webhook_id: delivery.webhook_id,
+                event: delivery.event.clone(),
+                payload: delivery.payload.clone(),
+                attempts: delivery.attempts,
+                url: webhook.url.clone(),
+                secret: webhook.secret.clone(),
+            });
+        }
+
+        Ok(claimed)
+    }
+
+    async fn record_webhook_attempt(
+        &self,
+        delivery_id: Uuid,
+        attempt: &WebhookAttempt,
+        outcome: DeliveryOutcome,
+    ) -> Result<(), AppError> {
+        let mut data = self.write()?;
+
+        let Some(delivery) = data.deliveries.iter_mut().find(|d| d.id == delivery_id) else {
+            return Ok(());
+        };
+        delivery.attempts += 1;
+        delivery.last_status_code = attempt.status_code;
+        delivery.last_error = attempt.error.clone();
+        (delivery.status, delivery.next_attempt_at, delivery.delivered_at) = match outcome {
+            DeliveryOutcome::Delivered => (WebhookDeliveryStatus::Delivered, None, Some(attempt.attempted_at)),
+            DeliveryOutcome::Retry { at } => (WebhookDeliveryStatus::Pending, Some(at), None),
+            DeliveryOutcome::Dead => (WebhookDeliveryStatus::Dead, None, None),
+        };
+        data.attempts.entry(delivery_id).or_default().push(attempt.clone());
+
+        Ok(())
+    }
+
+    async fn prune_webhook_deliveries(&self, before: DateTime<Utc>) -> Result<u64, AppError> {
+        let mut data = self.write()?;
+        let Data { deliveries, attempts, .. } = &mut *data;
+
+        let count = deliveries.len();
+        deliveries.retain(|d| {
+            let keep = d.status == WebhookDeliveryStatus::Pending || d.created_at >= before;
+            if !keep {
+                attempts.remove(&d.id);
+            }
+            keep
+        });
+
+        Ok((count - deliveries.len()) as u64)
+    }
+}
+
+#[async_trait]
+impl StatsStore for MemoryStore {
+    async fn todo_stats(&self, user_id: Uuid, range: &StatsRange) -> Result<StatsCounts, AppError> {
+        let data = self.read()?;
+        let in_range = |at: DateTime<Utc>| at >= range.start && at < range.end;
+        let mut counts = StatsCounts::default();
+        let mut completion_seconds = Vec::new();
+        let mut lists: HashMap<Uuid, ListStats> = HashMap::new();
+        let mut tags: HashMap<&str, TagStats> = HashMap::new();
+
+        for todo in data.todos.values().filter(|todo| data.role(todo, user_id).is_some()) {
+            let created = in_range(todo.created_at);
+            let completed_at = todo.completed_at.filter(|at| in_range(*at));
+            if !todo.completed && todo.scheduled_for.is_some_and(|due| due >= range.start && due < range.end.min(range.now)) {
+                counts.overdue += 1;
+            }
+            if created {
+                counts.created += 1;
+            }
+            if let Some(at) = completed_at {
+                counts.completed += 1;
+                if todo.scheduled_for.is_some_and(|due| at > due) {
+                    counts.completed_late += 1;
+                }
+                completion_seconds.push((at - todo.created_at).num_milliseconds() as f64 / 1000.0);
+                let date = at.with_timezone(&range.tz).date_naive();
+                match counts.completed_per_day.iter_mut().find(|day| day.date == date) {
+                    Some(day) => day.count += 1,
+                    None => counts.completed_per_day.push(DayCount { date, count: 1 }),
+                }
+            }
+            if !created && completed_at.is_none() {
+                continue;
+            }
+
+            let list = lists.entry(todo.user_id).or_insert_with(|| ListStats {
+                owner_id: todo.user_id,
+                username: data.users.get(&todo.user_id).map(|u| u.username.clone()).unwrap_or_default(),
+                created: 0,
+                completed: 0,
+            });
+            list.created += i64::from(created);
+            list.completed += i64::from(completed_at.is_some());
+            for tag in &todo.tags {
+                let stats = tags.entry(tag).or_insert_with(|| TagStats { tag: tag.clone(), created: 0, completed: 0 });
+                stats.created += i64::from(created);
+                stats.completed += i64::from(completed_at.is_some());
+            }
+        }
+
+        if !completion_seconds.is_empty() {
+            counts.average_completion_seconds = Some(completion_seconds.iter().sum::<f64>() / completion_seconds.len() as f64);
+        }
+        counts.by_list = lists.into_values().collect();
+        counts.by_tag = tags.into_values().collect();
+
+        Ok(counts)
+    }
+}
+
+#[async_trait]
+impl TimeEntryStore for MemoryStore {
+    async fn start_timer(
+        &self,
+        todo_id: Uuid,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<(TimeEntry, Option<TimeEntry>), AppError> {
+        let mut data = self.write()?;
+        if !data.todos.contains_key(&todo_id) {
+            return Err(AppError::NotFound("Todo not found".to_string()));
+        }
+
+        let stopped = data.stop_running(user_id, None, now);
+        let started = TimeEntry {
+            id: Uuid::new_v4(),
+            todo_id,
+            user_id,
+            started_at: now,
+            ended_at: None,
+            duration_seconds: None,
+            note: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        data.time_entries.push(started.clone());
+
+        Ok((started, stopped))
+    }
+
+    async fn stop_timer(&self, todo_id: Uuid, user_id: Uuid, now: DateTime<Utc>) -> Result<Option<TimeEntry>, AppError> {
+        Ok(self.write()?.stop_running(user_id, Some(todo_id), now))
+    }
+
+    async fn get_running_time_entry(&self, user_id: Uuid) -> Result<Option<TimeEntry>, AppError> {
+        let data = self.read()?;
+        Ok(data
+            .time_entries
+            .iter()
+            .find(|entry| entry.user_id == user_id && entry.ended_at.is_none())
+            .cloned())
+    }
+
+    async fn create_time_entry(
+        &self,
+        todo_id: Uuid,
+        user_id: Uuid,
+        started_at: DateTime<Utc>,
+        ended_at: DateTime<Utc>,
+        note: Option<&str>,
+    ) -> Result<TimeEntry, AppError> {
+        let mut data = self.write()?;
+        if !data.todos.contains_key(&todo_id) {
+            return Err(AppError::NotFound("Todo not found".to_string()));
+        }
+
+        let entry = TimeEntry {
+            id: Uuid::new_v4(),
+            todo_id,
+            user_id,
+            started_at,
+            ended_at: Some(ended_at),
+            duration_seconds: Some(time_tracking::duration_seconds(started_at, ended_at)),
+            note: note.map(str::to_string),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        data.time_entries.push(entry.clone());
+        data.update_tracked_seconds(todo_id);
+
+        Ok(entry)
+    }
+
+    async fn get_time_entries(&self, todo_id: Uuid) -> Result<Vec<TimeEntry>, AppError> {
+        let data = self.read()?;
+        let mut entries: Vec<TimeEntry> = data.time_entries.iter().filter(|entry| entry.todo_id == todo_id).cloned().collect();
+        entries.sort_by(|a, b| a.started_at.cmp(&b.started_at).then(a.id.cmp(&b.id)));
+
+        Ok(entries)
+    }
+
+    async fn get_time_entry(&self, entry_id: Uuid) -> Result<Option<TimeEntry>, AppError> {
+        let data = self.read()?;
+        Ok(data.time_entries.iter().find(|entry| entry.id == entry_id).cloned())
+    }
+
+    async fn update_time_entry(
+        &self,
+        entry_id: Uuid,
+        started_at: DateTime<Utc>,
+        ended_at: Option<DateTime<Utc>>,
+        note: Option<&str>,
+    ) -> Result<Option<TimeEntry>, AppError> {
+        let mut data = self.write()?;
+        let Some(entry) = data.time_entries.iter_mut().find(|entry| entry.id == entry_id) else {
+            return Ok(None);
+        };
+        entry.started_at = started_at;
+        entry.ended_at = ended_at;
+        entry.duration_seconds = ended_at.map(|ended_at| time_tracking::duration_seconds(started_at, ended_at));
+        entry.note = note.map(str::to_string);
+        entry.updated_at = Utc::now();
+        let updated = entry.clone();
+        data.update_tracked_seconds(updated.todo_id);
+
+        Ok(Some(updated))
+    }
+
+    async fn delete_time_entry(&self, entry_id: Uuid) -> Result<bool, AppError> {
+        let mut data = self.write()?;
+        let Some(index) = data.time_entries.iter().position(|entry| entry.id == entry_id) else {
+            return Ok(false);
+        };
+        let entry = data.time_entries.remove(index);
+        data.update_tracked_seconds(entry.todo_id);
+
+        Ok(true)
+    }
+
+    async fn time_report(&self, user_id: Uuid, range: &StatsRange) -> Result<Vec<TimeReportRow>, AppError> {
+        let data = self.read()?;
+        let mut rows: Vec<TimeReportRow> = Vec::new();
+        for entry in &data.time_entries {
+            let Some(seconds) = entry.duration_seconds else {
+                continue;
+            };
+            if entry.user_id != user_id || entry.started_at < range.start || entry.started_at >= range.end {
+                continue;
+            }
+            let Some(owner_id) = data.todos.get(&entry.todo_id).map(|todo| todo.user_id) else {
+                continue;
+            };
+
+            let date = entry.started_at.with_timezone(&range.tz).date_naive();
+            match rows.iter_mut().find(|row| row.date == date && row.owner_id == owner_id) {
+                Some(row) => row.seconds += seconds,
+                None => rows.push(TimeReportRow {
+                    date,
+                    owner_id,
+                    username: data.users.get(&owner_id).map(|u| u.username.clone()).unwrap_or_default(),
+                    seconds,
+                }),
+            }
+        }
+
+        Ok(rows)
+    }
+}
+
+#[async_trait]
+impl EventStore for MemoryStore {
+    async fn todo_events_since(&self, user_id: Uuid, after_id: i64, limit: i64) -> Result<Vec<TodoEvent>, AppError> {
+        Ok(self
+            .read()?
+            .events
+            .iter()
+            .filter(|e| e.is_for(user_id) && e.id > after_id)
+            .take(usize::try_from(limit).unwrap_or(0))
+            .cloned()
+            .collect())
+    }
+
+    async fn oldest_todo_event_id(&self) -> Result<Option<i64>, AppError> {
+        Ok(self.read()?.events.first().map(|e| e.id))
+    }
+
+    async fn prune_todo_events(&self, before: DateTime<Utc>) -> Result<u64, AppError> {
+        let mut data = self.write()?;
+        let keep_from = data
+            .events
+            .iter()
+            .position(|e| e.created_at >= before)
+            .unwrap_or(data.events.len())
+            .min(data.events.len().saturating_sub(1));
+        data.events.drain(..keep_from);
+        Ok(keep_from as u64)
+    }
+
+    async fn subscribe_todo_events(&self) -> Result<broadcast::Receiver<Notification>, AppError> {
+        Ok(self.events.subscribe())
+    }
+}
@@ -0,0 +1,771 @@
+pub mod memory;
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{migrate::Migrator, pool::PoolConnection, types::Json, Database, Pool};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::{
+    config::DatabaseConfig,
+    error::AppError,
+    events::Notification,
+    models::{
+        Attachment, Comment, CommentRevision, CreateTodo, Mention, NewAttachment, Priority, Role, Share, ShareRole, SyncMutation, TimeEntry,
+        TimeReportRow, Todo, TodoEvent, TodoEventKind, TodoResponse, UpdateTodo, UpdateWebhook, User, Webhook, WebhookAttempt, WebhookDelivery, WebhookDeliveryStatus,
+    },
+    stats::{StatsCounts, StatsRange},
+    sync::{FieldVersions, Merged, TodoChange},
+    webhooks::{DeliveryOutcome, PendingDelivery},
+};
+
+pub use memory::MemoryStore;
+pub use postgres::PgStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+/// Shared handle to whichever backend the service was started with.
+pub type DynStore = Arc<dyn Store>;
+
+#[async_trait]
+pub trait UserStore: Send + Sync {
+    async fn create_user(&self, username: &str, email: &str, password_hash: &str) -> Result<User, AppError>;
+    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>, AppError>;
+    async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>, AppError>;
+    /// Sets the time zone and locale given; `None` leaves one unchanged.
+    /// Both must already be valid.
+    async fn update_user_preferences(
+        &self,
+        user_id: Uuid,
+        timezone: Option<&str>,
+        locale: Option<&str>,
+    ) -> Result<Option<User>, AppError>;
+}
+
+/// Todo mutations also append a [`TodoEvent`] to a change log in the same
+/// transaction and publish it once committed, and take the next number of
+/// the owner's change sequence (see [`SyncStore`]), whoever makes them.
+///
+/// Methods taking only a todo id do not check who is asking; callers go
+/// through [`crate::access::authorize`] first.
+#[async_trait]
+pub trait TodoStore: Send + Sync {
+    async fn create_todo(&self, user_id: Uuid, todo: CreateTodo) -> Result<Todo, AppError>;
+    /// The todos the user owns.
+    async fn get_todos_by_user(&self, user_id: Uuid) -> Result<Vec<Todo>, AppError>;
+    /// The user's open todos scheduled from `from` (or any time before
+    /// `until` if `None`) up to but not including `until`, soonest first.
+    async fn get_scheduled_todos(
+        &self,
+        user_id: Uuid,
+        from: Option<DateTime<Utc>>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Todo>, AppError>;
+    /// The todo with the user's role on it, or `None` if it does not exist
+    /// or the user has no access to it.
+    async fn get_todo_access(&self, todo_id: Uuid, user_id: Uuid) -> Result<Option<(Todo, Role)>, AppError>;
+    async fn update_todo(&self, todo_id: Uuid, update: UpdateTodo) -> Result<Option<Todo>, AppError>;
+    async fn assign_todo(&self, todo_id: Uuid, assignee_id: Option<Uuid>) -> Result<Option<Todo>, AppError>;
+    /// Moves the todo's schedule to `until` and hides it from listings until
+    /// then, counting the snooze. The first snooze keeps the schedule it
+    /// replaced as the original one.
+    async fn snooze_todo(&self, todo_id: Uuid, until: DateTime<Utc>) -> Result<Option<Todo>, AppError>;
+    /// Shows a snoozed todo in listings again, leaving its schedule as is.
+    async fn unsnooze_todo(&self, todo_id: Uuid) -> Result<Option<Todo>, AppError>;
+    async fn delete_todo(&self, todo_id: Uuid) -> Result<bool, AppError>;
+    /// Other users' todos shared with the user, directly or through their
+    /// list, with the user's role on each.
+    async fn get_shared_todos(&self, user_id: Uuid) -> Result<Vec<(Todo, Role)>, AppError>;
+    /// Todos assigned to the user that they can still access.
+    async fn get_assigned_todos(&self, user_id: Uuid) -> Result<Vec<Todo>, AppError>;
+}
+
+/// Grants of access to todos and lists.
+#[async_trait]
+pub trait ShareStore: Send + Sync {
+    /// Creates the share, or changes the role of the grantee's existing
+    /// share of the same todo or list.
+    async fn upsert_share(
+        &self,
+        owner_id: Uuid,
+        grantee_id: Uuid,
+        todo_id: Option<Uuid>,
+        role: ShareRole,
+    ) -> Result<Share, AppError>;
+    /// Shares the user granted or received, oldest first.
+    async fn get_shares_for_user(&self, user_id: Uuid) -> Result<Vec<Share>, AppError>;
+    async fn get_share_by_id(&self, share_id: Uuid) -> Result<Option<Share>, AppError>;
+    async fn update_share(&self, share_id: Uuid, role: ShareRole) -> Result<Option<Share>, AppError>;
+    async fn delete_share(&self, share_id: Uuid) -> Result<bool, AppError>;
+}
+
+/// Comment threads on todos and the mentions inbox. Deleting a todo or a
+/// comment deletes what hangs off it.
+///
+/// Like [`TodoStore`], methods do not check who is asking.
+#[async_trait]
+pub trait CommentStore: Send + Sync {
+    /// Adds a comment, its first revision and a mention of each of
+    /// `mentioned`.
+    async fn create_comment(
+        &self,
+        todo_id: Uuid,
+        author_id: Uuid,
+        body: &str,
+        mentioned: &[Uuid],
+    ) -> Result<Comment, AppError>;
+    /// The todo's comments, oldest first.
+    async fn get_comments(&self, todo_id: Uuid) -> Result<Vec<Comment>, AppError>;
+    async fn get_comment(&self, comment_id: Uuid) -> Result<Option<Comment>, AppError>;
+    /// Replaces the body, keeping the old one as a revision, and mentions
+    /// those of `mentioned` not mentioned before.
+    async fn update_comment(&self, comment_id: Uuid, body: &str, mentioned: &[Uuid]) -> Result<Option<Comment>, AppError>;
+    async fn delete_comment(&self, comment_id: Uuid) -> Result<bool, AppError>;
+    /// Every version of the comment's body, oldest first; the last is the
+    /// current one.
+    async fn get_comment_revisions(&self, comment_id: Uuid) -> Result<Vec<CommentRevision>, AppError>;
+    /// Up to `limit` of the user's mentions, newest first, on todos they
+    /// can still access.
+    async fn get_mentions(&self, user_id: Uuid, unread_only: bool, limit: i64) -> Result<Vec<Mention>, AppError>;
+    /// Marks the user's mention in one comment, or all of them, read.
+    async fn mark_mentions_read(&self, user_id: Uuid, comment_id: Option<Uuid>) -> Result<u64, AppError>;
+}
+
+/// Records of files attached to todos; the files themselves are in a
+/// [`crate::blobs::BlobStore`]. Deleting a todo detaches its attachments
+/// rather than deleting them, so the cleanup in [`crate::attachments`] can
+/// delete each file before its record.
+///
+/// Like [`TodoStore`], methods do not check who is asking.
+#[async_trait]
+pub trait AttachmentStore: Send + Sync {
+    /// Records the attachment unless the uploader's attached files would
+    /// then take more than `quota_bytes`, in which case `None` is returned.
+    async fn create_attachment(&self, attachment: NewAttachment, quota_bytes: i64) -> Result<Option<Attachment>, AppError>;
+    /// The todo's attachments, oldest first.
+    async fn get_attachments(&self, todo_id: Uuid) -> Result<Vec<Attachment>, AppError>;
+    async fn get_attachment(&self, attachment_id: Uuid) -> Result<Option<Attachment>, AppError>;
+    /// Detaches the attachment from its todo, leaving it to the cleanup.
+    async fn detach_attachment(&self, attachment_id: Uuid) -> Result<bool, AppError>;
+    /// Total size of the user's uploads still attached to a todo.
+    async fn get_attachment_usage(&self, user_id: Uuid) -> Result<i64, AppError>;
+    /// Up to `limit` detached attachments.
+    async fn get_orphaned_attachments(&self, limit: i64) -> Result<Vec<Attachment>, AppError>;
+    /// Deletes the record of a detached attachment.
+    async fn purge_attachment(&self, attachment_id: Uuid) -> Result<bool, AppError>;
+}
+
+/// The todo change log and its live feed.
+#[async_trait]
+pub trait EventStore: Send + Sync {
+    /// Up to `limit` of the events for the user (see [`TodoEvent::is_for`]) with an id
+    /// above `after_id`, oldest first.
+    async fn todo_events_since(&self, user_id: Uuid, after_id: i64, limit: i64) -> Result<Vec<TodoEvent>, AppError>;
+    /// The oldest event kept, across all users.
+    async fn oldest_todo_event_id(&self) -> Result<Option<i64>, AppError>;
+    /// Deletes events created before `before`, always keeping the newest
+    /// one so [`EventStore::oldest_todo_event_id`] can detect gaps.
+    async fn prune_todo_events(&self, before: DateTime<Utc>) -> Result<u64, AppError>;
+    /// Receives every event committed from now on, by any instance sharing
+    /// the database.
+    async fn subscribe_todo_events(&self) -> Result<broadcast::Receiver<Notification>, AppError>;
+}
+
+/// Change feed and client mutations behind `/api/sync`.
+///
+/// Every todo mutation takes the next number of its owner's change
+/// sequence while holding the owner's row, so one user's changes commit in
+/// sequence order. A todo carries the number of its latest change and the
+/// time each field last changed; a deletion leaves a tombstone with its
+/// number. Tombstones are never pruned.
+#[async_trait]
+pub trait SyncStore: Send + Sync {
+    /// Up to `limit` of the user's changes numbered above `since`, in order.
+    async fn todo_changes_since(&self, user_id: Uuid, since: i64, limit: usize) -> Result<Vec<TodoChange>, AppError>;
+    /// Merges one client mutation with [`crate::sync::merge`] and applies the
+    /// result.
+    async fn apply_sync_mutation(&self, user_id: Uuid, mutation: &SyncMutation) -> Result<Merged, AppError>;
+}
+
+/// Webhook subscriptions and their durable delivery queue.
+///
+/// Todo mutations queue a delivery for each of the owner's active webhooks
+/// subscribed to the event, in the transaction that records the event.
+#[async_trait]
+pub trait WebhookStore: Send + Sync {
+    async fn create_webhook(
+        &self,
+        user_id: Uuid,
+        url: &str,
+        events: &[TodoEventKind],
+        secret: &str,
+        active: bool,
+    ) -> Result<Webhook, AppError>;
+    async fn get_webhooks_by_user(&self, user_id: Uuid) -> Result<Vec<Webhook>, AppError>;
+    async fn get_webhook_by_id(&self, webhook_id: Uuid, user_id: Uuid) -> Result<Option<Webhook>, AppError>;
+    async fn update_webhook(
+        &self,
+        webhook_id: Uuid,
+        user_id: Uuid,
+        update: UpdateWebhook,
+    ) -> Result<Option<Webhook>, AppError>;
+    /// Deletes the webhook along with its deliveries.
+    async fn delete_webhook(&self, webhook_id: Uuid, user_id: Uuid) -> Result<bool, AppError>;
+    /// Queues a delivery, due now.
+    async fn enqueue_webhook_delivery(
+        &self,
+        webhook_id: Uuid,
+        event: &str,
+        payload: &Value,
+    ) -> Result<WebhookDelivery, AppError>;
+    /// Up to `limit` of the webhook's deliveries, newest first.
+    async fn get_webhook_deliveries(&self, webhook_id: Uuid, limit: i64) -> Result<Vec<WebhookDelivery>, AppError>;
+    async fn get_webhook_delivery(
+        &self,
+        delivery_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Option<WebhookDelivery>, AppError>;
+    /// The delivery's attempts, oldest first.
+    async fn get_webhook_attempts(&self, delivery_id: Uuid) -> Result<Vec<WebhookAttempt>, AppError>;
+    /// Takes up to `limit` pending deliveries due by `now`, earliest first,
+    /// and moves their due time to `lease_until` so no one else takes them
+    /// meanwhile.
+    async fn claim_webhook_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<PendingDelivery>, AppError>;
+    /// Logs an attempt and moves the delivery on to `outcome`.
+    async fn record_webhook_attempt(
+        &self,
+        delivery_id: Uuid,
+        attempt: &WebhookAttempt,
+        outcome: DeliveryOutcome,
+    ) -> Result<(), AppError>;
+    /// Deletes delivered and dead deliveries created before `before`.
+    async fn prune_webhook_deliveries(&self, before: DateTime<Utc>) -> Result<u64, AppError>;
+}
+
+/// Time spent on todos, tracked with a timer or entered by hand. A user has
+/// at most one timer running. Every change to a todo's entries recomputes
+/// its `tracked_seconds` in the same transaction; that is not a change of
+/// the todo itself, so it takes no place in the change sequence.
+///
+/// Like [`TodoStore`], methods do not check who is asking.
+#[async_trait]
+pub trait TimeEntryStore: Send + Sync {
+    /// Starts the user's timer on the todo at `now`, first stopping the one
+    /// they had running, which is returned too.
+    async fn start_timer(
+        &self,
+        todo_id: Uuid,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<(TimeEntry, Option<TimeEntry>), AppError>;
+    /// Stops the user's timer at `now` if it is running on the todo.
+    async fn stop_timer(&self, todo_id: Uuid, user_id: Uuid, now: DateTime<Utc>) -> Result<Option<TimeEntry>, AppError>;
+    async fn get_running_time_entry(&self, user_id: Uuid) -> Result<Option<TimeEntry>, AppError>;
+    /// Records time already spent.
+    async fn create_time_entry(
+        &self,
+        todo_id: Uuid,
+        user_id: Uuid,
+        started_at: DateTime<Utc>,
+        ended_at: DateTime<Utc>,
+        note: Option<&str>,
+    ) -> Result<TimeEntry, AppError>;
+    /// The todo's entries by everyone, oldest first.
+    async fn get_time_entries(&self, todo_id: Uuid) -> Result<Vec<TimeEntry>, AppError>;
+    async fn get_time_entry(&self, entry_id: Uuid) -> Result<Option<TimeEntry>, AppError>;
+    /// Replaces the entry's times and note; an `ended_at` stops a running
+    /// timer.
+    async fn update_time_entry(
+        &self,
+        entry_id: Uuid,
+        started_at: DateTime<Utc>,
+        ended_at: Option<DateTime<Utc>>,
+        note: Option<&str>,
+    ) -> Result<Option<TimeEntry>, AppError>;
+    async fn delete_time_entry(&self, entry_id: Uuid) -> Result<bool, AppError>;
+    /// The user's stopped entries started in `range`, summed by day of
+    /// `range.tz` and by list, in any order.
+    async fn time_report(&self, user_id: Uuid, range: &StatsRange) -> Result<Vec<TimeReportRow>, AppError>;
+}
+
+/// Aggregates behind `/api/stats`, over every todo a user can see.
+#[async_trait]
+pub trait StatsStore: Send + Sync {
+    /// Counts the user's todos created, completed and falling due in
+    /// `range`, with completions per day of `range.tz` and by list and tag.
+    async fn todo_stats(&self, user_id: Uuid, range: &StatsRange) -> Result<StatsCounts, AppError>;
+}
+
+/// Schema management and connection lifecycle. Backends without a persistent
+/// schema or connections treat these as no-ops.
+#[async_trait]
+pub trait AdminStore: Send + Sync {
+    /// Applies all pending migrations.
+    async fn migrate(&self) -> Result<(), AppError>;
+    /// Reverts applied migrations newer than `target`, newest first.
+    /// A target of `0` reverts every migration.
+    async fn migrate_down(&self, target: i64) -> Result<(), AppError>;
+    /// Compares the embedded migrations with those recorded in the database.
+    async fn migration_status(&self) -> Result<Vec<MigrationStatus>, AppError>;
+    /// Round-trips to the database through the pool.
+    async fn ping(&self) -> Result<(), AppError>;
+    /// Waits for checked-out connections to be returned, then closes them.
+    async fn close(&self);
+    /// Connection pool figures for `/metrics`; `None` for backends without a pool.
+    fn pool_stats(&self) -> Option<PoolStats>;
+}
+
+pub trait Store:
+    UserStore
+    + TodoStore
+    + ShareStore
+    + CommentStore
+    + AttachmentStore
+    + EventStore
+    + SyncStore
+    + WebhookStore
+    + StatsStore
+    + TimeEntryStore
+    + AdminStore
+{
+}
+
+impl<T> Store for T where
+    T: UserStore
+        + TodoStore
+        + ShareStore
+        + CommentStore
+        + AttachmentStore
+        + EventStore
+        + SyncStore
+        + WebhookStore
+        + StatsStore
+        + TimeEntryStore
+        + AdminStore
+{
+}
+
+/// Opens the backend selected by the scheme of `config.url`:
+/// `postgres://`, `sqlite:` (with the `sqlite` feature) or `memory:`.
+pub async fn connect(config: &DatabaseConfig) -> Result<DynStore, AppError> {
+    let database_url = config.url.as_str();
+
+    if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        return Ok(Arc::new(PgStore::new(config).await?));
+    }
+
+    if database_url.starts_with("sqlite:") {
+        #[cfg(feature = "sqlite")]
+        return Ok(Arc::new(SqliteStore::new(config).await?));
+        #[cfg(not(feature = "sqlite"))]
+        return Err(AppError::Internal(
+            "SQLite support requires building with the `sqlite` feature".to_string(),
+        ));
+    }
+
+    if database_url.starts_with("memory:") {
+        return Ok(Arc::new(MemoryStore::new()));
+    }
+
+    let scheme = database_url.split(':').next().unwrap_or_default();
+    Err(AppError::Internal(format!("Unsupported database URL scheme: {}", scheme)))
+}
+
+/// Snapshot of a connection pool.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoolStats {
+    /// Open connections, idle or in use.
+    pub size: u32,
+    pub idle: u32,
+    pub max_size: u32,
+    /// Connections checked out since startup.
+    pub acquires: u64,
+    /// Total time spent waiting for those checkouts.
+    pub acquire_wait: Duration,
+}
+
+/// Checks connections out of a pool, keeping count of how long callers waited.
+#[derive(Debug, Default)]
+pub(crate) struct AcquireTimer {
+    count: AtomicU64,
+    wait_micros: AtomicU64,
+}
+
+impl AcquireTimer {
+    pub async fn acquire<DB: Database>(&self, pool: &Pool<DB>) -> Result<PoolConnection<DB>, AppError> {
+        let started = Instant::now();
+        let conn = pool.acquire().await?;
+        let waited = u64::try_from(started.elapsed().as_micros()).unwrap_or(u64::MAX);
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.wait_micros.fetch_add(waited, Ordering::Relaxed);
+
+        Ok(conn)
+    }
+
+    pub fn stats<DB: Database>(&self, pool: &Pool<DB>) -> PoolStats {
+        PoolStats {
+            size: pool.size(),
+            idle: u32::try_from(pool.num_idle()).unwrap_or(u32::MAX),
+            max_size: pool.options().get_max_connections(),
+            acquires: self.count.load(Ordering::Relaxed),
+            acquire_wait: Duration::from_micros(self.wait_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// State of a single migration as reported by `migrate status`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied { installed_on: DateTime<Utc> },
+    Pending,
+    /// Applied, but the embedded SQL no longer matches the recorded checksum.
+    Modified,
+    /// A previous run failed part-way through and left the version dirty.
+    Failed,
+    /// Recorded in the database but not embedded in this binary.
+    Missing,
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+/// A row of the `_sqlx_migrations` table.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    pub checksum: Vec<u8>,
+    pub success: bool,
+    pub installed_on: DateTime<Utc>,
+}
+
+pub(crate) fn migration_status(migrator: &Migrator, applied: Vec<AppliedMigration>) -> Vec<MigrationStatus> {
+    let mut applied: HashMap<i64, AppliedMigration> =
+        applied.into_iter().map(|m| (m.version, m)).collect();
+
+    let mut statuses: Vec<MigrationStatus> = migrator
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+        .map(|migration| {
+            let state = match applied.remove(&migration.version) {
+                None => MigrationState::Pending,
+                Some(row) if !row.success => MigrationState::Failed,
+                Some(row) if row.checksum != *migration.checksum => MigrationState::Modified,
+                Some(row) => MigrationState::Applied { installed_on: row.installed_on },
+            };
+            MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+            }
+        })
+        .collect();
+
+    statuses.extend(applied.into_values().map(|row| MigrationStatus {
+        version: row.version,
+        description: row.description,
+        state: MigrationState::Missing,
+    }));
+    statuses.sort_by_key(|s| s.version);
+
+    statuses
+}
+
+pub(crate) fn check_migration_target(migrator: &Migrator, target: i64) -> Result<(), AppError> {
+    if target != 0 && !migrator.version_exists(target) {
+        return Err(AppError::BadRequest(format!("Unknown migration version {}", target)));
+    }
+    Ok(())
+}
+
+/// Maps unique-constraint violations on `users` to the same client error the
+/// in-memory backend returns; everything else is passed through.
+pub(crate) fn map_user_conflict(err: sqlx::Error) -> AppError {
+    if let sqlx::Error::Database(ref db_err) = err {
+        if db_err.is_unique_violation() {
+            return if db_err.message().contains("email")
+                || db_err.constraint().is_some_and(|c| c.contains("email"))
+            {
+                AppError::BadRequest("Email already exists".to_string())
+            } else {
+                AppError::BadRequest("Username already exists".to_string())
+            };
+        }
+    }
+    AppError::Sqlx(err)
+}
+
+/// A row of the `todo_events` table.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct TodoEventRow {
+    pub id: i64,
+    pub user_id: Uuid,
+    pub todo_id: Uuid,
+    pub kind: String,
+    pub todo: Option<Json<TodoResponse>>,
+    pub created_at: DateTime<Utc>,
+    pub recipients: Json<Vec<Uuid>>,
+}
+
+impl TryFrom<TodoEventRow> for TodoEvent {
+    type Error = AppError;
+
+    fn try_from(row: TodoEventRow) -> Result<Self, Self::Error> {
+        Ok(TodoEvent {
+            id: row.id,
+            kind: row.kind.parse::<TodoEventKind>().map_err(AppError::Internal)?,
+            user_id: row.user_id,
+            todo_id: row.todo_id,
+            todo: row.todo.map(|Json(todo)| todo),
+            created_at: row.created_at,
+            recipients: row.recipients.0,
+        })
+    }
+}
+
+/// A todo with its sync metadata.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct SyncTodoRow {
+    #[sqlx(flatten)]
+    pub todo: Todo,
+    pub field_versions: Json<FieldVersions>,
+}
+
+/// A row of the change feed: a todo, or a tombstone with only `id`, `seq`
+/// and `deleted_at` set.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct TodoChangeRow {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub seq: i64,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub completed: Option<bool>,
+    pub scheduled_for: Option<DateTime<Utc>>,
+    pub assignee_id: Option<Uuid>,
+    pub priority: Option<Priority>,
+    pub tags: Option<Json<Vec<String>>>,
+    pub recurrence: Option<String>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub snooze_count: Option<i32>,
+    pub original_scheduled_for: Option<DateTime<Utc>>,
+    pub hidden_until: Option<DateTime<Utc>>,
+    pub tracked_seconds: Option<i64>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<TodoChangeRow> for TodoChange {
+    type Error = AppError;
+
+    fn try_from(row: TodoChangeRow) -> Result<Self, Self::Error> {
+        if let Some(deleted_at) = row.deleted_at {
+            return Ok(TodoChange::Deleted { seq: row.seq, id: row.id, deleted_at });
+        }
+
+        let missing = || AppError::Internal(format!("Incomplete change feed row for todo {}", row.id));
+        Ok(TodoChange::Upserted {
+            seq: row.seq,
+            todo: Box::new(Todo {
+                id: row.id,
+                user_id: row.user_id,
+                title: row.title.clone().ok_or_else(missing)?,
+                description: row.description.clone(),
+                completed: row.completed.ok_or_else(missing)?,
+                scheduled_for: row.scheduled_for,
+                assignee_id: row.assignee_id,
+                priority: row.priority,
+                tags: row.tags.clone().ok_or_else(missing)?.0,
+                recurrence: row.recurrence.clone(),
+                completed_at: row.completed_at,
+                snooze_count: row.snooze_count.ok_or_else(missing)?,
+                original_scheduled_for: row.original_scheduled_for,
+                hidden_until: row.hidden_until,
+                tracked_seconds: row.tracked_seconds.ok_or_else(missing)?,
+                created_at: row.created_at.ok_or_else(missing)?,
+                updated_at: row.updated_at.ok_or_else(missing)?,
+            }),
+        })
+    }
+}
+
+/// A todo with the role of the user it was looked up for.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct TodoAccessRow {
+    #[sqlx(flatten)]
+    pub todo: Todo,
+    pub role: String,
+}
+
+impl TryFrom<TodoAccessRow> for (Todo, Role) {
+    type Error = AppError;
+
+    fn try_from(row: TodoAccessRow) -> Result<Self, Self::Error> {
+        Ok((row.todo, row.role.parse().map_err(AppError::Internal)?))
+    }
+}
+
+/// The overall figures of [`StatsStore::todo_stats`].
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct StatsTotalsRow {
+    pub created: i64,
+    pub completed: i64,
+    pub completed_late: i64,
+    pub overdue: i64,
+    pub average_completion_seconds: Option<f64>,
+}
+
+/// A row of the `todo_shares` table joined with both users' names.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct ShareRow {
+    pub id: Uuid,
+    pub owner_id: Uuid,
+    pub owner_username: String,
+    pub grantee_id: Uuid,
+    pub grantee_username: String,
+    pub todo_id: Option<Uuid>,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<ShareRow> for Share {
+    type Error = AppError;
+
+    fn try_from(row: ShareRow) -> Result<Self, Self::Error> {
+        Ok(Share {
+            id: row.id,
+            owner_id: row.owner_id,
+            owner_username: row.owner_username,
+            grantee_id: row.grantee_id,
+            grantee_username: row.grantee_username,
+            todo_id: row.todo_id,
+            role: row.role.parse().map_err(AppError::Internal)?,
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// A row of the `webhooks` table.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct WebhookRow {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    pub events: Json<Vec<TodoEventKind>>,
+    pub secret: String,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<WebhookRow> for Webhook {
+    fn from(row: WebhookRow) -> Self {
+        Webhook {
+            id: row.id,
+            user_id: row.user_id,
+            url: row.url,
+            events: row.events.0,
+            secret: row.secret,
+            active: row.active,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+/// A row of the `webhook_deliveries` table.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct WebhookDeliveryRow {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub payload: Json<Value>,
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<WebhookDeliveryRow> for WebhookDelivery {
+    type Error = AppError;
+
+    fn try_from(row: WebhookDeliveryRow) -> Result<Self, Self::Error> {
+        Ok(WebhookDelivery {
+            id: row.id,
+            webhook_id: row.webhook_id,
+            event: row.event,
+            payload: row.payload.0,
+            status: row.status.parse().map_err(AppError::Internal)?,
+            attempts: row.attempts,
+            next_attempt_at: row.next_attempt_at,
+            last_status_code: row.last_status_code,
+            last_error: row.last_error,
+            created_at: row.created_at,
+            delivered_at: row.delivered_at,
+        })
+    }
+}
+
+/// A claimed delivery joined with its webhook.
+#[derive(Debug, sqlx::FromRow)]
+pub(crate) struct PendingDeliveryRow {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub payload: Json<Value>,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+impl From<PendingDeliveryRow> for PendingDelivery {
+    fn from(row: PendingDeliveryRow) -> Self {
+        PendingDelivery {
+            id: row.id,
+            webhook_id: row.webhook_id,
+            event: row.event,
+            payload: row.payload.0,
+            attempts: row.attempts,
+            url: row.url,
+            secret: row.secret,
+        }
+    }
+}
+
+/// Status, due time and delivery time of a delivery after `outcome`.
+pub(crate) fn delivery_state(
+    outcome: DeliveryOutcome,
+    now: DateTime<Utc>,
+) -> (&'static str, Option<DateTime<Utc>>, Option<DateTime<Utc>>) {
+    match outcome {
+        DeliveryOutcome::Delivered => (WebhookDeliveryStatus::Delivered.as_str(), None, Some(now)),
+        DeliveryOutcome::Retry { at } => (WebhookDeliveryStatus::Pending.as_str(), Some(at), None),
+        DeliveryOutcome::Dead => (WebhookDeliveryStatus::Dead.as_str(), None, None),
+    }
+}
@@ -0,0 +1,1938 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    pool::PoolConnection,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    types::Json,
+    Connection, Sqlite, SqliteConnection, SqlitePool,
+};
+use serde_json::Value;
+use std::{str::FromStr, sync::Arc, time::Duration};
+use tokio::sync::broadcast;
+use tracing::{info, instrument};
+use uuid::Uuid;
+
+use crate::{
+    config::DatabaseConfig,
+    error::AppError,
+    events::{EventBus, Notification},
+    models::{
+        Attachment, Comment, CommentRevision, CreateTodo, DayCount, ListStats, Mention, NewAttachment, Role, Share, ShareRole, SyncMutation, TagStats, TimeEntry, TimeReportRow, Todo, TodoEvent, TodoEventKind, TodoResponse, UpdateTodo,
+        UpdateWebhook, User, Webhook, WebhookAttempt, WebhookDelivery,
+    },
+    store::{
+        self, AcquireTimer, AdminStore, AppliedMigration, AttachmentStore, CommentStore, EventStore, MigrationStatus, PoolStats, ShareRow,
+        ShareStore, StatsStore, StatsTotalsRow, SyncStore, SyncTodoRow, TimeEntryStore, TodoAccessRow, TodoChangeRow, TodoEventRow, TodoStore, UserStore,
+        WebhookDeliveryRow, WebhookRow, WebhookStore,
+    },
+    stats::{StatsCounts, StatsRange},
+    sync::{self, Existing, FieldVersions, Merged, TodoChange, Write},
+    time_tracking,
+    webhooks::{DeliveryOutcome, PendingDelivery},
+};
+
+/// Schema migrations embedded from `migrations/sqlite/` at compile time.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+/// The todo columns in `Todo`'s field order.
+const TODO_COLUMNS: &str = "id, user_id, title, description, completed, scheduled_for, assignee_id, priority, tags, \
+    recurrence, completed_at, snooze_count, original_scheduled_for, hidden_until, tracked_seconds, created_at, updated_at";
+
+/// `TODO_COLUMNS` qualified with `todos.`, for queries joining another table.
+const QUALIFIED_TODO_COLUMNS: &str = "todos.id, todos.user_id, todos.title, todos.description, todos.completed, \
+    todos.scheduled_for, todos.assignee_id, todos.priority, todos.tags, todos.recurrence, todos.completed_at, \
+    todos.snooze_count, todos.original_scheduled_for, todos.hidden_until, todos.tracked_seconds, todos.created_at, \
+    todos.updated_at";
+
+/// Single-file backend for self-hosting without a Postgres server. Todo
+/// events reach streams on this process only, so run a single instance.
+#[derive(Clone)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+    acquire: Arc<AcquireTimer>,
+    events: EventBus,
+}
+
+impl SqliteStore {
+    pub async fn new(config: &DatabaseConfig) -> Result<Self, AppError> {
+        let options = SqliteConnectOptions::from_str(&config.url)?
+            .create_if_missing(true)
+            .foreign_keys(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(Duration::from_secs(5));
+
+        // Every connection to `:memory:` opens a separate database, so an
+        // in-memory URL must be served by exactly one connection.
+        let max_connections = if config.url.contains(":memory:") { 1 } else { config.max_connections };
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(max_connections)
+            .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+            .connect_with(options)
+            .await?;
+
+        Ok(SqliteStore { pool, acquire: Arc::default(), events: EventBus::new() })
+    }
+
+    async fn conn(&self) -> Result<PoolConnection<Sqlite>, AppError> {
+        self.acquire.acquire(&self.pool).await
+    }
+
+    /// Appends an event; publish it once the surrounding transaction commits.
+    /// Deletions are recorded before the todo goes, while its own shares still
+    /// say who to tell.
+    async fn record_event(
+        conn: &mut SqliteConnection,
+        kind: TodoEventKind,
+        user_id: Uuid,
+        todo_id: Uuid,
+        todo: Option<&Todo>,
+    ) -> Result<TodoEvent, AppError> {
+        let todo = todo.cloned().map(TodoResponse::from);
+        let created_at = Utc::now();
+        let recipients: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT DISTINCT grantee_id FROM todo_shares WHERE owner_id = ?1 AND (todo_id = ?2 OR todo_id IS NULL)"
+        )
+        .bind(user_id)
+        .bind(todo_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO todo_events (user_id, todo_id, kind, todo, created_at, recipients)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            RETURNING id
+            "#
+        )
+        .bind(user_id)
+        .bind(todo_id)
+        .bind(kind.as_str())
+        .bind(todo.as_ref().map(Json))
+        .bind(created_at)
+        .bind(Json(&recipients))
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let event = TodoEvent { id, kind, user_id, todo_id, todo, created_at, recipients };
+        Self::queue_webhooks(conn, &event).await?;
+        Ok(event)
+    }
+
+    /// Queues a delivery of `event` for each of its owner's active webhooks
+    /// subscribed to it.
+    async fn queue_webhooks(conn: &mut SqliteConnection, event: &TodoEvent) -> Result<(), AppError> {
+        let webhook_ids: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM webhooks
+            WHERE user_id = ?1 AND active AND EXISTS (SELECT 1 FROM json_each(webhooks.events) WHERE value = ?2)
+            "#
+        )
+        .bind(event.user_id)
+        .bind(event.kind.as_str())
+        .fetch_all(&mut *conn)
+        .await?;
+        if webhook_ids.is_empty() {
+            return Ok(());
+        }
+
+        let payload = serde_json::to_value(event).map_err(|e| AppError::Internal(e.to_string()))?;
+        for webhook_id in webhook_ids {
+            Self::insert_delivery(conn, webhook_id, event.kind.as_str(), &payload).await?;
+        }
+        Ok(())
+    }
+
+    async fn insert_delivery(
+        conn: &mut SqliteConnection,
+        webhook_id: Uuid,
+        event: &str,
+        payload: &Value,
+    ) -> Result<WebhookDelivery, AppError> {
+        let now = Utc::now();
+        let row = sqlx::query_as::<_, WebhookDeliveryRow>(
+            r#"
+            INSERT INTO webhook_deliveries (id, webhook_id, event, payload, next_attempt_at, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+            RETURNING id, webhook_id, event, payload, status, attempts, next_attempt_at, last_status_code,
+                      last_error, created_at, delivered_at
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(webhook_id)
+        .bind(event)
+        .bind(Json(payload))
+        .bind(now)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        row.try_into()
+    }
+
+    /// Takes the next number of the user's change sequence. Writing first
+    /// takes the database's write lock, so numbers commit in order.
+    async fn next_seq(conn: &mut SqliteConnection, user_id: Uuid) -> Result<i64, AppError> {
+        sqlx::query_scalar("UPDATE users SET change_seq = change_seq + 1 WHERE id = ?1 RETURNING change_seq")
+            .bind(user_id)
+            .fetch_optional(&mut *conn)
+            .await?
+            .ok_or(AppError::NotFound("User not found".to_string()))
+    }
+
+    /// Who owns the todo; changes are numbered in the owner's sequence
+    /// whoever makes them.
+    async fn owner_of(conn: &mut SqliteConnection, todo_id: Uuid) -> Result<Option<Uuid>, AppError> {
+        let owner_id = sqlx::query_scalar("SELECT user_id FROM todos WHERE id = ?1")
+            .bind(todo_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        Ok(owner_id)
+    }
+
+    /// Sets the todo's `tracked_seconds` to the total of its stopped entries.
+    async fn update_tracked_seconds(conn: &mut SqliteConnection, todo_id: Uuid) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            UPDATE todos
+            SET tracked_seconds = (SELECT COALESCE(SUM(duration_seconds), 0) FROM time_entries WHERE todo_id = ?1)
+            WHERE id = ?1
+            "#
+        )
+        .bind(todo_id)
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    /// Stops the user's running timer at `now`, if there is one and it is
+    /// on `todo_id` when given.
+    async fn stop_running(
+        conn: &mut SqliteConnection,
+        user_id: Uuid,
+        todo_id: Option<Uuid>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<TimeEntry>, AppError> {
+        let running = sqlx::query_as::<_, TimeEntry>(&format!(
+            "SELECT {} FROM time_entries WHERE user_id = ?1 AND ended_at IS NULL",
+            TIME_ENTRY_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+        let Some(running) = running.filter(|entry| todo_id.is_none_or(|id| id == entry.todo_id)) else {
+            return Ok(None);
+        };
+
+        let ended_at = now.max(running.started_at);
+        let stopped = sqlx::query_as::<_, TimeEntry>(&format!(
+            "UPDATE time_entries SET ended_at = ?2, duration_seconds = ?3, updated_at = ?4 WHERE id = ?1 RETURNING {}",
+            TIME_ENTRY_COLUMNS
+        ))
+        .bind(running.id)
+        .bind(ended_at)
+        .bind(time_tracking::duration_seconds(running.started_at, ended_at))
+        .bind(Utc::now())
+        .fetch_one(&mut *conn)
+        .await?;
+        Self::update_tracked_seconds(conn, stopped.todo_id).await?;
+
+        Ok(Some(stopped))
+    }
+
+    /// Records a version of a comment's body and mentions new to it.
+    async fn add_revision(
+        conn: &mut SqliteConnection,
+        comment_id: Uuid,
+        body: &str,
+        mentioned: &[Uuid],
+        now: DateTime<Utc>,
+    ) -> Result<(), AppError> {
+        sqlx::query("INSERT INTO todo_comment_revisions (comment_id, body, created_at) VALUES (?1, ?2, ?3)")
+            .bind(comment_id)
+            .bind(body)
+            .bind(now)
+            .execute(&mut *conn)
+            .await?;
+        for user_id in mentioned {
+            sqlx::query("INSERT OR IGNORE INTO comment_mentions (comment_id, user_id, created_at) VALUES (?1, ?2, ?3)")
+                .bind(comment_id)
+                .bind(user_id)
+                .bind(now)
+                .execute(&mut *conn)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Deletes a todo, leaving a tombstone numbered `seq`.
+    async fn delete_with_tombstone(
+        conn: &mut SqliteConnection,
+        todo_id: Uuid,
+        user_id: Uuid,
+        seq: i64,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM todos WHERE id = ?1 AND user_id = ?2")
+            .bind(todo_id)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query("INSERT INTO todo_tombstones (todo_id, user_id, seq, deleted_at) VALUES (?1, ?2, ?3, ?4)")
+            .bind(todo_id)
+            .bind(user_id)
+            .bind(seq)
+            .bind(Utc::now())
+            .execute(&mut *conn)
+            .await?;
+        Ok(true)
+    }
+
+    /// What is stored under `todo_id`.
+    async fn existing(conn: &mut SqliteConnection, user_id: Uuid, todo_id: Uuid) -> Result<Existing, AppError> {
+        let row = sqlx::query_as::<_, SyncTodoRow>(&format!(
+            r#"
+            SELECT {},
+                   field_versions
+            FROM todos
+            WHERE id = ?1
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(todo_id)
+        .fetch_optional(&mut *conn)
+        .await?;
+        if let Some(row) = row {
+            return Ok(if row.todo.user_id == user_id {
+                Existing::Todo { todo: Box::new(row.todo), versions: row.field_versions.0 }
+            } else {
+                Existing::Foreign
+            });
+        }
+
+        let tombstone: Option<(Uuid, DateTime<Utc>)> =
+            sqlx::query_as("SELECT user_id, deleted_at FROM todo_tombstones WHERE todo_id = ?1")
+                .bind(todo_id)
+                .fetch_optional(&mut *conn)
+                .await?;
+        Ok(match tombstone {
+            Some((owner, deleted_at)) if owner == user_id => Existing::Deleted { deleted_at },
+            Some(_) => Existing::Foreign,
+            None => Existing::None,
+        })
+    }
+}
+
+#[async_trait]
+impl AdminStore for SqliteStore {
+    async fn migrate(&self) -> Result<(), AppError> {
+        MIGRATOR.run(&self.pool).await?;
+        info!("Database migrations are up to date");
+        Ok(())
+    }
+
+    async fn migrate_down(&self, target: i64) -> Result<(), AppError> {
+        store::check_migration_target(&MIGRATOR, target)?;
+        MIGRATOR.undo(&self.pool, target).await?;
+        info!("Database migrated down to version {}", target);
+        Ok(())
+    }
+
+    async fn migration_status(&self) -> Result<Vec<MigrationStatus>, AppError> {
+        let mut conn = self.pool.acquire().await?;
+        conn.ensure_migrations_table().await?;
+
+        let applied = sqlx::query_as::<_, AppliedMigration>(
+            "SELECT version, description, checksum, success, installed_on FROM _sqlx_migrations ORDER BY version"
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(store::migration_status(&MIGRATOR, applied))
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "ping"))]
+    async fn ping(&self) -> Result<(), AppError> {
+        sqlx::query("SELECT 1").execute(&mut *self.conn().await?).await?;
+        Ok(())
+    }
+
+    async fn close(&self) {
+        self.pool.close().await;
+        info!("Database pool closed");
+    }
+
+    fn pool_stats(&self) -> Option<PoolStats> {
+        Some(self.acquire.stats(&self.pool))
+    }
+}
+
+#[async_trait]
+impl UserStore for SqliteStore {
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "create_user"))]
+    async fn create_user(&self, username: &str, email: &str, password_hash: &str) -> Result<User, AppError> {
+        let now = Utc::now();
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            INSERT INTO users (id, username, email, password_hash, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+            RETURNING id, username, email, password_hash, timezone, locale, created_at, updated_at
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(username)
+        .bind(email)
+        .bind(password_hash)
+        .bind(now)
+        .fetch_one(&mut *self.conn().await?)
+        .await
+        .map_err(store::map_user_conflict)?;
+
+        Ok(user)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_user_by_username"))]
+    async fn get_user_by_username(&self, username: &str) -> Result<Option<User>, AppError> {
+        let user = sqlx::query_as::<_, User>(
+            "SELECT id, username, email, password_hash, timezone, locale, created_at, updated_at FROM users WHERE username = ?1"
+        )
+        .bind(username)
+        .fetch_optional(&mut *self.conn().await?)
+        .await?;
+
+        Ok(user)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_user_by_id"))]
+    async fn get_user_by_id(&self, user_id: Uuid) -> Result<Option<User>, AppError> {
+        let user = sqlx::query_as::<_, User>(
+            "SELECT id, username, email, password_hash, timezone, locale, created_at, updated_at FROM users WHERE id = ?1"
+        )
+        .bind(user_id)
+        .fetch_optional(&mut *self.conn().await?)
+        .await?;
+
+        Ok(user)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "update_user_preferences"))]
+    async fn update_user_preferences(
+        &self,
+        user_id: Uuid,
+        timezone: Option<&str>,
+        locale: Option<&str>,
+    ) -> Result<Option<User>, AppError> {
+        let user = sqlx::query_as::<_, User>(
+            r#"
+            UPDATE users
+            SET timezone = COALESCE(?2, timezone),
+                locale = COALESCE(?3, locale),
+                updated_at = ?4
+            WHERE id = ?1
+            RETURNING id, username, email, password_hash, timezone, locale, created_at, updated_at
+            "#
+        )
+        .bind(user_id)
+        .bind(timezone)
+        .bind(locale)
+        .bind(Utc::now())
+        .fetch_optional(&mut *self.conn().await?)
+        .await?;
+
+        Ok(user)
+    }
+}
+
+#[async_trait]
+impl TodoStore for SqliteStore {
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "create_todo"))]
+    async fn create_todo(&self, user_id: Uuid, todo: CreateTodo) -> Result<Todo, AppError> {
+        let now = Utc::now();
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let seq = Self::next_seq(&mut tx, user_id).await?;
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            INSERT INTO todos
+                (id, user_id, title, description, completed, scheduled_for, priority, tags, recurrence, created_at,
+                 updated_at, seq, field_versions, completed_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?10, ?11, ?12, ?7, ?7, ?8, ?9, CASE WHEN ?5 THEN ?7 END)
+            RETURNING {}
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(&todo.title)
+        .bind(&todo.description)
+        .bind(todo.completed.unwrap_or(false))
+        .bind(todo.scheduled_for)
+        .bind(now)
+        .bind(seq)
+        .bind(Json(FieldVersions::all(now)))
+        .bind(todo.priority)
+        .bind(Json(todo.tags.unwrap_or_default()))
+        .bind(&todo.recurrence)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let event = Self::record_event(&mut tx, TodoEventKind::Created, user_id, todo.id, Some(&todo)).await?;
+        tx.commit().await?;
+        self.events.publish(event);
+
+        Ok(todo)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_todos_by_user"))]
+    async fn get_todos_by_user(&self, user_id: Uuid) -> Result<Vec<Todo>, AppError> {
+        let todos = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            SELECT {}
+            FROM todos
+            WHERE user_id = ?1
+            ORDER BY
+                CASE WHEN scheduled_for IS NULL THEN 1 ELSE 0 END,
+                scheduled_for ASC,
+                created_at DESC
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(todos)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_scheduled_todos"))]
+    async fn get_scheduled_todos(
+        &self,
+        user_id: Uuid,
+        from: Option<DateTime<Utc>>,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<Todo>, AppError> {
+        let todos = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            SELECT {}
+            FROM todos
+            WHERE user_id = ?1
+              AND NOT completed
+              AND (?2 IS NULL OR scheduled_for >= ?2)
+              AND scheduled_for < ?3
+            ORDER BY scheduled_for ASC, created_at DESC
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(from)
+        .bind(until)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(todos)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_todo_access"))]
+    async fn get_todo_access(&self, todo_id: Uuid, user_id: Uuid) -> Result<Option<(Todo, Role)>, AppError> {
+        let row = sqlx::query_as::<_, TodoAccessRow>(&format!(
+            r#"
+            SELECT {}, role
+            FROM (
+                SELECT todos.*,
+                    CASE WHEN todos.user_id = ?2 THEN 'owner' ELSE (
+                        SELECT CASE MAX(CASE role WHEN 'editor' THEN 2 ELSE 1 END) WHEN 2 THEN 'editor' WHEN 1 THEN 'viewer' END
+                        FROM todo_shares
+                        WHERE grantee_id = ?2
+                            AND owner_id = todos.user_id
+                            AND (todo_shares.todo_id = todos.id OR todo_shares.todo_id IS NULL)
+                    ) END AS role
+                FROM todos
+                WHERE todos.id = ?1
+            )
+            WHERE role IS NOT NULL
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(todo_id)
+        .bind(user_id)
+        .fetch_optional(&mut *self.conn().await?)
+        .await?;
+
+        row.map(<(Todo, Role)>::try_from).transpose()
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "update_todo"))]
+    async fn update_todo(&self, todo_id: Uuid, update: UpdateTodo) -> Result<Option<Todo>, AppError> {
+        let now = Utc::now();
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let Some(owner_id) = Self::owner_of(&mut tx, todo_id).await? else {
+            return Ok(None);
+        };
+        let seq = Self::next_seq(&mut tx, owner_id).await?;
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            UPDATE todos
+            SET title = COALESCE(?3, title),
+                description = COALESCE(?4, description),
+                completed = COALESCE(?5, completed),
+                completed_at = CASE
+                    WHEN ?5 IS NULL OR ?5 = completed THEN completed_at
+                    WHEN ?5 THEN ?7
+                END,
+                scheduled_for = COALESCE(?6, scheduled_for),
+                hidden_until = CASE WHEN ?6 IS NOT NULL AND ?6 IS NOT scheduled_for THEN NULL ELSE hidden_until END,
+                priority = CASE WHEN ?10 THEN ?11 ELSE priority END,
+                tags = COALESCE(?12, tags),
+                recurrence = CASE WHEN ?13 THEN ?14 ELSE recurrence END,
+                updated_at = ?7,
+                seq = ?8,
+                field_versions = json_patch(field_versions, ?9)
+            WHERE id = ?1 AND user_id = ?2
+            RETURNING {}
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(todo_id)
+        .bind(owner_id)
+        .bind(&update.title)
+        .bind(&update.description)
+        .bind(update.completed)
+        .bind(update.scheduled_for)
+        .bind(now)
+        .bind(seq)
+        .bind(Json(FieldVersions::touched(&update, now)))
+        .bind(update.priority.is_some())
+        .bind(update.priority.flatten())
+        .bind(update.tags.as_ref().map(Json))
+        .bind(update.recurrence.is_some())
+        .bind(update.recurrence.as_ref().and_then(Option::as_deref))
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // Dropping the transaction rolls back the sequence number as well
+        let Some(todo) = todo else {
+            return Ok(None);
+        };
+        let event = Self::record_event(&mut tx, TodoEventKind::Updated, owner_id, todo_id, Some(&todo)).await?;
+        tx.commit().await?;
+        self.events.publish(event);
+
+        Ok(Some(todo))
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "assign_todo"))]
+    async fn assign_todo(&self, todo_id: Uuid, assignee_id: Option<Uuid>) -> Result<Option<Todo>, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let Some(owner_id) = Self::owner_of(&mut tx, todo_id).await? else {
+            return Ok(None);
+        };
+        let seq = Self::next_seq(&mut tx, owner_id).await?;
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            UPDATE todos
+            SET assignee_id = ?3, updated_at = ?4, seq = ?5
+            WHERE id = ?1 AND user_id = ?2
+            RETURNING {}
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(todo_id)
+        .bind(owner_id)
+        .bind(assignee_id)
+        .bind(Utc::now())
+        .bind(seq)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(todo) = todo else {
+            return Ok(None);
+        };
+        let event = Self::record_event(&mut tx, TodoEventKind::Updated, owner_id, todo_id, Some(&todo)).await?;
+        tx.commit().await?;
+        self.events.publish(event);
+
+        Ok(Some(todo))
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "snooze_todo"))]
+    async fn snooze_todo(&self, todo_id: Uuid, until: DateTime<Utc>) -> Result<Option<Todo>, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let Some(owner_id) = Self::owner_of(&mut tx, todo_id).await? else {
+            return Ok(None);
+        };
+        let seq = Self::next_seq(&mut tx, owner_id).await?;
+        let now = Utc::now();
+        let versions = FieldVersions { scheduled_for: Some(now), ..Default::default() };
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            UPDATE todos
+            SET original_scheduled_for = CASE WHEN snooze_count = 0 THEN scheduled_for ELSE original_scheduled_for END,
+                scheduled_for = ?3,
+                hidden_until = ?3,
+                snooze_count = snooze_count + 1,
+                updated_at = ?4,
+                seq = ?5,
+                field_versions = json_patch(field_versions, ?6)
+            WHERE id = ?1 AND user_id = ?2
+            RETURNING {}
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(todo_id)
+        .bind(owner_id)
+        .bind(until)
+        .bind(now)
+        .bind(seq)
+        .bind(Json(versions))
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(todo) = todo else {
+            return Ok(None);
+        };
+        let event = Self::record_event(&mut tx, TodoEventKind::Updated, owner_id, todo_id, Some(&todo)).await?;
+        tx.commit().await?;
+        self.events.publish(event);
+
+        Ok(Some(todo))
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "unsnooze_todo"))]
+    async fn unsnooze_todo(&self, todo_id: Uuid) -> Result<Option<Todo>, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let Some(owner_id) = Self::owner_of(&mut tx, todo_id).await? else {
+            return Ok(None);
+        };
+        let seq = Self::next_seq(&mut tx, owner_id).await?;
+        let todo = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            UPDATE todos
+            SET hidden_until = NULL, updated_at = ?3, seq = ?4
+            WHERE id = ?1 AND user_id = ?2
+            RETURNING {}
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(todo_id)
+        .bind(owner_id)
+        .bind(Utc::now())
+        .bind(seq)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let Some(todo) = todo else {
+            return Ok(None);
+        };
+        let event = Self::record_event(&mut tx, TodoEventKind::Updated, owner_id, todo_id, Some(&todo)).await?;
+        tx.commit().await?;
+        self.events.publish(event);
+
+        Ok(Some(todo))
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "delete_todo"))]
+    async fn delete_todo(&self, todo_id: Uuid) -> Result<bool, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let Some(owner_id) = Self::owner_of(&mut tx, todo_id).await? else {
+            return Ok(false);
+        };
+        let seq = Self::next_seq(&mut tx, owner_id).await?;
+        let event = Self::record_event(&mut tx, TodoEventKind::Deleted, owner_id, todo_id, None).await?;
+        if !Self::delete_with_tombstone(&mut tx, todo_id, owner_id, seq).await? {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        self.events.publish(event);
+
+        Ok(true)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_shared_todos"))]
+    async fn get_shared_todos(&self, user_id: Uuid) -> Result<Vec<(Todo, Role)>, AppError> {
+        let rows = sqlx::query_as::<_, TodoAccessRow>(&format!(
+            r#"
+            SELECT {},
+                   CASE MAX(CASE todo_shares.role WHEN 'editor' THEN 2 ELSE 1 END) WHEN 2 THEN 'editor' ELSE 'viewer' END AS role
+            FROM todos
+            JOIN todo_shares
+                ON todo_shares.owner_id = todos.user_id
+                AND (todo_shares.todo_id = todos.id OR todo_shares.todo_id IS NULL)
+            WHERE todo_shares.grantee_id = ?1
+            GROUP BY todos.id
+            ORDER BY
+                CASE WHEN todos.scheduled_for IS NULL THEN 1 ELSE 0 END,
+                todos.scheduled_for ASC,
+                todos.created_at DESC
+            "#,
+            QUALIFIED_TODO_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        rows.into_iter().map(<(Todo, Role)>::try_from).collect()
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_assigned_todos"))]
+    async fn get_assigned_todos(&self, user_id: Uuid) -> Result<Vec<Todo>, AppError> {
+        let todos = sqlx::query_as::<_, Todo>(&format!(
+            r#"
+            SELECT {}
+            FROM todos
+            WHERE assignee_id = ?1
+                AND (user_id = ?1 OR EXISTS (
+                    SELECT 1 FROM todo_shares
+                    WHERE grantee_id = ?1
+                        AND owner_id = todos.user_id
+                        AND (todo_shares.todo_id = todos.id OR todo_shares.todo_id IS NULL)
+                ))
+            ORDER BY
+                CASE WHEN scheduled_for IS NULL THEN 1 ELSE 0 END,
+                scheduled_for ASC,
+                created_at DESC
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(todos)
+    }
+}
+
+/// Shares joined with the names of both users.
+const SELECT_SHARES: &str = r#"
+    SELECT s.id, s.owner_id, o.username AS owner_username, s.grantee_id, g.username AS grantee_username,
+           s.todo_id, s.role, s.created_at
+    FROM todo_shares s
+    JOIN users o ON o.id = s.owner_id
+    JOIN users g ON g.id = s.grantee_id
+"#;
+
+#[async_trait]
+impl ShareStore for SqliteStore {
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "upsert_share"))]
+    async fn upsert_share(
+        &self,
+        owner_id: Uuid,
+        grantee_id: Uuid,
+        todo_id: Option<Uuid>,
+        role: ShareRole,
+    ) -> Result<Share, AppError> {
+        // Each kind of share has its own partial unique index
+        let conflict = match todo_id {
+            None => "(owner_id, grantee_id) WHERE todo_id IS NULL",
+            Some(_) => "(todo_id, grantee_id) WHERE todo_id IS NOT NULL",
+        };
+        let mut conn = self.conn().await?;
+        let share_id: Uuid = sqlx::query_scalar(&format!(
+            r#"
+            INSERT INTO todo_shares (id, owner_id, grantee_id, todo_id, role, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT {} DO UPDATE SET role = excluded.role
+            RETURNING id
+            "#,
+            conflict
+        ))
+        .bind(Uuid::new_v4())
+        .bind(owner_id)
+        .bind(grantee_id)
+        .bind(todo_id)
+        .bind(role.as_str())
+        .bind(Utc::now())
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let row = sqlx::query_as::<_, ShareRow>(&format!("{} WHERE s.id = ?1", SELECT_SHARES))
+            .bind(share_id)
+            .fetch_one(&mut *conn)
+            .await?;
+        row.try_into()
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_shares_for_user"))]
+    async fn get_shares_for_user(&self, user_id: Uuid) -> Result<Vec<Share>, AppError> {
+        let rows = sqlx::query_as::<_, ShareRow>(&format!(
+            "{} WHERE s.owner_id = ?1 OR s.grantee_id = ?1 ORDER BY s.created_at, s.id",
+            SELECT_SHARES
+        ))
+        .bind(user_id)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        rows.into_iter().map(Share::try_from).collect()
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_share_by_id"))]
+    async fn get_share_by_id(&self, share_id: Uuid) -> Result<Option<Share>, AppError> {
+        let row = sqlx::query_as::<_, ShareRow>(&format!("{} WHERE s.id = ?1", SELECT_SHARES))
+            .bind(share_id)
+            .fetch_optional(&mut *self.conn().await?)
+            .await?;
+
+        row.map(Share::try_from).transpose()
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "update_share"))]
+    async fn update_share(&self, share_id: Uuid, role: ShareRole) -> Result<Option<Share>, AppError> {
+        let mut conn = self.conn().await?;
+        let result = sqlx::query("UPDATE todo_shares SET role = ?2 WHERE id = ?1")
+            .bind(share_id)
+            .bind(role.as_str())
+            .execute(&mut *conn)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        let row = sqlx::query_as::<_, ShareRow>(&format!("{} WHERE s.id = ?1", SELECT_SHARES))
+            .bind(share_id)
+            .fetch_optional(&mut *conn)
+            .await?;
+        row.map(Share::try_from).transpose()
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "delete_share"))]
+    async fn delete_share(&self, share_id: Uuid) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM todo_shares WHERE id = ?1")
+            .bind(share_id)
+            .execute(&mut *self.conn().await?)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Comments joined with their author's name.
+const SELECT_COMMENTS: &str = r#"
+    SELECT c.id, c.todo_id, c.author_id, a.username AS author_username, c.body, c.created_at, c.edited_at
+    FROM todo_comments c
+    JOIN users a ON a.id = c.author_id
+"#;
+
+#[async_trait]
+impl CommentStore for SqliteStore {
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "create_comment"))]
+    async fn create_comment(
+        &self,
+        todo_id: Uuid,
+        author_id: Uuid,
+        body: &str,
+        mentioned: &[Uuid],
+    ) -> Result<Comment, AppError> {
+        let now = Utc::now();
+        let comment_id = Uuid::new_v4();
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query("INSERT INTO todo_comments (id, todo_id, author_id, body, created_at) VALUES (?1, ?2, ?3, ?4, ?5)")
+            .bind(comment_id)
+            .bind(todo_id)
+            .bind(author_id)
+            .bind(body)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        Self::add_revision(&mut tx, comment_id, body, mentioned, now).await?;
+
+        let comment = sqlx::query_as::<_, Comment>(&format!("{} WHERE c.id = ?1", SELECT_COMMENTS))
+            .bind(comment_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(comment)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_comments"))]
+    async fn get_comments(&self, todo_id: Uuid) -> Result<Vec<Comment>, AppError> {
+        let comments = sqlx::query_as::<_, Comment>(&format!(
+            "{} WHERE c.todo_id = ?1 ORDER BY c.created_at, c.id",
+            SELECT_COMMENTS
+        ))
+        .bind(todo_id)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(comments)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_comment"))]
+    async fn get_comment(&self, comment_id: Uuid) -> Result<Option<Comment>, AppError> {
+        let comment = sqlx::query_as::<_, Comment>(&format!("{} WHERE c.id = ?1", SELECT_COMMENTS))
+            .bind(comment_id)
+            .fetch_optional(&mut *self.conn().await?)
+            .await?;
+
+        Ok(comment)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "update_comment"))]
+    async fn update_comment(&self, comment_id: Uuid, body: &str, mentioned: &[Uuid]) -> Result<Option<Comment>, AppError> {
+        let now = Utc::now();
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let result = sqlx::query("UPDATE todo_comments SET body = ?2, edited_at = ?3 WHERE id = ?1")
+            .bind(comment_id)
+            .bind(body)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+        Self::add_revision(&mut tx, comment_id, body, mentioned, now).await?;
+
+        let comment = sqlx::query_as::<_, Comment>(&format!("{} WHERE c.id = ?1", SELECT_COMMENTS))
+            .bind(comment_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(comment)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "delete_comment"))]
+    async fn delete_comment(&self, comment_id: Uuid) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM todo_comments WHERE id = ?1")
+            .bind(comment_id)
+            .execute(&mut *self.conn().await?)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_comment_revisions"))]
+    async fn get_comment_revisions(&self, comment_id: Uuid) -> Result<Vec<CommentRevision>, AppError> {
+        let revisions = sqlx::query_as::<_, CommentRevision>(
+            "SELECT body, created_at FROM todo_comment_revisions WHERE comment_id = ?1 ORDER BY id"
+        )
+        .bind(comment_id)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(revisions)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_mentions"))]
+    async fn get_mentions(&self, user_id: Uuid, unread_only: bool, limit: i64) -> Result<Vec<Mention>, AppError> {
+        let mentions = sqlx::query_as::<_, Mention>(
+            r#"
+            SELECT c.id, c.todo_id, c.author_id, a.username AS author_username, c.body, c.created_at, c.edited_at,
+                   m.created_at AS mentioned_at, m.read_at
+            FROM comment_mentions m
+            JOIN todo_comments c ON c.id = m.comment_id
+            JOIN users a ON a.id = c.author_id
+            JOIN todos t ON t.id = c.todo_id
+            WHERE m.user_id = ?1
+                AND (NOT ?2 OR m.read_at IS NULL)
+                AND (t.user_id = ?1 OR EXISTS (
+                    SELECT 1 FROM todo_shares s
+                    WHERE s.grantee_id = ?1
+                        AND s.owner_id = t.user_id
+                        AND (s.todo_id = t.id OR s.todo_id IS NULL)
+                ))
+            ORDER BY m.created_at DESC, c.id
+            LIMIT ?3
+            "#
+        )
+        .bind(user_id)
+        .bind(unread_only)
+        .bind(limit)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(mentions)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "mark_mentions_read"))]
+    async fn mark_mentions_read(&self, user_id: Uuid, comment_id: Option<Uuid>) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE comment_mentions SET read_at = ?3
+            WHERE user_id = ?1 AND read_at IS NULL AND (?2 IS NULL OR comment_id = ?2)
+            "#
+        )
+        .bind(user_id)
+        .bind(comment_id)
+        .bind(Utc::now())
+        .execute(&mut *self.conn().await?)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Attachments joined with their uploader's name.
+const SELECT_ATTACHMENTS: &str = r#"
+    SELECT a.id, a.todo_id, a.user_id, u.username, a.filename, a.content_type, a.size_bytes, a.blob_key, a.created_at
+    FROM todo_attachments a
+    JOIN users u ON u.id = a.user_id
+"#;
+
+#[async_trait]
+impl AttachmentStore for SqliteStore {
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "create_attachment"))]
+    async fn create_attachment(&self, attachment: NewAttachment, quota_bytes: i64) -> Result<Option<Attachment>, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        // One statement, so the quota is checked under the write lock
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO todo_attachments (id, todo_id, user_id, filename, content_type, size_bytes, blob_key, created_at)
+            SELECT ?1, id, ?3, ?4, ?5, ?6, ?7, ?8 FROM todos
+            WHERE id = ?2
+                AND (SELECT COALESCE(SUM(size_bytes), 0) FROM todo_attachments
+                     WHERE user_id = ?3 AND todo_id IS NOT NULL) + ?6 <= ?9
+            "#
+        )
+        .bind(attachment.id)
+        .bind(attachment.todo_id)
+        .bind(attachment.user_id)
+        .bind(&attachment.filename)
+        .bind(&attachment.content_type)
+        .bind(attachment.size_bytes)
+        .bind(&attachment.blob_key)
+        .bind(Utc::now())
+        .bind(quota_bytes)
+        .execute(&mut *tx)
+        .await?;
+        if inserted.rows_affected() == 0 {
+            return match Self::owner_of(&mut tx, attachment.todo_id).await? {
+                Some(_) => Ok(None),
+                None => Err(AppError::NotFound("Todo not found".to_string())),
+            };
+        }
+
+        let created = sqlx::query_as::<_, Attachment>(&format!("{} WHERE a.id = ?1", SELECT_ATTACHMENTS))
+            .bind(attachment.id)
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(Some(created))
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_attachments"))]
+    async fn get_attachments(&self, todo_id: Uuid) -> Result<Vec<Attachment>, AppError> {
+        let attachments = sqlx::query_as::<_, Attachment>(&format!(
+            "{} WHERE a.todo_id = ?1 ORDER BY a.created_at, a.id",
+            SELECT_ATTACHMENTS
+        ))
+        .bind(todo_id)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(attachments)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_attachment"))]
+    async fn get_attachment(&self, attachment_id: Uuid) -> Result<Option<Attachment>, AppError> {
+        let attachment = sqlx::query_as::<_, Attachment>(&format!("{} WHERE a.id = ?1", SELECT_ATTACHMENTS))
+            .bind(attachment_id)
+            .fetch_optional(&mut *self.conn().await?)
+            .await?;
+
+        Ok(attachment)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "detach_attachment"))]
+    async fn detach_attachment(&self, attachment_id: Uuid) -> Result<bool, AppError> {
+        let result = sqlx::query("UPDATE todo_attachments SET todo_id = NULL WHERE id = ?1 AND todo_id IS NOT NULL")
+            .bind(attachment_id)
+            .execute(&mut *self.conn().await?)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_attachment_usage"))]
+    async fn get_attachment_usage(&self, user_id: Uuid) -> Result<i64, AppError> {
+        let used = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(size_bytes), 0) FROM todo_attachments WHERE user_id = ?1 AND todo_id IS NOT NULL"
+        )
+        .bind(user_id)
+        .fetch_one(&mut *self.conn().await?)
+        .await?;
+
+        Ok(used)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_orphaned_attachments"))]
+    async fn get_orphaned_attachments(&self, limit: i64) -> Result<Vec<Attachment>, AppError> {
+        let attachments = sqlx::query_as::<_, Attachment>(&format!(
+            "{} WHERE a.todo_id IS NULL ORDER BY a.created_at LIMIT ?1",
+            SELECT_ATTACHMENTS
+        ))
+        .bind(limit)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(attachments)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "purge_attachment"))]
+    async fn purge_attachment(&self, attachment_id: Uuid) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM todo_attachments WHERE id = ?1 AND todo_id IS NULL")
+            .bind(attachment_id)
+            .execute(&mut *self.conn().await?)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+impl SyncStore for SqliteStore {
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "todo_changes_since"))]
+    async fn todo_changes_since(&self, user_id: Uuid, since: i64, limit: usize) -> Result<Vec<TodoChange>, AppError> {
+        let rows = sqlx::query_as::<_, TodoChangeRow>(&format!(
+            r#"
+            SELECT {}, seq, NULL AS deleted_at
+            FROM todos
+            WHERE user_id = ?1 AND seq > ?2
+            UNION ALL
+            SELECT todo_id, user_id, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, NULL, seq, deleted_at
+            FROM todo_tombstones
+            WHERE user_id = ?1 AND seq > ?2
+            ORDER BY seq
+            LIMIT ?3
+            "#,
+            TODO_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(since)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        rows.into_iter().map(TodoChange::try_from).collect()
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "apply_sync_mutation"))]
+    async fn apply_sync_mutation(&self, user_id: Uuid, mutation: &SyncMutation) -> Result<Merged, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let seq = Self::next_seq(&mut tx, user_id).await?;
+        let existing = Self::existing(&mut tx, user_id, mutation.id).await?;
+        let merged = sync::merge(user_id, existing, mutation, Utc::now())?;
+
+        let event = match &merged.write {
+            Write::Nothing => return Ok(merged),
+            Write::Insert(todo, versions) => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO todos
+                        (id, user_id, title, description, completed, scheduled_for, created_at, updated_at, seq, field_versions,
+                         completed_at, priority, tags, recurrence)
+                    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+                    "#
+                )
+                .bind(todo.id)
+                .bind(user_id)
+                .bind(&todo.title)
+                .bind(&todo.description)
+                .bind(todo.completed)
+                .bind(todo.scheduled_for)
+                .bind(todo.created_at)
+                .bind(todo.updated_at)
+                .bind(seq)
+                .bind(Json(versions))
+                .bind(todo.completed_at)
+                .bind(todo.priority)
+                .bind(Json(&todo.tags))
+                .bind(&todo.recurrence)
+                .execute(&mut *tx)
+                .await?;
+                Self::record_event(&mut tx, TodoEventKind::Created, user_id, todo.id, Some(todo)).await?
+            }
+            Write::Update(todo, versions) => {
+                sqlx::query(
+                    r#"
+                    UPDATE todos
+                    SET title = ?3, description = ?4, completed = ?5, scheduled_for = ?6, updated_at = ?7,
+                        seq = ?8, field_versions = ?9, completed_at = ?10,
+                        priority = ?11, tags = ?12, recurrence = ?13,
+                        hidden_until = CASE WHEN ?6 IS NOT scheduled_for THEN NULL ELSE hidden_until END
+                    WHERE id = ?1 AND user_id = ?2
+                    "#
+                )
+                .bind(todo.id)
+                .bind(user_id)
+                .bind(&todo.title)
+                .bind(&todo.description)
+                .bind(todo.completed)
+                .bind(todo.scheduled_for)
+                .bind(todo.updated_at)
+                .bind(seq)
+                .bind(Json(versions))
+                .bind(todo.completed_at)
+                .bind(todo.priority)
+                .bind(Json(&todo.tags))
+                .bind(&todo.recurrence)
+                .execute(&mut *tx)
+                .await?;
+                Self::record_event(&mut tx, TodoEventKind::Updated, user_id, todo.id, Some(todo)).await?
+            }
+            Write::Delete(todo_id) => {
+                let event = Self::record_event(&mut tx, TodoEventKind::Deleted, user_id, *todo_id, None).await?;
+                Self::delete_with_tombstone(&mut tx, *todo_id, user_id, seq).await?;
+                event
+            }
+        };
+        tx.commit().await?;
+        self.events.publish(event);
+
+        Ok(merged)
+    }
+}
+
+#[async_trait]
+impl WebhookStore for SqliteStore {
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "create_webhook"))]
+    async fn create_webhook(
+        &self,
+        user_id: Uuid,
+        url: &str,
+        events: &[TodoEventKind],
+        secret: &str,
+        active: bool,
+    ) -> Result<Webhook, AppError> {
+        let row = sqlx::query_as::<_, WebhookRow>(
+            r#"
+            INSERT INTO webhooks (id, user_id, url, events, secret, active, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+            RETURNING id, user_id, url, events, secret, active, created_at, updated_at
+            "#
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(url)
+        .bind(Json(events))
+        .bind(secret)
+        .bind(active)
+        .bind(Utc::now())
+        .fetch_one(&mut *self.conn().await?)
+        .await?;
+
+        Ok(row.into())
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_webhooks_by_user"))]
+    async fn get_webhooks_by_user(&self, user_id: Uuid) -> Result<Vec<Webhook>, AppError> {
+        let rows = sqlx::query_as::<_, WebhookRow>(
+            r#"
+            SELECT id, user_id, url, events, secret, active, created_at, updated_at
+            FROM webhooks
+            WHERE user_id = ?1
+            ORDER BY created_at
+            "#
+        )
+        .bind(user_id)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(rows.into_iter().map(Webhook::from).collect())
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_webhook_by_id"))]
+    async fn get_webhook_by_id(&self, webhook_id: Uuid, user_id: Uuid) -> Result<Option<Webhook>, AppError> {
+        let row = sqlx::query_as::<_, WebhookRow>(
+            r#"
+            SELECT id, user_id, url, events, secret, active, created_at, updated_at
+            FROM webhooks
+            WHERE id = ?1 AND user_id = ?2
+            "#
+        )
+        .bind(webhook_id)
+        .bind(user_id)
+        .fetch_optional(&mut *self.conn().await?)
+        .await?;
+
+        Ok(row.map(Webhook::from))
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "update_webhook"))]
+    async fn update_webhook(
+        &self,
+        webhook_id: Uuid,
+        user_id: Uuid,
+        update: UpdateWebhook,
+    ) -> Result<Option<Webhook>, AppError> {
+        let row = sqlx::query_as::<_, WebhookRow>(
+            r#"
+            UPDATE webhooks
+            SET url = COALESCE(?3, url),
+                events = COALESCE(?4, events),
+                secret = COALESCE(?5, secret),
+                active = COALESCE(?6, active),
+                updated_at = ?7
+            WHERE id = ?1 AND user_id = ?2
+            RETURNING id, user_id, url, events, secret, active, created_at, updated_at
+            "#
+        )
+        .bind(webhook_id)
+        .bind(user_id)
+        .bind(&update.url)
+        .bind(update.events.as_ref().map(Json))
+        .bind(&update.secret)
+        .bind(update.active)
+        .bind(Utc::now())
+        .fetch_optional(&mut *self.conn().await?)
+        .await?;
+
+        Ok(row.map(Webhook::from))
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "delete_webhook"))]
+    async fn delete_webhook(&self, webhook_id: Uuid, user_id: Uuid) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM webhooks WHERE id = ?1 AND user_id = ?2")
+            .bind(webhook_id)
+            .bind(user_id)
+            .execute(&mut *self.conn().await?)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "enqueue_webhook_delivery"))]
+    async fn enqueue_webhook_delivery(
+        &self,
+        webhook_id: Uuid,
+        event: &str,
+        payload: &Value,
+    ) -> Result<WebhookDelivery, AppError> {
+        Self::insert_delivery(&mut *self.conn().await?, webhook_id, event, payload).await
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_webhook_deliveries"))]
+    async fn get_webhook_deliveries(&self, webhook_id: Uuid, limit: i64) -> Result<Vec<WebhookDelivery>, AppError> {
+        let rows = sqlx::query_as::<_, WebhookDeliveryRow>(
+            r#"
+            SELECT id, webhook_id, event, payload, status, attempts, next_attempt_at, last_status_code,
+                   last_error, created_at, delivered_at
+            FROM webhook_deliveries
+            WHERE webhook_id = ?1
+            ORDER BY created_at DESC, rowid DESC
+            LIMIT ?2
+            "#
+        )
+        .bind(webhook_id)
+        .bind(limit)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        rows.into_iter().map(WebhookDelivery::try_from).collect()
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_webhook_delivery"))]
+    async fn get_webhook_delivery(
+        &self,
+        delivery_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Option<WebhookDelivery>, AppError> {
+        let row = sqlx::query_as::<_, WebhookDeliveryRow>(
+            r#"
+            SELECT id, webhook_id, event, payload, status, attempts, next_attempt_at, last_status_code,
+                   last_error, created_at, delivered_at
+            FROM webhook_deliveries
+            WHERE id = ?1 AND webhook_id = ?2
+            "#
+        )
+        .bind(delivery_id)
+        .bind(webhook_id)
+        .fetch_optional(&mut *self.conn().await?)
+        .await?;
+
+        row.map(WebhookDelivery::try_from).transpose()
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_webhook_attempts"))]
+    async fn get_webhook_attempts(&self, delivery_id: Uuid) -> Result<Vec<WebhookAttempt>, AppError> {
+        let attempts = sqlx::query_as::<_, WebhookAttempt>(
+            r#"
+            SELECT attempted_at, status_code, error, duration_ms
+            FROM webhook_attempts
+            WHERE delivery_id = ?1
+            ORDER BY id
+            "#
+        )
+        .bind(delivery_id)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(attempts)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "claim_webhook_deliveries"))]
+    async fn claim_webhook_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<PendingDelivery>, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+
+        // Updating first takes the write lock before anything is read
+        let claimed: Vec<(Uuid, Uuid, String, Json<Value>, i32)> = sqlx::query_as(
+            r#"
+            UPDATE webhook_deliveries
+            SET next_attempt_at = ?2
+            WHERE id IN (
+                SELECT id FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= ?1
+                ORDER BY next_attempt_at
+                LIMIT ?3
+            )
+            RETURNING id, webhook_id, event, payload, attempts
+            "#
+        )
+        .bind(now)
+        .bind(lease_until)
+        .bind(limit)
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let mut pending = Vec::with_capacity(claimed.len());
+        for (id, webhook_id, event, Json(payload), attempts) in claimed {
+            let (url, secret): (String, String) = sqlx::query_as("SELECT url, secret FROM webhooks WHERE id = ?1")
+                .bind(webhook_id)
+                .fetch_one(&mut *tx)
+                .await?;
+            pending.push(PendingDelivery { id, webhook_id, event, payload, attempts, url, secret });
+        }
+        tx.commit().await?;
+
+        Ok(pending)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "record_webhook_attempt"))]
+    async fn record_webhook_attempt(
+        &self,
+        delivery_id: Uuid,
+        attempt: &WebhookAttempt,
+        outcome: DeliveryOutcome,
+    ) -> Result<(), AppError> {
+        let (status, next_attempt_at, delivered_at) = store::delivery_state(outcome, attempt.attempted_at);
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+
+        sqlx::query(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = ?2, attempts = attempts + 1, next_attempt_at = ?3, last_status_code = ?4,
+                last_error = ?5, delivered_at = ?6
+            WHERE id = ?1
+            "#
+        )
+        .bind(delivery_id)
+        .bind(status)
+        .bind(next_attempt_at)
+        .bind(attempt.status_code)
+        .bind(&attempt.error)
+        .bind(delivered_at)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO webhook_attempts (delivery_id, attempted_at, status_code, error, duration_ms)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#
+        )
+        .bind(delivery_id)
+        .bind(attempt.attempted_at)
+        .bind(attempt.status_code)
+        .bind(&attempt.error)
+        .bind(attempt.duration_ms)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "prune_webhook_deliveries"))]
+    async fn prune_webhook_deliveries(&self, before: DateTime<Utc>) -> Result<u64, AppError> {
+        let result = sqlx::query("DELETE FROM webhook_deliveries WHERE status <> 'pending' AND created_at < ?1")
+            .bind(before)
+            .execute(&mut *self.conn().await?)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// The todos a user owns or has been shared, directly or through a list.
+const VISIBLE_TODOS: &str = r#"
+    WITH visible AS (
+        SELECT todos.*
+        FROM todos
+        WHERE todos.user_id = ?1
+           OR EXISTS (
+               SELECT 1 FROM todo_shares
+               WHERE grantee_id = ?1
+                 AND owner_id = todos.user_id
+                 AND (todo_shares.todo_id = todos.id OR todo_shares.todo_id IS NULL)
+           )
+    )
+"#;
+
+#[async_trait]
+impl StatsStore for SqliteStore {
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "todo_stats"))]
+    async fn todo_stats(&self, user_id: Uuid, range: &StatsRange) -> Result<StatsCounts, AppError> {
+        let mut conn = self.conn().await?;
+        let totals = sqlx::query_as::<_, StatsTotalsRow>(&format!(
+            r#"
+            {}
+            SELECT
+                COUNT(*) FILTER (WHERE created_at >= ?2 AND created_at < ?3) AS created,
+                COUNT(*) FILTER (WHERE completed_at >= ?2 AND completed_at < ?3) AS completed,
+                COUNT(*) FILTER (WHERE completed_at >= ?2 AND completed_at < ?3 AND completed_at > scheduled_for) AS completed_late,
+                COUNT(*) FILTER (WHERE NOT completed AND scheduled_for >= ?2 AND scheduled_for < MIN(?3, ?4)) AS overdue,
+                AVG((julianday(completed_at) - julianday(created_at)) * 86400)
+                    FILTER (WHERE completed_at >= ?2 AND completed_at < ?3) AS average_completion_seconds
+            FROM visible
+            "#,
+            VISIBLE_TODOS
+        ))
+        .bind(user_id)
+        .bind(range.start)
+        .bind(range.end)
+        .bind(range.now)
+        .fetch_one(&mut *conn)
+        .await?;
+
+        // SQLite knows no time zones, so each day's bounds are passed in,
+        // written the way timestamps are stored so they compare as text
+        let days: Vec<(NaiveDate, String, String)> = range
+            .days()
+            .map(|(date, start, end)| {
+                (date, start.to_rfc3339_opts(SecondsFormat::AutoSi, false), end.to_rfc3339_opts(SecondsFormat::AutoSi, false))
+            })
+            .collect();
+        let completed_per_day = sqlx::query_as::<_, DayCount>(&format!(
+            r#"
+            {}
+            SELECT json_extract(day.value, '$[0]') AS date, COUNT(*) AS count
+            FROM visible
+            JOIN json_each(?2) AS day
+              ON completed_at >= json_extract(day.value, '$[1]') AND completed_at < json_extract(day.value, '$[2]')
+            GROUP BY 1
+            "#,
+            VISIBLE_TODOS
+        ))
+        .bind(user_id)
+        .bind(Json(days))
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let by_list = sqlx::query_as::<_, ListStats>(&format!(
+            r#"
+            {}
+            SELECT visible.user_id AS owner_id, users.username,
+                   COUNT(*) FILTER (WHERE visible.created_at >= ?2 AND visible.created_at < ?3) AS created,
+                   COUNT(*) FILTER (WHERE completed_at >= ?2 AND completed_at < ?3) AS completed
+            FROM visible
+            JOIN users ON users.id = visible.user_id
+            WHERE (visible.created_at >= ?2 AND visible.created_at < ?3)
+               OR (completed_at >= ?2 AND completed_at < ?3)
+            GROUP BY visible.user_id, users.username
+            "#,
+            VISIBLE_TODOS
+        ))
+        .bind(user_id)
+        .bind(range.start)
+        .bind(range.end)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let by_tag = sqlx::query_as::<_, TagStats>(&format!(
+            r#"
+            {}
+            SELECT tag.value AS tag,
+                   COUNT(*) FILTER (WHERE created_at >= ?2 AND created_at < ?3) AS created,
+                   COUNT(*) FILTER (WHERE completed_at >= ?2 AND completed_at < ?3) AS completed
+            FROM visible, json_each(visible.tags) AS tag
+            WHERE (created_at >= ?2 AND created_at < ?3)
+               OR (completed_at >= ?2 AND completed_at < ?3)
+            GROUP BY tag.value
+            "#,
+            VISIBLE_TODOS
+        ))
+        .bind(user_id)
+        .bind(range.start)
+        .bind(range.end)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        Ok(StatsCounts {
+            created: totals.created,
+            completed: totals.completed,
+            completed_late: totals.completed_late,
+            overdue: totals.overdue,
+            average_completion_seconds: totals.average_completion_seconds,
+            completed_per_day,
+            by_list,
+            by_tag,
+        })
+    }
+}
+
+const TIME_ENTRY_COLUMNS: &str =
+    "id, todo_id, user_id, started_at, ended_at, duration_seconds, note, created_at, updated_at";
+
+#[async_trait]
+impl TimeEntryStore for SqliteStore {
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "start_timer"))]
+    async fn start_timer(
+        &self,
+        todo_id: Uuid,
+        user_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<(TimeEntry, Option<TimeEntry>), AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let stopped = Self::stop_running(&mut tx, user_id, None, now).await?;
+        let started = sqlx::query_as::<_, TimeEntry>(&format!(
+            r#"
+            INSERT INTO time_entries (id, todo_id, user_id, started_at, created_at, updated_at)
+            SELECT ?1, id, ?3, ?4, ?5, ?5 FROM todos WHERE id = ?2
+            RETURNING {}
+            "#,
+            TIME_ENTRY_COLUMNS
+        ))
+        .bind(Uuid::new_v4())
+        .bind(todo_id)
+        .bind(user_id)
+        .bind(now)
+        .bind(Utc::now())
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::NotFound("Todo not found".to_string()))?;
+        tx.commit().await?;
+
+        Ok((started, stopped))
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "stop_timer"))]
+    async fn stop_timer(&self, todo_id: Uuid, user_id: Uuid, now: DateTime<Utc>) -> Result<Option<TimeEntry>, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let stopped = Self::stop_running(&mut tx, user_id, Some(todo_id), now).await?;
+        tx.commit().await?;
+
+        Ok(stopped)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_running_time_entry"))]
+    async fn get_running_time_entry(&self, user_id: Uuid) -> Result<Option<TimeEntry>, AppError> {
+        let running = sqlx::query_as::<_, TimeEntry>(&format!(
+            "SELECT {} FROM time_entries WHERE user_id = ?1 AND ended_at IS NULL",
+            TIME_ENTRY_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_optional(&mut *self.conn().await?)
+        .await?;
+
+        Ok(running)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "create_time_entry"))]
+    async fn create_time_entry(
+        &self,
+        todo_id: Uuid,
+        user_id: Uuid,
+        started_at: DateTime<Utc>,
+        ended_at: DateTime<Utc>,
+        note: Option<&str>,
+    ) -> Result<TimeEntry, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let created = sqlx::query_as::<_, TimeEntry>(&format!(
+            r#"
+            INSERT INTO time_entries (id, todo_id, user_id, started_at, ended_at, duration_seconds, note, created_at, updated_at)
+            SELECT ?1, id, ?3, ?4, ?5, ?6, ?7, ?8, ?8 FROM todos WHERE id = ?2
+            RETURNING {}
+            "#,
+            TIME_ENTRY_COLUMNS
+        ))
+        .bind(Uuid::new_v4())
+        .bind(todo_id)
+        .bind(user_id)
+        .bind(started_at)
+        .bind(ended_at)
+        .bind(time_tracking::duration_seconds(started_at, ended_at))
+        .bind(note)
+        .bind(Utc::now())
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(AppError::NotFound("Todo not found".to_string()))?;
+        Self::update_tracked_seconds(&mut tx, todo_id).await?;
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_time_entries"))]
+    async fn get_time_entries(&self, todo_id: Uuid) -> Result<Vec<TimeEntry>, AppError> {
+        let entries = sqlx::query_as::<_, TimeEntry>(&format!(
+            "SELECT {} FROM time_entries WHERE todo_id = ?1 ORDER BY started_at, id",
+            TIME_ENTRY_COLUMNS
+        ))
+        .bind(todo_id)
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(entries)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "get_time_entry"))]
+    async fn get_time_entry(&self, entry_id: Uuid) -> Result<Option<TimeEntry>, AppError> {
+        let entry = sqlx::query_as::<_, TimeEntry>(&format!("SELECT {} FROM time_entries WHERE id = ?1", TIME_ENTRY_COLUMNS))
+            .bind(entry_id)
+            .fetch_optional(&mut *self.conn().await?)
+            .await?;
+
+        Ok(entry)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "update_time_entry"))]
+    async fn update_time_entry(
+        &self,
+        entry_id: Uuid,
+        started_at: DateTime<Utc>,
+        ended_at: Option<DateTime<Utc>>,
+        note: Option<&str>,
+    ) -> Result<Option<TimeEntry>, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let updated = sqlx::query_as::<_, TimeEntry>(&format!(
+            r#"
+            UPDATE time_entries
+            SET started_at = ?2, ended_at = ?3, duration_seconds = ?4, note = ?5, updated_at = ?6
+            WHERE id = ?1
+            RETURNING {}
+            "#,
+            TIME_ENTRY_COLUMNS
+        ))
+        .bind(entry_id)
+        .bind(started_at)
+        .bind(ended_at)
+        .bind(ended_at.map(|ended_at| time_tracking::duration_seconds(started_at, ended_at)))
+        .bind(note)
+        .bind(Utc::now())
+        .fetch_optional(&mut *tx)
+        .await?;
+        if let Some(entry) = &updated {
+            Self::update_tracked_seconds(&mut tx, entry.todo_id).await?;
+        }
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "delete_time_entry"))]
+    async fn delete_time_entry(&self, entry_id: Uuid) -> Result<bool, AppError> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        let todo_id: Option<Uuid> = sqlx::query_scalar("DELETE FROM time_entries WHERE id = ?1 RETURNING todo_id")
+            .bind(entry_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+        if let Some(todo_id) = todo_id {
+            Self::update_tracked_seconds(&mut tx, todo_id).await?;
+        }
+        tx.commit().await?;
+
+        Ok(todo_id.is_some())
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "time_report"))]
+    async fn time_report(&self, user_id: Uuid, range: &StatsRange) -> Result<Vec<TimeReportRow>, AppError> {
+        // Each day's bounds are passed in, as for the stats
+        let days: Vec<(NaiveDate, String, String)> = range
+            .days()
+            .map(|(date, start, end)| {
+                (date, start.to_rfc3339_opts(SecondsFormat::AutoSi, false), end.to_rfc3339_opts(SecondsFormat::AutoSi, false))
+            })
+            .collect();
+        let rows = sqlx::query_as::<_, TimeReportRow>(
+            r#"
+            SELECT json_extract(day.value, '$[0]') AS date, todos.user_id AS owner_id, users.username,
+                   SUM(e.duration_seconds) AS seconds
+            FROM time_entries e
+            JOIN json_each(?2) AS day
+              ON e.started_at >= json_extract(day.value, '$[1]') AND e.started_at < json_extract(day.value, '$[2]')
+            JOIN todos ON todos.id = e.todo_id
+            JOIN users ON users.id = todos.user_id
+            WHERE e.user_id = ?1 AND e.ended_at IS NOT NULL
+            GROUP BY 1, 2, 3
+            "#
+        )
+        .bind(user_id)
+        .bind(Json(days))
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        Ok(rows)
+    }
+}
+
+#[async_trait]
+impl EventStore for SqliteStore {
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "todo_events_since"))]
+    async fn todo_events_since(&self, user_id: Uuid, after_id: i64, limit: i64) -> Result<Vec<TodoEvent>, AppError> {
+        let rows = sqlx::query_as::<_, TodoEventRow>(
+            r#"
+            SELECT id, user_id, todo_id, kind, todo, created_at, recipients
+            FROM todo_events
+            WHERE (user_id = ?1 OR EXISTS (SELECT 1 FROM json_each(recipients) WHERE value = ?4)) AND id > ?2
+            ORDER BY id
+            LIMIT ?3
+            "#
+        )
+        .bind(user_id)
+        .bind(after_id)
+        .bind(limit)
+        .bind(user_id.to_string())
+        .fetch_all(&mut *self.conn().await?)
+        .await?;
+
+        rows.into_iter().map(TodoEvent::try_from).collect()
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "oldest_todo_event_id"))]
+    async fn oldest_todo_event_id(&self) -> Result<Option<i64>, AppError> {
+        let oldest: Option<i64> = sqlx::query_scalar("SELECT MIN(id) FROM todo_events")
+            .fetch_one(&mut *self.conn().await?)
+            .await?;
+
+        Ok(oldest)
+    }
+
+    #[instrument(name = "db.query", skip_all, fields(db.system = "sqlite", db.operation = "prune_todo_events"))]
+    async fn prune_todo_events(&self, before: DateTime<Utc>) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            "DELETE FROM todo_events WHERE created_at < ?1 AND id < (SELECT MAX(id) FROM todo_events)"
+        )
+        .bind(before)
+        .execute(&mut *self.conn().await?)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn subscribe_todo_events(&self) -> Result<broadcast::Receiver<Notification>, AppError> {
+        Ok(self.events.subscribe())
+    }
+}
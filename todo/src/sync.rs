@@ -0,0 +1,371 @@
+use chrono::{DateTime, SubsecRound, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{SyncConflict, SyncMutation, SyncOp, SyncResult, SyncStatus, Todo, UpdateTodo},
+};
+
+/// Changes returned per `GET /api/sync` page.
+pub const PAGE_LIMIT: usize = 500;
+
+/// When each syncable field of a todo last changed, as stored alongside it.
+/// Last-writer-wins compares a client's `changed_at` with these.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FieldVersions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled_for: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<DateTime<Utc>>,
+}
+
+impl FieldVersions {
+    /// Every field changed at `at`, as for a new todo.
+    pub fn all(at: DateTime<Utc>) -> Self {
+        Self {
+            title: Some(at),
+            description: Some(at),
+            completed: Some(at),
+            scheduled_for: Some(at),
+            priority: Some(at),
+            tags: Some(at),
+            recurrence: Some(at),
+        }
+    }
+
+    /// The fields `update` sets, changed at `at`; stores merge this over the
+    /// existing versions.
+    pub fn touched(update: &UpdateTodo, at: DateTime<Utc>) -> Self {
+        Self {
+            title: update.title.as_ref().map(|_| at),
+            description: update.description.as_ref().map(|_| at),
+            completed: update.completed.map(|_| at),
+            scheduled_for: update.scheduled_for.map(|_| at),
+            priority: update.priority.map(|_| at),
+            tags: update.tags.as_ref().map(|_| at),
+            recurrence: update.recurrence.as_ref().map(|_| at),
+        }
+    }
+
+    /// Overlays the fields set in `other`.
+    pub fn extend(&mut self, other: &FieldVersions) {
+        self.title = other.title.or(self.title);
+        self.description = other.description.or(self.description);
+        self.completed = other.completed.or(self.completed);
+        self.scheduled_for = other.scheduled_for.or(self.scheduled_for);
+        self.priority = other.priority.or(self.priority);
+        self.tags = other.tags.or(self.tags);
+        self.recurrence = other.recurrence.or(self.recurrence);
+    }
+}
+
+/// One entry of a user's change feed.
+#[derive(Debug, Clone)]
+pub enum TodoChange {
+    /// The todo was created or changed; this is its current state.
+    Upserted { seq: i64, todo: Box<Todo> },
+    /// The todo was deleted.
+    Deleted { seq: i64, id: Uuid, deleted_at: DateTime<Utc> },
+}
+
+impl TodoChange {
+    pub fn seq(&self) -> i64 {
+        match *self {
+            TodoChange::Upserted { seq, .. } | TodoChange::Deleted { seq, .. } => seq,
+        }
+    }
+}
+
+/// What the store holds under a mutation's id.
+#[derive(Debug, Clone)]
+pub enum Existing {
+    None,
+    Todo { todo: Box<Todo>, versions: FieldVersions },
+    Deleted { deleted_at: DateTime<Utc> },
+    /// A todo or tombstone of another user.
+    Foreign,
+}
+
+/// The write a store performs to apply a merge.
+#[derive(Debug, Clone)]
+pub enum Write {
+    Nothing,
+    Insert(Todo, FieldVersions),
+    Update(Todo, FieldVersions),
+    Delete(Uuid),
+}
+
+/// Outcome of merging one client mutation into the stored state.
+#[derive(Debug, Clone)]
+pub struct Merged {
+    pub write: Write,
+    pub status: SyncStatus,
+    pub conflicts: Vec<SyncConflict>,
+    /// The todo before and after the merge; `None` when absent or deleted.
+    pub before: Option<Todo>,
+    pub after: Option<Todo>,
+}
+
+impl Merged {
+    fn unchanged(status: SyncStatus, todo: Option<Todo>, conflicts: Vec<SyncConflict>) -> Self {
+        Merged {
+            write: Write::Nothing,
+            status,
+            conflicts,
+            before: todo.clone(),
+            after: todo,
+        }
+    }
+
+    pub fn into_result(self, id: Uuid) -> SyncResult {
+        SyncResult {
+            id,
+            status: self.status,
+            todo: self.after.map(Into::into),
+            conflicts: self.conflicts,
+            error: None,
+        }
+    }
+}
+
+/// Merges `mutation` into `existing` with last-writer-wins per field.
+///
+/// A field is taken from the client only if its `changed_at`, capped at
+/// `now` so a fast client clock cannot win every future conflict, is later
+/// than the field's stored version. Fields the server changed later are
+/// kept and reported as conflicts. A deletion wins only if it is later than
+/// every field; a deleted todo stays deleted.
+pub fn merge(user_id: Uuid, existing: Existing, mutation: &SyncMutation, now: DateTime<Utc>) -> Result<Merged, AppError> {
+    // Timestamps are stored to the microsecond; compare them that way so a
+    // retried mutation matches what it wrote the first time.
+    let now = now.trunc_subsecs(6);
+    let changed_at = mutation.changed_at.trunc_subsecs(6).min(now);
+    let scheduled_for = mutation.scheduled_for.map(|at| at.map(|at| at.trunc_subsecs(6)));
+
+    match (mutation.op, existing) {
+        (_, Existing::Foreign) => Err(AppError::BadRequest("Todo id is already in use".to_string())),
+
+        (SyncOp::Delete, Existing::None | Existing::Deleted { .. }) => {
+            Ok(Merged::unchanged(SyncStatus::Applied, None, Vec::new()))
+        }
+
+        (SyncOp::Delete, Existing::Todo { todo, versions }) => {
+            let todo = *todo;
+            let conflicts = newer_fields(&todo, &versions, changed_at);
+            if !conflicts.is_empty() {
+                return Ok(Merged::unchanged(SyncStatus::Conflict, Some(todo), conflicts));
+            }
+            Ok(Merged {
+                write: Write::Delete(todo.id),
+                status: SyncStatus::Applied,
+                conflicts,
+                before: Some(todo),
+                after: None,
+            })
+        }
+
+        (SyncOp::Upsert, Existing::Deleted { deleted_at }) => {
+            let conflict = SyncConflict {
+                field: "deleted".to_string(),
+                server_value: json!(true),
+                server_changed_at: deleted_at,
+            };
+            Ok(Merged::unchanged(SyncStatus::Conflict, None, vec![conflict]))
+        }
+
+        (SyncOp::Upsert, Existing::None) => {
+            let title = mutation.title.clone().ok_or_else(|| {
+                AppError::invalid_field("title", "required", "A title is required to create a todo")
+            })?;
+            let todo = Todo {
+                id: mutation.id,
+                user_id,
+                title,
+                description: mutation.description.clone().flatten(),
+                completed: mutation.completed.unwrap_or(false),
+                scheduled_for: scheduled_for.flatten(),
+                assignee_id: None,
+                priority: mutation.priority.flatten(),
+                tags: mutation.tags.clone().unwrap_or_default(),
+                recurrence: mutation.recurrence.clone().flatten(),
+                completed_at: mutation.completed.unwrap_or(false).then_some(now),
+                snooze_count: 0,
+                original_scheduled_for: None,
+                hidden_until: None,
+                tracked_seconds: 0,
+                created_at: now,
+                updated_at: now,
+            };
+            Ok(Merged {
+                write: Write::Insert(todo.clone(), FieldVersions::all(changed_at)),
+                status: SyncStatus::Applied,
+                conflicts: Vec::new(),
+                before: None,
+                after: Some(todo),
+            })
+        }
+
+        (SyncOp::Upsert, Existing::Todo { todo, mut versions }) => {
+            let todo = *todo;
+            let mut merged = todo.clone();
+            let mut conflicts = Vec::new();
+            let fallback = todo.created_at;
+            let mut changed = false;
+
+            changed |= resolve(
+                "title",
+                &mut merged.title,
+                &mut versions.title,
+                mutation.title.clone(),
+                changed_at,
+                fallback,
+                &mut conflicts,
+            );
+            changed |= resolve(
+                "description",
+                &mut merged.description,
+                &mut versions.description,
+                mutation.description.clone(),
+                changed_at,
+                fallback,
+                &mut conflicts,
+            );
+            changed |= resolve(
+                "completed",
+                &mut merged.completed,
+                &mut versions.completed,
+                mutation.completed,
+                changed_at,
+                fallback,
+                &mut conflicts,
+            );
+            changed |= resolve(
+                "scheduled_for",
+                &mut merged.scheduled_for,
+                &mut versions.scheduled_for,
+                scheduled_for,
+                changed_at,
+                fallback,
+                &mut conflicts,
+            );
+            changed |= resolve(
+                "priority",
+                &mut merged.priority,
+                &mut versions.priority,
+                mutation.priority,
+                changed_at,
+                fallback,
+                &mut conflicts,
+            );
+            changed |= resolve(
+                "tags",
+                &mut merged.tags,
+                &mut versions.tags,
+                mutation.tags.clone(),
+                changed_at,
+                fallback,
+                &mut conflicts,
+            );
+            changed |= resolve(
+                "recurrence",
+                &mut merged.recurrence,
+                &mut versions.recurrence,
+                mutation.recurrence.clone(),
+                changed_at,
+                fallback,
+                &mut conflicts,
+            );
+
+            let status = if conflicts.is_empty() { SyncStatus::Applied } else { SyncStatus::Conflict };
+            if !changed {
+                return Ok(Merged::unchanged(status, Some(todo), conflicts));
+            }
+
+            if merged.completed != todo.completed {
+                merged.completed_at = merged.completed.then_some(now);
+            }
+            // Rescheduling ends a snooze, as through the REST API
+            if merged.scheduled_for != todo.scheduled_for {
+                merged.hidden_until = None;
+            }
+            merged.updated_at = now;
+            Ok(Merged {
+                write: Write::Update(merged.clone(), versions),
+                status,
+                conflicts,
+                before: Some(todo),
+                after: Some(merged),
+            })
+        }
+    }
+}
+
+/// Applies the client's value for one field if it is newer, recording a
+/// conflict if the server's differing value is. Returns whether the field
+/// changed.
+fn resolve<T: Serialize + PartialEq>(
+    field: &str,
+    server: &mut T,
+    version: &mut Option<DateTime<Utc>>,
+    client: Option<T>,
+    changed_at: DateTime<Utc>,
+    fallback: DateTime<Utc>,
+    conflicts: &mut Vec<SyncConflict>,
+) -> bool {
+    let Some(client) = client else {
+        return false;
+    };
+    if *server == client {
+        return false;
+    }
+
+    let server_changed_at = version.unwrap_or(fallback);
+    if changed_at > server_changed_at {
+        *server = client;
+        *version = Some(changed_at);
+        return true;
+    }
+
+    conflicts.push(SyncConflict {
+        field: field.to_string(),
+        server_value: json!(server),
+        server_changed_at,
+    });
+    false
+}
+
+/// Fields the server changed at or after `changed_at`.
+fn newer_fields(todo: &Todo, versions: &FieldVersions, changed_at: DateTime<Utc>) -> Vec<SyncConflict> {
+    let fallback = todo.created_at;
+    [
+        ("title", versions.title, json!(todo.title)),
+        ("description", versions.description, json!(todo.description)),
+        ("completed", versions.completed, json!(todo.completed)),
+        ("scheduled_for", versions.scheduled_for, json!(todo.scheduled_for)),
+        ("priority", versions.priority, json!(todo.priority)),
+        ("tags", versions.tags, json!(todo.tags)),
+        ("recurrence", versions.recurrence, json!(todo.recurrence)),
+    ]
+    .into_iter()
+    .filter_map(|(field, version, value)| {
+        let server_changed_at = version.unwrap_or(fallback);
+        (server_changed_at >= changed_at).then(|| SyncConflict {
+            field: field.to_string(),
+            server_value: value,
+            server_changed_at,
+        })
+    })
+    .collect()
+}
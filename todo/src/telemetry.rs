@@ -0,0 +1,161 @@
+use axum::extract::{MatchedPath, Request};
+use serde::{Deserialize, Serialize};
+use std::{io, str::FromStr};
+use tower_http::{
+    classify::{ServerErrorsAsFailures, SharedClassifier},
+    trace::{DefaultOnFailure, DefaultOnResponse, MakeSpan, TraceLayer},
+};
+use tracing::{field::Empty, Level, Span, Subscriber};
+use tracing_subscriber::{
+    fmt::MakeWriter, layer::SubscriberExt, registry::LookupSpan, util::SubscriberInitExt, EnvFilter, Layer,
+};
+
+use crate::{config::LoggingConfig, request_id::X_REQUEST_ID};
+
+/// Log line format, selected with `logging.format` or `LOG_FORMAT`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines for local development.
+    #[default]
+    Text,
+    /// One JSON object per line, carrying the fields of every enclosing span.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" | "pretty" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("Unknown log format '{}', expected 'text' or 'json'", other)),
+        }
+    }
+}
+
+/// Flushes exported spans when dropped; keep it alive until the process exits.
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    otel: bool,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if self.otel {
+            opentelemetry::global::shutdown_tracer_provider();
+        }
+    }
+}
+
+/// Installs the global subscriber: `RUST_LOG` filtering (`logging.level`
+/// when unset), the configured log format, and OTLP export when
+/// `otlp_endpoint` is set and the `otel` feature is enabled.
+pub fn init(config: &LoggingConfig) -> anyhow::Result<TelemetryGuard> {
+    let filter = EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(&config.level))?;
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer(config.format, io::stdout));
+    let otlp_endpoint = config.otlp_endpoint.as_deref().filter(|e| !e.is_empty());
+
+    #[cfg(feature = "otel")]
+    if let Some(endpoint) = otlp_endpoint {
+        registry.with(otel::layer(endpoint)?).try_init()?;
+        tracing::info!("Exporting traces to {}", endpoint);
+        return Ok(TelemetryGuard { otel: true });
+    }
+
+    #[cfg(not(feature = "otel"))]
+    if otlp_endpoint.is_some() {
+        registry.try_init()?;
+        tracing::warn!("OTEL_EXPORTER_OTLP_ENDPOINT is set but this build lacks the `otel` feature");
+        return Ok(TelemetryGuard {});
+    }
+
+    registry.try_init()?;
+    Ok(TelemetryGuard {
+        #[cfg(feature = "otel")]
+        otel: false,
+    })
+}
+
+/// Log output layer writing to `writer` in `format`.
+pub fn fmt_layer<S, W>(format: LogFormat, writer: W) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer().with_writer(writer);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().with_current_span(false).boxed(),
+    }
+}
+
+/// Wraps every request in a `request` span with its method, route template
+/// and request id, logging the status and latency when the response is sent.
+/// `user_id` is filled in once the caller has authenticated.
+pub fn trace_layer() -> TraceLayer<SharedClassifier<ServerErrorsAsFailures>, RequestSpan> {
+    TraceLayer::new_for_http()
+        .make_span_with(RequestSpan)
+        .on_response(DefaultOnResponse::new().level(Level::INFO))
+        .on_failure(DefaultOnFailure::new().level(Level::ERROR))
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RequestSpan;
+
+impl<B> MakeSpan<B> for RequestSpan {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        let route = request
+            .extensions()
+            .get::<MatchedPath>()
+            .map(MatchedPath::as_str)
+            .unwrap_or("unmatched");
+        let request_id = request
+            .headers()
+            .get(&X_REQUEST_ID)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        tracing::info_span!(
+            "request",
+            method = %request.method(),
+            route,
+            request_id,
+            user_id = Empty,
+        )
+    }
+}
+
+/// Records the authenticated user on the current request span.
+pub fn record_user(user_id: impl std::fmt::Display) {
+    Span::current().record("user_id", tracing::field::display(user_id));
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{runtime, trace, Resource};
+    use tracing::Subscriber;
+    use tracing_subscriber::{registry::LookupSpan, Layer};
+
+    pub fn layer<S>(endpoint: &str) -> anyhow::Result<impl Layer<S>>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+            .with_trace_config(trace::config().with_resource(Resource::new([
+                KeyValue::new("service.name", env!("CARGO_PKG_NAME")),
+                KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+            ])))
+            .install_batch(runtime::Tokio)?;
+
+        Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+    }
+}
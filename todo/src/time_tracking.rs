@@ -0,0 +1,100 @@
+//! Time entries: the checks on their times, and the report the stores'
+//! sums are turned into, as JSON or CSV.
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    error::AppError,
+    models::{TimeReport, TimeReportDay, TimeReportList, TimeReportRow},
+    stats::StatsRange,
+};
+
+/// Whole seconds from `started_at` to `ended_at`.
+pub fn duration_seconds(started_at: DateTime<Utc>, ended_at: DateTime<Utc>) -> i64 {
+    (ended_at - started_at).num_seconds()
+}
+
+/// An entry starts before it ends, and neither is in the future; a running
+/// timer has no end yet.
+pub fn check_times(started_at: DateTime<Utc>, ended_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Result<(), AppError> {
+    if started_at > now {
+        return Err(AppError::invalid_field("started_at", "in_future", "Time cannot be tracked ahead"));
+    }
+    let Some(ended_at) = ended_at else {
+        return Ok(());
+    };
+    if ended_at <= started_at {
+        return Err(AppError::invalid_field("ended_at", "before_start", "An entry ends after it starts"));
+    }
+    if ended_at > now {
+        return Err(AppError::invalid_field("ended_at", "in_future", "Time cannot be tracked ahead"));
+    }
+    Ok(())
+}
+
+/// The report for `range` from the store's `rows`.
+pub fn summarize(range: &StatsRange, mut rows: Vec<TimeReportRow>) -> TimeReport {
+    rows.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.username.cmp(&b.username)));
+
+    let by_day = range
+        .from
+        .iter_days()
+        .take_while(|date| *date <= range.to)
+        .map(|date| TimeReportDay {
+            date,
+            seconds: rows.iter().filter(|row| row.date == date).map(|row| row.seconds).sum(),
+        })
+        .collect();
+
+    let mut by_list: Vec<TimeReportList> = Vec::new();
+    for row in &rows {
+        match by_list.iter_mut().find(|list| list.owner_id == row.owner_id) {
+            Some(list) => list.seconds += row.seconds,
+            None => by_list.push(TimeReportList {
+                owner_id: row.owner_id,
+                username: row.username.clone(),
+                seconds: row.seconds,
+            }),
+        }
+    }
+    by_list.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.username.cmp(&b.username)));
+
+    TimeReport {
+        timezone: range.tz.name().to_string(),
+        from: range.from,
+        to: range.to,
+        total_seconds: rows.iter().map(|row| row.seconds).sum(),
+        by_day,
+        by_list,
+        rows,
+    }
+}
+
+/// The report's rows as CSV, one line per day and list, with a header.
+pub fn to_csv(report: &TimeReport) -> String {
+    let mut csv = String::from("date,owner_id,list,seconds,hours\r\n");
+    for row in &report.rows {
+        csv.push_str(&format!(
+            "{},{},{},{},{:.2}\r\n",
+            row.date,
+            row.owner_id,
+            csv_field(&row.username),
+            row.seconds,
+            row.seconds as f64 / 3600.0
+        ));
+    }
+    csv
+}
+
+/// Quotes a field holding a separator, quote or line break, doubling its
+/// quotes. One a spreadsheet would take for a formula is prefixed with `'`
+/// and quoted, so it opens as text.
+fn csv_field(value: &str) -> String {
+    if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("\"'{}\"", value.replace('"', "\"\""))
+    } else if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,104 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::Request,
+    http::{header, uri::Authority, StatusCode, Uri},
+    response::{IntoResponse, Redirect, Response},
+    Router,
+};
+use axum_server::tls_rustls::RustlsConfig;
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{info, warn};
+
+use crate::shutdown::Shutdown;
+
+/// Loads the certificate chain and private key. The resulting config
+/// advertises HTTP/2 and HTTP/1.1 over ALPN.
+pub async fn load(cert: &Path, key: &Path) -> Result<RustlsConfig> {
+    // Several crates in the tree enable rustls providers, so pick one
+    // explicitly; a second call finds it already installed.
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    RustlsConfig::from_pem_file(cert, key)
+        .await
+        .with_context(|| format!("Failed to load TLS certificate {} and key {}", cert.display(), key.display()))
+}
+
+/// Reloads `rustls` from disk whenever the certificate or key file changes,
+/// checking every `every` until shutdown. New connections use the new
+/// certificate; established ones keep theirs. A pair that fails to load
+/// (say, a key written before its certificate) is retried on the next check
+/// while the previous certificate stays in use.
+pub fn watch(rustls: RustlsConfig, cert: PathBuf, key: PathBuf, every: Duration, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        let mut ticker = interval(every);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut loaded = modified(&cert, &key);
+
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.requested() => return,
+            }
+
+            let current = modified(&cert, &key);
+            if current == loaded {
+                continue;
+            }
+
+            match rustls.reload_from_pem_file(&cert, &key).await {
+                Ok(()) => {
+                    info!("Reloaded TLS certificate from {}", cert.display());
+                    loaded = current;
+                }
+                Err(e) => warn!("Failed to reload TLS certificate, keeping the previous one: {}", e),
+            }
+        }
+    });
+}
+
+fn modified(cert: &Path, key: &Path) -> Option<(SystemTime, SystemTime)> {
+    let mtime = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+    Some((mtime(cert)?, mtime(key)?))
+}
+
+/// Plain-HTTP app answering every request with a permanent redirect to the
+/// same host and path on the HTTPS port.
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new().fallback(move |request: Request| async move { redirect(request, https_port) })
+}
+
+fn redirect(request: Request, https_port: u16) -> Response {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|host| host.parse::<Authority>().ok())
+        .or_else(|| request.uri().authority().cloned());
+    let Some(host) = host else {
+        return (StatusCode::BAD_REQUEST, "Missing Host header").into_response();
+    };
+
+    let authority = match https_port {
+        443 => host.host().to_string(),
+        port => format!("{}:{}", host.host(), port),
+    };
+    let path_and_query = request
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+
+    match Uri::builder()
+        .scheme("https")
+        .authority(authority)
+        .path_and_query(path_and_query)
+        .build()
+    {
+        Ok(uri) => Redirect::permanent(&uri.to_string()).into_response(),
+        Err(_) => (StatusCode::BAD_REQUEST, "Invalid Host header").into_response(),
+    }
+}
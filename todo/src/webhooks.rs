@@ -0,0 +1,411 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::{header, redirect, Client, ClientBuilder};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::{broadcast::error::RecvError, Notify},
+    task::JoinSet,
+    time::sleep,
+};
+use tracing::{debug, error, warn};
+use url::{Host, Url};
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    metrics::Metrics,
+    models::WebhookAttempt,
+    shutdown::Shutdown,
+    store::DynStore,
+};
+
+pub const ID_HEADER: &str = "x-webhook-id";
+pub const EVENT_HEADER: &str = "x-webhook-event";
+pub const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+
+/// Deliveries claimed per round; more are claimed straight away if full.
+const BATCH_SIZE: i64 = 32;
+
+/// How often delivered and dead deliveries past retention are deleted.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Longest error message kept per attempt.
+const MAX_ERROR_LEN: usize = 500;
+
+/// How deliveries are sent and retried.
+#[derive(Debug, Clone)]
+pub struct WebhookOptions {
+    /// Attempts before a delivery is marked dead.
+    pub max_attempts: u32,
+    /// Wait before the first retry; doubled for each retry after it.
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// Per-attempt request timeout.
+    pub timeout: Duration,
+    /// How often the queue is checked when nothing wakes the dispatcher.
+    pub poll_interval: Duration,
+    /// How long delivered and dead deliveries are kept for the logs.
+    pub retention: Duration,
+    /// Allow URLs on loopback, private and link-local addresses.
+    pub allow_private_targets: bool,
+}
+
+impl Default for WebhookOptions {
+    fn default() -> Self {
+        Self {
+            max_attempts: 8,
+            backoff_base: Duration::from_secs(30),
+            backoff_max: Duration::from_secs(6 * 3600),
+            timeout: Duration::from_secs(10),
+            poll_interval: Duration::from_secs(5),
+            retention: Duration::from_secs(7 * 24 * 3600),
+            allow_private_targets: false,
+        }
+    }
+}
+
+/// Handle shared by the handlers and the dispatcher.
+#[derive(Clone, Default)]
+pub struct Webhooks {
+    options: Arc<WebhookOptions>,
+    wake: Arc<Notify>,
+}
+
+impl Webhooks {
+    pub fn new(options: WebhookOptions) -> Self {
+        Self {
+            options: Arc::new(options),
+            wake: Arc::default(),
+        }
+    }
+
+    pub fn options(&self) -> &WebhookOptions {
+        &self.options
+    }
+
+    /// Tells the dispatcher new deliveries are waiting.
+    pub fn wake(&self) {
+        self.wake.notify_one();
+    }
+
+    /// Checks that `url` is one deliveries may be sent to. Host names are
+    /// checked again, once resolved, before each attempt, which then connects
+    /// to the addresses checked.
+    pub fn check_target(&self, url: &str) -> Result<(), AppError> {
+        let url = Url::parse(url)
+            .map_err(|_| AppError::invalid_field("url", "url", "Webhook URL must be a valid URL"))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(AppError::invalid_field("url", "scheme", "Webhook URL must use http or https"));
+        }
+        if self.options.allow_private_targets {
+            return Ok(());
+        }
+
+        let private = match url.host() {
+            Some(Host::Domain(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+            Some(Host::Ipv4(ip)) => is_private(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => is_private(IpAddr::V6(ip)),
+            None => true,
+        };
+        if private {
+            return Err(AppError::invalid_field(
+                "url",
+                "private_address",
+                "Webhook URL must not point at a private or local address",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A pending delivery claimed for sending, with its webhook's target.
+#[derive(Debug, Clone)]
+pub struct PendingDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub payload: Value,
+    /// Attempts made before this one.
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+/// Where a delivery stands after an attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryOutcome {
+    Delivered,
+    Retry { at: DateTime<Utc> },
+    Dead,
+}
+
+impl DeliveryOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            DeliveryOutcome::Delivered => "delivered",
+            DeliveryOutcome::Retry { .. } => "retry",
+            DeliveryOutcome::Dead => "dead",
+        }
+    }
+}
+
+/// A random signing secret.
+pub fn generate_secret() -> String {
+    format!("whsec_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// The `X-Webhook-Signature` value: `sha256=` and the hex HMAC-SHA256 of
+/// `"{timestamp}.{body}"` keyed with the webhook's secret.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// The JSON body sent for a delivery.
+pub fn envelope(delivery_id: Uuid, event: &str, payload: &Value) -> Value {
+    json!({
+        "id": delivery_id,
+        "type": event,
+        "data": payload,
+    })
+}
+
+/// Wait before retrying a delivery that has failed `attempts` times.
+pub fn backoff(options: &WebhookOptions, attempts: i32) -> Duration {
+    let doublings = u32::try_from(attempts.saturating_sub(1)).unwrap_or(0).min(30);
+    options
+        .backoff_base
+        .saturating_mul(1 << doublings)
+        .min(options.backoff_max)
+}
+
+/// Sends queued deliveries until shutdown: whenever a todo event is
+/// committed, [`Webhooks::wake`] is called, or `poll_interval` passes.
+///
+/// Claiming a delivery pushes its due time past the attempt's timeout, so a
+/// delivery whose instance dies mid-attempt is picked up again later, and
+/// instances sharing a database do not send the same delivery twice.
+pub fn spawn_dispatcher(store: DynStore, webhooks: Webhooks, metrics: Metrics, shutdown: Shutdown) {
+    tokio::spawn(async move {
+        let client = match client_builder().build() {
+            Ok(client) => client,
+            Err(e) => {
+                error!("Failed to build webhook client: {}", e);
+                return;
+            }
+        };
+        let mut live = match store.subscribe_todo_events().await {
+            Ok(live) => Some(live),
+            Err(e) => {
+                warn!("Webhook dispatcher cannot follow todo events, polling only: {}", e);
+                None
+            }
+        };
+        let options = webhooks.options().clone();
+        let mut last_pruned: Option<Instant> = None;
+
+        loop {
+            if last_pruned.is_none_or(|at| at.elapsed() >= PRUNE_INTERVAL) {
+                prune(&store, &options).await;
+                last_pruned = Some(Instant::now());
+            }
+
+            let claimed = dispatch(&store, &client, &options, &metrics).await;
+            if claimed >= BATCH_SIZE as usize {
+                continue;
+            }
+
+            // Any committed event may have queued deliveries
+            let event = async {
+                if let Some(live) = live.as_mut() {
+                    if !matches!(live.recv().await, Err(RecvError::Closed)) {
+                        return;
+                    }
+                }
+                std::future::pending::<()>().await
+            };
+            tokio::select! {
+                _ = sleep(options.poll_interval) => {}
+                _ = webhooks.wake.notified() => {}
+                _ = event => {}
+                _ = shutdown.requested() => return,
+            }
+        }
+    });
+}
+
+/// Claims one batch of due deliveries and sends them concurrently; returns
+/// how many were claimed.
+async fn dispatch(store: &DynStore, client: &Client, options: &WebhookOptions, metrics: &Metrics) -> usize {
+    let now = Utc::now();
+    let lease = chrono::Duration::from_std(options.timeout * 2).unwrap_or_else(|_| chrono::Duration::minutes(1));
+    let claimed = match store.claim_webhook_deliveries(now, now + lease, BATCH_SIZE).await {
+        Ok(claimed) => claimed,
+        Err(e) => {
+            error!("Failed to claim webhook deliveries: {}", e);
+            return 0;
+        }
+    };
+    let count = claimed.len();
+
+    let mut sending = JoinSet::new();
+    for delivery in claimed {
+        let (store, client, options, metrics) = (store.clone(), client.clone(), options.clone(), metrics.clone());
+        sending.spawn(async move {
+            let attempt = attempt(&client, &options, &delivery).await;
+            let outcome = if attempt.error.is_none() {
+                DeliveryOutcome::Delivered
+            } else if delivery.attempts + 1 >= options.max_attempts as i32 {
+                warn!(delivery_id = %delivery.id, webhook_id = %delivery.webhook_id, "Webhook delivery failed for good");
+                DeliveryOutcome::Dead
+            } else {
+                let wait = backoff(&options, delivery.attempts + 1);
+                let wait = chrono::Duration::from_std(wait).unwrap_or_else(|_| chrono::Duration::hours(1));
+                DeliveryOutcome::Retry { at: Utc::now() + wait }
+            };
+
+            metrics.webhook_deliveries.with_label_values(&[outcome.label()]).inc();
+            if let Err(e) = store.record_webhook_attempt(delivery.id, &attempt, outcome).await {
+                error!(delivery_id = %delivery.id, "Failed to record webhook attempt: {}", e);
+            }
+        });
+    }
+    while sending.join_next().await.is_some() {}
+
+    count
+}
+
+/// Sends one delivery; any answer but a 2xx is a failure.
+async fn attempt(client: &Client, options: &WebhookOptions, delivery: &PendingDelivery) -> WebhookAttempt {
+    let attempted_at = Utc::now();
+    let started = Instant::now();
+    let result = send(client, options, delivery).await;
+    let duration_ms = i64::try_from(started.elapsed().as_millis()).unwrap_or(i64::MAX);
+    debug!(delivery_id = %delivery.id, ?result, "Webhook attempt");
+
+    let (status_code, error) = match result {
+        Ok(status) if status.is_success() => (Some(i32::from(status.as_u16())), None),
+        Ok(status) => (Some(i32::from(status.as_u16())), Some(format!("Receiver responded with {}", status))),
+        Err(e) => (None, Some(e)),
+    };
+    WebhookAttempt {
+        attempted_at,
+        status_code,
+        error: error.map(|e| e.chars().take(MAX_ERROR_LEN).collect()),
+        duration_ms,
+    }
+}
+
+async fn send(
+    client: &Client,
+    options: &WebhookOptions,
+    delivery: &PendingDelivery,
+) -> Result<reqwest::StatusCode, String> {
+    let url = Url::parse(&delivery.url).map_err(|e| format!("Invalid URL: {}", e))?;
+    let pinned;
+    let client = match url.host() {
+        _ if options.allow_private_targets => client,
+        Some(Host::Domain(domain)) => {
+            // Connect to the addresses checked, not to a second lookup's
+            let addresses = check_resolved(domain, url.port_or_known_default().unwrap_or(443)).await?;
+            pinned = client_builder()
+                .resolve_to_addrs(domain, &addresses)
+                .build()
+                .map_err(|e| format!("Could not build client: {}", e))?;
+            &pinned
+        }
+        Some(Host::Ipv4(ip)) if !is_private(IpAddr::V4(ip)) => client,
+        Some(Host::Ipv6(ip)) if !is_private(IpAddr::V6(ip)) => client,
+        _ => return Err("URL points at a private or local address".to_string()),
+    };
+
+    let body = serde_json::to_vec(&envelope(delivery.id, &delivery.event, &delivery.payload))
+        .map_err(|e| e.to_string())?;
+    let timestamp = Utc::now().timestamp();
+    let response = client
+        .post(url)
+        .timeout(options.timeout)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(ID_HEADER, delivery.id.to_string())
+        .header(EVENT_HEADER, &delivery.event)
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, sign(&delivery.secret, timestamp, &body))
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| e.without_url().to_string())?;
+
+    Ok(response.status())
+}
+
+/// Settings shared by every client deliveries are sent with.
+fn client_builder() -> ClientBuilder {
+    Client::builder()
+        .user_agent(concat!(env!("CARGO_PKG_NAME"), "-webhooks/", env!("CARGO_PKG_VERSION")))
+        .redirect(redirect::Policy::none())
+}
+
+/// Resolves `host`, failing if any of its addresses is private or local.
+async fn check_resolved(host: &str, port: u16) -> Result<Vec<SocketAddr>, String> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| format!("Could not resolve {}: {}", host, e))?
+        .collect();
+    if addresses.is_empty() {
+        return Err(format!("{} has no addresses", host));
+    }
+    if addresses.iter().any(|address| is_private(address.ip())) {
+        return Err(format!("{} resolves to a private or local address", host));
+    }
+    Ok(addresses)
+}
+
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_private_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_private_v4(ip),
+            None => is_private_v6(ip),
+        },
+    }
+}
+
+fn is_private_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // Carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+}
+
+fn is_private_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // Unique local, fc00::/7, and link-local, fe80::/10
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+}
+
+async fn prune(store: &DynStore, options: &WebhookOptions) {
+    let Ok(retention) = chrono::Duration::from_std(options.retention) else {
+        return;
+    };
+    match store.prune_webhook_deliveries(Utc::now() - retention).await {
+        Ok(pruned) => debug!("Pruned {} webhook deliveries", pruned),
+        Err(e) => error!("Failed to prune webhook deliveries: {}", e),
+    }
+}
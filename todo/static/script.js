@@ -15,15 +15,32 @@ class TodoApp {
         document.getElementById('login-form-element').addEventListener('submit', (e) => this.handleLogin(e));
         document.getElementById('register-form-element').addEventListener('submit', (e) => this.handleRegister(e));
         document.getElementById('logout-btn').addEventListener('click', () => this.handleLogout());
+        document.getElementById('login-tab').addEventListener('click', () => this.showLogin());
+        document.getElementById('register-tab').addEventListener('click', () => this.showRegister());
 
         // Todo form events
         document.getElementById('add-todo-form').addEventListener('submit', (e) => this.handleAddTodo(e));
+
+        // Todo item events, delegated because the list is re-rendered
+        const todoList = document.getElementById('todo-list');
+        todoList.addEventListener('change', (e) => {
+            if (e.target.classList.contains('todo-checkbox')) {
+                this.toggleTodo(e.target.closest('.todo-item').dataset.id, e.target.checked);
+            }
+        });
+        todoList.addEventListener('click', (e) => {
+            const button = e.target.closest('[data-action="delete"]');
+            if (button) {
+                this.deleteTodo(button.closest('.todo-item').dataset.id);
+            }
+        });
     }
 
     checkAuth() {
         if (this.token && this.user) {
             this.showTodoSection();
             this.loadTodos();
+            this.watchTodos();
         } else {
             this.showAuthSection();
         }
@@ -80,10 +97,11 @@ class TodoApp {
                 this.showMessage('Login successful!', 'success');
                 this.showTodoSection();
                 this.loadTodos();
+                this.watchTodos();
                 e.target.reset();
             } else {
                 const error = await response.json();
-                this.showMessage(error.error || 'Login failed', 'error');
+                this.showMessage(this.problemMessage(error, 'Login failed'), 'error');
             }
         } catch (error) {
             this.showMessage('Network error occurred', 'error');
@@ -117,10 +135,11 @@ class TodoApp {
                 this.showMessage('Registration successful!', 'success');
                 this.showTodoSection();
                 this.loadTodos();
+                this.watchTodos();
                 e.target.reset();
             } else {
                 const error = await response.json();
-                this.showMessage(error.error || 'Registration failed', 'error');
+                this.showMessage(this.problemMessage(error, 'Registration failed'), 'error');
             }
         } catch (error) {
             this.showMessage('Network error occurred', 'error');
@@ -128,6 +147,7 @@ class TodoApp {
     }
 
     handleLogout() {
+        this.stopWatching();
         this.token = null;
         this.user = null;
         localStorage.removeItem('token');
@@ -180,7 +200,7 @@ class TodoApp {
                 e.target.reset();
             } else {
                 const error = await response.json();
-                this.showMessage(error.error || 'Failed to add todo', 'error');
+                this.showMessage(this.problemMessage(error, 'Failed to add todo'), 'error');
             }
         } catch (error) {
             this.showMessage('Network error occurred', 'error');
@@ -206,6 +226,66 @@ class TodoApp {
         }
     }
 
+    // Keeps a WebSocket to /api/ws open while logged in: the list reloads
+    // whenever a todo changes, from this tab or any other, and completions
+    // are sent over it. Browsers cannot set headers on WebSockets, so the
+    // token goes in the query string.
+    watchTodos() {
+        this.stopWatching();
+        const scheme = location.protocol === 'https:' ? 'wss:' : 'ws:';
+        const socket = new WebSocket(`${scheme}//${location.host}/api/ws?access_token=${encodeURIComponent(this.token)}`);
+        this.socket = socket;
+
+        socket.addEventListener('message', (e) => {
+            const message = JSON.parse(e.data);
+            switch (message.type) {
+                case 'event':
+                case 'resync':
+                    this.loadTodos();
+                    break;
+                case 'presence':
+                    this.renderPresence(message.viewers);
+                    break;
+                case 'ack':
+                    this.showMessage(`Todo ${message.todo.completed ? 'completed' : 'marked as pending'}!`, 'success');
+                    break;
+                case 'error':
+                    this.showMessage(this.problemMessage(message.error, 'Failed to update todo'), 'error');
+                    this.loadTodos(); // Reload to reset checkbox state
+                    break;
+            }
+        });
+        socket.addEventListener('close', () => {
+            if (this.socket !== socket) {
+                return;
+            }
+            this.socket = null;
+            this.renderPresence([]);
+            // Reconnect after a pause and catch up on what was missed
+            setTimeout(() => {
+                if (this.token && !this.socket) {
+                    this.loadTodos();
+                    this.watchTodos();
+                }
+            }, 3000);
+        });
+    }
+
+    stopWatching() {
+        const socket = this.socket;
+        this.socket = null;
+        if (socket) {
+            socket.close();
+        }
+        this.renderPresence([]);
+    }
+
+    renderPresence(viewers) {
+        const others = viewers.length - 1;
+        document.getElementById('presence-display').textContent =
+            others > 0 ? `Open in ${others} other ${others === 1 ? 'window' : 'windows'}` : '';
+    }
+
     renderTodos(todos) {
         const todoList = document.getElementById('todo-list');
         
@@ -223,15 +303,14 @@ class TodoApp {
             <div class="todo-item ${todo.completed ? 'completed' : ''} ${todo.scheduled_for ? 'scheduled' : 'unscheduled'}" data-id="${todo.id}">
                 <div class="todo-header">
                     <div class="todo-title">
-                        <input type="checkbox" class="todo-checkbox" ${todo.completed ? 'checked' : ''}
-                               onchange="app.toggleTodo('${todo.id}', this.checked)">
+                        <input type="checkbox" class="todo-checkbox" ${todo.completed ? 'checked' : ''}>
                         <div class="todo-title-content">
                             <h4>${this.escapeHtml(todo.title)}</h4>
                             ${todo.scheduled_for ? `<div class="todo-schedule">📅 Scheduled for: ${this.formatScheduledDate(todo.scheduled_for)}</div>` : ''}
                         </div>
                     </div>
                     <div class="todo-actions">
-                        <button class="btn btn-danger" onclick="app.deleteTodo('${todo.id}')">Delete</button>
+                        <button class="btn btn-danger" data-action="delete">Delete</button>
                     </div>
                 </div>
                 ${todo.description ? `<div class="todo-description">${this.escapeHtml(todo.description)}</div>` : ''}
@@ -250,6 +329,11 @@ class TodoApp {
     }
 
     async toggleTodo(todoId, completed) {
+        if (this.socket && this.socket.readyState === WebSocket.OPEN) {
+            this.socket.send(JSON.stringify({ type: 'complete', todo_id: todoId, completed }));
+            return;
+        }
+
         try {
             // Get the current todo to preserve scheduled_for when updating
             const currentTodos = await this.getCurrentTodos();
@@ -274,7 +358,7 @@ class TodoApp {
                 this.showMessage(`Todo ${completed ? 'completed' : 'marked as pending'}!`, 'success');
             } else {
                 const error = await response.json();
-                this.showMessage(error.error || 'Failed to update todo', 'error');
+                this.showMessage(this.problemMessage(error, 'Failed to update todo'), 'error');
                 this.loadTodos(); // Reload to reset checkbox state
             }
         } catch (error) {
@@ -317,13 +401,23 @@ class TodoApp {
                 this.loadTodos();
             } else {
                 const error = await response.json();
-                this.showMessage(error.error || 'Failed to delete todo', 'error');
+                this.showMessage(this.problemMessage(error, 'Failed to delete todo'), 'error');
             }
         } catch (error) {
             this.showMessage('Network error occurred', 'error');
         }
     }
 
+    // Error responses are RFC 7807 problem details; prefer the first
+    // field-level message, e.g. "title: must be between 1 and 255 characters".
+    problemMessage(problem, fallback) {
+        if (problem && Array.isArray(problem.errors) && problem.errors.length > 0) {
+            const first = problem.errors[0];
+            return `${first.field}: ${first.message}`;
+        }
+        return (problem && problem.detail) || fallback;
+    }
+
     showMessage(message, type) {
         const messageEl = document.getElementById('message');
         messageEl.textContent = message;
@@ -374,14 +468,5 @@ class TodoApp {
     }
 }
 
-// Global functions for HTML onclick handlers
-function showLogin() {
-    app.showLogin();
-}
-
-function showRegister() {
-    app.showRegister();
-}
-
 // Initialize the app
 const app = new TodoApp();
\ No newline at end of file
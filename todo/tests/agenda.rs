@@ -0,0 +1,174 @@
+mod common;
+
+use chrono::{Days, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{TestApp, TestUser};
+use todo_service::calendar;
+
+fn titles(todos: &Value) -> Vec<&str> {
+    todos.as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect()
+}
+
+/// The user's today, as the server works it out.
+fn today_in(timezone: &str) -> NaiveDate {
+    calendar::today(timezone.parse().unwrap(), Utc::now())
+}
+
+async fn set_timezone(app: &TestApp, user: &TestUser, timezone: &str) {
+    let response = app.put_json("/api/preferences", &user.token, &json!({ "timezone": timezone })).await;
+    assert_eq!(response.status(), 200);
+}
+
+#[test]
+fn days_clocks_change_on_are_shorter_or_longer() {
+    let new_york: Tz = "America/New_York".parse().unwrap();
+    let date = |month, day| NaiveDate::from_ymd_opt(2026, month, day).unwrap();
+    let utc = |month, day, hour| Utc.with_ymd_and_hms(2026, month, day, hour, 0, 0).unwrap();
+
+    assert_eq!(calendar::day_bounds(new_york, date(3, 8)), (utc(3, 8, 5), utc(3, 9, 4)));
+    assert_eq!(calendar::day_bounds(new_york, date(11, 1)), (utc(11, 1, 4), utc(11, 2, 5)));
+    assert_eq!(calendar::day_bounds(new_york, date(7, 1)), (utc(7, 1, 4), utc(7, 2, 4)));
+
+    // Chile moves its clocks at midnight, so 6 September starts at 1:00
+    let santiago: Tz = "America/Santiago".parse().unwrap();
+    assert_eq!(calendar::day_bounds(santiago, date(9, 6)).0, utc(9, 6, 4));
+}
+
+#[test]
+fn locales_are_checked_and_cased() {
+    for (tag, expected) in [("en-us", "en-US"), ("fr", "fr"), ("zh_hant_tw", "zh-Hant-TW"), ("es-419", "es-419")] {
+        assert_eq!(calendar::normalize_locale(tag).as_deref(), Some(expected), "{}", tag);
+    }
+    for tag in ["", "english", "e", "en-USA", "en-US-x", "12"] {
+        assert_eq!(calendar::normalize_locale(tag), None, "{}", tag);
+    }
+}
+
+#[tokio::test]
+async fn preferences_default_to_utc_and_can_be_changed() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let preferences: Value = app.get("/api/preferences", &user.token).await.json().await.unwrap();
+    assert_eq!(preferences, json!({ "timezone": "UTC", "locale": "en-US" }));
+
+    let response = app
+        .put_json("/api/preferences", &user.token, &json!({ "timezone": "Europe/Paris", "locale": "fr-fr" }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let preferences: Value = response.json().await.unwrap();
+    assert_eq!(preferences, json!({ "timezone": "Europe/Paris", "locale": "fr-FR" }));
+
+    // Omitted fields are left alone
+    set_timezone(&app, &user, "Asia/Tokyo").await;
+    let preferences: Value = app.get("/api/preferences", &user.token).await.json().await.unwrap();
+    assert_eq!(preferences, json!({ "timezone": "Asia/Tokyo", "locale": "fr-FR" }));
+
+    let response = app
+        .post_json("/api/auth/login", None, &json!({ "username": user.username, "password": "password123" }))
+        .await;
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["user"]["timezone"], "Asia/Tokyo");
+    assert_eq!(body["user"]["locale"], "fr-FR");
+
+    for (body, field, code) in [
+        (json!({ "timezone": "Mars/Olympus" }), "timezone", "unknown_timezone"),
+        (json!({ "locale": "not a locale" }), "locale", "invalid_locale"),
+    ] {
+        let response = app.put_json("/api/preferences", &user.token, &body).await;
+        assert_eq!(response.status(), 400);
+        let problem: Value = response.json().await.unwrap();
+        assert_eq!(problem["errors"][0]["field"], field);
+        assert_eq!(problem["errors"][0]["code"], code);
+    }
+}
+
+#[tokio::test]
+async fn dates_without_a_time_are_due_at_the_end_of_the_users_day() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    // Fourteen hours ahead of UTC, so often already tomorrow there
+    let timezone = "Pacific/Kiritimati";
+    set_timezone(&app, &user, timezone).await;
+    let today = today_in(timezone);
+
+    let todo = app.create_todo(&user.token, json!({ "title": "Today", "scheduled_for": today })).await;
+    let due = calendar::local_to_utc(timezone.parse().unwrap(), today.and_time(calendar::end_of_day()));
+    assert_eq!(todo["scheduled_for"].as_str().unwrap().parse::<chrono::DateTime<Utc>>().unwrap(), due);
+
+    let yesterday = today - Days::new(1);
+    let response = app
+        .post_json("/api/todos", Some(&user.token), &json!({ "title": "Too late", "scheduled_for": yesterday }))
+        .await;
+    assert_eq!(response.status(), 400);
+    let problem: Value = response.json().await.unwrap();
+    assert_eq!(problem["errors"][0]["code"], "not_in_past");
+
+    let path = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    let response = app.put_json(&path, &user.token, &json!({ "scheduled_for": yesterday })).await;
+    assert_eq!(response.status(), 400);
+
+    let response = app
+        .post_json("/api/todos", Some(&user.token), &json!({ "title": "Someday", "scheduled_for": "soon" }))
+        .await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn agenda_views_split_todos_by_the_users_days() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let timezone = "Asia/Tokyo";
+    set_timezone(&app, &user, timezone).await;
+    let today = today_in(timezone);
+
+    // Only sync accepts times already past, as offline edits
+    let three_days_ago = Utc::now() - chrono::Duration::days(3);
+    let response = app
+        .post_json(
+            "/api/sync",
+            Some(&user.token),
+            &json!({ "mutations": [{
+                "id": Uuid::new_v4(),
+                "op": "upsert",
+                "changed_at": Utc::now(),
+                "title": "Overdue",
+                "scheduled_for": three_days_ago,
+            }] }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+
+    app.create_todo(&user.token, json!({ "title": "Today", "scheduled_for": today })).await;
+    app.create_todo(&user.token, json!({ "title": "Tomorrow", "scheduled_for": today + Days::new(1) })).await;
+    app.create_todo(&user.token, json!({ "title": "In three days", "scheduled_for": today + Days::new(3) })).await;
+    app.create_todo(&user.token, json!({ "title": "Next month", "scheduled_for": today + Days::new(30) })).await;
+    app.create_todo(&user.token, json!({ "title": "Whenever" })).await;
+    app.create_todo(&user.token, json!({ "title": "Done", "scheduled_for": today, "completed": true })).await;
+
+    let agenda: Value = app.get("/api/todos/today", &user.token).await.json().await.unwrap();
+    assert_eq!(agenda["date"], today.to_string());
+    assert_eq!(titles(&agenda["todos"]), ["Today"]);
+
+    let overdue: Value = app.get("/api/todos/overdue", &user.token).await.json().await.unwrap();
+    assert_eq!(titles(&overdue), ["Overdue"]);
+
+    let upcoming: Value = app.get("/api/todos/upcoming?days=3", &user.token).await.json().await.unwrap();
+    let days = upcoming.as_array().unwrap();
+    assert_eq!(days.len(), 3);
+    assert_eq!(days[0]["date"], (today + Days::new(1)).to_string());
+    assert_eq!(titles(&days[0]["todos"]), ["Tomorrow"]);
+    assert_eq!(titles(&days[1]["todos"]), Vec::<&str>::new());
+    assert_eq!(titles(&days[2]["todos"]), ["In three days"]);
+
+    let upcoming: Value = app.get("/api/todos/upcoming", &user.token).await.json().await.unwrap();
+    assert_eq!(upcoming.as_array().unwrap().len(), 7);
+
+    for days in [0, 91] {
+        let response = app.get(&format!("/api/todos/upcoming?days={}", days), &user.token).await;
+        assert_eq!(response.status(), 400);
+    }
+}
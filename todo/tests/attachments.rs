@@ -0,0 +1,207 @@
+mod common;
+
+use reqwest::{
+    header,
+    multipart::{Form, Part},
+    Response,
+};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use common::{test_attachment_options, test_blobs, TestApp, TestUser};
+use todo_service::attachments::{self, AttachmentOptions};
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n0123456789abcdef";
+
+async fn upload(app: &TestApp, user: &TestUser, todo_id: &str, filename: &str, content_type: &str, data: &[u8]) -> Response {
+    let part = Part::bytes(data.to_vec())
+        .file_name(filename.to_string())
+        .mime_str(content_type)
+        .unwrap();
+    app.client
+        .post(app.url(&format!("/api/todos/{}/attachments", todo_id)))
+        .bearer_auth(&user.token)
+        .multipart(Form::new().part("file", part))
+        .send()
+        .await
+        .expect("request failed")
+}
+
+async fn attach(app: &TestApp, user: &TestUser, todo_id: &str, filename: &str, content_type: &str, data: &[u8]) -> Value {
+    let response = upload(app, user, todo_id, filename, content_type, data).await;
+    assert_eq!(response.status(), 201, "upload failed");
+    response.json().await.unwrap()
+}
+
+async fn download(app: &TestApp, user: &TestUser, path: &str, range: Option<&str>) -> Response {
+    let mut request = app.client.get(app.url(path)).bearer_auth(&user.token);
+    if let Some(range) = range {
+        request = request.header(header::RANGE, range);
+    }
+    request.send().await.expect("request failed")
+}
+
+async fn error_code(response: Response) -> String {
+    let body: Value = response.json().await.unwrap();
+    body["code"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn files_upload_and_download_in_ranges() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Expenses" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+
+    let attachment = attach(&app, &user, todo_id, "../../receipt é.png", "image/png", PNG).await;
+    assert_eq!(attachment["filename"], "receipt é.png");
+    assert_eq!(attachment["content_type"], "image/png");
+    assert_eq!(attachment["size_bytes"], PNG.len());
+    assert_eq!(attachment["uploaded_by"]["username"], user.username.as_str());
+
+    let listed: Vec<Value> = app
+        .get(&format!("/api/todos/{}/attachments", todo_id), &user.token)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["id"], attachment["id"]);
+
+    let path = format!("/api/todos/{}/attachments/{}", todo_id, attachment["id"].as_str().unwrap());
+    let response = download(&app, &user, &path, None).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "image/png");
+    assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+    let disposition = response.headers()[header::CONTENT_DISPOSITION].to_str().unwrap().to_string();
+    assert!(disposition.starts_with("attachment;"), "{}", disposition);
+    assert!(disposition.contains("filename*=UTF-8''receipt%20%C3%A9.png"), "{}", disposition);
+    assert_eq!(response.bytes().await.unwrap(), PNG);
+
+    for (range, expected, content_range) in [
+        ("bytes=8-11", &b"0123"[..], "bytes 8-11/24"),
+        ("bytes=20-", b"cdef", "bytes 20-23/24"),
+        ("bytes=-4", b"cdef", "bytes 20-23/24"),
+        ("bytes=20-99", b"cdef", "bytes 20-23/24"),
+    ] {
+        let response = download(&app, &user, &path, Some(range)).await;
+        assert_eq!(response.status(), 206, "{}", range);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], content_range);
+        assert_eq!(response.bytes().await.unwrap(), expected, "{}", range);
+    }
+
+    let response = download(&app, &user, &path, Some("bytes=24-")).await;
+    assert_eq!(response.status(), 416);
+    assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */24");
+
+    // Several ranges are answered with the whole file
+    let response = download(&app, &user, &path, Some("bytes=0-1,4-5")).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.bytes().await.unwrap(), PNG);
+}
+
+#[tokio::test]
+async fn uploads_are_limited_by_size_type_and_quota() {
+    let app = TestApp::spawn_with_attachment_options(AttachmentOptions {
+        max_size: 32,
+        quota: 64,
+        allowed_types: vec!["image/png".to_string(), "text/*".to_string()],
+        ..test_attachment_options()
+    })
+    .await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Paperwork" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+
+    let response = upload(&app, &user, todo_id, "big.txt", "text/plain", &[b'a'; 33]).await;
+    assert_eq!(response.status(), 413);
+    assert_eq!(error_code(response).await, "payload_too_large");
+
+    let response = upload(&app, &user, todo_id, "doc.pdf", "application/pdf", b"%PDF-1.7").await;
+    assert_eq!(response.status(), 415);
+    assert_eq!(error_code(response).await, "unsupported_media_type");
+
+    // The contents must match the declared type
+    let response = upload(&app, &user, todo_id, "fake.png", "image/png", b"<svg onload=alert(1)>").await;
+    assert_eq!(response.status(), 415);
+
+    let response = upload(&app, &user, todo_id, "empty.txt", "text/plain", b"").await;
+    assert_eq!(response.status(), 400);
+
+    let first = attach(&app, &user, todo_id, "a.txt", "text/plain", &[b'a'; 30]).await;
+    attach(&app, &user, todo_id, "b.csv", "text/csv; charset=utf-8", &[b'b'; 30]).await;
+    let response = upload(&app, &user, todo_id, "c.txt", "text/plain", &[b'c'; 5]).await;
+    assert_eq!(response.status(), 413);
+    assert_eq!(error_code(response).await, "quota_exceeded");
+
+    let usage: Value = app.get("/api/attachments/usage", &user.token).await.json().await.unwrap();
+    assert_eq!(usage["used_bytes"], 60);
+    assert_eq!(usage["quota_bytes"], 64);
+    assert_eq!(usage["max_size_bytes"], 32);
+
+    // Deleting frees the space straight away
+    let path = format!("/api/todos/{}/attachments/{}", todo_id, first["id"].as_str().unwrap());
+    assert_eq!(app.delete(&path, &user.token).await.status(), 204);
+    attach(&app, &user, todo_id, "c.txt", "text/plain", &[b'c'; 5]).await;
+    let usage: Value = app.get("/api/attachments/usage", &user.token).await.json().await.unwrap();
+    assert_eq!(usage["used_bytes"], 35);
+}
+
+#[tokio::test]
+async fn editors_upload_viewers_download_and_owners_moderate() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let editor = app.create_user().await;
+    let viewer = app.create_user().await;
+    let stranger = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Shared" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    for (grantee, role) in [(&editor, "editor"), (&viewer, "viewer")] {
+        let response = app
+            .post_json(
+                "/api/shares",
+                Some(&owner.token),
+                &json!({ "username": grantee.username, "role": role, "todo_id": todo_id }),
+            )
+            .await;
+        assert_eq!(response.status(), 201);
+    }
+
+    assert_eq!(upload(&app, &viewer, todo_id, "v.png", "image/png", PNG).await.status(), 403);
+    assert_eq!(upload(&app, &stranger, todo_id, "s.png", "image/png", PNG).await.status(), 404);
+    let attachment = attach(&app, &editor, todo_id, "e.png", "image/png", PNG).await;
+    let path = format!("/api/todos/{}/attachments/{}", todo_id, attachment["id"].as_str().unwrap());
+
+    assert_eq!(download(&app, &viewer, &path, None).await.status(), 200);
+    assert_eq!(download(&app, &stranger, &path, None).await.status(), 404);
+    let other = app.create_todo(&owner.token, json!({ "title": "Other" })).await;
+    let elsewhere = format!("/api/todos/{}/attachments/{}", other["id"].as_str().unwrap(), attachment["id"].as_str().unwrap());
+    assert_eq!(download(&app, &owner, &elsewhere, None).await.status(), 404);
+
+    assert_eq!(app.delete(&path, &viewer.token).await.status(), 403);
+    assert_eq!(app.delete(&path, &owner.token).await.status(), 204);
+    assert_eq!(download(&app, &owner, &path, None).await.status(), 404);
+}
+
+#[tokio::test]
+async fn deleting_a_todo_deletes_its_files() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Temporary" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    let attachment = attach(&app, &user, todo_id, "scan.png", "image/png", PNG).await;
+    let key = attachments::blob_key(attachment["id"].as_str().unwrap().parse().unwrap());
+    assert!(test_blobs().contains(&key));
+
+    assert_eq!(app.delete(&format!("/api/todos/{}", todo_id), &user.token).await.status(), 204);
+    let usage: Value = app.get("/api/attachments/usage", &user.token).await.json().await.unwrap();
+    assert_eq!(usage["used_bytes"], 0);
+
+    for _ in 0..50 {
+        if !test_blobs().contains(&key) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("the file of a deleted todo was not cleaned up");
+}
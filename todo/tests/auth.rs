@@ -0,0 +1,168 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{unique_username, TestApp, TEST_JWT_SECRET};
+use todo_service::models::Claims;
+
+fn sign(claims: &Claims, secret: &str) -> String {
+    encode(&Header::default(), claims, &EncodingKey::from_secret(secret.as_ref())).unwrap()
+}
+
+fn claims_for(sub: String, expires_in: Duration) -> Claims {
+    Claims {
+        sub,
+        username: "someone".to_string(),
+        exp: (Utc::now() + expires_in).timestamp() as usize,
+    }
+}
+
+#[tokio::test]
+async fn register_returns_token_and_user_without_password() {
+    let app = TestApp::spawn().await;
+    let username = unique_username();
+
+    let response = app.register(&username).await;
+
+    assert_eq!(response.status(), 201);
+    let body: Value = response.json().await.unwrap();
+    assert!(body["token"].as_str().is_some_and(|t| !t.is_empty()));
+    assert_eq!(body["user"]["username"], username);
+    assert!(body["user"].get("password_hash").is_none());
+}
+
+#[tokio::test]
+async fn register_rejects_duplicate_username() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let response = app
+        .post_json(
+            "/api/auth/register",
+            None,
+            &json!({
+                "username": user.username,
+                "email": "another@example.com",
+                "password": "password123",
+            }),
+        )
+        .await;
+
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn register_rejects_invalid_input() {
+    let app = TestApp::spawn().await;
+
+    for body in [
+        json!({ "username": "ab", "email": "ab@example.com", "password": "password123" }),
+        json!({ "username": unique_username(), "email": "not-an-email", "password": "password123" }),
+        json!({ "username": unique_username(), "email": "short@example.com", "password": "123" }),
+    ] {
+        let response = app.post_json("/api/auth/register", None, &body).await;
+        assert_eq!(response.status(), 400, "accepted {}", body);
+    }
+}
+
+#[tokio::test]
+async fn login_returns_token_for_valid_credentials() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let response = app
+        .post_json(
+            "/api/auth/login",
+            None,
+            &json!({ "username": user.username, "password": "password123" }),
+        )
+        .await;
+
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    let token = body["token"].as_str().unwrap();
+
+    let response = app.get("/api/todos", token).await;
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn login_rejects_wrong_password_and_unknown_user() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let wrong_password = app
+        .post_json(
+            "/api/auth/login",
+            None,
+            &json!({ "username": user.username, "password": "wrong-password" }),
+        )
+        .await;
+    assert_eq!(wrong_password.status(), 401);
+
+    let unknown_user = app
+        .post_json(
+            "/api/auth/login",
+            None,
+            &json!({ "username": unique_username(), "password": "password123" }),
+        )
+        .await;
+    assert_eq!(unknown_user.status(), 401);
+}
+
+#[tokio::test]
+async fn protected_routes_require_bearer_token() {
+    let app = TestApp::spawn().await;
+
+    let missing = app.client.get(app.url("/api/todos")).send().await.unwrap();
+    assert_eq!(missing.status(), 401);
+
+    let wrong_scheme = app
+        .client
+        .get(app.url("/api/todos"))
+        .header("Authorization", "Basic dXNlcjpwYXNz")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(wrong_scheme.status(), 401);
+}
+
+#[tokio::test]
+async fn rejects_malformed_and_foreign_tokens() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let garbage = app.get("/api/todos", "not-a-jwt").await;
+    assert_eq!(garbage.status(), 401);
+
+    let foreign = sign(&claims_for(user.id.to_string(), Duration::hours(1)), "some-other-secret");
+    let response = app.get("/api/todos", &foreign).await;
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn rejects_expired_token() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let expired = sign(&claims_for(user.id.to_string(), Duration::hours(-2)), TEST_JWT_SECRET);
+    let response = app.get("/api/todos", &expired).await;
+
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn rejects_token_for_unknown_or_invalid_subject() {
+    let app = TestApp::spawn().await;
+
+    let unknown = sign(&claims_for(Uuid::new_v4().to_string(), Duration::hours(1)), TEST_JWT_SECRET);
+    let response = app.get("/api/todos", &unknown).await;
+    assert_eq!(response.status(), 401);
+
+    let invalid = sign(&claims_for("not-a-uuid".to_string(), Duration::hours(1)), TEST_JWT_SECRET);
+    let response = app.get("/api/todos", &invalid).await;
+    assert_eq!(response.status(), 401);
+}
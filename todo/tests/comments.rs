@@ -0,0 +1,200 @@
+mod common;
+
+use serde_json::{json, Value};
+
+use common::{TestApp, TestUser};
+
+async fn comment(app: &TestApp, user: &TestUser, todo_id: &str, body: &str) -> Value {
+    let response = app
+        .post_json(&format!("/api/todos/{}/comments", todo_id), Some(&user.token), &json!({ "body": body }))
+        .await;
+    assert_eq!(response.status(), 201, "commenting failed");
+    response.json().await.unwrap()
+}
+
+async fn share(app: &TestApp, owner: &TestUser, grantee: &TestUser, role: &str) {
+    let response = app
+        .post_json("/api/shares", Some(&owner.token), &json!({ "username": grantee.username, "role": role }))
+        .await;
+    assert_eq!(response.status(), 201, "sharing failed");
+}
+
+async fn mentions(app: &TestApp, user: &TestUser, query: &str) -> Vec<Value> {
+    let response = app.get(&format!("/api/mentions{}", query), &user.token).await;
+    assert_eq!(response.status(), 200);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn comments_render_markdown_and_keep_their_history() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Discuss" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+
+    let created = comment(&app, &user, todo_id, "**Bold** <script>alert(1)</script>[link](javascript:alert(1))").await;
+    assert_eq!(created["todo_id"], todo_id);
+    assert_eq!(created["author"]["username"], user.username.as_str());
+    assert!(created["edited_at"].is_null());
+    let html = created["body_html"].as_str().unwrap();
+    assert!(html.contains("<strong>Bold</strong>"), "{}", html);
+    assert!(!html.contains("<script"), "{}", html);
+    assert!(!html.contains("javascript:"), "{}", html);
+
+    let path = format!("/api/todos/{}/comments/{}", todo_id, created["id"].as_str().unwrap());
+    let response = app.put_json(&path, &user.token, &json!({ "body": "Second thoughts" })).await;
+    assert_eq!(response.status(), 200);
+    let edited: Value = response.json().await.unwrap();
+    assert_eq!(edited["body"], "Second thoughts");
+    assert!(edited["edited_at"].is_string());
+    assert_eq!(edited["created_at"], created["created_at"]);
+
+    let history: Vec<Value> = app.get(&format!("{}/history", path), &user.token).await.json().await.unwrap();
+    let bodies: Vec<&str> = history.iter().map(|r| r["body"].as_str().unwrap()).collect();
+    assert_eq!(bodies, [created["body"].as_str().unwrap(), "Second thoughts"]);
+
+    comment(&app, &user, todo_id, "Another").await;
+    let listed: Vec<Value> = app
+        .get(&format!("/api/todos/{}/comments", todo_id), &user.token)
+        .await
+        .json()
+        .await
+        .unwrap();
+    let bodies: Vec<&str> = listed.iter().map(|c| c["body"].as_str().unwrap()).collect();
+    assert_eq!(bodies, ["Second thoughts", "Another"]);
+
+    let response = app
+        .post_json(&format!("/api/todos/{}/comments", todo_id), Some(&user.token), &json!({ "body": "" }))
+        .await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn anyone_who_can_see_a_todo_comments_but_only_authors_edit() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let viewer = app.create_user().await;
+    let stranger = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Shared" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    share(&app, &owner, &viewer, "viewer").await;
+
+    let response = app
+        .post_json(&format!("/api/todos/{}/comments", todo_id), Some(&stranger.token), &json!({ "body": "Hi" }))
+        .await;
+    assert_eq!(response.status(), 404);
+
+    let from_viewer = comment(&app, &viewer, todo_id, "Viewers can talk").await;
+    let from_owner = comment(&app, &owner, todo_id, "So can owners").await;
+    let viewer_path = format!("/api/todos/{}/comments/{}", todo_id, from_viewer["id"].as_str().unwrap());
+    let owner_path = format!("/api/todos/{}/comments/{}", todo_id, from_owner["id"].as_str().unwrap());
+
+    assert_eq!(app.get(&viewer_path, &stranger.token).await.status(), 404);
+    let response = app.put_json(&owner_path, &viewer.token, &json!({ "body": "Hijacked" })).await;
+    assert_eq!(response.status(), 403);
+    let response = app.put_json(&viewer_path, &owner.token, &json!({ "body": "Hijacked" })).await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(app.delete(&owner_path, &viewer.token).await.status(), 403);
+
+    // Owners moderate their todos
+    assert_eq!(app.delete(&viewer_path, &owner.token).await.status(), 204);
+    assert_eq!(app.get(&viewer_path, &owner.token).await.status(), 404);
+
+    // Comments go with their todo
+    assert_eq!(app.delete(&format!("/api/todos/{}", todo_id), &owner.token).await.status(), 204);
+    assert_eq!(app.get(&owner_path, &owner.token).await.status(), 404);
+}
+
+#[tokio::test]
+async fn mentions_reach_the_inbox_of_users_who_can_see_the_todo() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let editor = app.create_user().await;
+    let stranger = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Plan" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    share(&app, &owner, &editor, "editor").await;
+
+    let body = format!(
+        "@{} please look. Not @{}, not `@{}`, not mail@{} and not @nobody_at_all.",
+        editor.username, stranger.username, owner.username, owner.username
+    );
+    let created = comment(&app, &owner, todo_id, &body).await;
+
+    let inbox = mentions(&app, &editor, "").await;
+    assert_eq!(inbox.len(), 1);
+    assert_eq!(inbox[0]["comment"]["id"], created["id"]);
+    assert_eq!(inbox[0]["comment"]["author"]["id"], owner.id.to_string());
+    assert!(inbox[0]["read_at"].is_null());
+    assert!(mentions(&app, &stranger, "").await.is_empty());
+    assert!(mentions(&app, &owner, "").await.is_empty());
+
+    // Mentioning the same user again in an edit does not notify twice;
+    // newly mentioned users are notified
+    let path = format!("/api/todos/{}/comments/{}", todo_id, created["id"].as_str().unwrap());
+    let edit = format!("@{} and @{}", editor.username, owner.username);
+    let response = app.put_json(&path, &owner.token, &json!({ "body": edit })).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(mentions(&app, &editor, "").await.len(), 1);
+    // Authors are not notified of their own mentions
+    assert!(mentions(&app, &owner, "").await.is_empty());
+
+    let reply = comment(&app, &editor, todo_id, &format!("Done, @{}.", owner.username)).await;
+    let inbox = mentions(&app, &owner, "").await;
+    assert_eq!(inbox.len(), 1);
+    assert_eq!(inbox[0]["comment"]["id"], reply["id"]);
+}
+
+#[tokio::test]
+async fn mentions_can_be_marked_read() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let editor = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Plan" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    share(&app, &owner, &editor, "editor").await;
+
+    let first = comment(&app, &owner, todo_id, &format!("@{} one", editor.username)).await;
+    let second = comment(&app, &owner, todo_id, &format!("@{} two", editor.username)).await;
+
+    // Newest first
+    let inbox = mentions(&app, &editor, "?unread=true").await;
+    let ids: Vec<&Value> = inbox.iter().map(|m| &m["comment"]["id"]).collect();
+    assert_eq!(ids, [&second["id"], &first["id"]]);
+
+    let read_path = format!("/api/mentions/{}/read", first["id"].as_str().unwrap());
+    let response = app.post_json(&read_path, Some(&editor.token), &json!({})).await;
+    assert_eq!(response.status(), 204);
+    let unread = mentions(&app, &editor, "?unread=true").await;
+    assert_eq!(unread.len(), 1);
+    assert_eq!(unread[0]["comment"]["id"], second["id"]);
+    let all = mentions(&app, &editor, "").await;
+    assert_eq!(all.len(), 2);
+    assert!(all[1]["read_at"].is_string());
+
+    // Already read
+    let response = app.post_json(&read_path, Some(&editor.token), &json!({})).await;
+    assert_eq!(response.status(), 404);
+
+    let response = app.post_json("/api/mentions/read", Some(&editor.token), &json!({})).await;
+    assert_eq!(response.status(), 204);
+    assert!(mentions(&app, &editor, "?unread=true").await.is_empty());
+}
+
+#[tokio::test]
+async fn revoking_access_hides_mentions() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let viewer = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Private soon" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    share(&app, &owner, &viewer, "viewer").await;
+    comment(&app, &owner, todo_id, &format!("@{} fyi", viewer.username)).await;
+    assert_eq!(mentions(&app, &viewer, "").await.len(), 1);
+
+    let shares: Vec<Value> = app.get("/api/shares", &owner.token).await.json().await.unwrap();
+    let path = format!("/api/shares/{}", shares[0]["id"].as_str().unwrap());
+    assert_eq!(app.delete(&path, &owner.token).await.status(), 204);
+
+    assert!(mentions(&app, &viewer, "").await.is_empty());
+}
@@ -0,0 +1,227 @@
+#![allow(dead_code)]
+
+use reqwest::{Client, Response};
+use serde_json::{json, Value};
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+
+use todo_service::{
+    attachments::{self, AttachmentOptions, Attachments},
+    auth::JwtService,
+    blobs::MemoryBlobStore,
+    config::{CorsConfig, DatabaseConfig, SecurityConfig},
+    metrics,
+    routes::create_routes,
+    security,
+    shutdown::Shutdown,
+    state::AppState,
+    store,
+    webhooks::{self, WebhookOptions, Webhooks},
+};
+
+/// The secret test apps sign and verify tokens with.
+pub const TEST_JWT_SECRET: &str = "test-only-jwt-secret-with-enough-length";
+
+/// A running instance of the API bound to an ephemeral local port.
+///
+/// Each instance gets its own store: a fresh in-memory store by default, or
+/// the backend named by `TEST_DATABASE_URL` (e.g. a scratch Postgres
+/// database). Tests only use usernames from [`unique_username`], so runs
+/// against a shared database do not interfere with each other.
+pub struct TestApp {
+    pub address: String,
+    /// Base URL of the separate `/metrics` listener.
+    pub metrics_address: String,
+    /// Shutdown handle of the app; requesting it does not stop the server.
+    pub shutdown: Shutdown,
+    pub client: Client,
+}
+
+pub struct TestUser {
+    pub id: Uuid,
+    pub username: String,
+    pub token: String,
+}
+
+impl TestApp {
+    pub async fn spawn() -> Self {
+        Self::spawn_with(&CorsConfig::default(), &SecurityConfig::default()).await
+    }
+
+    /// Spawns an app with the given CORS policy and security headers.
+    pub async fn spawn_with(cors: &CorsConfig, security_config: &SecurityConfig) -> Self {
+        Self::spawn_full(cors, security_config, test_attachment_options()).await
+    }
+
+    /// Spawns an app with the given attachment limits.
+    pub async fn spawn_with_attachment_options(options: AttachmentOptions) -> Self {
+        Self::spawn_full(&CorsConfig::default(), &SecurityConfig::default(), options).await
+    }
+
+    async fn spawn_full(cors: &CorsConfig, security_config: &SecurityConfig, options: AttachmentOptions) -> Self {
+        let database_url =
+            std::env::var("TEST_DATABASE_URL").unwrap_or_else(|_| "memory:".to_string());
+        let store = store::connect(&DatabaseConfig { url: database_url, ..Default::default() })
+            .await
+            .expect("failed to open test store");
+        store.migrate().await.expect("failed to migrate test store");
+
+        let jwt = JwtService::new(TEST_JWT_SECRET, chrono::Duration::hours(24));
+        let state = AppState::new(store.clone(), jwt)
+            .with_webhooks(Webhooks::new(test_webhook_options()))
+            .with_attachments(Attachments::new(test_blobs(), options));
+        webhooks::spawn_dispatcher(store.clone(), state.webhooks.clone(), state.metrics.clone(), state.shutdown.clone());
+        attachments::spawn_cleanup(store, state.attachments.clone(), state.shutdown.clone());
+        let metrics_app = metrics::router(state.metrics.clone());
+        let shutdown = state.shutdown.clone();
+        let app = security::apply(create_routes(state), cors, security_config)
+            .expect("invalid security config");
+        let (address, listener) = bind().await;
+        let (metrics_address, metrics_listener) = bind().await;
+
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("test server failed");
+        });
+        tokio::spawn(async move {
+            axum::serve(metrics_listener, metrics_app).await.expect("test metrics server failed");
+        });
+
+        TestApp {
+            address,
+            metrics_address,
+            shutdown,
+            client: Client::new(),
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.address, path)
+    }
+
+    pub async fn post_json(&self, path: &str, token: Option<&str>, body: &Value) -> Response {
+        let mut request = self.client.post(self.url(path)).json(body);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send().await.expect("request failed")
+    }
+
+    pub async fn put_json(&self, path: &str, token: &str, body: &Value) -> Response {
+        self.client
+            .put(self.url(path))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("request failed")
+    }
+
+    pub async fn get(&self, path: &str, token: &str) -> Response {
+        self.client
+            .get(self.url(path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("request failed")
+    }
+
+    pub async fn delete(&self, path: &str, token: &str) -> Response {
+        self.client
+            .delete(self.url(path))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("request failed")
+    }
+
+    pub async fn register(&self, username: &str) -> Response {
+        self.post_json(
+            "/api/auth/register",
+            None,
+            &json!({
+                "username": username,
+                "email": format!("{}@example.com", username),
+                "password": "password123",
+            }),
+        )
+        .await
+    }
+
+    /// Registers a fresh user and returns its id and token.
+    pub async fn create_user(&self) -> TestUser {
+        let username = unique_username();
+        let response = self.register(&username).await;
+        assert_eq!(response.status(), 201, "registration failed");
+
+        let body: Value = response.json().await.unwrap();
+        TestUser {
+            id: body["user"]["id"].as_str().unwrap().parse().unwrap(),
+            username,
+            token: body["token"].as_str().unwrap().to_string(),
+        }
+    }
+
+    /// Scrapes `/metrics` and returns the exposition text.
+    pub async fn metrics(&self) -> String {
+        let response = self
+            .client
+            .get(format!("{}/metrics", self.metrics_address))
+            .send()
+            .await
+            .expect("request failed");
+        assert_eq!(response.status(), 200, "metrics scrape failed");
+        response.text().await.unwrap()
+    }
+
+    /// Creates a todo and returns the response body.
+    pub async fn create_todo(&self, token: &str, body: Value) -> Value {
+        let response = self.post_json("/api/todos", Some(token), &body).await;
+        assert_eq!(response.status(), 201, "todo creation failed");
+        response.json().await.unwrap()
+    }
+}
+
+async fn bind() -> (String, TcpListener) {
+    let listener = TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("failed to bind ephemeral port");
+    (format!("http://{}", listener.local_addr().unwrap()), listener)
+}
+
+pub fn unique_username() -> String {
+    format!("user_{}", &Uuid::new_v4().simple().to_string()[..12])
+}
+
+/// Quick retries so tests see deliveries give up in well under a second,
+/// and private targets so they can be sent to local receivers.
+pub fn test_webhook_options() -> WebhookOptions {
+    WebhookOptions {
+        max_attempts: 3,
+        backoff_base: Duration::from_millis(100),
+        backoff_max: Duration::from_millis(400),
+        timeout: Duration::from_secs(2),
+        poll_interval: Duration::from_millis(100),
+        allow_private_targets: true,
+        ..WebhookOptions::default()
+    }
+}
+
+/// The blob store of every test app in the process. Apps may share a
+/// database, and any app's cleanup may delete another's attachments, so
+/// they share blobs too, as instances of the service do.
+pub fn test_blobs() -> Arc<MemoryBlobStore> {
+    static BLOBS: OnceLock<Arc<MemoryBlobStore>> = OnceLock::new();
+    BLOBS.get_or_init(Arc::default).clone()
+}
+
+/// The default limits, cleaning up often enough for tests to wait on it.
+pub fn test_attachment_options() -> AttachmentOptions {
+    AttachmentOptions {
+        cleanup_interval: Duration::from_millis(100),
+        ..AttachmentOptions::default()
+    }
+}
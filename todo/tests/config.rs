@@ -0,0 +1,230 @@
+// `figment::Jail` closures must return `figment::Result`.
+#![allow(clippy::result_large_err)]
+
+use figment::Jail;
+use std::{path::Path, time::Duration};
+
+use todo_service::{
+    config::{Config, Environment, DEFAULT_CONFIG_FILE, INSECURE_JWT_SECRET},
+    telemetry::LogFormat,
+};
+
+const STRONG_SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+fn load() -> figment::Result<Config> {
+    Config::figment(Path::new(DEFAULT_CONFIG_FILE)).extract()
+}
+
+#[test]
+fn file_then_legacy_env_then_prefixed_env() {
+    Jail::expect_with(|jail| {
+        jail.clear_env();
+        jail.create_file(
+            DEFAULT_CONFIG_FILE,
+            r#"
+            environment = "development"
+
+            [server]
+            host = "127.0.0.1"
+            port = 8000
+
+            [database]
+            url = "memory:"
+            max_connections = 12
+
+            [logging]
+            format = "json"
+            "#,
+        )?;
+        jail.set_env("SERVER_PORT", 8001);
+        jail.set_env("JWT_SECRET", STRONG_SECRET);
+        jail.set_env("TODO_DATABASE__MAX_CONNECTIONS", 20);
+
+        let config = load()?;
+
+        assert_eq!(config.environment, Environment::Development);
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.server.port, 8001, "legacy env overrides the file");
+        assert_eq!(config.server.metrics_port, 9090, "defaults fill the gaps");
+        assert_eq!(config.database.url, "memory:");
+        assert_eq!(config.database.max_connections, 20, "TODO_ env overrides the file");
+        assert_eq!(config.auth.jwt_secret, STRONG_SECRET);
+        assert_eq!(config.logging.format, LogFormat::Json);
+
+        jail.set_env("TODO_SERVER__PORT", 8002);
+        assert_eq!(load()?.server.port, 8002, "TODO_ env overrides legacy env");
+        Ok(())
+    });
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    Jail::expect_with(|jail| {
+        jail.clear_env();
+        jail.create_file(DEFAULT_CONFIG_FILE, "[server]\nprot = 8000\n")?;
+
+        let error = load().expect_err("typo accepted");
+        assert!(error.to_string().contains("prot"), "{}", error);
+        Ok(())
+    });
+}
+
+#[test]
+fn default_secret_is_refused_outside_development() {
+    Jail::expect_with(|jail| {
+        jail.clear_env();
+
+        let config = load()?;
+        assert_eq!(config.environment, Environment::Production);
+        assert_eq!(config.auth.jwt_secret, INSECURE_JWT_SECRET);
+        let problems = config.validate().expect_err("insecure secret accepted");
+        assert!(problems.iter().any(|p| p.contains("jwt_secret")), "{:?}", problems);
+
+        jail.set_env("APP_ENV", "development");
+        assert!(load()?.validate().is_ok());
+
+        jail.set_env("APP_ENV", "production");
+        jail.set_env("JWT_SECRET", "short");
+        assert!(load()?.validate().is_err());
+
+        jail.set_env("JWT_SECRET", STRONG_SECRET);
+        assert!(load()?.validate().is_ok());
+        Ok(())
+    });
+}
+
+#[test]
+fn validation_reports_every_problem() {
+    Jail::expect_with(|jail| {
+        jail.clear_env();
+        jail.set_env("JWT_SECRET", STRONG_SECRET);
+        jail.set_env("TODO_SERVER__METRICS_PORT", 3000);
+        jail.set_env("TODO_DATABASE__MAX_CONNECTIONS", 0);
+        jail.set_env("TODO_CORS__ALLOWED_ORIGINS", r#"["example.com"]"#);
+
+        let problems = load()?.validate().expect_err("invalid config accepted");
+        assert_eq!(problems.len(), 3, "{:?}", problems);
+        Ok(())
+    });
+}
+
+#[test]
+fn retention_is_bounded() {
+    Jail::expect_with(|jail| {
+        jail.clear_env();
+        jail.set_env("JWT_SECRET", STRONG_SECRET);
+        jail.set_env("TODO_EVENTS__RETENTION_HOURS", u64::MAX);
+        jail.set_env("TODO_WEBHOOKS__RETENTION_HOURS", u64::MAX / 3600 + 1);
+
+        let config = load()?;
+        let problems = config.validate().expect_err("unbounded retention accepted");
+        assert!(problems.iter().any(|p| p.starts_with("events.retention_hours")), "{:?}", problems);
+        assert!(problems.iter().any(|p| p.starts_with("webhooks.retention_hours")), "{:?}", problems);
+        assert_eq!(config.webhook_options().retention, Duration::from_secs(u64::MAX));
+        Ok(())
+    });
+}
+
+#[test]
+fn wildcard_origin_is_limited_to_development() {
+    Jail::expect_with(|jail| {
+        jail.clear_env();
+        jail.set_env("JWT_SECRET", STRONG_SECRET);
+        jail.set_env("TODO_CORS__ALLOWED_ORIGINS", r#"["*"]"#);
+
+        let problems = load()?.validate().expect_err("wildcard accepted in production");
+        assert!(problems.iter().any(|p| p.contains("allowed_origins")), "{:?}", problems);
+
+        jail.set_env("APP_ENV", "development");
+        assert!(load()?.validate().is_ok());
+
+        jail.set_env("TODO_CORS__ALLOW_CREDENTIALS", true);
+        let problems = load()?.validate().expect_err("wildcard accepted with credentials");
+        assert!(problems.iter().any(|p| p.contains("allow_credentials")), "{:?}", problems);
+
+        jail.set_env("TODO_CORS__ALLOWED_ORIGINS", r#"["https://todo.example.com"]"#);
+        assert!(load()?.validate().is_ok());
+        Ok(())
+    });
+}
+
+#[test]
+fn tls_needs_both_paths() {
+    Jail::expect_with(|jail| {
+        jail.clear_env();
+        jail.set_env("JWT_SECRET", STRONG_SECRET);
+        jail.set_env("TODO_TLS__CERT_PATH", "cert.pem");
+        jail.set_env("TODO_TLS__REDIRECT_PORT", 3000);
+
+        let problems = load()?.validate().expect_err("half-configured TLS accepted");
+        assert!(problems.iter().any(|p| p.contains("key_path")), "{:?}", problems);
+        assert!(problems.iter().any(|p| p.contains("must differ")), "{:?}", problems);
+
+        jail.set_env("TODO_TLS__KEY_PATH", "key.pem");
+        jail.set_env("TODO_TLS__REDIRECT_PORT", 8080);
+        let config = load()?;
+        assert!(config.validate().is_ok());
+        assert_eq!(config.tls.paths(), Some((Path::new("cert.pem"), Path::new("key.pem"))));
+        Ok(())
+    });
+}
+
+#[test]
+fn s3_attachments_need_a_bucket_and_credentials() {
+    Jail::expect_with(|jail| {
+        jail.clear_env();
+        jail.set_env("JWT_SECRET", STRONG_SECRET);
+        jail.set_env("TODO_ATTACHMENTS__BACKEND", "s3");
+        jail.set_env("TODO_ATTACHMENTS__S3__ENDPOINT", "localhost:9000");
+
+        let problems = load()?.validate().expect_err("half-configured S3 accepted");
+        assert_eq!(problems.len(), 5, "{:?}", problems);
+
+        jail.set_env("TODO_ATTACHMENTS__S3__ENDPOINT", "http://localhost:9000");
+        jail.set_env("TODO_ATTACHMENTS__S3__BUCKET", "attachments");
+        jail.set_env("TODO_ATTACHMENTS__S3__REGION", "us-east-1");
+        jail.set_env("TODO_ATTACHMENTS__S3__ACCESS_KEY_ID", "minio");
+        jail.set_env("TODO_ATTACHMENTS__S3__SECRET_ACCESS_KEY", "minio-secret");
+        assert!(load()?.validate().is_ok());
+
+        jail.set_env("TODO_ATTACHMENTS__ALLOWED_TYPES", r#"["image/*", "pdf"]"#);
+        let problems = load()?.validate().expect_err("bad MIME type accepted");
+        assert!(problems.iter().any(|p| p.contains("'pdf'")), "{:?}", problems);
+        Ok(())
+    });
+}
+
+#[test]
+fn redaction_hides_secrets() {
+    Jail::expect_with(|jail| {
+        jail.clear_env();
+        jail.set_env("JWT_SECRET", STRONG_SECRET);
+        jail.set_env("DATABASE_URL", "postgres://todouser:hunter2@db:5432/todoapp");
+        jail.set_env("TODO_ATTACHMENTS__S3__SECRET_ACCESS_KEY", "s3-secret");
+
+        let redacted = toml::to_string(&load()?.redacted()).unwrap();
+
+        assert!(!redacted.contains(STRONG_SECRET));
+        assert!(!redacted.contains("hunter2"));
+        assert!(!redacted.contains("s3-secret"));
+        assert!(redacted.contains("postgres://todouser:[redacted]@db:5432/todoapp"));
+        Ok(())
+    });
+}
+
+#[test]
+fn redaction_covers_query_passwords() {
+    Jail::expect_with(|jail| {
+        jail.clear_env();
+        jail.set_env("JWT_SECRET", STRONG_SECRET);
+        jail.set_env("DATABASE_URL", "postgres://todouser@db:5432/todoapp?password=hunter2&application_name=todo@db");
+
+        let redacted = load()?.redacted();
+
+        assert_eq!(
+            redacted.database.url,
+            "postgres://todouser@db:5432/todoapp?password=[redacted]&application_name=todo%40db"
+        );
+        Ok(())
+    });
+}
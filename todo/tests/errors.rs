@@ -0,0 +1,154 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use reqwest::Response;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{unique_username, TestApp};
+
+async fn problem(response: Response) -> (u16, Option<String>, Value) {
+    let status = response.status().as_u16();
+    assert_eq!(
+        response.headers()["content-type"],
+        "application/problem+json",
+        "error responses are problem details"
+    );
+    let request_id = response
+        .headers()
+        .get("x-request-id")
+        .map(|v| v.to_str().unwrap().to_string());
+    (status, request_id, response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn validation_failures_list_every_field() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .post_json(
+            "/api/auth/register",
+            None,
+            &json!({ "username": "ab", "email": "nope", "password": "secret-password" }),
+        )
+        .await;
+    let (status, _, body) = problem(response).await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["type"], "about:blank");
+    assert_eq!(body["status"], 400);
+    assert_eq!(body["code"], "validation_failed");
+
+    let errors = body["errors"].as_array().unwrap();
+    let fields: Vec<&str> = errors.iter().map(|e| e["field"].as_str().unwrap()).collect();
+    assert_eq!(fields, ["email", "username"]);
+
+    let username = &errors[1];
+    assert_eq!(username["code"], "length");
+    assert_eq!(username["params"]["min"], 3);
+    assert_eq!(username["params"]["max"], 50);
+    assert_eq!(username["message"], "must be between 3 and 50 characters");
+    assert!(username["params"].get("value").is_none(), "input is not echoed");
+}
+
+#[tokio::test]
+async fn scheduled_in_past_is_reported_against_the_field() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let response = app
+        .post_json(
+            "/api/todos",
+            Some(&user.token),
+            &json!({ "title": "late", "scheduled_for": Utc::now() - Duration::hours(1) }),
+        )
+        .await;
+    let (status, _, body) = problem(response).await;
+
+    assert_eq!(status, 400);
+    assert_eq!(body["errors"][0]["field"], "scheduled_for");
+    assert_eq!(body["errors"][0]["code"], "not_in_past");
+}
+
+#[tokio::test]
+async fn problem_carries_request_id_from_header() {
+    let app = TestApp::spawn().await;
+
+    let response = app.client.get(app.url("/api/todos")).send().await.unwrap();
+    let (status, header_id, body) = problem(response).await;
+
+    assert_eq!(status, 401);
+    assert_eq!(body["code"], "unauthorized");
+    assert_eq!(body["title"], "Unauthorized");
+    let header_id = header_id.expect("response has X-Request-Id");
+    assert_eq!(body["request_id"], header_id);
+}
+
+#[tokio::test]
+async fn well_formed_incoming_request_id_is_kept() {
+    let app = TestApp::spawn().await;
+
+    let response = app
+        .client
+        .get(app.url("/api/todos"))
+        .header("X-Request-Id", "client-abc.123")
+        .send()
+        .await
+        .unwrap();
+    let (_, header_id, body) = problem(response).await;
+    assert_eq!(header_id.as_deref(), Some("client-abc.123"));
+    assert_eq!(body["request_id"], "client-abc.123");
+
+    let response = app
+        .client
+        .get(app.url("/api/todos"))
+        .header("X-Request-Id", "has spaces and <html>")
+        .send()
+        .await
+        .unwrap();
+    let (_, header_id, _) = problem(response).await;
+    assert_ne!(header_id.as_deref(), Some("has spaces and <html>"));
+}
+
+#[tokio::test]
+async fn malformed_bodies_and_paths_are_problems() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let response = app
+        .client
+        .post(app.url("/api/todos"))
+        .bearer_auth(&user.token)
+        .header("content-type", "application/json")
+        .body("{not json")
+        .send()
+        .await
+        .unwrap();
+    let (status, _, body) = problem(response).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "bad_request");
+
+    let response = app
+        .post_json("/api/auth/login", None, &json!({ "username": unique_username() }))
+        .await;
+    let (status, _, body) = problem(response).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "bad_request");
+
+    let (status, _, body) = problem(app.get("/api/todos/not-a-uuid", &user.token).await).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["code"], "bad_request");
+}
+
+#[tokio::test]
+async fn not_found_has_stable_code() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let response = app.get(&format!("/api/todos/{}", Uuid::new_v4()), &user.token).await;
+    let (status, _, body) = problem(response).await;
+
+    assert_eq!(status, 404);
+    assert_eq!(body["code"], "not_found");
+    assert_eq!(body["detail"], "Todo not found");
+}
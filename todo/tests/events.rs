@@ -0,0 +1,216 @@
+mod common;
+
+use chrono::Utc;
+use reqwest::Response;
+use serde_json::{json, Value};
+use std::{sync::Arc, time::Duration};
+use tokio::time::timeout;
+
+use common::{TestApp, TestUser};
+use todo_service::{
+    events,
+    models::CreateTodo,
+    store::{DynStore, MemoryStore},
+};
+
+/// One parsed Server-Sent Event.
+#[derive(Debug)]
+struct SseEvent {
+    id: Option<String>,
+    event: String,
+    data: Value,
+}
+
+/// Reads events from a `text/event-stream` response, skipping keep-alives.
+struct EventStream {
+    response: Response,
+    buffer: String,
+}
+
+impl EventStream {
+    async fn open(app: &TestApp, user: &TestUser, last_event_id: Option<&str>) -> Self {
+        let mut request = app.client.get(app.url("/api/todos/events")).bearer_auth(&user.token);
+        if let Some(id) = last_event_id {
+            request = request.header("Last-Event-ID", id);
+        }
+        let response = request.send().await.expect("request failed");
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        EventStream { response, buffer: String::new() }
+    }
+
+    /// The next event, or `None` once the server ends the stream.
+    async fn next(&mut self) -> Option<SseEvent> {
+        loop {
+            if let Some(end) = self.buffer.find("\n\n") {
+                let block: String = self.buffer.drain(..end + 2).collect();
+                if let Some(event) = parse(&block) {
+                    return Some(event);
+                }
+                continue;
+            }
+
+            let chunk = timeout(Duration::from_secs(5), self.response.chunk())
+                .await
+                .expect("timed out waiting for an event")
+                .expect("stream failed")?;
+            self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+    }
+
+    async fn expect(&mut self) -> SseEvent {
+        self.next().await.expect("stream ended")
+    }
+}
+
+fn parse(block: &str) -> Option<SseEvent> {
+    let (mut id, mut event, mut data) = (None, None, String::new());
+    for line in block.lines() {
+        match line.split_once(':') {
+            Some(("id", value)) => id = Some(value.trim().to_string()),
+            Some(("event", value)) => event = Some(value.trim().to_string()),
+            Some(("data", value)) => data.push_str(value.trim()),
+            _ => {}
+        }
+    }
+    Some(SseEvent {
+        id,
+        event: event?,
+        data: serde_json::from_str(&data).unwrap(),
+    })
+}
+
+#[tokio::test]
+async fn events_require_authentication() {
+    let app = TestApp::spawn().await;
+
+    let response = app.client.get(app.url("/api/todos/events")).send().await.unwrap();
+
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn streams_the_users_own_changes() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let other = app.create_user().await;
+    let mut stream = EventStream::open(&app, &user, None).await;
+
+    app.create_todo(&other.token, json!({ "title": "Not mine" })).await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Mine" })).await;
+    let id = todo["id"].as_str().unwrap();
+    let path = format!("/api/todos/{}", id);
+    app.put_json(&path, &user.token, &json!({ "completed": true })).await;
+    app.delete(&path, &user.token).await;
+
+    let created = stream.expect().await;
+    assert_eq!(created.event, "created");
+    assert_eq!(created.data["type"], "created");
+    assert_eq!(created.data["todo_id"], id);
+    assert_eq!(created.data["todo"]["title"], "Mine");
+    assert_eq!(created.id.as_deref(), Some(created.data["id"].to_string().as_str()));
+
+    let updated = stream.expect().await;
+    assert_eq!(updated.event, "updated");
+    assert_eq!(updated.data["todo"]["completed"], true);
+
+    let deleted = stream.expect().await;
+    assert_eq!(deleted.event, "deleted");
+    assert_eq!(deleted.data["todo_id"], id);
+    assert!(deleted.data["todo"].is_null());
+
+    let ids: Vec<i64> = [&created, &updated, &deleted]
+        .iter()
+        .map(|e| e.data["id"].as_i64().unwrap())
+        .collect();
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]), "ids increase: {:?}", ids);
+}
+
+#[tokio::test]
+async fn streams_changes_to_todos_shared_with_the_user() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let grantee = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Shared" })).await;
+    let id = todo["id"].as_str().unwrap();
+    let response = app
+        .post_json("/api/shares", Some(&owner.token), &json!({ "username": grantee.username, "role": "viewer", "todo_id": id }))
+        .await;
+    assert_eq!(response.status(), 201);
+    let mut stream = EventStream::open(&app, &grantee, None).await;
+
+    app.create_todo(&owner.token, json!({ "title": "Not shared" })).await;
+    let path = format!("/api/todos/{}", id);
+    app.put_json(&path, &owner.token, &json!({ "completed": true })).await;
+    app.delete(&path, &owner.token).await;
+
+    let updated = stream.expect().await;
+    assert_eq!(updated.event, "updated");
+    assert_eq!(updated.data["todo_id"], id);
+    assert_eq!(updated.data["user_id"], owner.id.to_string());
+    assert!(updated.data.get("recipients").is_none());
+    // The todo's own share goes with it, but the deletion still arrives
+    let deleted = stream.expect().await;
+    assert_eq!(deleted.event, "deleted");
+    assert_eq!(deleted.data["todo_id"], id);
+
+    // And is replayed on resume
+    let last_event_id = (updated.data["id"].as_i64().unwrap() - 1).to_string();
+    let mut resumed = EventStream::open(&app, &grantee, Some(&last_event_id)).await;
+    assert_eq!(resumed.expect().await.data["id"], updated.data["id"]);
+    assert_eq!(resumed.expect().await.data["id"], deleted.data["id"]);
+}
+
+#[tokio::test]
+async fn resumes_after_last_event_id() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let mut stream = EventStream::open(&app, &user, None).await;
+    app.create_todo(&user.token, json!({ "title": "First" })).await;
+    let first = stream.expect().await;
+    drop(stream);
+
+    app.create_todo(&user.token, json!({ "title": "Second" })).await;
+    app.create_todo(&user.token, json!({ "title": "Third" })).await;
+
+    let mut stream = EventStream::open(&app, &user, first.id.as_deref()).await;
+    assert_eq!(stream.expect().await.data["todo"]["title"], "Second");
+    assert_eq!(stream.expect().await.data["todo"]["title"], "Third");
+
+    // Then live events follow the replayed ones
+    app.create_todo(&user.token, json!({ "title": "Fourth" })).await;
+    assert_eq!(stream.expect().await.data["todo"]["title"], "Fourth");
+}
+
+#[tokio::test]
+async fn stream_ends_on_shutdown() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let mut stream = EventStream::open(&app, &user, None).await;
+
+    app.shutdown.request();
+
+    assert!(stream.next().await.is_none(), "stream should end");
+}
+
+#[tokio::test]
+async fn backlog_requires_resync_once_events_are_pruned() {
+    let store: DynStore = Arc::new(MemoryStore::new());
+    let user = store.create_user("resync_user", "resync@example.com", "hash").await.unwrap();
+    for title in ["One", "Two", "Three"] {
+        let todo = CreateTodo {
+            title: title.to_string(),
+            ..Default::default()
+        };
+        store.create_todo(user.id, todo).await.unwrap();
+    }
+
+    let backlog = events::backlog(&store, user.id, 1).await.unwrap().expect("complete backlog");
+    assert_eq!(backlog.iter().map(|e| e.id).collect::<Vec<_>>(), [2, 3]);
+
+    // Pruning keeps only the newest event, so resuming from 1 would skip 2
+    assert_eq!(store.prune_todo_events(Utc::now()).await.unwrap(), 2);
+    assert!(events::backlog(&store, user.id, 1).await.unwrap().is_none());
+    assert_eq!(events::backlog(&store, user.id, 2).await.unwrap().unwrap().len(), 1);
+}
@@ -0,0 +1,66 @@
+mod common;
+
+use serde_json::Value;
+
+use common::TestApp;
+
+async fn get_json(app: &TestApp, path: &str) -> (u16, Value) {
+    let response = app.client.get(app.url(path)).send().await.unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn liveness_needs_nothing() {
+    let app = TestApp::spawn().await;
+
+    let response = app.client.get(app.url("/healthz")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "ok");
+}
+
+#[tokio::test]
+async fn ready_reports_every_check() {
+    let app = TestApp::spawn().await;
+
+    let (status, body) = get_json(&app, "/readyz").await;
+
+    assert_eq!(status, 200);
+    assert_eq!(body["status"], "ok");
+    for check in ["database", "migrations", "shutdown"] {
+        assert_eq!(body["checks"][check]["status"], "ok", "{} check failed: {}", check, body);
+        assert!(body["checks"][check].get("detail").is_none());
+    }
+}
+
+#[tokio::test]
+async fn not_ready_once_shutdown_is_requested() {
+    let app = TestApp::spawn().await;
+
+    app.shutdown.request();
+    let (status, body) = get_json(&app, "/readyz").await;
+
+    assert_eq!(status, 503);
+    assert_eq!(body["status"], "fail");
+    assert_eq!(body["checks"]["shutdown"]["status"], "fail");
+    assert_eq!(body["checks"]["shutdown"]["detail"], "Shutting down");
+    assert_eq!(body["checks"]["database"]["status"], "ok");
+
+    // Liveness is unaffected while draining.
+    let response = app.client.get(app.url("/healthz")).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn version_reports_build_metadata() {
+    let app = TestApp::spawn().await;
+
+    let (status, body) = get_json(&app, "/version").await;
+
+    assert_eq!(status, 200);
+    assert_eq!(body["name"], "todo-service");
+    assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+    assert!(!body["git_sha"].as_str().unwrap().is_empty());
+    assert!(body["rustc"].as_str().unwrap().starts_with("rustc "));
+    assert!(body["build_time"].is_string());
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use serde_json::json;
+use uuid::Uuid;
+
+use common::TestApp;
+
+/// Value of the sample whose name and labels are exactly `series`.
+fn sample(metrics: &str, series: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+        .map(|value| value.parse().unwrap())
+}
+
+#[tokio::test]
+async fn requests_are_counted_by_route_template() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Count me" })).await;
+
+    app.get(&format!("/api/todos/{}", todo["id"].as_str().unwrap()), &user.token).await;
+    app.get(&format!("/api/todos/{}", Uuid::new_v4()), &user.token).await;
+    app.get("/api/nowhere", &user.token).await;
+
+    let metrics = app.metrics().await;
+
+    assert_eq!(
+        sample(&metrics, r#"http_requests_total{method="GET",route="/api/todos/:id",status="2xx"}"#),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(&metrics, r#"http_requests_total{method="GET",route="/api/todos/:id",status="4xx"}"#),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(&metrics, r#"http_requests_total{method="GET",route="unmatched",status="4xx"}"#),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(&metrics, r#"http_request_duration_seconds_count{method="GET",route="/api/todos/:id"}"#),
+        Some(2.0)
+    );
+    assert!(!metrics.contains(todo["id"].as_str().unwrap()), "raw ids must not become labels");
+}
+
+#[tokio::test]
+async fn business_counters_track_created_and_completed_todos() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let todo = app.create_todo(&user.token, json!({ "title": "First" })).await;
+    app.create_todo(&user.token, json!({ "title": "Already done", "completed": true })).await;
+
+    let path = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    app.put_json(&path, &user.token, &json!({ "completed": true })).await;
+    // Completing an already completed todo is not counted again.
+    app.put_json(&path, &user.token, &json!({ "completed": true })).await;
+
+    let metrics = app.metrics().await;
+    assert_eq!(sample(&metrics, "todos_created_total"), Some(2.0));
+    assert_eq!(sample(&metrics, "todos_completed_total"), Some(2.0));
+}
+
+#[tokio::test]
+async fn pool_metrics_follow_the_backend() {
+    let app = TestApp::spawn().await;
+    app.create_user().await;
+
+    let metrics = app.metrics().await;
+    let pooled = std::env::var("TEST_DATABASE_URL").is_ok_and(|url| !url.starts_with("memory:"));
+
+    assert_eq!(metrics.contains("db_pool_connections "), pooled);
+    if pooled {
+        assert!(sample(&metrics, "db_pool_acquire_wait_seconds_count").unwrap() >= 1.0);
+        assert!(sample(&metrics, "db_pool_max_connections").unwrap() >= 1.0);
+    }
+}
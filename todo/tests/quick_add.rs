@@ -0,0 +1,237 @@
+mod common;
+
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde_json::{json, Value};
+
+use common::TestApp;
+use todo_service::{
+    models::{ParsedTodo, Priority},
+    quick_add,
+};
+
+/// A Wednesday afternoon, 16:00 in London.
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 14, 15, 0, 0).unwrap()
+}
+
+fn parse(text: &str, tz: &str) -> ParsedTodo {
+    quick_add::parse(text, now(), tz.parse::<Tz>().unwrap())
+}
+
+fn utc(text: &str) -> DateTime<Utc> {
+    text.parse().unwrap()
+}
+
+#[test]
+fn dates_times_tags_and_priority_are_taken_out_of_the_title() {
+    let parsed = parse("Pay rent every 1st at 9am #Finance !high", "Europe/London");
+    assert_eq!(parsed.title, "Pay rent");
+    assert_eq!(parsed.recurrence.as_deref(), Some("FREQ=MONTHLY;BYMONTHDAY=1"));
+    // London is back on GMT by November
+    assert_eq!(parsed.scheduled_for, Some(utc("2026-11-01T09:00:00Z")));
+    assert_eq!(parsed.tags, ["finance"]);
+    assert_eq!(parsed.priority, Some(Priority::High));
+
+    let parsed = parse("Buy milk", "UTC");
+    assert_eq!(parsed.title, "Buy milk");
+    assert_eq!(parsed.scheduled_for, None);
+    assert_eq!(parsed.recurrence, None);
+    assert!(parsed.tags.is_empty());
+    assert_eq!(parsed.priority, None);
+
+    let parsed = parse("Tidy up #home #Home #a-b #2fast !med", "UTC");
+    assert_eq!(parsed.title, "Tidy up #2fast");
+    assert_eq!(parsed.tags, ["home", "a-b"]);
+    assert_eq!(parsed.priority, Some(Priority::Medium));
+}
+
+#[test]
+fn dates_and_times_are_read_in_the_time_zone() {
+    let cases = [
+        ("Call mom tomorrow at 5pm", "UTC", "2026-10-15T17:00:00Z"),
+        ("Call mom tomorrow at 5pm", "Europe/Paris", "2026-10-15T15:00:00Z"),
+        // A date alone is due at the end of the day
+        ("Submit report by friday", "Europe/London", "2026-10-16T22:59:00Z"),
+        ("Renew passport nov 3rd 2027", "UTC", "2027-11-03T23:59:00Z"),
+        ("Book flights on 2026-12-01 at 08:15", "UTC", "2026-12-01T08:15:00Z"),
+        // A time alone is its next occurrence
+        ("Dinner at 19:30", "UTC", "2026-10-14T19:30:00Z"),
+        ("Run at 7", "UTC", "2026-10-15T07:00:00Z"),
+        ("Lunch at noon", "UTC", "2026-10-15T12:00:00Z"),
+        ("Stretch in 2 hours", "UTC", "2026-10-14T17:00:00Z"),
+        ("Water plants in 3 days", "UTC", "2026-10-17T23:59:00Z"),
+        ("Meet on sun", "UTC", "2026-10-18T23:59:00Z"),
+        ("Review next wednesday", "UTC", "2026-10-21T23:59:00Z"),
+    ];
+    for (text, tz, expected) in cases {
+        assert_eq!(parse(text, tz).scheduled_for, Some(utc(expected)), "{}", text);
+    }
+
+    // Abbreviated weekdays are words too
+    let parsed = parse("Go to the sun deck", "UTC");
+    assert_eq!(parsed.title, "Go to the sun deck");
+    assert_eq!(parsed.scheduled_for, None);
+}
+
+#[test]
+fn repeats_start_at_their_next_occurrence() {
+    // It is already past 9:30 on Wednesday, so Friday comes first
+    let parsed = parse("Standup every mon, wed and fri at 9:30am", "Europe/London");
+    assert_eq!(parsed.title, "Standup");
+    assert_eq!(parsed.recurrence.as_deref(), Some("FREQ=WEEKLY;BYDAY=MO,WE,FR"));
+    assert_eq!(parsed.scheduled_for, Some(utc("2026-10-16T08:30:00Z")));
+
+    let cases = [
+        ("Clean garage every other week", "FREQ=WEEKLY;INTERVAL=2"),
+        ("Backups every 3 days", "FREQ=DAILY;INTERVAL=3"),
+        ("Water plants daily", "FREQ=DAILY"),
+        ("Sleep in every weekend", "FREQ=WEEKLY;BYDAY=SA,SU"),
+        ("Birthday card yearly", "FREQ=YEARLY"),
+    ];
+    for (text, expected) in cases {
+        assert_eq!(parse(text, "UTC").recurrence.as_deref(), Some(expected), "{}", text);
+    }
+}
+
+#[test]
+fn times_skipped_or_repeated_by_daylight_saving_are_resolved() {
+    let new_york: Tz = "America/New_York".parse().unwrap();
+
+    // Clocks go from 2:00 to 3:00 on 8 March, so 2:30 is an hour on
+    let before_spring = Utc.with_ymd_and_hms(2026, 3, 7, 12, 0, 0).unwrap();
+    let parsed = quick_add::parse("Water plants tomorrow at 2:30am", before_spring, new_york);
+    assert_eq!(parsed.scheduled_for, Some(utc("2026-03-08T07:30:00Z")));
+
+    // Clocks go from 2:00 back to 1:00 on 1 November; the first 1:30 counts
+    let before_fall = Utc.with_ymd_and_hms(2026, 10, 31, 12, 0, 0).unwrap();
+    let parsed = quick_add::parse("Feed cat tomorrow at 1:30am", before_fall, new_york);
+    assert_eq!(parsed.scheduled_for, Some(utc("2026-11-01T05:30:00Z")));
+}
+
+#[tokio::test]
+async fn quick_add_previews_without_committing() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let response = app
+        .post_json(
+            "/api/todos/quick",
+            Some(&user.token),
+            &json!({ "text": "Call mom tomorrow at 5pm #family !low", "timezone": "Europe/Paris" }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["parsed"]["title"], "Call mom");
+    assert_eq!(body["parsed"]["tags"], json!(["family"]));
+    assert_eq!(body["parsed"]["priority"], "low");
+    assert!(body["parsed"]["scheduled_for"].is_string());
+    assert!(body["todo"].is_null());
+
+    let todos: Vec<Value> = app.get("/api/todos", &user.token).await.json().await.unwrap();
+    assert!(todos.is_empty());
+}
+
+#[tokio::test]
+async fn quick_add_commits_what_it_read() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let response = app
+        .post_json(
+            "/api/todos/quick",
+            Some(&user.token),
+            &json!({ "text": "Pay rent every 1st at 9am #finance !high", "timezone": "Europe/London", "commit": true }),
+        )
+        .await;
+    assert_eq!(response.status(), 201);
+    let body: Value = response.json().await.unwrap();
+    let todo = &body["todo"];
+    assert_eq!(todo["title"], "Pay rent");
+    assert_eq!(todo["priority"], "high");
+    assert_eq!(todo["tags"], json!(["finance"]));
+    assert_eq!(todo["recurrence"], "FREQ=MONTHLY;BYMONTHDAY=1");
+    assert_eq!(todo["scheduled_for"], body["parsed"]["scheduled_for"]);
+
+    let fetched: Value = app
+        .get(&format!("/api/todos/{}", todo["id"].as_str().unwrap()), &user.token)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(&fetched, todo);
+}
+
+#[tokio::test]
+async fn quick_add_rejects_unknown_time_zones_and_missing_titles() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let response = app
+        .post_json("/api/todos/quick", Some(&user.token), &json!({ "text": "Nap at 3pm", "timezone": "Mars/Olympus" }))
+        .await;
+    assert_eq!(response.status(), 400);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["field"], "timezone");
+    assert_eq!(body["errors"][0]["code"], "unknown_timezone");
+
+    let response = app
+        .post_json("/api/todos/quick", Some(&user.token), &json!({ "text": "tomorrow #later !low", "commit": true }))
+        .await;
+    assert_eq!(response.status(), 400);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["field"], "text");
+    assert_eq!(body["errors"][0]["code"], "no_title");
+}
+
+#[tokio::test]
+async fn tags_priority_and_repeats_can_be_set_and_cleared() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let todo = app
+        .create_todo(
+            &user.token,
+            json!({
+                "title": "Gym",
+                "priority": "medium",
+                "tags": ["Health", "health", "routine"],
+                "recurrence": "freq=weekly;byday=tu,th",
+            }),
+        )
+        .await;
+    assert_eq!(todo["priority"], "medium");
+    assert_eq!(todo["tags"], json!(["health", "routine"]));
+    assert_eq!(todo["recurrence"], "FREQ=WEEKLY;BYDAY=TU,TH");
+    let path = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+
+    let response = app.put_json(&path, &user.token, &json!({ "tags": ["fitness"] })).await;
+    assert_eq!(response.status(), 200);
+    let updated: Value = response.json().await.unwrap();
+    assert_eq!(updated["tags"], json!(["fitness"]));
+    assert_eq!(updated["priority"], "medium");
+    assert_eq!(updated["recurrence"], "FREQ=WEEKLY;BYDAY=TU,TH");
+
+    let response = app
+        .put_json(&path, &user.token, &json!({ "priority": null, "recurrence": null, "tags": [] }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let cleared: Value = response.json().await.unwrap();
+    assert!(cleared["priority"].is_null());
+    assert!(cleared["recurrence"].is_null());
+    assert_eq!(cleared["tags"], json!([]));
+
+    for (body, field, code) in [
+        (json!({ "tags": ["not a tag"] }), "tags", "invalid_tag"),
+        (json!({ "tags": (0..21).map(|i| format!("t{}", i)).collect::<Vec<_>>() }), "tags", "too_many"),
+        (json!({ "recurrence": "FREQ=HOURLY" }), "recurrence", "invalid_recurrence"),
+        (json!({ "recurrence": "FREQ=DAILY;BYDAY=MO" }), "recurrence", "invalid_recurrence"),
+    ] {
+        let response = app.put_json(&path, &user.token, &body).await;
+        assert_eq!(response.status(), 400, "{}", body);
+        let problem: Value = response.json().await.unwrap();
+        assert_eq!(problem["errors"][0]["field"], field);
+        assert_eq!(problem["errors"][0]["code"], code);
+    }
+}
@@ -0,0 +1,128 @@
+mod common;
+
+use common::TestApp;
+use todo_service::config::{CorsConfig, SecurityConfig, DEFAULT_CONTENT_SECURITY_POLICY};
+
+const FRONTEND: &str = "https://todo.example.com";
+
+fn cors_for_frontend() -> CorsConfig {
+    CorsConfig {
+        allowed_origins: vec![FRONTEND.to_string()],
+        ..Default::default()
+    }
+}
+
+async fn preflight(app: &TestApp, origin: &str) -> reqwest::Response {
+    app.client
+        .request(reqwest::Method::OPTIONS, app.url("/api/todos"))
+        .header("Origin", origin)
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "authorization,content-type")
+        .send()
+        .await
+        .expect("request failed")
+}
+
+#[tokio::test]
+async fn responses_carry_security_headers() {
+    let app = TestApp::spawn().await;
+
+    let response = app.client.get(app.url("/healthz")).send().await.unwrap();
+
+    assert_eq!(response.status(), 200);
+    let headers = response.headers();
+    assert_eq!(headers["content-security-policy"], DEFAULT_CONTENT_SECURITY_POLICY);
+    assert_eq!(headers["x-content-type-options"], "nosniff");
+    assert_eq!(headers["x-frame-options"], "DENY");
+    assert_eq!(headers["referrer-policy"], "strict-origin-when-cross-origin");
+    assert_eq!(headers["strict-transport-security"], "max-age=31536000; includeSubDomains");
+}
+
+#[tokio::test]
+async fn hsts_can_be_disabled() {
+    let security = SecurityConfig {
+        hsts_max_age_secs: 0,
+        ..Default::default()
+    };
+    let app = TestApp::spawn_with(&CorsConfig::default(), &security).await;
+
+    let response = app.client.get(app.url("/healthz")).send().await.unwrap();
+
+    assert!(response.headers().get("strict-transport-security").is_none());
+    assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+}
+
+#[tokio::test]
+async fn api_docs_keep_their_own_policy() {
+    let app = TestApp::spawn().await;
+
+    let response = app.client.get(app.url("/api/docs")).send().await.unwrap();
+
+    assert_eq!(response.status(), 200);
+    let csp = response.headers()["content-security-policy"].to_str().unwrap();
+    assert!(csp.contains("https://unpkg.com"), "docs policy admits the CDN: {}", csp);
+
+    let script = app
+        .client
+        .get(app.url("/api/docs/swagger-initializer.js"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(script.status(), 200);
+    assert_eq!(script.headers()["content-type"], "text/javascript");
+}
+
+#[tokio::test]
+async fn no_cors_headers_by_default() {
+    let app = TestApp::spawn().await;
+
+    let response = preflight(&app, FRONTEND).await;
+
+    assert!(response.headers().get("access-control-allow-origin").is_none());
+}
+
+#[tokio::test]
+async fn preflight_from_allowed_origin_succeeds() {
+    let app = TestApp::spawn_with(&cors_for_frontend(), &SecurityConfig::default()).await;
+
+    let response = preflight(&app, FRONTEND).await;
+
+    assert_eq!(response.status(), 200);
+    let headers = response.headers();
+    assert_eq!(headers["access-control-allow-origin"], FRONTEND);
+    let methods = headers["access-control-allow-methods"].to_str().unwrap();
+    assert!(methods.contains("POST"), "allowed methods: {}", methods);
+    assert_eq!(headers["access-control-max-age"], "600");
+    assert!(headers.get("access-control-allow-credentials").is_none());
+}
+
+#[tokio::test]
+async fn preflight_from_other_origin_is_not_allowed() {
+    let app = TestApp::spawn_with(&cors_for_frontend(), &SecurityConfig::default()).await;
+
+    let response = preflight(&app, "https://evil.example.com").await;
+
+    assert!(response.headers().get("access-control-allow-origin").is_none());
+}
+
+#[tokio::test]
+async fn credentials_are_allowed_when_configured() {
+    let cors = CorsConfig {
+        allow_credentials: true,
+        ..cors_for_frontend()
+    };
+    let app = TestApp::spawn_with(&cors, &SecurityConfig::default()).await;
+
+    let response = app
+        .client
+        .get(app.url("/healthz"))
+        .header("Origin", FRONTEND)
+        .send()
+        .await
+        .unwrap();
+
+    let headers = response.headers();
+    assert_eq!(headers["access-control-allow-origin"], FRONTEND);
+    assert_eq!(headers["access-control-allow-credentials"], "true");
+    assert_eq!(headers["access-control-expose-headers"], "x-request-id");
+}
@@ -0,0 +1,260 @@
+mod common;
+
+use serde_json::{json, Value};
+
+use common::{TestApp, TestUser};
+
+/// Shares with `grantee` and returns the share.
+async fn share(app: &TestApp, owner: &TestUser, grantee: &TestUser, role: &str, todo_id: Option<&str>) -> Value {
+    let response = app
+        .post_json(
+            "/api/shares",
+            Some(&owner.token),
+            &json!({ "username": grantee.username, "role": role, "todo_id": todo_id }),
+        )
+        .await;
+    assert_eq!(response.status(), 201, "sharing failed");
+    response.json().await.unwrap()
+}
+
+async fn titles(app: &TestApp, path: &str, user: &TestUser) -> Vec<String> {
+    let response = app.get(path, &user.token).await;
+    assert_eq!(response.status(), 200);
+    let todos: Vec<Value> = response.json().await.unwrap();
+    todos.iter().map(|t| t["title"].as_str().unwrap().to_string()).collect()
+}
+
+#[tokio::test]
+async fn viewers_can_read_but_not_change_a_shared_todo() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let viewer = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Shared" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    let path = format!("/api/todos/{}", todo_id);
+
+    // Strangers cannot tell the todo exists
+    assert_eq!(app.get(&path, &viewer.token).await.status(), 404);
+
+    let created = share(&app, &owner, &viewer, "viewer", Some(todo_id)).await;
+    assert_eq!(created["owner"]["id"], owner.id.to_string());
+    assert_eq!(created["grantee"]["username"], viewer.username.as_str());
+    assert_eq!(created["todo_id"], todo_id);
+    assert_eq!(created["role"], "viewer");
+
+    let response = app.get(&path, &viewer.token).await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["title"], "Shared");
+
+    let response = app.put_json(&path, &viewer.token, &json!({ "completed": true })).await;
+    assert_eq!(response.status(), 403);
+    let response = app
+        .put_json(&format!("{}/assignee", path), &viewer.token, &json!({ "assignee_id": viewer.id }))
+        .await;
+    assert_eq!(response.status(), 403);
+    assert_eq!(app.delete(&path, &viewer.token).await.status(), 403);
+
+    let shared: Vec<Value> = app.get("/api/todos/shared", &viewer.token).await.json().await.unwrap();
+    assert_eq!(shared.len(), 1);
+    assert_eq!(shared[0]["id"], todo_id);
+    assert_eq!(shared[0]["owner_id"], owner.id.to_string());
+    assert_eq!(shared[0]["role"], "viewer");
+
+    // Shared todos stay out of the grantee's own list
+    assert!(titles(&app, "/api/todos", &viewer).await.is_empty());
+}
+
+#[tokio::test]
+async fn editors_change_todos_but_only_owners_delete_or_share_them() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let editor = app.create_user().await;
+    let third = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Draft" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    let path = format!("/api/todos/{}", todo_id);
+    share(&app, &owner, &editor, "editor", Some(todo_id)).await;
+
+    let response = app.put_json(&path, &editor.token, &json!({ "title": "Edited" })).await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["title"], "Edited");
+
+    // The owner sees the edit
+    assert_eq!(titles(&app, "/api/todos", &owner).await, ["Edited"]);
+
+    assert_eq!(app.delete(&path, &editor.token).await.status(), 403);
+    let response = app
+        .post_json(
+            "/api/shares",
+            Some(&editor.token),
+            &json!({ "username": third.username, "role": "viewer", "todo_id": todo_id }),
+        )
+        .await;
+    assert_eq!(response.status(), 403);
+
+    assert_eq!(app.delete(&path, &owner.token).await.status(), 204);
+    assert_eq!(app.get(&path, &editor.token).await.status(), 404);
+    assert!(titles(&app, "/api/todos/shared", &editor).await.is_empty());
+}
+
+#[tokio::test]
+async fn list_shares_cover_every_todo_including_later_ones() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let grantee = app.create_user().await;
+    app.create_todo(&owner.token, json!({ "title": "Before" })).await;
+    share(&app, &owner, &grantee, "viewer", None).await;
+    let later = app.create_todo(&owner.token, json!({ "title": "After" })).await;
+    let later_id = later["id"].as_str().unwrap();
+
+    let mut shared = titles(&app, "/api/todos/shared", &grantee).await;
+    shared.sort();
+    assert_eq!(shared, ["After", "Before"]);
+
+    // A todo share can raise the role for one todo of a list share
+    share(&app, &owner, &grantee, "editor", Some(later_id)).await;
+    let shared: Vec<Value> = app.get("/api/todos/shared", &grantee.token).await.json().await.unwrap();
+    for todo in &shared {
+        let expected = if todo["id"] == later_id { "editor" } else { "viewer" };
+        assert_eq!(todo["role"], expected, "role of {}", todo["title"]);
+    }
+    let response = app
+        .put_json(&format!("/api/todos/{}", later_id), &grantee.token, &json!({ "completed": true }))
+        .await;
+    assert_eq!(response.status(), 200);
+}
+
+#[tokio::test]
+async fn sharing_again_changes_the_role_and_shares_can_be_managed() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let grantee = app.create_user().await;
+    let stranger = app.create_user().await;
+
+    let first = share(&app, &owner, &grantee, "viewer", None).await;
+    let second = share(&app, &owner, &grantee, "editor", None).await;
+    assert_eq!(first["id"], second["id"]);
+    assert_eq!(second["role"], "editor");
+    let path = format!("/api/shares/{}", first["id"].as_str().unwrap());
+
+    // Both sides list it; nobody else sees it
+    for user in [&owner, &grantee] {
+        let shares: Vec<Value> = app.get("/api/shares", &user.token).await.json().await.unwrap();
+        assert_eq!(shares.len(), 1);
+        assert_eq!(app.get(&path, &user.token).await.status(), 200);
+    }
+    assert_eq!(app.get(&path, &stranger.token).await.status(), 404);
+
+    // Only the owner changes it
+    let response = app.put_json(&path, &grantee.token, &json!({ "role": "viewer" })).await;
+    assert_eq!(response.status(), 403);
+    let response = app.put_json(&path, &owner.token, &json!({ "role": "viewer" })).await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["role"], "viewer");
+
+    // The grantee can leave it
+    assert_eq!(app.delete(&path, &grantee.token).await.status(), 204);
+    assert_eq!(app.get(&path, &owner.token).await.status(), 404);
+}
+
+#[tokio::test]
+async fn rejects_unknown_users_self_shares_and_other_users_todos() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let other = app.create_user().await;
+    let todo = app.create_todo(&other.token, json!({ "title": "Not yours" })).await;
+
+    let response = app
+        .post_json("/api/shares", Some(&owner.token), &json!({ "username": "nobody_here", "role": "viewer" }))
+        .await;
+    assert_eq!(response.status(), 400);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["field"], "username");
+    assert_eq!(body["errors"][0]["code"], "unknown_user");
+
+    let response = app
+        .post_json("/api/shares", Some(&owner.token), &json!({ "username": owner.username, "role": "viewer" }))
+        .await;
+    assert_eq!(response.status(), 400);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["code"], "self");
+
+    let response = app
+        .post_json(
+            "/api/shares",
+            Some(&owner.token),
+            &json!({ "username": other.username, "role": "viewer", "todo_id": todo["id"] }),
+        )
+        .await;
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn assigns_todos_to_users_who_can_see_them() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let editor = app.create_user().await;
+    let stranger = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Chore" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    let path = format!("/api/todos/{}/assignee", todo_id);
+    let list_share = share(&app, &owner, &editor, "editor", None).await;
+
+    let response = app.put_json(&path, &owner.token, &json!({ "assignee_id": stranger.id })).await;
+    assert_eq!(response.status(), 400);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["errors"][0]["field"], "assignee_id");
+    assert_eq!(body["errors"][0]["code"], "no_access");
+
+    // Editors can assign, including to themselves
+    let response = app.put_json(&path, &editor.token, &json!({ "assignee_id": editor.id })).await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["assignee_id"], editor.id.to_string());
+    assert_eq!(titles(&app, "/api/todos/assigned", &editor).await, ["Chore"]);
+    assert!(titles(&app, "/api/todos/assigned", &owner).await.is_empty());
+
+    // Revoking access hides the assignment
+    let share_path = format!("/api/shares/{}", list_share["id"].as_str().unwrap());
+    assert_eq!(app.delete(&share_path, &owner.token).await.status(), 204);
+    assert!(titles(&app, "/api/todos/assigned", &editor).await.is_empty());
+
+    let response = app.put_json(&path, &owner.token, &json!({ "assignee_id": owner.id })).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(titles(&app, "/api/todos/assigned", &owner).await, ["Chore"]);
+
+    let response = app.put_json(&path, &owner.token, &json!({ "assignee_id": null })).await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert!(body["assignee_id"].is_null());
+}
+
+#[tokio::test]
+async fn changes_by_editors_reach_the_owners_sync_feed() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let editor = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Synced" })).await;
+    let todo_id = todo["id"].as_str().unwrap();
+    share(&app, &owner, &editor, "editor", Some(todo_id)).await;
+
+    let full: Value = app.get("/api/sync", &owner.token).await.json().await.unwrap();
+    let token = full["token"].as_str().unwrap().to_string();
+
+    let response = app
+        .put_json(&format!("/api/todos/{}", todo_id), &editor.token, &json!({ "title": "Renamed" }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    let delta: Value = app.get(&format!("/api/sync?since={}", token), &owner.token).await.json().await.unwrap();
+    let todos = delta["todos"].as_array().unwrap();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0]["title"], "Renamed");
+
+    // The editor's own feed does not carry it
+    let editor_feed: Value = app.get("/api/sync", &editor.token).await.json().await.unwrap();
+    assert!(editor_feed["todos"].as_array().unwrap().is_empty());
+}
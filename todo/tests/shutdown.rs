@@ -0,0 +1,111 @@
+use axum::{routing::get, Router};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpListener, sync::Notify, task::JoinHandle, time::sleep};
+
+use todo_service::{
+    server,
+    shutdown::{Shutdown, ShutdownOptions},
+};
+
+struct Server {
+    address: String,
+    shutdown: Shutdown,
+    handle: JoinHandle<std::io::Result<()>>,
+    /// Notified each time the `/slow` handler starts.
+    started: Arc<Notify>,
+}
+
+/// Serves a router whose `/slow` handler takes `delay` to respond.
+async fn spawn(delay: Duration, options: ShutdownOptions) -> Server {
+    let started = Arc::new(Notify::new());
+    let notify = started.clone();
+    let app = Router::new().route(
+        "/slow",
+        get(move || async move {
+            notify.notify_one();
+            sleep(delay).await;
+            "done"
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    let shutdown = Shutdown::new();
+    let handle = tokio::spawn(server::serve(listener, app, shutdown.clone(), options));
+
+    Server { address, shutdown, handle, started }
+}
+
+#[tokio::test]
+async fn in_flight_requests_finish_before_server_exits() {
+    let server = spawn(
+        Duration::from_millis(500),
+        ShutdownOptions { drain_delay: Duration::ZERO, timeout: Duration::from_secs(5) },
+    )
+    .await;
+
+    let url = format!("{}/slow", server.address);
+    let in_flight = tokio::spawn(async move { reqwest::get(url).await });
+    server.started.notified().await;
+
+    server.shutdown.request();
+    assert!(server.shutdown.is_requested());
+
+    let response = in_flight.await.unwrap().expect("in-flight request was cut off");
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await.unwrap(), "done");
+
+    tokio::time::timeout(Duration::from_secs(2), server.handle)
+        .await
+        .expect("server did not exit after draining")
+        .unwrap()
+        .unwrap();
+
+    let refused = reqwest::Client::new()
+        .get(format!("{}/slow", server.address))
+        .timeout(Duration::from_secs(1))
+        .send()
+        .await;
+    assert!(refused.is_err(), "listener still accepting after shutdown");
+}
+
+#[tokio::test]
+async fn drain_delay_keeps_accepting_after_request() {
+    let server = spawn(
+        Duration::ZERO,
+        ShutdownOptions { drain_delay: Duration::from_millis(1500), timeout: Duration::from_secs(5) },
+    )
+    .await;
+
+    server.shutdown.request();
+
+    let response = reqwest::get(format!("{}/slow", server.address)).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    tokio::time::timeout(Duration::from_secs(4), server.handle)
+        .await
+        .expect("server did not exit after the drain delay")
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn timeout_bounds_how_long_shutdown_waits() {
+    let server = spawn(
+        Duration::from_secs(30),
+        ShutdownOptions { drain_delay: Duration::ZERO, timeout: Duration::from_millis(300) },
+    )
+    .await;
+
+    let url = format!("{}/slow", server.address);
+    let _stuck = tokio::spawn(async move { reqwest::get(url).await });
+    server.started.notified().await;
+
+    let started = Instant::now();
+    server.shutdown.request();
+    server.handle.await.unwrap().unwrap();
+
+    assert!(started.elapsed() < Duration::from_secs(2));
+}
@@ -0,0 +1,163 @@
+mod common;
+
+use chrono::{DateTime, Days, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde_json::{json, Value};
+
+use common::{TestApp, TestUser};
+use todo_service::{calendar, models::SnoozePreset};
+
+fn instant(value: &Value) -> DateTime<Utc> {
+    value.as_str().unwrap().parse().unwrap()
+}
+
+fn titles(todos: &Value) -> Vec<&str> {
+    todos.as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect()
+}
+
+async fn snooze(app: &TestApp, user: &TestUser, todo: &Value, body: Value) -> reqwest::Response {
+    app.post_json(&format!("/api/todos/{}/snooze", todo["id"].as_str().unwrap()), Some(&user.token), &body)
+        .await
+}
+
+#[test]
+fn presets_are_taken_in_the_users_time_zone() {
+    let new_york: Tz = "America/New_York".parse().unwrap();
+    let utc = |month, day, hour, minute| Utc.with_ymd_and_hms(2026, month, day, hour, minute, 0).unwrap();
+
+    // Saturday 7 March, 23:30 in New York; clocks go forward overnight
+    let now = utc(3, 8, 4, 30);
+    assert_eq!(calendar::snooze_until(SnoozePreset::LaterToday, new_york, now), utc(3, 8, 7, 30));
+    assert_eq!(calendar::snooze_until(SnoozePreset::TomorrowMorning, new_york, now), utc(3, 8, 13, 0));
+    assert_eq!(calendar::snooze_until(SnoozePreset::NextWeek, new_york, now), utc(3, 9, 13, 0));
+
+    // On a Monday, next week is the Monday after
+    let now = utc(3, 9, 14, 0);
+    assert_eq!(calendar::snooze_until(SnoozePreset::NextWeek, new_york, now), utc(3, 16, 13, 0));
+}
+
+#[tokio::test]
+async fn snoozed_todos_leave_listings_until_they_resurface() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let tomorrow = calendar::today(Tz::UTC, Utc::now()) + Days::new(1);
+    let todo = app.create_todo(&user.token, json!({ "title": "Call the bank", "scheduled_for": tomorrow })).await;
+    app.create_todo(&user.token, json!({ "title": "Water plants" })).await;
+    assert_eq!(todo["snooze_count"], 0);
+    assert!(todo["hidden_until"].is_null());
+
+    let before = Utc::now();
+    let response = snooze(&app, &user, &todo, json!({ "minutes": 90 })).await;
+    assert_eq!(response.status(), 200);
+    let snoozed: Value = response.json().await.unwrap();
+    let until = instant(&snoozed["hidden_until"]);
+    assert!(until >= before + chrono::Duration::minutes(90) && until <= Utc::now() + chrono::Duration::minutes(90));
+    assert_eq!(snoozed["scheduled_for"], snoozed["hidden_until"]);
+    assert_eq!(snoozed["snooze_count"], 1);
+    assert_eq!(snoozed["original_scheduled_for"], todo["scheduled_for"]);
+
+    let listed: Value = app.get("/api/todos", &user.token).await.json().await.unwrap();
+    assert_eq!(titles(&listed), ["Water plants"]);
+    let listed: Value = app.get("/api/todos?include_hidden=true", &user.token).await.json().await.unwrap();
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+    let path = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    assert_eq!(app.get(&path, &user.token).await.status(), 200);
+
+    // Snoozing again keeps the first schedule as the original
+    let snoozed: Value = snooze(&app, &user, &todo, json!({ "preset": "next_week" })).await.json().await.unwrap();
+    assert_eq!(snoozed["snooze_count"], 2);
+    assert_eq!(snoozed["original_scheduled_for"], todo["scheduled_for"]);
+    assert!(instant(&snoozed["hidden_until"]) > until);
+
+    let response = app.delete(&format!("{}/snooze", path), &user.token).await;
+    assert_eq!(response.status(), 200);
+    let woken: Value = response.json().await.unwrap();
+    assert!(woken["hidden_until"].is_null());
+    assert_eq!(woken["scheduled_for"], snoozed["scheduled_for"]);
+    assert_eq!(woken["snooze_count"], 2);
+    let listed: Value = app.get("/api/todos", &user.token).await.json().await.unwrap();
+    assert_eq!(listed.as_array().unwrap().len(), 2);
+
+    // A todo without a schedule gets one
+    let someday = app.create_todo(&user.token, json!({ "title": "Someday" })).await;
+    let snoozed: Value = snooze(&app, &user, &someday, json!({ "preset": "tomorrow_morning" })).await.json().await.unwrap();
+    assert!(snoozed["original_scheduled_for"].is_null());
+    assert_eq!(snoozed["scheduled_for"], snoozed["hidden_until"]);
+}
+
+#[tokio::test]
+async fn rescheduling_a_snoozed_todo_brings_it_back() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Renew passport" })).await;
+    assert_eq!(snooze(&app, &user, &todo, json!({ "preset": "next_week" })).await.status(), 200);
+    let path = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+
+    // Other edits leave it hidden
+    let response = app.put_json(&path, &user.token, &json!({ "title": "Renew passport soon" })).await;
+    assert_eq!(response.status(), 200);
+    let edited: Value = response.json().await.unwrap();
+    assert!(edited["hidden_until"].is_string());
+
+    let tomorrow = calendar::today(Tz::UTC, Utc::now()) + Days::new(1);
+    let response = app.put_json(&path, &user.token, &json!({ "scheduled_for": tomorrow })).await;
+    assert_eq!(response.status(), 200);
+    let rescheduled: Value = response.json().await.unwrap();
+    assert!(rescheduled["hidden_until"].is_null());
+    assert_eq!(rescheduled["snooze_count"], 1);
+
+    let listed: Value = app.get("/api/todos", &user.token).await.json().await.unwrap();
+    assert_eq!(titles(&listed), ["Renew passport soon"]);
+}
+
+#[tokio::test]
+async fn snoozed_todos_are_hidden_from_shared_and_agenda_views() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let grantee = app.create_user().await;
+    let today = calendar::today(Tz::UTC, Utc::now());
+    let todo = app.create_todo(&owner.token, json!({ "title": "Today", "scheduled_for": today })).await;
+    let response = app
+        .post_json("/api/shares", Some(&owner.token), &json!({ "username": grantee.username, "role": "viewer" }))
+        .await;
+    assert_eq!(response.status(), 201);
+
+    // One minute on is still today, but the todo stays hidden until then
+    assert_eq!(snooze(&app, &owner, &todo, json!({ "minutes": 1 })).await.status(), 200);
+    let agenda: Value = app.get("/api/todos/today", &owner.token).await.json().await.unwrap();
+    assert_eq!(titles(&agenda["todos"]), Vec::<&str>::new());
+    let shared: Value = app.get("/api/todos/shared", &grantee.token).await.json().await.unwrap();
+    assert_eq!(shared, json!([]));
+
+    // Viewers cannot snooze
+    assert_eq!(snooze(&app, &grantee, &todo, json!({ "minutes": 5 })).await.status(), 403);
+
+    // Offline clients still get it, with the fields to hide it themselves
+    let changes: Value = app.get("/api/sync", &owner.token).await.json().await.unwrap();
+    let synced = &changes["todos"][0];
+    assert_eq!(synced["snooze_count"], 1);
+    assert!(synced["hidden_until"].is_string());
+}
+
+#[tokio::test]
+async fn snoozes_need_one_preset_or_a_number_of_minutes() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Later" })).await;
+
+    for (body, field, code) in [
+        (json!({}), "preset", "required"),
+        (json!({ "preset": "later_today", "minutes": 5 }), "minutes", "exclusive"),
+        (json!({ "minutes": 0 }), "minutes", "out_of_range"),
+        (json!({ "minutes": 525_601 }), "minutes", "out_of_range"),
+    ] {
+        let response = snooze(&app, &user, &todo, body.clone()).await;
+        assert_eq!(response.status(), 400, "{}", body);
+        let problem: Value = response.json().await.unwrap();
+        assert_eq!(problem["errors"][0]["field"], field);
+        assert_eq!(problem["errors"][0]["code"], code);
+    }
+
+    let response = snooze(&app, &user, &todo, json!({ "preset": "someday" })).await;
+    assert_eq!(response.status(), 400);
+}
@@ -0,0 +1,206 @@
+mod common;
+
+use chrono::{Days, NaiveDate, TimeZone, Utc};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{TestApp, TestUser};
+use todo_service::{
+    calendar,
+    models::DayCount,
+    stats::{self, StatsCounts, StatsRange},
+};
+
+fn today_in(timezone: &str) -> NaiveDate {
+    calendar::today(timezone.parse().unwrap(), Utc::now())
+}
+
+async fn stats(app: &TestApp, user: &TestUser, query: &str) -> Value {
+    let response = app.get(&format!("/api/stats{}", query), &user.token).await;
+    assert_eq!(response.status(), 200);
+    response.json().await.unwrap()
+}
+
+async fn complete(app: &TestApp, user: &TestUser, todo: &Value, completed: bool) -> Value {
+    let path = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    let response = app.put_json(&path, &user.token, &json!({ "completed": completed })).await;
+    assert_eq!(response.status(), 200);
+    response.json().await.unwrap()
+}
+
+#[test]
+fn days_weeks_and_streaks_are_filled_in() {
+    let date = |day| NaiveDate::from_ymd_opt(2026, 3, day).unwrap();
+    // Sunday 1 March to Thursday 12 March, seen on the 12th before anything was done
+    let range = StatsRange::new(chrono_tz::UTC, date(1), date(12), Utc.with_ymd_and_hms(2026, 3, 12, 9, 0, 0).unwrap());
+    let completed_per_day = [1, 2, 3, 5, 9, 10, 11]
+        .into_iter()
+        .map(|day| DayCount { date: date(day), count: 2 })
+        .collect();
+    let response = stats::summarize(&range, StatsCounts { completed_per_day, ..Default::default() });
+
+    assert_eq!(response.completed_per_day.len(), 12);
+    assert_eq!(response.completed_per_day[3].count, 0);
+    let weeks: Vec<_> = response.completed_per_week.iter().map(|w| (w.week_start, w.count)).collect();
+    assert_eq!(weeks, [(NaiveDate::from_ymd_opt(2026, 2, 23).unwrap(), 2), (date(2), 6), (date(9), 6)]);
+    // Nothing done today yet does not break the run of the 9th to the 11th
+    assert_eq!((response.streaks.current, response.streaks.longest), (3, 3));
+
+    // Once today is over, it does
+    let range = StatsRange::new(chrono_tz::UTC, date(1), date(12), Utc.with_ymd_and_hms(2026, 3, 13, 9, 0, 0).unwrap());
+    let completed_per_day = vec![DayCount { date: date(11), count: 1 }];
+    let response = stats::summarize(&range, StatsCounts { completed_per_day, ..Default::default() });
+    assert_eq!((response.streaks.current, response.streaks.longest), (0, 1));
+}
+
+#[tokio::test]
+async fn completions_are_counted_with_their_time_and_tags() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let today = today_in("UTC");
+
+    let report = app.create_todo(&user.token, json!({ "title": "Report", "tags": ["work"] })).await;
+    assert!(report["completed_at"].is_null());
+    let done = complete(&app, &user, &report, true).await;
+    let completed_at = done["completed_at"].as_str().unwrap().parse::<chrono::DateTime<Utc>>().unwrap();
+    assert!(completed_at >= done["created_at"].as_str().unwrap().parse::<chrono::DateTime<Utc>>().unwrap());
+
+    app.create_todo(&user.token, json!({ "title": "Shopping", "tags": ["home", "work"], "completed": true })).await;
+    let reopened = app.create_todo(&user.token, json!({ "title": "Taxes", "tags": ["home"] })).await;
+    complete(&app, &user, &reopened, true).await;
+    assert!(complete(&app, &user, &reopened, false).await["completed_at"].is_null());
+
+    // Only sync takes a due time already past
+    let due = Utc::now() - chrono::Duration::hours(2);
+    let mutation = |id: Uuid, title: &str, scheduled_for| {
+        json!({ "id": id, "op": "upsert", "changed_at": Utc::now(), "title": title, "scheduled_for": scheduled_for })
+    };
+    let late = Uuid::new_v4();
+    let response = app
+        .post_json(
+            "/api/sync",
+            Some(&user.token),
+            &json!({ "mutations": [mutation(late, "Late", due), mutation(Uuid::new_v4(), "Overdue", due)] }),
+        )
+        .await;
+    assert_eq!(response.status(), 200);
+    let late: Value = app.get(&format!("/api/todos/{}", late), &user.token).await.json().await.unwrap();
+    complete(&app, &user, &late, true).await;
+
+    let body = stats(&app, &user, "").await;
+    assert_eq!(body["timezone"], "UTC");
+    assert_eq!(body["to"], today.to_string());
+    assert_eq!(body["from"], (today - Days::new(29)).to_string());
+    assert_eq!(body["created"], 5);
+    assert_eq!(body["completed"], 3);
+    assert_eq!(body["completed_late"], 1);
+    assert_eq!(body["overdue"], 1);
+    let average = body["average_completion_seconds"].as_f64().unwrap();
+    assert!((0.0..60.0).contains(&average), "{}", average);
+
+    let days = body["completed_per_day"].as_array().unwrap();
+    assert_eq!(days.len(), 30);
+    assert_eq!(days[29], json!({ "date": today.to_string(), "count": 3 }));
+    let weeks = body["completed_per_week"].as_array().unwrap();
+    assert_eq!(weeks.iter().map(|w| w["count"].as_i64().unwrap()).sum::<i64>(), 3);
+    assert_eq!(body["streaks"], json!({ "current": 1, "longest": 1 }));
+
+    assert_eq!(
+        body["by_list"],
+        json!([{ "owner_id": user.id, "username": user.username, "created": 5, "completed": 3 }])
+    );
+    assert_eq!(
+        body["by_tag"],
+        json!([
+            { "tag": "work", "created": 2, "completed": 2 },
+            { "tag": "home", "created": 2, "completed": 1 },
+        ])
+    );
+
+    // A range before anything happened
+    let body = stats(&app, &user, &format!("?from={}&to={}", today - Days::new(10), today - Days::new(1))).await;
+    assert_eq!((body["created"].as_i64(), body["completed"].as_i64()), (Some(0), Some(0)));
+    assert!(body["average_completion_seconds"].is_null());
+    assert_eq!(body["completed_per_day"].as_array().unwrap().len(), 10);
+    assert_eq!(body["by_list"], json!([]));
+}
+
+#[tokio::test]
+async fn days_are_taken_in_the_requested_time_zone() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    app.create_todo(&user.token, json!({ "title": "Done", "completed": true })).await;
+
+    // Twenty-five hours apart, so never on the same date
+    for timezone in ["Pacific/Kiritimati", "Pacific/Pago_Pago"] {
+        let today = today_in(timezone);
+        let body = stats(&app, &user, &format!("?timezone={}&from={}&to={}", timezone, today - Days::new(2), today + Days::new(2))).await;
+        assert_eq!(body["timezone"], timezone);
+        let busy: Vec<&Value> = body["completed_per_day"].as_array().unwrap().iter().filter(|d| d["count"] == 1).collect();
+        assert_eq!(busy.len(), 1);
+        assert_eq!(busy[0]["date"], today.to_string());
+    }
+
+    // Without one, the user's own
+    let response = app.put_json("/api/preferences", &user.token, &json!({ "timezone": "Pacific/Kiritimati" })).await;
+    assert_eq!(response.status(), 200);
+    let body = stats(&app, &user, "").await;
+    assert_eq!(body["timezone"], "Pacific/Kiritimati");
+    assert_eq!(body["to"], today_in("Pacific/Kiritimati").to_string());
+}
+
+#[tokio::test]
+async fn shared_lists_count_towards_the_grantees_figures() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let grantee = app.create_user().await;
+    app.create_todo(&owner.token, json!({ "title": "Theirs", "completed": true })).await;
+    app.create_todo(&grantee.token, json!({ "title": "Mine" })).await;
+
+    let body = stats(&app, &grantee, "").await;
+    assert_eq!(body["created"], 1);
+
+    let response = app
+        .post_json("/api/shares", Some(&owner.token), &json!({ "username": grantee.username, "role": "viewer" }))
+        .await;
+    assert_eq!(response.status(), 201);
+
+    let body = stats(&app, &grantee, "").await;
+    assert_eq!(body["created"], 2);
+    assert_eq!(body["completed"], 1);
+    assert_eq!(
+        body["by_list"],
+        json!([
+            { "owner_id": owner.id, "username": owner.username, "created": 1, "completed": 1 },
+            { "owner_id": grantee.id, "username": grantee.username, "created": 1, "completed": 0 },
+        ])
+    );
+
+    // The owner's figures are their own
+    assert_eq!(stats(&app, &owner, "").await["created"], 1);
+}
+
+#[tokio::test]
+async fn ranges_are_checked() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let today = today_in("UTC");
+
+    for (query, field, code) in [
+        (format!("?from={}&to={}", today, today - Days::new(1)), "from", "after_to"),
+        (format!("?from={}&to={}", today - Days::new(366), today), "from", "out_of_range"),
+        ("?timezone=Mars/Olympus".to_string(), "timezone", "unknown_timezone"),
+    ] {
+        let response = app.get(&format!("/api/stats{}", query), &user.token).await;
+        assert_eq!(response.status(), 400, "{}", query);
+        let problem: Value = response.json().await.unwrap();
+        assert_eq!(problem["errors"][0]["field"], field);
+        assert_eq!(problem["errors"][0]["code"], code);
+    }
+
+    // 366 days is the most
+    let response = app.get(&format!("/api/stats?from={}&to={}", today - Days::new(365), today), &user.token).await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(body["completed_per_day"].as_array().unwrap().len(), 366);
+}
@@ -0,0 +1,337 @@
+mod common;
+
+use chrono::{Duration, Utc};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{TestApp, TestUser};
+
+async fn pull(app: &TestApp, user: &TestUser, since: Option<&str>) -> Value {
+    let path = match since {
+        Some(token) => format!("/api/sync?since={}", token),
+        None => "/api/sync".to_string(),
+    };
+    let response = app.get(&path, &user.token).await;
+    assert_eq!(response.status(), 200);
+    response.json().await.unwrap()
+}
+
+async fn push(app: &TestApp, user: &TestUser, mutations: Value) -> Vec<Value> {
+    let response = app
+        .post_json("/api/sync", Some(&user.token), &json!({ "mutations": mutations }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let body: Value = response.json().await.unwrap();
+    body["results"].as_array().unwrap().clone()
+}
+
+/// A time comfortably before the server's own changes.
+fn earlier() -> String {
+    (Utc::now() - Duration::hours(1)).to_rfc3339()
+}
+
+/// A time after the server's changes; the server caps it at its own clock.
+fn later() -> String {
+    (Utc::now() + Duration::hours(1)).to_rfc3339()
+}
+
+#[tokio::test]
+async fn pulls_everything_then_only_new_changes() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let other = app.create_user().await;
+    let first = app.create_todo(&user.token, json!({ "title": "First" })).await;
+    let second = app.create_todo(&user.token, json!({ "title": "Second" })).await;
+    app.create_todo(&other.token, json!({ "title": "Not mine" })).await;
+
+    let full = pull(&app, &user, None).await;
+    let titles: Vec<&str> = full["todos"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["First", "Second"]);
+    assert_eq!(full["has_more"], false);
+    let token = full["token"].as_str().unwrap().to_string();
+
+    // Nothing new yet
+    let empty = pull(&app, &user, Some(&token)).await;
+    assert!(empty["todos"].as_array().unwrap().is_empty());
+    assert_eq!(empty["token"], token);
+
+    // Changes made over REST show up, including deletions as tombstones
+    app.put_json(&format!("/api/todos/{}", first["id"].as_str().unwrap()), &user.token, &json!({ "completed": true }))
+        .await;
+    app.delete(&format!("/api/todos/{}", second["id"].as_str().unwrap()), &user.token).await;
+
+    let delta = pull(&app, &user, Some(&token)).await;
+    let todos = delta["todos"].as_array().unwrap();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0]["id"], first["id"]);
+    assert_eq!(todos[0]["completed"], true);
+    let deleted = delta["deleted"].as_array().unwrap();
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0]["id"], second["id"]);
+    assert_ne!(delta["token"], token);
+}
+
+#[tokio::test]
+async fn rejects_invalid_tokens() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    for token in ["abc", "-1"] {
+        let response = app.get(&format!("/api/sync?since={}", token), &user.token).await;
+        assert_eq!(response.status(), 400);
+    }
+
+    let response = reqwest::get(app.url("/api/sync")).await.unwrap();
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn pages_large_change_sets() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let mutations: Vec<Value> = (0..500)
+        .map(|i| json!({ "id": Uuid::new_v4(), "op": "upsert", "changed_at": earlier(), "title": format!("Todo {}", i) }))
+        .collect();
+    push(&app, &user, json!(mutations)).await;
+    app.create_todo(&user.token, json!({ "title": "One more" })).await;
+
+    let page = pull(&app, &user, None).await;
+    assert_eq!(page["todos"].as_array().unwrap().len(), 500);
+    assert_eq!(page["has_more"], true);
+
+    let rest = pull(&app, &user, page["token"].as_str()).await;
+    let todos = rest["todos"].as_array().unwrap();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0]["title"], "One more");
+    assert_eq!(rest["has_more"], false);
+}
+
+#[tokio::test]
+async fn creates_todos_with_client_ids_idempotently() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let id = Uuid::new_v4();
+    let mutation = json!([{
+        "id": id,
+        "op": "upsert",
+        "changed_at": earlier(),
+        "title": "Written offline",
+        "description": "On the train",
+        "scheduled_for": earlier(),
+    }]);
+
+    let results = push(&app, &user, mutation.clone()).await;
+    assert_eq!(results[0]["status"], "applied");
+    assert_eq!(results[0]["todo"]["id"], id.to_string());
+    assert_eq!(results[0]["todo"]["title"], "Written offline");
+
+    // Retrying the batch changes nothing
+    let results = push(&app, &user, mutation).await;
+    assert_eq!(results[0]["status"], "applied");
+    let todos: Value = app.get("/api/todos", &user.token).await.json().await.unwrap();
+    assert_eq!(todos.as_array().unwrap().len(), 1);
+    assert_eq!(todos[0]["description"], "On the train");
+}
+
+#[tokio::test]
+async fn resolves_conflicts_per_field() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let id = Uuid::new_v4().to_string();
+    let created = (Utc::now() - Duration::hours(2)).to_rfc3339();
+    push(
+        &app,
+        &user,
+        json!([{ "id": id, "op": "upsert", "changed_at": created, "title": "Original", "description": "Notes" }]),
+    )
+    .await;
+    app.put_json(&format!("/api/todos/{}", id), &user.token, &json!({ "title": "Renamed on the web" }))
+        .await;
+
+    // An offline edit from before the rename loses the title but keeps its
+    // completion, which the server has not changed since
+    let results = push(
+        &app,
+        &user,
+        json!([{ "id": id, "op": "upsert", "changed_at": earlier(), "title": "Renamed offline", "completed": true }]),
+    )
+    .await;
+    assert_eq!(results[0]["status"], "conflict");
+    let conflicts = results[0]["conflicts"].as_array().unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0]["field"], "title");
+    assert_eq!(conflicts[0]["server_value"], "Renamed on the web");
+    assert_eq!(results[0]["todo"]["completed"], true);
+    assert_eq!(results[0]["todo"]["title"], "Renamed on the web");
+
+    // A later edit wins, and null clears the description
+    let results = push(
+        &app,
+        &user,
+        json!([{ "id": id, "op": "upsert", "changed_at": later(), "title": "Final", "description": null }]),
+    )
+    .await;
+    assert_eq!(results[0]["status"], "applied");
+    assert!(results[0].get("conflicts").is_none());
+
+    let stored: Value = app.get(&format!("/api/todos/{}", id), &user.token).await.json().await.unwrap();
+    assert_eq!(stored["title"], "Final");
+    assert!(stored["description"].is_null());
+}
+
+#[tokio::test]
+async fn syncs_priority_tags_and_recurrence() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let id = Uuid::new_v4().to_string();
+    let created = (Utc::now() - Duration::hours(2)).to_rfc3339();
+    let results = push(
+        &app,
+        &user,
+        json!([{
+            "id": id, "op": "upsert", "changed_at": created, "title": "Water plants",
+            "priority": "high", "tags": ["Home", "home", "garden"], "recurrence": "FREQ=WEEKLY"
+        }]),
+    )
+    .await;
+    assert_eq!(results[0]["status"], "applied");
+    let stored: Value = app.get(&format!("/api/todos/{}", id), &user.token).await.json().await.unwrap();
+    assert_eq!(stored["priority"], "high");
+    assert_eq!(stored["tags"], json!(["home", "garden"]));
+    assert_eq!(stored["recurrence"], "FREQ=WEEKLY");
+
+    // Each field has its own version: a web edit of the priority wins over
+    // an older offline one, while the tags, untouched since, are replaced
+    app.put_json(&format!("/api/todos/{}", id), &user.token, &json!({ "priority": "low" }))
+        .await;
+    let results = push(
+        &app,
+        &user,
+        json!([{ "id": id, "op": "upsert", "changed_at": earlier(), "priority": "medium", "tags": [] }]),
+    )
+    .await;
+    assert_eq!(results[0]["status"], "conflict");
+    assert_eq!(results[0]["conflicts"][0]["field"], "priority");
+    assert_eq!(results[0]["conflicts"][0]["server_value"], "low");
+    assert_eq!(results[0]["todo"]["tags"], json!([]));
+
+    let results = push(
+        &app,
+        &user,
+        json!([{ "id": id, "op": "upsert", "changed_at": later(), "priority": null, "recurrence": null }]),
+    )
+    .await;
+    assert_eq!(results[0]["status"], "applied");
+    assert!(results[0]["todo"]["priority"].is_null());
+    assert!(results[0]["todo"]["recurrence"].is_null());
+
+    let results = push(
+        &app,
+        &user,
+        json!([
+            { "id": id, "op": "upsert", "changed_at": later(), "tags": ["no spaces"] },
+            { "id": id, "op": "upsert", "changed_at": later(), "recurrence": "every day" }
+        ]),
+    )
+    .await;
+    assert_eq!(results[0]["status"], "rejected");
+    assert_eq!(results[1]["status"], "rejected");
+}
+
+#[tokio::test]
+async fn rescheduling_a_snoozed_todo_brings_it_back() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Snoozed" })).await;
+    let id = todo["id"].as_str().unwrap();
+    let response = app
+        .post_json(&format!("/api/todos/{}/snooze", id), Some(&user.token), &json!({ "preset": "next_week" }))
+        .await;
+    assert_eq!(response.status(), 200);
+
+    let tomorrow = (Utc::now() + Duration::days(1)).to_rfc3339();
+    let results = push(
+        &app,
+        &user,
+        json!([{ "id": id, "op": "upsert", "changed_at": later(), "scheduled_for": tomorrow }]),
+    )
+    .await;
+    assert_eq!(results[0]["status"], "applied");
+    assert!(results[0]["todo"]["hidden_until"].is_null());
+
+    let listed: Value = app.get("/api/todos", &user.token).await.json().await.unwrap();
+    assert_eq!(listed[0]["id"], id);
+    assert!(listed[0]["hidden_until"].is_null());
+}
+
+#[tokio::test]
+async fn deletions_lose_to_later_edits_and_win_otherwise() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Contested" })).await;
+    let id = todo["id"].as_str().unwrap();
+
+    let results = push(&app, &user, json!([{ "id": id, "op": "delete", "changed_at": earlier() }])).await;
+    assert_eq!(results[0]["status"], "conflict");
+    assert_eq!(results[0]["todo"]["title"], "Contested");
+
+    let results = push(&app, &user, json!([{ "id": id, "op": "delete", "changed_at": later() }])).await;
+    assert_eq!(results[0]["status"], "applied");
+    assert!(results[0]["todo"].is_null());
+    assert_eq!(app.get(&format!("/api/todos/{}", id), &user.token).await.status(), 404);
+
+    // A deleted todo is not brought back by a stale edit
+    let results = push(
+        &app,
+        &user,
+        json!([{ "id": id, "op": "upsert", "changed_at": later(), "title": "Resurrected" }]),
+    )
+    .await;
+    assert_eq!(results[0]["status"], "conflict");
+    assert_eq!(results[0]["conflicts"][0]["field"], "deleted");
+
+    let changes = pull(&app, &user, None).await;
+    assert!(changes["todos"].as_array().unwrap().is_empty());
+    assert_eq!(changes["deleted"][0]["id"], id);
+}
+
+#[tokio::test]
+async fn rejects_invalid_mutations_individually() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let other = app.create_user().await;
+    let theirs = app.create_todo(&other.token, json!({ "title": "Not yours" })).await;
+
+    let results = push(
+        &app,
+        &user,
+        json!([
+            { "id": theirs["id"], "op": "upsert", "changed_at": later(), "title": "Hijacked" },
+            { "id": Uuid::new_v4(), "op": "upsert", "changed_at": later(), "completed": true },
+            { "id": Uuid::new_v4(), "op": "upsert", "changed_at": later(), "title": "" },
+            { "id": Uuid::new_v4(), "op": "upsert", "changed_at": later(), "title": "Fine" },
+        ]),
+    )
+    .await;
+    let statuses: Vec<&str> = results.iter().map(|r| r["status"].as_str().unwrap()).collect();
+    assert_eq!(statuses, ["rejected", "rejected", "rejected", "applied"]);
+    assert_eq!(results[0]["error"]["code"], "bad_request");
+    assert_eq!(results[1]["error"]["errors"][0]["field"], "title");
+    assert_eq!(results[2]["error"]["code"], "validation_failed");
+
+    let stored: Value = app
+        .get(&format!("/api/todos/{}", theirs["id"].as_str().unwrap()), &other.token)
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(stored["title"], "Not yours");
+
+    let too_many: Vec<Value> = (0..501)
+        .map(|_| json!({ "id": Uuid::new_v4(), "op": "delete", "changed_at": later() }))
+        .collect();
+    let response = app
+        .post_json("/api/sync", Some(&user.token), &json!({ "mutations": too_many }))
+        .await;
+    assert_eq!(response.status(), 400);
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use serde_json::{json, Value};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+use tracing_subscriber::{fmt::MakeWriter, layer::SubscriberExt, EnvFilter};
+
+use common::TestApp;
+use todo_service::telemetry::{self, LogFormat};
+
+/// Collects everything the subscriber writes.
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Captured {
+    fn lines(&self) -> Vec<Value> {
+        let bytes = self.0.lock().unwrap().clone();
+        String::from_utf8(bytes)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("log line is JSON"))
+            .collect()
+    }
+}
+
+impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for Captured {
+    type Writer = Captured;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// The `request` span of the response log line for `route`.
+fn request_span<'a>(lines: &'a [Value], route: &str) -> Option<&'a Value> {
+    lines
+        .iter()
+        .filter(|line| line["fields"]["message"] == "finished processing request")
+        .filter_map(|line| line["spans"].as_array()?.iter().find(|s| s["name"] == "request"))
+        .find(|span| span["route"] == route)
+}
+
+#[tokio::test]
+async fn request_spans_carry_request_id_route_and_user() {
+    let captured = Captured::default();
+    let subscriber = tracing_subscriber::registry()
+        .with(EnvFilter::new("info"))
+        .with(telemetry::fmt_layer(LogFormat::Json, captured.clone()));
+    // Tests run on a current-thread runtime, so the server tasks share this
+    // thread's default subscriber.
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Traced" })).await;
+
+    let response = app
+        .client
+        .get(app.url(&format!("/api/todos/{}", todo["id"].as_str().unwrap())))
+        .bearer_auth(&user.token)
+        .header("X-Request-Id", "trace-me-123")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    let lines = captured.lines();
+    let span = request_span(&lines, "/api/todos/:id").expect("no request span logged for the route");
+
+    assert_eq!(span["method"], "GET");
+    assert_eq!(span["request_id"], "trace-me-123");
+    assert_eq!(span["user_id"], user.id.to_string());
+
+    let register = request_span(&lines, "/api/auth/register").expect("no request span for register");
+    assert!(register.get("user_id").is_none(), "anonymous requests have no user");
+    assert!(!register["request_id"].as_str().unwrap().is_empty());
+}
+
+#[test]
+fn log_format_parses_config_values() {
+    assert_eq!("json".parse::<LogFormat>(), Ok(LogFormat::Json));
+    assert_eq!("TEXT".parse::<LogFormat>(), Ok(LogFormat::Text));
+    assert!("xml".parse::<LogFormat>().is_err());
+}
@@ -0,0 +1,289 @@
+mod common;
+
+use chrono::{DateTime, Duration, NaiveDate, SubsecRound, Utc};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{TestApp, TestUser};
+use todo_service::{
+    models::TimeReportRow,
+    stats::StatsRange,
+    time_tracking,
+};
+
+fn entries_path(todo: &Value) -> String {
+    format!("/api/todos/{}/time-entries", todo["id"].as_str().unwrap())
+}
+
+async fn timer(app: &TestApp, user: &TestUser, todo: &Value, action: &str) -> reqwest::Response {
+    app.post_json(&format!("/api/todos/{}/timer/{}", todo["id"].as_str().unwrap(), action), Some(&user.token), &json!({}))
+        .await
+}
+
+async fn tracked_seconds(app: &TestApp, user: &TestUser, todo: &Value) -> i64 {
+    let todo: Value = app
+        .get(&format!("/api/todos/{}", todo["id"].as_str().unwrap()), &user.token)
+        .await
+        .json()
+        .await
+        .unwrap();
+    todo["tracked_seconds"].as_i64().unwrap()
+}
+
+/// Whole seconds, so times read back compare equal on every backend.
+fn hours_ago(hours: i64) -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(0) - Duration::hours(hours)
+}
+
+#[test]
+fn reports_fill_in_days_and_quote_csv_fields() {
+    let date = |day| NaiveDate::from_ymd_opt(2026, 3, day).unwrap();
+    let range = StatsRange::new(chrono_tz::UTC, date(1), date(3), Utc::now());
+    let (mine, theirs) = (Uuid::new_v4(), Uuid::new_v4());
+    let row = |day, owner_id, username: &str, seconds| TimeReportRow { date: date(day), owner_id, username: username.to_string(), seconds };
+    let report = time_tracking::summarize(
+        &range,
+        vec![row(3, mine, "me", 1800), row(1, theirs, "acme, \"inc\"", 5400), row(3, theirs, "acme, \"inc\"", 600)],
+    );
+
+    assert_eq!(report.total_seconds, 7800);
+    let days: Vec<_> = report.by_day.iter().map(|d| (d.date, d.seconds)).collect();
+    assert_eq!(days, [(date(1), 5400), (date(2), 0), (date(3), 2400)]);
+    let lists: Vec<_> = report.by_list.iter().map(|l| (l.owner_id, l.seconds)).collect();
+    assert_eq!(lists, [(theirs, 6000), (mine, 1800)]);
+
+    let csv = time_tracking::to_csv(&report);
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "date,owner_id,list,seconds,hours");
+    assert_eq!(lines[1], format!("2026-03-01,{},\"acme, \"\"inc\"\"\",5400,1.50", theirs));
+    assert_eq!(lines.len(), 4);
+}
+
+#[test]
+fn csv_cells_never_open_as_formulas() {
+    let date = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+    let range = StatsRange::new(chrono_tz::UTC, date, date, Utc::now());
+    let names = ["=HYPERLINK(\"http://evil\")", "+1", "-1", "@SUM(A1)", "\tcmd", "\rcmd", "plain-name"];
+    let rows = names
+        .iter()
+        .map(|name| TimeReportRow { date, owner_id: Uuid::nil(), username: name.to_string(), seconds: 60 })
+        .collect();
+    let csv = time_tracking::to_csv(&time_tracking::summarize(&range, rows));
+
+    let cells: Vec<&str> = csv.split("\r\n").skip(1).filter(|line| !line.is_empty()).map(|line| line.split(',').nth(2).unwrap()).collect();
+    assert_eq!(
+        cells,
+        ["\"'\tcmd\"", "\"'\rcmd\"", "\"'+1\"", "\"'-1\"", "\"'=HYPERLINK(\"\"http://evil\"\")\"", "\"'@SUM(A1)\"", "plain-name"]
+    );
+}
+
+#[tokio::test]
+async fn one_timer_runs_at_a_time() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let first = app.create_todo(&user.token, json!({ "title": "Write invoice" })).await;
+    let second = app.create_todo(&user.token, json!({ "title": "Review contract" })).await;
+    assert_eq!(first["tracked_seconds"], 0);
+
+    let response = app.get("/api/timer", &user.token).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.json::<Value>().await.unwrap(), Value::Null);
+
+    let response = timer(&app, &user, &first, "start").await;
+    assert_eq!(response.status(), 201);
+    let started: Value = response.json().await.unwrap();
+    assert_eq!(started["running"]["todo_id"], first["id"]);
+    assert!(started["running"]["ended_at"].is_null());
+    assert!(started["stopped"].is_null());
+    let running: Value = app.get("/api/timer", &user.token).await.json().await.unwrap();
+    assert_eq!(running["id"], started["running"]["id"]);
+
+    // Starting another stops the first
+    let switched: Value = timer(&app, &user, &second, "start").await.json().await.unwrap();
+    assert_eq!(switched["stopped"]["id"], started["running"]["id"]);
+    assert!(switched["stopped"]["ended_at"].is_string());
+    assert!(switched["stopped"]["duration_seconds"].as_i64().unwrap() >= 0);
+    assert_eq!(timer(&app, &user, &first, "stop").await.status(), 404);
+
+    let response = timer(&app, &user, &second, "stop").await;
+    assert_eq!(response.status(), 200);
+    let stopped: Value = response.json().await.unwrap();
+    assert_eq!(stopped["id"], switched["running"]["id"]);
+    assert_eq!(app.get("/api/timer", &user.token).await.json::<Value>().await.unwrap(), Value::Null);
+    assert_eq!(timer(&app, &user, &second, "stop").await.status(), 404);
+
+    let entries: Value = app.get(&entries_path(&first), &user.token).await.json().await.unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 1);
+    assert_eq!(tracked_seconds(&app, &user, &second).await, stopped["duration_seconds"].as_i64().unwrap());
+
+    // Timers of different users run side by side
+    let other = app.create_user().await;
+    let theirs = app.create_todo(&other.token, json!({ "title": "Theirs" })).await;
+    assert_eq!(timer(&app, &user, &first, "start").await.status(), 201);
+    let started: Value = timer(&app, &other, &theirs, "start").await.json().await.unwrap();
+    assert!(started["stopped"].is_null());
+    assert_eq!(timer(&app, &other, &first, "start").await.status(), 404);
+}
+
+#[tokio::test]
+async fn manual_entries_are_checked_and_keep_the_total() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Client call" })).await;
+    let path = entries_path(&todo);
+
+    let response = app
+        .post_json(
+            &path,
+            Some(&user.token),
+            &json!({ "started_at": hours_ago(2), "ended_at": hours_ago(1), "note": "Kick-off" }),
+        )
+        .await;
+    assert_eq!(response.status(), 201);
+    let entry: Value = response.json().await.unwrap();
+    assert_eq!(entry["duration_seconds"], 3600);
+    assert_eq!(entry["note"], "Kick-off");
+    assert_eq!(entry["user_id"], user.id.to_string());
+    assert_eq!(tracked_seconds(&app, &user, &todo).await, 3600);
+
+    for (body, field, code) in [
+        (json!({ "started_at": hours_ago(1), "ended_at": hours_ago(2) }), "ended_at", "before_start"),
+        (json!({ "started_at": hours_ago(1), "ended_at": hours_ago(1) }), "ended_at", "before_start"),
+        (json!({ "started_at": hours_ago(1), "ended_at": hours_ago(-1) }), "ended_at", "in_future"),
+        (json!({ "started_at": hours_ago(-1), "ended_at": hours_ago(-2) }), "started_at", "in_future"),
+        (json!({ "started_at": hours_ago(2), "ended_at": hours_ago(1), "note": "x".repeat(1001) }), "note", "length"),
+    ] {
+        let response = app.post_json(&path, Some(&user.token), &body).await;
+        assert_eq!(response.status(), 400, "{}", body);
+        let problem: Value = response.json().await.unwrap();
+        assert_eq!(problem["errors"][0]["field"], field);
+        assert_eq!(problem["errors"][0]["code"], code);
+    }
+
+    // Omitted fields are kept, a null note is cleared
+    let entry_path = format!("{}/{}", path, entry["id"].as_str().unwrap());
+    let response = app.put_json(&entry_path, &user.token, &json!({ "started_at": hours_ago(3), "note": null })).await;
+    assert_eq!(response.status(), 200);
+    let updated: Value = response.json().await.unwrap();
+    assert_eq!(updated["duration_seconds"], 7200);
+    assert_eq!(updated["ended_at"], entry["ended_at"]);
+    assert!(updated["note"].is_null());
+    assert_eq!(tracked_seconds(&app, &user, &todo).await, 7200);
+    let response = app.put_json(&entry_path, &user.token, &json!({ "started_at": hours_ago(0) })).await;
+    assert_eq!(response.status(), 400);
+
+    // Ending a running timer by hand stops it
+    let started: Value = timer(&app, &user, &todo, "start").await.json().await.unwrap();
+    let running_path = format!("{}/{}", path, started["running"]["id"].as_str().unwrap());
+    let response = app
+        .put_json(&running_path, &user.token, &json!({ "started_at": hours_ago(1), "ended_at": Utc::now().trunc_subsecs(0) }))
+        .await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.json::<Value>().await.unwrap()["duration_seconds"], 3600);
+    assert_eq!(app.get("/api/timer", &user.token).await.json::<Value>().await.unwrap(), Value::Null);
+    assert_eq!(tracked_seconds(&app, &user, &todo).await, 10800);
+
+    assert_eq!(app.delete(&entry_path, &user.token).await.status(), 204);
+    assert_eq!(app.get(&entry_path, &user.token).await.status(), 404);
+    assert_eq!(tracked_seconds(&app, &user, &todo).await, 3600);
+}
+
+#[tokio::test]
+async fn grantees_track_their_own_time_on_shared_todos() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let grantee = app.create_user().await;
+    let stranger = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Shared work" })).await;
+    let response = app
+        .post_json("/api/shares", Some(&owner.token), &json!({ "username": grantee.username, "role": "viewer" }))
+        .await;
+    assert_eq!(response.status(), 201);
+
+    let path = entries_path(&todo);
+    let body = json!({ "started_at": hours_ago(2), "ended_at": hours_ago(1) });
+    let theirs: Value = app.post_json(&path, Some(&grantee.token), &body).await.json().await.unwrap();
+    let mine: Value = app.post_json(&path, Some(&owner.token), &body).await.json().await.unwrap();
+    assert_eq!(theirs["user_id"], grantee.id.to_string());
+    assert_eq!(tracked_seconds(&app, &owner, &todo).await, 7200);
+
+    let entries: Value = app.get(&path, &grantee.token).await.json().await.unwrap();
+    assert_eq!(entries.as_array().unwrap().len(), 2);
+    assert_eq!(app.get(&path, &stranger.token).await.status(), 404);
+    assert_eq!(app.post_json(&path, Some(&stranger.token), &body).await.status(), 404);
+
+    // Only the tracker edits an entry; the owner can also delete it
+    let mine_path = format!("{}/{}", path, mine["id"].as_str().unwrap());
+    let theirs_path = format!("{}/{}", path, theirs["id"].as_str().unwrap());
+    assert_eq!(app.put_json(&mine_path, &grantee.token, &json!({ "note": "Mine now" })).await.status(), 403);
+    assert_eq!(app.delete(&mine_path, &grantee.token).await.status(), 403);
+    assert_eq!(app.put_json(&theirs_path, &owner.token, &json!({ "note": "Checked" })).await.status(), 403);
+    assert_eq!(app.delete(&theirs_path, &owner.token).await.status(), 204);
+    assert_eq!(tracked_seconds(&app, &owner, &todo).await, 3600);
+
+    // Deleting the todo takes its entries with it
+    assert_eq!(timer(&app, &grantee, &todo, "start").await.status(), 201);
+    assert_eq!(app.delete(&format!("/api/todos/{}", todo["id"].as_str().unwrap()), &owner.token).await.status(), 204);
+    assert_eq!(app.get("/api/timer", &grantee.token).await.json::<Value>().await.unwrap(), Value::Null);
+}
+
+#[tokio::test]
+async fn reports_sum_the_users_time_by_day_and_list() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let user = app.create_user().await;
+    let shared = app.create_todo(&owner.token, json!({ "title": "Client work" })).await;
+    let own = app.create_todo(&user.token, json!({ "title": "Admin" })).await;
+    let response = app
+        .post_json("/api/shares", Some(&owner.token), &json!({ "username": user.username, "role": "editor" }))
+        .await;
+    assert_eq!(response.status(), 201);
+
+    let (started_at, ended_at) = (hours_ago(3), hours_ago(1));
+    for (tracker, todo) in [(&user, &shared), (&user, &own), (&owner, &shared)] {
+        let body = json!({ "started_at": started_at, "ended_at": ended_at });
+        assert_eq!(app.post_json(&entries_path(todo), Some(&tracker.token), &body).await.status(), 201);
+    }
+    // Running timers are left out
+    assert_eq!(timer(&app, &user, &own, "start").await.status(), 201);
+
+    let today = Utc::now().date_naive();
+    let day = started_at.date_naive();
+    let query = format!("?timezone=UTC&from={}&to={}", today - chrono::Days::new(1), today);
+    let response = app.get(&format!("/api/time-entries/report{}", query), &user.token).await;
+    assert_eq!(response.status(), 200);
+    let report: Value = response.json().await.unwrap();
+    assert_eq!(report["timezone"], "UTC");
+    assert_eq!(report["total_seconds"], 14400);
+    let days = report["by_day"].as_array().unwrap();
+    assert_eq!(days.len(), 2);
+    assert_eq!(days.iter().find(|d| d["date"] == day.to_string()).unwrap()["seconds"], 14400);
+
+    let mut lists: Vec<(&str, i64)> = report["by_list"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| (l["username"].as_str().unwrap(), l["seconds"].as_i64().unwrap()))
+        .collect();
+    lists.sort();
+    let mut expected = vec![(owner.username.as_str(), 7200), (user.username.as_str(), 7200)];
+    expected.sort();
+    assert_eq!(lists, expected);
+    assert_eq!(report["rows"].as_array().unwrap().len(), 2);
+
+    let response = app.get(&format!("/api/time-entries/report{}&format=csv", query), &user.token).await;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "text/csv; charset=utf-8");
+    assert!(response.headers()["content-disposition"].to_str().unwrap().starts_with("attachment"));
+    let csv = response.text().await.unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines.contains(&format!("{},{},{},7200,2.00", day, owner.id, owner.username).as_str()));
+
+    // The owner's report has only their own time
+    let report: Value = app.get(&format!("/api/time-entries/report{}", query), &owner.token).await.json().await.unwrap();
+    assert_eq!(report["total_seconds"], 7200);
+
+    let response = app.get("/api/time-entries/report?format=xml", &user.token).await;
+    assert_eq!(response.status(), 400);
+}
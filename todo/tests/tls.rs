@@ -0,0 +1,173 @@
+use axum::{routing::get, Router};
+use rcgen::Certificate;
+use reqwest::{tls::TlsInfo, Client, StatusCode, Version};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{net::TcpListener, task::JoinHandle, time::sleep};
+use uuid::Uuid;
+
+use todo_service::{
+    server,
+    shutdown::{Shutdown, ShutdownOptions},
+    tls,
+};
+
+const OPTIONS: ShutdownOptions = ShutdownOptions {
+    drain_delay: Duration::ZERO,
+    timeout: Duration::from_secs(5),
+};
+
+struct TlsServer {
+    /// `https://localhost:PORT`, matching the certificate's name.
+    address: String,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    shutdown: Shutdown,
+    handle: JoinHandle<std::io::Result<()>>,
+}
+
+fn certificate() -> Certificate {
+    rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap()
+}
+
+fn write_pem(cert: &Certificate, cert_path: &Path, key_path: &Path) {
+    std::fs::write(cert_path, cert.serialize_pem().unwrap()).unwrap();
+    std::fs::write(key_path, cert.serialize_private_key_pem()).unwrap();
+}
+
+/// Serves a `/` route over TLS with `cert`, reloading every 100ms.
+async fn spawn(cert: &Certificate) -> TlsServer {
+    let dir = std::env::temp_dir().join(format!("todo-tls-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    write_pem(cert, &cert_path, &key_path);
+
+    let shutdown = Shutdown::new();
+    let rustls = tls::load(&cert_path, &key_path).await.expect("failed to load certificate");
+    tls::watch(
+        rustls.clone(),
+        cert_path.clone(),
+        key_path.clone(),
+        Duration::from_millis(100),
+        shutdown.clone(),
+    );
+
+    let app = Router::new().route("/", get(|| async { "ok" }));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("https://localhost:{}", listener.local_addr().unwrap().port());
+    let handle = tokio::spawn(server::serve_tls(listener, app, rustls, shutdown.clone(), OPTIONS));
+
+    TlsServer { address, cert_path, key_path, shutdown, handle }
+}
+
+/// Whether `peer` (DER) carries the public key of `cert`. rcgen signs anew
+/// on every serialization, so the certificate bytes themselves differ.
+fn has_key_of(peer: &[u8], cert: &Certificate) -> bool {
+    let key = cert.get_key_pair().public_key_raw();
+    peer.windows(key.len()).any(|window| window == key)
+}
+
+/// A client trusting only `roots`, exposing the server certificate.
+fn client(roots: &[&Certificate]) -> reqwest::ClientBuilder {
+    roots
+        .iter()
+        .fold(Client::builder().use_rustls_tls().tls_info(true), |builder, cert| {
+            let root = reqwest::Certificate::from_der(&cert.serialize_der().unwrap()).unwrap();
+            builder.add_root_certificate(root)
+        })
+}
+
+#[tokio::test]
+async fn negotiates_http2_and_http1_over_tls() {
+    let cert = certificate();
+    let server = spawn(&cert).await;
+
+    let response = client(&[&cert]).build().unwrap().get(&server.address).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.version(), Version::HTTP_2);
+    assert_eq!(response.text().await.unwrap(), "ok");
+
+    let response = client(&[&cert])
+        .http1_only()
+        .build()
+        .unwrap()
+        .get(&server.address)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.version(), Version::HTTP_11);
+}
+
+#[tokio::test]
+async fn reloads_certificate_when_files_change() {
+    let old = certificate();
+    let new = certificate();
+    let server = spawn(&old).await;
+    let peer_certificate = || async {
+        // A fresh client each time, so no pooled connection hides the change.
+        let response = client(&[&old, &new]).build().unwrap().get(&server.address).send().await.unwrap();
+        let info = response.extensions().get::<TlsInfo>().expect("no TLS info");
+        info.peer_certificate().unwrap().to_vec()
+    };
+    assert!(has_key_of(&peer_certificate().await, &old));
+
+    write_pem(&new, &server.cert_path, &server.key_path);
+
+    for _ in 0..50 {
+        if has_key_of(&peer_certificate().await, &new) {
+            return;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    panic!("certificate was not reloaded");
+}
+
+#[tokio::test]
+async fn tls_server_exits_on_shutdown() {
+    let cert = certificate();
+    let server = spawn(&cert).await;
+
+    server.shutdown.request();
+
+    tokio::time::timeout(Duration::from_secs(2), server.handle)
+        .await
+        .expect("server did not exit")
+        .unwrap()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn redirects_plain_http_to_https() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(server::serve(listener, tls::redirect_router(8443), Shutdown::new(), OPTIONS));
+    let client = Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap();
+
+    let response = client
+        .get(format!("{}/api/todos?completed=true", address))
+        .header("Host", "todo.example.com:8080")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(
+        response.headers()["location"],
+        "https://todo.example.com:8443/api/todos?completed=true"
+    );
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(server::serve(listener, tls::redirect_router(443), Shutdown::new(), OPTIONS));
+
+    let response = client
+        .get(format!("{}/", address))
+        .header("Host", "todo.example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.headers()["location"], "https://todo.example.com/");
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use chrono::{Duration, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::TestApp;
+
+fn in_days(days: i64) -> String {
+    (Utc::now() + Duration::days(days)).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[tokio::test]
+async fn todo_crud_round_trip() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let created = app
+        .create_todo(
+            &user.token,
+            json!({ "title": "Write tests", "description": "End to end" }),
+        )
+        .await;
+    assert_eq!(created["title"], "Write tests");
+    assert_eq!(created["completed"], false);
+    let id = created["id"].as_str().unwrap();
+
+    let fetched: Value = app.get(&format!("/api/todos/{}", id), &user.token).await.json().await.unwrap();
+    assert_eq!(fetched, created);
+
+    let response = app
+        .put_json(&format!("/api/todos/{}", id), &user.token, &json!({ "completed": true }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let updated: Value = response.json().await.unwrap();
+    assert_eq!(updated["completed"], true);
+    assert_eq!(updated["title"], "Write tests");
+
+    let response = app.delete(&format!("/api/todos/{}", id), &user.token).await;
+    assert_eq!(response.status(), 204);
+
+    let response = app.get(&format!("/api/todos/{}", id), &user.token).await;
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn list_orders_scheduled_todos_first() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    app.create_todo(&user.token, json!({ "title": "unscheduled" })).await;
+    app.create_todo(&user.token, json!({ "title": "later", "scheduled_for": in_days(5) })).await;
+    app.create_todo(&user.token, json!({ "title": "sooner", "scheduled_for": in_days(1) })).await;
+
+    let todos: Vec<Value> = app.get("/api/todos", &user.token).await.json().await.unwrap();
+    let titles: Vec<&str> = todos.iter().map(|t| t["title"].as_str().unwrap()).collect();
+
+    assert_eq!(titles, ["sooner", "later", "unscheduled"]);
+}
+
+#[tokio::test]
+async fn users_cannot_see_or_modify_each_others_todos() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let intruder = app.create_user().await;
+
+    let todo = app.create_todo(&owner.token, json!({ "title": "private" })).await;
+    let path = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+
+    let listed: Vec<Value> = app.get("/api/todos", &intruder.token).await.json().await.unwrap();
+    assert!(listed.is_empty());
+
+    assert_eq!(app.get(&path, &intruder.token).await.status(), 404);
+    assert_eq!(
+        app.put_json(&path, &intruder.token, &json!({ "title": "mine now" })).await.status(),
+        404
+    );
+    assert_eq!(app.delete(&path, &intruder.token).await.status(), 404);
+
+    let still_there: Value = app.get(&path, &owner.token).await.json().await.unwrap();
+    assert_eq!(still_there["title"], "private");
+}
+
+#[tokio::test]
+async fn missing_todo_returns_not_found() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let path = format!("/api/todos/{}", Uuid::new_v4());
+
+    assert_eq!(app.get(&path, &user.token).await.status(), 404);
+    assert_eq!(app.put_json(&path, &user.token, &json!({ "completed": true })).await.status(), 404);
+    assert_eq!(app.delete(&path, &user.token).await.status(), 404);
+}
+
+#[tokio::test]
+async fn create_and_update_validate_input() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    for body in [
+        json!({ "title": "" }),
+        json!({ "title": "x".repeat(256) }),
+        json!({ "title": "ok", "description": "x".repeat(1001) }),
+    ] {
+        let response = app.post_json("/api/todos", Some(&user.token), &body).await;
+        assert_eq!(response.status(), 400, "accepted {}", body);
+    }
+
+    let todo = app.create_todo(&user.token, json!({ "title": "valid" })).await;
+    let path = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    let response = app.put_json(&path, &user.token, &json!({ "title": "" })).await;
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn rejects_malformed_todo_id() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let response = app.get("/api/todos/not-a-uuid", &user.token).await;
+
+    assert_eq!(response.status(), 400);
+}
+
+#[tokio::test]
+async fn scheduled_date_cannot_be_in_the_past() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let response = app
+        .post_json(
+            "/api/todos",
+            Some(&user.token),
+            &json!({ "title": "too late", "scheduled_for": in_days(-1) }),
+        )
+        .await;
+    assert_eq!(response.status(), 400);
+
+    let todo = app
+        .create_todo(&user.token, json!({ "title": "on time", "scheduled_for": in_days(1) }))
+        .await;
+
+    let path = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    let response = app
+        .put_json(&path, &user.token, &json!({ "scheduled_for": in_days(-1) }))
+        .await;
+    assert_eq!(response.status(), 400);
+}
@@ -0,0 +1,344 @@
+mod common;
+
+use axum::{body::Bytes, extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+use serde_json::{json, Value};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tokio::{net::TcpListener, time::sleep};
+
+use common::{TestApp, TestUser};
+use todo_service::webhooks::{self, WebhookOptions, Webhooks};
+
+const SECRET: &str = "receiver-secret-0123456789";
+
+/// One request as the receiver saw it.
+struct Received {
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl Received {
+    fn header(&self, name: &str) -> &str {
+        self.headers.get(name).unwrap().to_str().unwrap()
+    }
+
+    fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap()
+    }
+}
+
+#[derive(Default)]
+struct Inbox {
+    requests: Mutex<Vec<Received>>,
+    /// Requests still to be answered with a 500.
+    failures: AtomicUsize,
+}
+
+/// A local HTTP server standing in for a webhook receiver.
+struct Receiver {
+    url: String,
+    inbox: Arc<Inbox>,
+}
+
+impl Receiver {
+    /// Starts a receiver that answers the first `failures` requests with a 500.
+    async fn start(failures: usize) -> Self {
+        let inbox = Arc::new(Inbox::default());
+        inbox.failures.store(failures, Ordering::SeqCst);
+        let app = Router::new().route("/hook", post(receive)).with_state(inbox.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        Receiver { url, inbox }
+    }
+
+    /// Waits until at least `count` requests arrived, then takes them all.
+    async fn take(&self, count: usize) -> Vec<Received> {
+        for _ in 0..100 {
+            {
+                let mut requests = self.inbox.requests.lock().unwrap();
+                if requests.len() >= count {
+                    return std::mem::take(&mut *requests);
+                }
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+        panic!("receiver did not get {} requests", count);
+    }
+}
+
+async fn receive(State(inbox): State<Arc<Inbox>>, headers: HeaderMap, body: Bytes) -> StatusCode {
+    inbox.requests.lock().unwrap().push(Received { headers, body });
+    let failing = inbox
+        .failures
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| left.checked_sub(1))
+        .is_ok();
+    if failing {
+        StatusCode::INTERNAL_SERVER_ERROR
+    } else {
+        StatusCode::NO_CONTENT
+    }
+}
+
+async fn create_webhook(app: &TestApp, user: &TestUser, body: Value) -> Value {
+    let response = app.post_json("/api/webhooks", Some(&user.token), &body).await;
+    assert_eq!(response.status(), 201);
+    response.json().await.unwrap()
+}
+
+/// Polls the delivery log until the delivery reaches `status`.
+async fn wait_for_status(app: &TestApp, user: &TestUser, webhook_id: &str, delivery_id: &str, status: &str) -> Value {
+    let path = format!("/api/webhooks/{}/deliveries/{}", webhook_id, delivery_id);
+    for _ in 0..100 {
+        let log: Value = app.get(&path, &user.token).await.json().await.unwrap();
+        if log["status"] == status {
+            return log;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!("delivery {} never became {}", delivery_id, status);
+}
+
+/// Checks the signature headers the way a receiver should.
+fn assert_signed(request: &Received, secret: &str) {
+    let timestamp: i64 = request.header(webhooks::TIMESTAMP_HEADER).parse().unwrap();
+    assert_eq!(
+        request.header(webhooks::SIGNATURE_HEADER),
+        webhooks::sign(secret, timestamp, &request.body)
+    );
+    assert_ne!(
+        request.header(webhooks::SIGNATURE_HEADER),
+        webhooks::sign("some-other-secret", timestamp, &request.body)
+    );
+}
+
+#[tokio::test]
+async fn delivers_signed_events_the_webhook_subscribes_to() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let other = app.create_user().await;
+    let receiver = Receiver::start(0).await;
+    let webhook = create_webhook(
+        &app,
+        &user,
+        json!({ "url": receiver.url, "events": ["created", "deleted"], "secret": SECRET }),
+    )
+    .await;
+
+    let todo = app.create_todo(&user.token, json!({ "title": "Hooked" })).await;
+    let path = format!("/api/todos/{}", todo["id"].as_str().unwrap());
+    app.put_json(&path, &user.token, &json!({ "completed": true })).await;
+    app.delete(&path, &user.token).await;
+    app.create_todo(&other.token, json!({ "title": "Someone else's" })).await;
+
+    let mut requests = receiver.take(2).await;
+    requests.sort_by_key(|request| request.json()["data"]["id"].as_i64());
+    let types: Vec<Value> = requests.iter().map(|request| request.json()["type"].clone()).collect();
+    assert_eq!(types, ["created", "deleted"]);
+    for request in &requests {
+        assert_signed(request, SECRET);
+        assert_eq!(request.header("content-type"), "application/json");
+        assert_eq!(request.json()["id"], request.header(webhooks::ID_HEADER));
+        assert_eq!(request.header(webhooks::EVENT_HEADER), request.json()["type"]);
+    }
+    let created = requests[0].json();
+    assert_eq!(created["data"]["todo_id"], todo["id"]);
+    assert_eq!(created["data"]["todo"]["title"], "Hooked");
+
+    let deliveries: Value = app
+        .get(&format!("/api/webhooks/{}/deliveries", webhook["id"].as_str().unwrap()), &user.token)
+        .await
+        .json()
+        .await
+        .unwrap();
+    let deliveries = deliveries.as_array().unwrap();
+    assert_eq!(deliveries.len(), 2);
+    assert_eq!(deliveries[0]["event"], "deleted");
+    for delivery in deliveries {
+        let log = wait_for_status(
+            &app,
+            &user,
+            webhook["id"].as_str().unwrap(),
+            delivery["id"].as_str().unwrap(),
+            "delivered",
+        )
+        .await;
+        assert_eq!(log["attempts"].as_array().unwrap().len(), 1);
+        assert_eq!(log["attempts"][0]["status_code"], 204);
+    }
+
+    let metrics = app.metrics().await;
+    assert!(metrics.contains("webhook_deliveries_total{outcome=\"delivered\"}"));
+}
+
+#[tokio::test]
+async fn retries_with_backoff_until_delivered() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let receiver = Receiver::start(1).await;
+    let webhook = create_webhook(&app, &user, json!({ "url": receiver.url, "events": ["created"] })).await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+
+    let response = app.post_json(&format!("/api/webhooks/{}/test", webhook_id), Some(&user.token), &json!({})).await;
+    assert_eq!(response.status(), 202);
+    let delivery: Value = response.json().await.unwrap();
+    assert_eq!(delivery["event"], "ping");
+    assert_eq!(delivery["status"], "pending");
+
+    let log = wait_for_status(&app, &user, webhook_id, delivery["id"].as_str().unwrap(), "delivered").await;
+    assert_eq!(log["attempts"].as_array().unwrap().len(), 2);
+    assert_eq!(log["attempts"][0]["status_code"], 500);
+    assert!(log["attempts"][0]["error"].is_string());
+    assert!(log["attempts"][1]["error"].is_null());
+    assert!(log["next_attempt_at"].is_null());
+    assert!(log["delivered_at"].is_string());
+
+    // The same delivery is retried, signed with the generated secret
+    let requests = receiver.take(2).await;
+    let secret = webhook["secret"].as_str().unwrap();
+    assert!(secret.starts_with("whsec_"));
+    for request in &requests {
+        assert_signed(request, secret);
+        assert_eq!(request.header(webhooks::ID_HEADER), delivery["id"]);
+        assert_eq!(request.json()["data"]["webhook_id"], webhook["id"]);
+    }
+}
+
+#[tokio::test]
+async fn gives_up_after_max_attempts() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let receiver = Receiver::start(usize::MAX).await;
+    let webhook = create_webhook(&app, &user, json!({ "url": receiver.url, "events": ["created"] })).await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+
+    app.create_todo(&user.token, json!({ "title": "Never arrives" })).await;
+    let deliveries: Value =
+        app.get(&format!("/api/webhooks/{}/deliveries", webhook_id), &user.token).await.json().await.unwrap();
+    let delivery_id = deliveries[0]["id"].as_str().unwrap();
+
+    let log = wait_for_status(&app, &user, webhook_id, delivery_id, "dead").await;
+    assert_eq!(log["attempts"].as_array().unwrap().len(), 3);
+    assert_eq!(log["last_status_code"], 500);
+    assert!(log["next_attempt_at"].is_null());
+
+    // Dead deliveries are not retried
+    sleep(Duration::from_millis(500)).await;
+    assert_eq!(receiver.inbox.requests.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn unreachable_receivers_are_logged() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    // Bound and dropped, so nothing listens there
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    drop(listener);
+    let webhook = create_webhook(&app, &user, json!({ "url": url, "events": ["created"] })).await;
+    let webhook_id = webhook["id"].as_str().unwrap();
+
+    let response = app.post_json(&format!("/api/webhooks/{}/test", webhook_id), Some(&user.token), &json!({})).await;
+    let delivery: Value = response.json().await.unwrap();
+
+    let log = wait_for_status(&app, &user, webhook_id, delivery["id"].as_str().unwrap(), "dead").await;
+    assert!(log["last_status_code"].is_null());
+    assert!(log["attempts"][0]["status_code"].is_null());
+    assert!(!log["attempts"][0]["error"].as_str().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn manages_webhooks() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let other = app.create_user().await;
+    let webhook = create_webhook(
+        &app,
+        &user,
+        json!({ "url": "https://example.com/hook", "events": ["updated"], "secret": SECRET }),
+    )
+    .await;
+    assert_eq!(webhook["secret"], SECRET);
+    assert_eq!(webhook["active"], true);
+    let path = format!("/api/webhooks/{}", webhook["id"].as_str().unwrap());
+
+    // The secret is never shown again
+    let listed: Value = app.get("/api/webhooks", &user.token).await.json().await.unwrap();
+    assert_eq!(listed.as_array().unwrap().len(), 1);
+    assert!(listed[0].get("secret").is_none());
+
+    let response = app
+        .put_json(&path, &user.token, &json!({ "events": ["created", "updated"], "active": false }))
+        .await;
+    assert_eq!(response.status(), 200);
+    let updated: Value = response.json().await.unwrap();
+    assert_eq!(updated["events"], json!(["created", "updated"]));
+    assert_eq!(updated["active"], false);
+    assert_eq!(updated["url"], "https://example.com/hook");
+
+    // Inactive webhooks get no deliveries
+    app.create_todo(&user.token, json!({ "title": "Quiet" })).await;
+    let deliveries: Value = app.get(&format!("{}/deliveries", path), &user.token).await.json().await.unwrap();
+    assert!(deliveries.as_array().unwrap().is_empty());
+
+    // Other users cannot see or touch it
+    assert_eq!(app.get(&path, &other.token).await.status(), 404);
+    assert_eq!(app.put_json(&path, &other.token, &json!({ "active": true })).await.status(), 404);
+    assert_eq!(app.get(&format!("{}/deliveries", path), &other.token).await.status(), 404);
+    assert_eq!(app.post_json(&format!("{}/test", path), Some(&other.token), &json!({})).await.status(), 404);
+    assert_eq!(app.delete(&path, &other.token).await.status(), 404);
+
+    assert_eq!(app.delete(&path, &user.token).await.status(), 204);
+    assert_eq!(app.get(&path, &user.token).await.status(), 404);
+}
+
+#[tokio::test]
+async fn rejects_invalid_webhooks() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    for (body, field) in [
+        (json!({ "url": "not a url", "events": ["created"] }), "url"),
+        (json!({ "url": "ftp://example.com/hook", "events": ["created"] }), "url"),
+        (json!({ "url": "https://example.com/hook", "events": [] }), "events"),
+        (json!({ "url": "https://example.com/hook", "events": ["created"], "secret": "short" }), "secret"),
+    ] {
+        let response = app.post_json("/api/webhooks", Some(&user.token), &body).await;
+        assert_eq!(response.status(), 400, "{}", body);
+        let problem: Value = response.json().await.unwrap();
+        assert_eq!(problem["errors"][0]["field"], field, "{}", body);
+    }
+
+    let response = app
+        .post_json("/api/webhooks", Some(&user.token), &json!({ "url": "https://example.com", "events": ["archived"] }))
+        .await;
+    assert_eq!(response.status(), 400);
+
+    let response = reqwest::get(app.url("/api/webhooks")).await.unwrap();
+    assert_eq!(response.status(), 401);
+}
+
+#[tokio::test]
+async fn refuses_private_targets_by_default() {
+    let webhooks = Webhooks::new(WebhookOptions::default());
+    for url in [
+        "http://localhost/hook",
+        "http://127.0.0.1:8080/hook",
+        "http://10.1.2.3/hook",
+        "http://192.168.0.1/hook",
+        "http://169.254.169.254/latest/meta-data",
+        "http://[::1]/hook",
+        "http://[fd00::1]/hook",
+    ] {
+        assert!(webhooks.check_target(url).is_err(), "{}", url);
+    }
+    assert!(webhooks.check_target("https://example.com/hook").is_ok());
+    assert!(webhooks.check_target("http://93.184.216.34/hook").is_ok());
+}
@@ -0,0 +1,300 @@
+mod common;
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::{net::TcpStream, time::timeout};
+use tokio_tungstenite::{
+    connect_async,
+    tungstenite::{client::IntoClientRequest, protocol::frame::coding::CloseCode, Error, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+use common::{TestApp, TestUser};
+
+/// A connected `/api/ws` client that reads JSON messages.
+struct WsClient {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    session_id: Value,
+}
+
+impl WsClient {
+    /// Connects with the token in the query string, as browsers do.
+    async fn connect(app: &TestApp, user: &TestUser) -> Self {
+        let url = format!("{}?access_token={}", ws_url(app), user.token);
+        let (stream, _) = connect_async(url).await.expect("WebSocket connect failed");
+        let mut client = WsClient { stream, session_id: Value::Null };
+
+        let welcome = client.expect("welcome").await;
+        assert_eq!(welcome["user"]["id"], user.id.to_string());
+        client.session_id = welcome["session_id"].clone();
+        client
+    }
+
+    async fn send(&mut self, message: Value) {
+        self.stream.send(Message::Text(message.to_string())).await.unwrap();
+    }
+
+    /// The next message, or `None` once the server closes the connection.
+    async fn next(&mut self) -> Option<Value> {
+        loop {
+            let frame = timeout(Duration::from_secs(5), self.stream.next())
+                .await
+                .expect("timed out waiting for a message")?
+                .expect("WebSocket failed");
+            match frame {
+                Message::Text(text) => return Some(serde_json::from_str(&text).unwrap()),
+                Message::Close(_) => return None,
+                _ => continue,
+            }
+        }
+    }
+
+    /// The next message, which must be of type `kind`.
+    async fn expect(&mut self, kind: &str) -> Value {
+        let message = self.next().await.expect("connection closed");
+        assert_eq!(message["type"], kind, "unexpected message {}", message);
+        message
+    }
+}
+
+fn ws_url(app: &TestApp) -> String {
+    app.url("/api/ws").replacen("http://", "ws://", 1)
+}
+
+#[tokio::test]
+async fn rejects_connections_without_a_valid_token() {
+    let app = TestApp::spawn().await;
+
+    for url in [ws_url(&app), format!("{}?access_token=not-a-jwt", ws_url(&app))] {
+        match connect_async(url).await {
+            Err(Error::Http(response)) => assert_eq!(response.status(), 401),
+            other => panic!("expected a 401, got {:?}", other.map(|_| ())),
+        }
+    }
+}
+
+#[tokio::test]
+async fn accepts_the_authorization_header() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+
+    let mut request = ws_url(&app).into_client_request().unwrap();
+    request
+        .headers_mut()
+        .insert("Authorization", format!("Bearer {}", user.token).parse().unwrap());
+    let (stream, _) = connect_async(request).await.expect("WebSocket connect failed");
+    let mut client = WsClient { stream, session_id: Value::Null };
+
+    let welcome = client.expect("welcome").await;
+    assert_eq!(welcome["user"]["username"], user.username);
+    assert_eq!(welcome["heartbeat_secs"], 30);
+}
+
+#[tokio::test]
+async fn commands_are_acknowledged_and_broadcast() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let mut client = WsClient::connect(&app, &user).await;
+    client.expect("presence").await;
+
+    client
+        .send(json!({ "type": "create", "ref": "c1", "todo": { "title": "Over the socket" } }))
+        .await;
+    let ack = client.expect("ack").await;
+    assert_eq!(ack["ref"], "c1");
+    assert_eq!(ack["todo"]["title"], "Over the socket");
+    assert_eq!(ack["todo"]["completed"], false);
+    let id = ack["todo"]["id"].as_str().unwrap().to_string();
+
+    let event = client.expect("event").await;
+    assert_eq!(event["event"]["type"], "created");
+    assert_eq!(event["event"]["todo_id"], id);
+
+    client.send(json!({ "type": "complete", "ref": "c2", "todo_id": id })).await;
+    assert_eq!(client.expect("ack").await["todo"]["completed"], true);
+    assert_eq!(client.expect("event").await["event"]["todo"]["completed"], true);
+
+    client
+        .send(json!({ "type": "update", "ref": "c3", "todo_id": id, "changes": { "title": "Renamed" } }))
+        .await;
+    assert_eq!(client.expect("ack").await["todo"]["title"], "Renamed");
+    client.expect("event").await;
+
+    // The changes went to the same store as the REST API
+    let todo: Value = app.get(&format!("/api/todos/{}", id), &user.token).await.json().await.unwrap();
+    assert_eq!(todo["title"], "Renamed");
+    assert_eq!(todo["completed"], true);
+
+    client.send(json!({ "type": "ping", "ref": "p" })).await;
+    assert_eq!(client.expect("pong").await["ref"], "p");
+}
+
+#[tokio::test]
+async fn failed_commands_return_problem_details() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let other = app.create_user().await;
+    let theirs = app.create_todo(&other.token, json!({ "title": "Not yours" })).await;
+    let mut client = WsClient::connect(&app, &user).await;
+    client.expect("presence").await;
+
+    client.send(json!({ "type": "create", "ref": "empty", "todo": { "title": "" } })).await;
+    let error = client.expect("error").await;
+    assert_eq!(error["ref"], "empty");
+    assert_eq!(error["error"]["code"], "validation_failed");
+    assert_eq!(error["error"]["errors"][0]["field"], "title");
+
+    client
+        .send(json!({ "type": "complete", "ref": "theirs", "todo_id": theirs["id"] }))
+        .await;
+    let error = client.expect("error").await;
+    assert_eq!(error["ref"], "theirs");
+    assert_eq!(error["error"]["code"], "not_found");
+
+    client.send(json!({ "type": "explode", "ref": "unknown" })).await;
+    let error = client.expect("error").await;
+    assert_eq!(error["ref"], "unknown");
+    assert_eq!(error["error"]["code"], "bad_request");
+
+    client.stream.send(Message::Text("{not json".to_string())).await.unwrap();
+    let error = client.expect("error").await;
+    assert!(error["ref"].is_null());
+    assert_eq!(error["error"]["code"], "bad_request");
+
+    // The connection survives bad input
+    client.send(json!({ "type": "ping" })).await;
+    client.expect("pong").await;
+}
+
+#[tokio::test]
+async fn pushes_changes_made_elsewhere_to_their_owner_only() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let other = app.create_user().await;
+    let mut client = WsClient::connect(&app, &user).await;
+    client.expect("presence").await;
+
+    app.create_todo(&other.token, json!({ "title": "Someone else's" })).await;
+    let todo = app.create_todo(&user.token, json!({ "title": "Made over REST" })).await;
+
+    let event = client.expect("event").await;
+    assert_eq!(event["event"]["todo_id"], todo["id"]);
+    assert_eq!(event["event"]["todo"]["title"], "Made over REST");
+}
+
+#[tokio::test]
+async fn pushes_changes_to_shared_todos_and_who_is_viewing_them() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let grantee = app.create_user().await;
+    let response = app
+        .post_json("/api/shares", Some(&owner.token), &json!({ "username": grantee.username, "role": "viewer" }))
+        .await;
+    assert_eq!(response.status(), 201);
+    let mut owners = WsClient::connect(&app, &owner).await;
+    owners.expect("presence").await;
+
+    // The grantee joins their own list and the one shared with them
+    let mut grantees = WsClient::connect(&app, &grantee).await;
+    let own = grantees.expect("presence").await;
+    assert_eq!(own["owner_id"], grantee.id.to_string());
+    assert_eq!(own["viewers"].as_array().unwrap().len(), 1);
+    let shared = grantees.expect("presence").await;
+    assert_eq!(shared["owner_id"], owner.id.to_string());
+    assert_eq!(shared["viewers"].as_array().unwrap().len(), 2);
+    let seen = owners.expect("presence").await;
+    assert_eq!(seen["owner_id"], owner.id.to_string());
+    assert_eq!(seen["viewers"][1]["username"], grantee.username);
+
+    let todo = app.create_todo(&owner.token, json!({ "title": "For both" })).await;
+    let event = grantees.expect("event").await;
+    assert_eq!(event["event"]["todo_id"], todo["id"]);
+    assert_eq!(event["event"]["user_id"], owner.id.to_string());
+    assert_eq!(owners.expect("event").await["event"]["todo_id"], todo["id"]);
+}
+
+#[tokio::test]
+async fn keeps_presence_to_grantees_of_the_whole_list() {
+    let app = TestApp::spawn().await;
+    let owner = app.create_user().await;
+    let grantee = app.create_user().await;
+    let todo = app.create_todo(&owner.token, json!({ "title": "Only this one" })).await;
+    let body = json!({ "username": grantee.username, "role": "viewer", "todo_id": todo["id"] });
+    let response = app.post_json("/api/shares", Some(&owner.token), &body).await;
+    assert_eq!(response.status(), 201);
+
+    // Sharing one todo does not put the grantee on the owner's list
+    let mut grantees = WsClient::connect(&app, &grantee).await;
+    assert_eq!(grantees.expect("presence").await["owner_id"], grantee.id.to_string());
+    let mut owners = WsClient::connect(&app, &owner).await;
+    assert_eq!(owners.expect("presence").await["viewers"].as_array().unwrap().len(), 1);
+    grantees.send(json!({ "type": "ping" })).await;
+    grantees.expect("pong").await;
+    drop(grantees);
+
+    let body = json!({ "username": grantee.username, "role": "viewer" });
+    let share: Value = app.post_json("/api/shares", Some(&owner.token), &body).await.json().await.unwrap();
+    let mut grantees = WsClient::connect(&app, &grantee).await;
+    grantees.expect("presence").await;
+    assert_eq!(grantees.expect("presence").await["owner_id"], owner.id.to_string());
+    while owners.expect("presence").await["viewers"].as_array().unwrap().len() != 2 {}
+
+    // Once revoked, the grantee leaves the list on its next change and is
+    // not told about it
+    let response = app.delete(&format!("/api/shares/{}", share["id"].as_str().unwrap()), &owner.token).await;
+    assert_eq!(response.status(), 204);
+    let mut second = WsClient::connect(&app, &owner).await;
+    loop {
+        let viewers = second.expect("presence").await["viewers"].as_array().unwrap().clone();
+        if viewers.iter().all(|viewer| viewer["username"] == owner.username) {
+            break;
+        }
+    }
+    grantees.send(json!({ "type": "ping" })).await;
+    grantees.expect("pong").await;
+}
+
+#[tokio::test]
+async fn reports_presence_as_connections_come_and_go() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let mut first = WsClient::connect(&app, &user).await;
+    let viewers = first.expect("presence").await["viewers"].as_array().unwrap().clone();
+    assert_eq!(viewers.len(), 1);
+    assert_eq!(viewers[0]["username"], user.username);
+
+    let mut second = WsClient::connect(&app, &user).await;
+    assert_eq!(second.expect("presence").await["viewers"].as_array().unwrap().len(), 2);
+    assert_eq!(first.expect("presence").await["viewers"].as_array().unwrap().len(), 2);
+
+    // Another user's connection is not on this list
+    let other = app.create_user().await;
+    let mut theirs = WsClient::connect(&app, &other).await;
+    assert_eq!(theirs.expect("presence").await["viewers"].as_array().unwrap().len(), 1);
+
+    second.stream.close(None).await.unwrap();
+    let viewers = first.expect("presence").await["viewers"].as_array().unwrap().clone();
+    assert_eq!(viewers.len(), 1);
+    assert_eq!(viewers[0]["session_id"], first.session_id);
+}
+
+#[tokio::test]
+async fn closes_connections_on_shutdown() {
+    let app = TestApp::spawn().await;
+    let user = app.create_user().await;
+    let mut client = WsClient::connect(&app, &user).await;
+    client.expect("presence").await;
+
+    app.shutdown.request();
+
+    let frame = timeout(Duration::from_secs(5), client.stream.next())
+        .await
+        .expect("timed out waiting for close")
+        .expect("stream ended without a close frame")
+        .unwrap();
+    match frame {
+        Message::Close(Some(close)) => assert_eq!(close.code, CloseCode::Away),
+        other => panic!("expected a close frame, got {:?}", other),
+    }
+}